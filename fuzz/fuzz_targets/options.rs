@@ -0,0 +1,44 @@
+use arbitrary::Arbitrary;
+use sanitize_filename::{LengthUnit, Options, TruncationStrategy};
+
+/// The options that change how much of a name is left, picked by the fuzzer.
+#[derive(Arbitrary, Debug)]
+pub struct FuzzOptions<'a> {
+    windows: bool,
+    trim: bool,
+    fixed_point: bool,
+    replacement: &'a str,
+    empty_fallback: Option<&'a str>,
+    max_length: u16,
+    min_length: Option<u8>,
+    length_unit: u8,
+    truncation: u8,
+}
+
+impl<'a> FuzzOptions<'a> {
+    pub fn options(&self) -> Options<'a> {
+        let mut options = Options::new()
+            .with_windows(self.windows)
+            .with_trim(self.trim)
+            .with_fixed_point(self.fixed_point)
+            .with_replacement(self.replacement)
+            .with_max_length(self.max_length.into())
+            .with_length_unit(match self.length_unit % 3 {
+                0 => LengthUnit::Bytes,
+                1 => LengthUnit::Chars,
+                _ => LengthUnit::Utf16,
+            })
+            .with_truncation(match self.truncation % 3 {
+                0 => TruncationStrategy::CutEnd,
+                1 => TruncationStrategy::PreserveExtension,
+                _ => TruncationStrategy::None,
+            });
+        if let Some(empty_fallback) = self.empty_fallback {
+            options = options.with_empty_fallback(empty_fallback);
+        }
+        if let Some(min_length) = self.min_length {
+            options = options.with_min_length(min_length.into());
+        }
+        options
+    }
+}
@@ -0,0 +1,39 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use sanitize_filename::{SanitizedFileName, Sanitizer};
+
+mod options;
+
+fuzz_target!(|input: (&str, options::FuzzOptions)| {
+    let (name, options) = input;
+    let options = options.options();
+    let sanitizer = Sanitizer::new(options.clone());
+
+    let sanitized = sanitizer.sanitize(name);
+    // Nothing may be left, e.g. of `???`, but what is left passes the check.
+    assert!(
+        sanitized.is_empty() || sanitizer.is_sanitized(&sanitized),
+        "{:?} sanitized to {:?}",
+        name,
+        sanitized
+    );
+    if options.fixed_point {
+        assert_eq!(sanitizer.sanitize(&sanitized), sanitized);
+    }
+    if options.empty_fallback.is_some() && Sanitizer::try_new(options.clone()).is_ok() {
+        assert!(!sanitized.is_empty(), "{:?} sanitized to nothing", name);
+    }
+
+    let mut in_place = name.to_owned();
+    sanitizer.sanitize_in_place(&mut in_place);
+    assert_eq!(in_place, sanitized);
+
+    sanitize_filename::violations(name, (&options).into());
+    sanitize_filename::classify(name, &options);
+    sanitize_filename::sanitize_bytes(name.as_bytes(), &options);
+    let _ = sanitize_filename::try_sanitize_with_options(name, options.clone());
+    let _ = sanitize_filename::sanitize_path(name, &options);
+    let _ = sanitize_filename::sanitize_in_dir(name, name, &options);
+    let _ = name.parse::<SanitizedFileName>();
+});
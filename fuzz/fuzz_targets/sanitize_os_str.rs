@@ -0,0 +1,48 @@
+#![no_main]
+
+use std::ffi::OsString;
+use std::path::Path;
+
+use libfuzzer_sys::fuzz_target;
+
+mod options;
+
+// `bytes` as the OS could hand them over, invalid sequences included.
+#[cfg(unix)]
+fn os_string(bytes: &[u8]) -> OsString {
+    use std::os::unix::ffi::OsStringExt;
+
+    OsString::from_vec(bytes.to_vec())
+}
+
+#[cfg(windows)]
+fn os_string(bytes: &[u8]) -> OsString {
+    use std::os::windows::ffi::OsStringExt;
+
+    let wide = bytes
+        .chunks(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair.get(1).copied().unwrap_or(0)]))
+        .collect::<Vec<_>>();
+    OsString::from_wide(&wide)
+}
+
+fuzz_target!(|input: (&[u8], &[u8], options::FuzzOptions)| {
+    let (name, dir, options) = input;
+    let options = options.options();
+    let (name, dir) = (os_string(name), os_string(dir));
+
+    let sanitized = sanitize_filename::sanitize_os_str(&name, &options);
+    let sanitized = sanitized
+        .to_str()
+        .expect("sanitized names are valid Unicode");
+    assert!(
+        sanitized.is_empty()
+            || sanitize_filename::is_sanitized_with_options(sanitized, (&options).into())
+    );
+
+    if let Ok(path) = sanitize_filename::sanitize_path(Path::new(&name), &options) {
+        assert!(path.to_str().is_some());
+    }
+    let _ = sanitize_filename::sanitize_in_dir(Path::new(&dir), sanitized, &options);
+    let _ = sanitize_filename::sanitize_in_dir(Path::new(&name), sanitized, &options);
+});
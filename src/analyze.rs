@@ -0,0 +1,179 @@
+use std::fmt;
+use std::ops::Range;
+
+use crate::risk::double_extension_dot;
+use crate::{
+    is_listed_reserved, truncate_in, violations, OptionsForCheck, Violation, WINDOWS_TRAILING_CHARS,
+};
+
+/// A problem with a name, as [`analyze_with_options`] reports it: the rule it breaks, and
+/// the part of the name that breaks it, so a UI can highlight it.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Issue {
+    pub violation: Violation,
+    /// The byte range of the name involved, e.g. the character that is illegal, the
+    /// trailing dots that Windows strips or the part past the maximum length. Rules about
+    /// the whole name cover all of it, except for reserved names, which cover the part that
+    /// is reserved (`con` of `con.txt`, all of `desktop.ini`); a name that is too short gets
+    /// the empty range at its end.
+    pub range: Range<usize>,
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} at {}..{}",
+            self.violation, self.range.start, self.range.end
+        )
+    }
+}
+
+/// Analyzes `name` under the default [`OptionsForCheck`].
+pub fn analyze<S: AsRef<str>>(name: S) -> Vec<Issue> {
+    analyze_with_options(name, OptionsForCheck::default())
+}
+
+/// Lists every rule that `name` breaks under `options`, like
+/// [`violations`](crate::violations), with the part of the name involved:
+///
+/// ```
+/// use sanitize_filename::{analyze_with_options, OptionsForCheck, Violation};
+///
+/// let issues = analyze_with_options("notes?.", OptionsForCheck::new().with_windows(true));
+/// assert_eq!(issues[0].violation, Violation::IllegalChar { offset: 5, c: '?' });
+/// assert_eq!(issues[0].range, 5..6);
+/// assert_eq!(issues[1].violation, Violation::WindowsTrailing);
+/// assert_eq!(issues[1].range, 6..7);
+/// ```
+pub fn analyze_with_options<S: AsRef<str>>(name: S, options: OptionsForCheck) -> Vec<Issue> {
+    let name = name.as_ref();
+    violations(name, options.clone())
+        .into_iter()
+        .map(|violation| Issue {
+            range: range(name, &options, violation),
+            violation,
+        })
+        .collect()
+}
+
+fn range(name: &str, options: &OptionsForCheck, violation: Violation) -> Range<usize> {
+    let whole = 0..name.len();
+    let last_extension = || name.rfind('.').filter(|&idx| idx > 0).unwrap_or(name.len());
+    match violation {
+        Violation::IllegalChar { offset, c }
+        | Violation::ControlChar { offset, c }
+        | Violation::NonPortableChar { offset, c }
+        | Violation::NotAllowlisted { offset, c }
+        | Violation::NonAscii { offset, c }
+        | Violation::BidiControl { offset, c }
+        | Violation::ShellMetachar { offset, c }
+        | Violation::ExpansionChar { offset, c }
+        | Violation::Confusable { offset, c } => offset..offset + c.len_utf8(),
+        Violation::DeniedSubstring { offset, len } => offset..offset + len,
+        Violation::NonPortableEdge if name.starts_with(['.', '-']) => 0..1,
+        Violation::NonPortableEdge => name.len() - 1..name.len(),
+        Violation::HiddenPrefix | Violation::LeadingHyphen => 0..1,
+        Violation::DoubleExtension => double_extension_dot(name).unwrap_or(0)..name.len(),
+        Violation::TooLong { .. } => {
+            truncate_in(name, options.max_length, options.length_unit).len()..name.len()
+        }
+        Violation::TooShort { .. } => name.len()..name.len(),
+        // Listed names can have dots of their own, as `desktop.ini` does.
+        Violation::WindowsReserved => {
+            let listed = (options.reserved_names.iter())
+                .filter(|reserved| is_listed_reserved(name, std::slice::from_ref(reserved)))
+                .map(String::len)
+                .max();
+            0..listed.unwrap_or_else(|| name.find('.').unwrap_or(name.len()))
+        }
+        Violation::WindowsTrailing => {
+            name.trim_end_matches(WINDOWS_TRAILING_CHARS).len()..name.len()
+        }
+        Violation::DisallowedExtension | Violation::UnnormalizedExtension => {
+            last_extension()..name.len()
+        }
+        Violation::TooManyExtensions => {
+            // The dots in front of the extensions past the maximum
+            let dots: Vec<usize> = name
+                .match_indices('.')
+                .map(|(idx, _)| idx)
+                .filter(|&idx| idx > 0)
+                .collect();
+            let excess = dots.len() - options.max_extensions.unwrap_or(0).min(dots.len());
+            match dots.get(excess) {
+                Some(&end) if excess > 0 => dots[0]..end,
+                _ => dots.first().map_or(whole, |&start| start..name.len()),
+            }
+        }
+        Violation::DeniedPattern { index } => (options.deny_patterns.get(index))
+            .and_then(|pattern| pattern.find(name))
+            .unwrap_or(whole),
+        Violation::DotsOnly | Violation::WhitespaceOnly | Violation::SyncJunk => whole,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{analyze, analyze_with_options, Issue};
+    use crate::{OptionsForCheck, Violation};
+
+    #[test]
+    fn it_locates_issues() {
+        let ranges = |name: &str, options: OptionsForCheck| {
+            analyze_with_options(name, options)
+                .into_iter()
+                .map(|issue| (issue.range.start, issue.range.end))
+                .collect::<Vec<_>>()
+        };
+        let windows = OptionsForCheck::new().with_windows(true);
+
+        assert!(analyze("ok.txt").is_empty());
+        assert_eq!(
+            analyze("a/é\u{1}"),
+            vec![
+                Issue {
+                    violation: Violation::IllegalChar { offset: 1, c: '/' },
+                    range: 1..2
+                },
+                Issue {
+                    violation: Violation::ControlChar {
+                        offset: 4,
+                        c: '\u{1}'
+                    },
+                    range: 4..5
+                },
+            ]
+        );
+        assert_eq!(ranges("con.txt", windows.clone()), [(0, 3)]);
+        let listed = windows
+            .clone()
+            .with_reserved_names(["desktop", "desktop.ini"]);
+        assert_eq!(ranges("Desktop.ini", listed.clone()), [(0, 11)]);
+        assert_eq!(ranges("desktop.ini.bak", listed.clone()), [(0, 11)]);
+        assert_eq!(ranges("desktop.txt", listed.clone()), [(0, 7)]);
+        assert_eq!(ranges("con.ini", listed), [(0, 3)]);
+        assert_eq!(ranges("a. . ", windows.clone()), [(1, 5)]);
+        assert_eq!(ranges("..", windows), [(0, 2), (0, 2)]);
+        assert_eq!(
+            ranges("abcdef", OptionsForCheck::new().with_max_length(4)),
+            [(4, 6)]
+        );
+        assert_eq!(
+            ranges("ab", OptionsForCheck::new().with_min_length(3)),
+            [(2, 2)]
+        );
+        assert_eq!(
+            ranges("a.b.c.d", OptionsForCheck::new().with_max_extensions(1)),
+            [(1, 5)]
+        );
+        assert_eq!(
+            ranges("-x.", OptionsForCheck::from(&crate::Options::archival())),
+            [(0, 1), (2, 3)]
+        );
+        assert_eq!(
+            format!("{}", analyze("a:b")[0]),
+            format!("{} at 1..2", Violation::IllegalChar { offset: 1, c: ':' })
+        );
+    }
+}
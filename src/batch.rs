@@ -0,0 +1,319 @@
+use std::collections::HashSet;
+
+use crate::{
+    length_limit, sanitize_with_options, split_extension, truncate_in, Options, Sanitizer,
+};
+
+/// The suffix that tells colliding names apart, as in [`next_available_name`]; the
+/// counter starts at 2.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum SuffixStyle {
+    /// `name (2).ext`, as Windows Explorer and most browsers do.
+    #[default]
+    Parenthesized,
+    /// `name_2.ext`
+    Underscore,
+    /// `name_002.ext`, which sorts in order up to 999.
+    ZeroPadded,
+    /// `name-k3x9q2.ext`, a short hash of the name and the counter, which does not reveal
+    /// how many names collided.
+    Hash,
+}
+
+impl SuffixStyle {
+    fn suffix(&self, stem: &str, n: usize) -> String {
+        match self {
+            SuffixStyle::Parenthesized if stem.is_empty() => format!("({})", n),
+            SuffixStyle::Parenthesized => format!(" ({})", n),
+            SuffixStyle::Underscore => format!("_{}", n),
+            SuffixStyle::ZeroPadded => format!("_{:03}", n),
+            SuffixStyle::Hash => {
+                // FNV-1a, so the suffixes are the same on every platform and release.
+                let mut hash = 0xcbf2_9ce4_8422_2325u64;
+                for b in stem.bytes().chain(n.to_le_bytes()) {
+                    hash = (hash ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3);
+                }
+                let encoded = crate::Fallback::Base32.encode(&hash.to_le_bytes());
+                format!("-{}", &encoded[..6])
+            }
+        }
+    }
+}
+
+/// Sanitizes every name in `names`.
+pub fn sanitize_many<I, S>(names: I, options: &Options) -> Vec<String>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    SanitizeIter::new(names, options).collect()
+}
+
+/// A lazy adaptor that sanitizes names as they are pulled from the underlying iterator,
+/// e.g. while walking a directory or reading an archive listing.
+///
+/// ```
+/// let options = sanitize_filename::Options::default();
+/// let mut names = sanitize_filename::SanitizeIter::new(["a/b", "c?"], &options);
+/// assert_eq!(names.next().as_deref(), Some("ab"));
+/// ```
+#[derive(Clone, Debug)]
+pub struct SanitizeIter<'a, I> {
+    names: I,
+    sanitizer: Sanitizer<'a>,
+}
+
+impl<'a, I, S> SanitizeIter<'a, I>
+where
+    I: Iterator<Item = S>,
+    S: AsRef<str>,
+{
+    pub fn new<T>(names: T, options: &'a Options) -> Self
+    where
+        T: IntoIterator<IntoIter = I>,
+    {
+        SanitizeIter {
+            names: names.into_iter(),
+            sanitizer: Sanitizer::new(options.borrowed()),
+        }
+    }
+}
+
+impl<'a, I, S> Iterator for SanitizeIter<'a, I>
+where
+    I: Iterator<Item = S>,
+    S: AsRef<str>,
+{
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        self.names.next().map(|name| self.sanitizer.sanitize(name))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.names.size_hint()
+    }
+}
+
+impl<'a, I, S> ExactSizeIterator for SanitizeIter<'a, I>
+where
+    I: ExactSizeIterator<Item = S>,
+    S: AsRef<str>,
+{
+}
+
+// `name (n).ext` (or another suffix style), with the stem shortened as needed to stay
+// within the length limit; `None` if the suffix and the extension alone do not fit.
+fn numbered(name: &str, n: usize, options: &Options) -> Option<String> {
+    let (stem, extension) = split_extension(name, options);
+    let style = options.suffix_style;
+    let suffix = style.suffix(stem, n);
+    let stem = match length_limit(options) {
+        Some(limit) => {
+            let unit = options.length_unit;
+            let room = limit.checked_sub(unit.measure(&suffix) + unit.measure(extension))?;
+            let cut = truncate_in(stem, room, unit);
+            if cut.len() < stem.len() {
+                cut.trim_end()
+            } else {
+                cut
+            }
+        }
+        None => stem,
+    };
+    // ` (n)` is only spaced from a stem that is left after shortening.
+    let suffix = match style {
+        SuffixStyle::Parenthesized if stem.is_empty() => style.suffix(stem, n),
+        _ => suffix,
+    };
+    Some(format!("{}{}{}", stem, suffix, extension))
+}
+
+/// Returns `name` if `exists` says it is free, or else the first free `name (2).ext`,
+/// `name (3).ext`, ... variant (or another suffix, see [`Options::suffix_style`]).
+///
+/// `name` is expected to be sanitized already; the counter is inserted before the
+/// extension and the stem is shortened if needed so the result stays within the length
+/// limit of `options`. `exists` is typically backed by the file system or by a database
+/// of already stored names. Gives up with `None` if the first million variants are all
+/// taken, or once the counter and the extension no longer fit within the length limit.
+pub fn next_available_name<F>(name: &str, options: &Options, exists: F) -> Option<String>
+where
+    F: Fn(&str) -> bool,
+{
+    if !exists(name) {
+        return Some(name.to_owned());
+    }
+    (2..=MAX_COUNTER)
+        .map_while(|n| numbered(name, n, options))
+        .find(|candidate| !exists(candidate))
+}
+
+// The highest counter that `next_available_name` tries.
+const MAX_COUNTER: usize = 1_000_000;
+
+/// Sanitizes `name` and makes it unique against the names for which `exists` returns true;
+/// see [`next_available_name`].
+pub fn sanitize_unique<S, F>(name: S, options: &Options, exists: F) -> Option<String>
+where
+    S: AsRef<str>,
+    F: Fn(&str) -> bool,
+{
+    next_available_name(
+        &sanitize_with_options(name, options.borrowed()),
+        options,
+        exists,
+    )
+}
+
+/// Sanitizes every name in `names`, making the results unique within the batch as well as
+/// against the names for which `exists` returns true (pass `|_| false` to only consider
+/// the batch). `None` if one of them has no free variant.
+pub fn sanitize_many_unique<I, S, F>(names: I, options: &Options, exists: F) -> Option<Vec<String>>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+    F: Fn(&str) -> bool,
+{
+    let mut issued = HashSet::new();
+    let mut out = Vec::new();
+    for name in names {
+        let unique = sanitize_unique(name, options, |candidate| {
+            issued.contains(candidate) || exists(candidate)
+        })?;
+        issued.insert(unique.clone());
+        out.push(unique);
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        next_available_name, sanitize_many, sanitize_many_unique, SanitizeIter, SuffixStyle,
+    };
+
+    #[test]
+    fn it_sanitizes_collections() {
+        let options = crate::Options {
+            windows: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            sanitize_many(
+                vec!["a:b".to_owned(), "con".to_owned(), "c.".to_owned()],
+                &options
+            ),
+            vec!["ab", "", "c"]
+        );
+
+        let mut pulled = Vec::new();
+        let iter = SanitizeIter::new(
+            ["x?", "y*"].iter().inspect(|name| pulled.push(**name)),
+            &options,
+        );
+        assert_eq!(iter.len(), 2);
+        assert_eq!(iter.take(1).collect::<Vec<_>>(), vec!["x"]);
+        assert_eq!(pulled, vec!["x?"]);
+    }
+
+    #[test]
+    fn it_avoids_collisions() {
+        let options = crate::Options::default();
+        let stored = ["report.pdf", "report (2).pdf", "notes"];
+        let exists = |name: &str| stored.contains(&name);
+
+        assert_eq!(
+            next_available_name("other.pdf", &options, exists).unwrap(),
+            "other.pdf"
+        );
+        assert_eq!(
+            next_available_name("report.pdf", &options, exists).unwrap(),
+            "report (3).pdf"
+        );
+        assert_eq!(
+            next_available_name("notes", &options, exists).unwrap(),
+            "notes (2)"
+        );
+
+        assert_eq!(
+            sanitize_many_unique(["re:port.pdf", "report?.pdf", "a", "a"], &options, exists)
+                .unwrap(),
+            vec!["report (3).pdf", "report (4).pdf", "a", "a (2)"]
+        );
+
+        let long = format!("{}.txt", "x".repeat(251));
+        let next = next_available_name(&long, &options, |name| name == long).unwrap();
+        assert_eq!(next.len(), 255);
+        assert!(next.ends_with("x (2).txt"));
+
+        assert_eq!(
+            next_available_name("taken", &options, |name| name != "taken (12)"),
+            Some("taken (12)".to_owned())
+        );
+        assert_eq!(next_available_name("taken", &options, |_| true), None);
+        assert_eq!(sanitize_many_unique(["a"], &options, |_| true), None);
+    }
+
+    #[test]
+    fn it_formats_suffixes() {
+        let exists = |name: &str| name == "a.txt" || name == "a_2.txt";
+        let next = |style| {
+            next_available_name(
+                "a.txt",
+                &crate::Options::new().with_suffix_style(style),
+                exists,
+            )
+            .unwrap()
+        };
+
+        assert_eq!(next(SuffixStyle::Parenthesized), "a (2).txt");
+        assert_eq!(next(SuffixStyle::Underscore), "a_3.txt");
+        assert_eq!(next(SuffixStyle::ZeroPadded), "a_002.txt");
+        let hashed = next(SuffixStyle::Hash);
+        assert_eq!(hashed.len(), "a-xxxxxx.txt".len());
+        assert_eq!(hashed, next(SuffixStyle::Hash));
+
+        let options = crate::Options::new()
+            .with_max_length(8)
+            .with_suffix_style(SuffixStyle::ZeroPadded);
+        assert_eq!(
+            next_available_name("abcdefgh", &options, |name| name == "abcdefgh").unwrap(),
+            "abcd_002"
+        );
+
+        let options = crate::Options::new().with_max_length(8);
+        let next = next_available_name("abcd.txt", &options, |name| name == "abcd.txt");
+        assert_eq!(next.as_deref(), Some("(2).txt"));
+        assert!(crate::is_sanitized_with_options(
+            next.unwrap(),
+            (&options).into()
+        ));
+        let next = next_available_name("ab cd.txt", &options.clone().with_max_length(11), |name| {
+            name == "ab cd.txt"
+        });
+        assert_eq!(next.as_deref(), Some("ab (2).txt"));
+        let options = options
+            .with_max_length(6)
+            .with_suffix_style(SuffixStyle::ZeroPadded);
+        assert_eq!(
+            next_available_name("abc.json", &options, |name| name == "abc.json"),
+            None
+        );
+
+        let junk = format!("file.{}", "x".repeat(20));
+        let options = crate::Options::new().with_max_extension_len(Some(10));
+        assert_eq!(
+            next_available_name(&junk, &options, |name| name == junk).unwrap(),
+            format!("{} (2)", junk)
+        );
+        assert_eq!(
+            next_available_name("a.tar", &options, |name| name == "a.tar").unwrap(),
+            "a (2).tar"
+        );
+    }
+}
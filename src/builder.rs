@@ -0,0 +1,252 @@
+use crate::{
+    affixes_len, length_limit, sanitize_part, sanitize_with_options, truncate_in, Options,
+};
+
+#[derive(Clone, Debug)]
+enum Part {
+    Text { text: String, priority: u32 },
+    Separator(String),
+}
+
+/// Assembles a file name from several parts, replacing the characters of each one as it
+/// is added; the rules for whole names, such as reserved names, `min_length` and the
+/// affixes, apply once to the assembled name.
+///
+/// When the assembled name would exceed the length limit, the least important text
+/// parts are shortened (and dropped, along with their separators, if nothing is left)
+/// until it fits. Parts pushed with [`push`](FileNameBuilder::push) are considered less
+/// important the later they were added; use
+/// [`push_with_priority`](FileNameBuilder::push_with_priority) to rank them explicitly.
+/// The extension is never shortened unless it does not fit on its own.
+///
+/// ```
+/// let name = sanitize_filename::FileNameBuilder::new()
+///     .push("Quarterly report")
+///     .push_separator('-')
+///     .push("2024/Q3")
+///     .set_extension("pdf")
+///     .build();
+/// assert_eq!(name, "Quarterly report-2024Q3.pdf");
+/// ```
+#[derive(Clone, Debug)]
+pub struct FileNameBuilder<'a> {
+    options: Options<'a>,
+    parts: Vec<Part>,
+    extension: Option<String>,
+}
+
+impl<'a> Default for FileNameBuilder<'a> {
+    fn default() -> Self {
+        FileNameBuilder::with_options(Options::default())
+    }
+}
+
+impl<'a> FileNameBuilder<'a> {
+    pub fn new() -> Self {
+        FileNameBuilder::default()
+    }
+
+    pub fn with_options(options: Options<'a>) -> Self {
+        FileNameBuilder {
+            options,
+            parts: Vec::new(),
+            extension: None,
+        }
+    }
+
+    // Parts are only cleaned of characters here; whole-name rules such as reserved
+    // names, trailing dots and the affixes are applied to the assembled name in `build`.
+    fn clean(&self, part: &str) -> String {
+        sanitize_part(part, &self.options)
+    }
+
+    pub fn push<S: AsRef<str>>(&mut self, part: S) -> &mut Self {
+        self.push_with_priority(part, 0)
+    }
+
+    /// Adds a part that is truncated after all parts with a lower `priority`.
+    pub fn push_with_priority<S: AsRef<str>>(&mut self, part: S, priority: u32) -> &mut Self {
+        let text = self.clean(part.as_ref());
+        self.parts.push(Part::Text { text, priority });
+        self
+    }
+
+    // Adds `text` as is, for callers that have already vetted it.
+    pub(crate) fn push_trusted(&mut self, text: &str, priority: u32) -> &mut Self {
+        self.parts.push(Part::Text {
+            text: text.to_owned(),
+            priority,
+        });
+        self
+    }
+
+    pub fn push_separator(&mut self, separator: char) -> &mut Self {
+        let separator = self.clean(separator.encode_utf8(&mut [0; 4]));
+        self.parts.push(Part::Separator(separator));
+        self
+    }
+
+    /// Sets the extension, given with or without its leading dot.
+    pub fn set_extension<S: AsRef<str>>(&mut self, extension: S) -> &mut Self {
+        let extension = self.clean(extension.as_ref().trim_start_matches('.'));
+        self.extension = if extension.is_empty() {
+            None
+        } else {
+            Some(extension)
+        };
+        self
+    }
+
+    // Joins the non-empty text parts, keeping only separators that sit between two of
+    // them.
+    fn join(parts: &[Part]) -> String {
+        let mut out = String::new();
+        let mut separator: Option<&str> = None;
+        for part in parts {
+            match part {
+                Part::Separator(s) => {
+                    if !out.is_empty() && separator.is_none() {
+                        separator = Some(s);
+                    }
+                }
+                Part::Text { text, .. } if !text.is_empty() => {
+                    if let Some(s) = separator.take() {
+                        out.push_str(s);
+                    }
+                    out.push_str(text);
+                }
+                Part::Text { .. } => {}
+            }
+        }
+        out
+    }
+
+    pub fn build(&self) -> String {
+        let mut parts = self.parts.clone();
+        let suffix = match self.extension {
+            Some(ref extension) => format!(".{}", extension),
+            None => String::new(),
+        };
+
+        // The affixes are added by sanitizing the assembled name, so they are left room.
+        if let Some(limit) = length_limit(&self.options) {
+            let limit = limit.saturating_sub(affixes_len(&self.options));
+            let unit = self.options.length_unit;
+            loop {
+                let len = unit.measure(&Self::join(&parts)) + unit.measure(&suffix);
+                if len <= limit {
+                    break;
+                }
+                let victim = parts
+                    .iter_mut()
+                    .enumerate()
+                    .filter_map(|(idx, part)| match part {
+                        Part::Text { text, priority } if !text.is_empty() => {
+                            Some((*priority, idx, text))
+                        }
+                        _ => None,
+                    })
+                    .min_by(|a, b| a.0.cmp(&b.0).then(b.1.cmp(&a.1)));
+                match victim {
+                    Some((_, _, text)) => {
+                        let keep = unit.measure(text).saturating_sub(len - limit);
+                        let kept = truncate_in(text, keep, unit).len();
+                        text.truncate(kept);
+                    }
+                    None => break,
+                }
+            }
+        }
+
+        let name = Self::join(&parts) + &suffix;
+        sanitize_with_options(name, self.options.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FileNameBuilder;
+
+    #[test]
+    fn it_builds_names() {
+        let options = crate::Options {
+            windows: true,
+            ..Default::default()
+        };
+
+        let name = FileNameBuilder::with_options(options.clone())
+            .push("a:b")
+            .push_separator('-')
+            .push("")
+            .push_separator('-')
+            .push("c?d")
+            .set_extension(".pdf")
+            .build();
+        assert_eq!(name, "ab-cd.pdf");
+
+        let name = FileNameBuilder::with_options(options.clone())
+            .push("title")
+            .push_separator('_')
+            .push("x".repeat(300))
+            .set_extension("txt")
+            .build();
+        assert_eq!(name.len(), 255);
+        assert!(name.starts_with("title_xxx"));
+        assert!(name.ends_with("x.txt"));
+
+        let name = FileNameBuilder::with_options(options)
+            .push_with_priority("y".repeat(300), 1)
+            .push_separator('_')
+            .push("dropped")
+            .build();
+        assert_eq!(name, "y".repeat(255));
+    }
+
+    #[test]
+    fn it_applies_whole_name_rules_once() {
+        let build = |options: crate::Options| {
+            FileNameBuilder::with_options(options)
+                .push("a")
+                .push_separator('-')
+                .push("")
+                .push_separator('-')
+                .push("b")
+                .set_extension("pdf")
+                .build()
+        };
+        let options = crate::Options::new();
+
+        assert_eq!(build(options.clone()), "a-b.pdf");
+        assert_eq!(build(options.clone().with_prefix("up_")), "up_a-b.pdf");
+        assert_eq!(build(options.clone().with_min_length(3)), "a-b.pdf");
+        assert_eq!(build(options.clone().with_min_length(9)), "a-b__.pdf");
+        assert_eq!(
+            build(options.clone().with_empty_fallback("untitled")),
+            "a-b.pdf"
+        );
+        assert_eq!(
+            build(options.clone().with_prefix("up_").with_max_length(9)),
+            "up_a.pdf"
+        );
+
+        let name = FileNameBuilder::with_options(options.clone())
+            .push("a")
+            .push_separator('.')
+            .push("b")
+            .build();
+        assert_eq!(name, "a.b");
+        let name = FileNameBuilder::with_options(options.clone().with_trim(true))
+            .push(" a")
+            .push_separator(' ')
+            .push("b ")
+            .build();
+        assert_eq!(name, "a b");
+        let name = FileNameBuilder::with_options(
+            options.with_leading_dot(crate::LeadingDotPolicy::Prefix),
+        )
+        .push(".a")
+        .push(".env")
+        .build();
+        assert_eq!(name, "_.a.env");
+    }
+}
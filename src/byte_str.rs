@@ -0,0 +1,80 @@
+use bstr::{BStr, BString, ByteSlice};
+
+use crate::{
+    is_sanitized_with_options, sanitize_with_options, NotSanitized, Options, OptionsForCheck,
+    SanitizedFileName,
+};
+
+/// Sanitizes a conventionally UTF-8 byte string, as used by git-like tools.
+///
+/// Invalid UTF-8 sequences are replaced with U+FFFD before sanitizing, so the result is
+/// always valid UTF-8.
+///
+/// ```
+/// use bstr::ByteSlice;
+///
+/// let name = sanitize_filename::sanitize_bstr(b"a/b\xff.txt".as_bstr(), &Default::default());
+/// assert_eq!(name, "ab\u{FFFD}.txt");
+/// ```
+pub fn sanitize_bstr<B: AsRef<[u8]>>(name: B, options: &Options) -> BString {
+    sanitize_with_options(name.as_ref().to_str_lossy(), options.borrowed()).into()
+}
+
+/// Checks a conventionally UTF-8 byte string; names that are not valid UTF-8 are never
+/// sanitized, as [`sanitize_bstr`] would change them.
+pub fn is_sanitized_bstr<B: AsRef<[u8]>>(name: B, options: OptionsForCheck) -> bool {
+    match name.as_ref().to_str() {
+        Ok(name) => is_sanitized_with_options(name, options),
+        Err(_) => false,
+    }
+}
+
+impl AsRef<BStr> for SanitizedFileName {
+    fn as_ref(&self) -> &BStr {
+        self.as_str().as_bytes().as_bstr()
+    }
+}
+
+impl From<SanitizedFileName> for BString {
+    fn from(name: SanitizedFileName) -> BString {
+        name.into_string().into()
+    }
+}
+
+impl TryFrom<&BStr> for SanitizedFileName {
+    type Error = NotSanitized;
+
+    /// Accepts `name` if it is valid UTF-8 and sanitized according to the default
+    /// [`OptionsForCheck`].
+    fn try_from(name: &BStr) -> Result<Self, NotSanitized> {
+        match name.to_str() {
+            Ok(name) => SanitizedFileName::try_new(name, OptionsForCheck::default()),
+            Err(_) => Err(NotSanitized(name.to_str_lossy().into_owned())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bstr::{BStr, BString, ByteSlice};
+
+    use super::{is_sanitized_bstr, sanitize_bstr};
+    use crate::SanitizedFileName;
+
+    #[test]
+    fn it_handles_byte_strings() {
+        let options = crate::Options::new().with_replacement("_");
+        let check = crate::OptionsForCheck::new();
+
+        assert_eq!(sanitize_bstr(BString::from("a:b"), &options), "a_b");
+        assert_eq!(sanitize_bstr(b"\xc3(", &options), "\u{FFFD}(");
+        assert!(is_sanitized_bstr(b"ok", check.clone()));
+        assert!(!is_sanitized_bstr(b"\xc3(", check.clone()));
+        assert!(!is_sanitized_bstr("a/b", check));
+
+        let name = SanitizedFileName::try_from(b"ok.txt".as_bstr()).unwrap();
+        assert_eq!(AsRef::<BStr>::as_ref(&name), "ok.txt");
+        assert_eq!(BString::from(name), "ok.txt");
+        assert!(SanitizedFileName::try_from(b"\xff".as_bstr()).is_err());
+    }
+}
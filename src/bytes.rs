@@ -0,0 +1,171 @@
+use std::borrow::Cow;
+use std::str;
+
+use crate::{is_control_char, is_illegal_char, length_limit, Options};
+
+/// Sanitizes a name as raw bytes, as Unix filenames are, without decoding it: bytes that
+/// are not ASCII are kept as they are, valid UTF-8 or not, so that backup and archive
+/// tools can keep names in other encodings intact.
+///
+/// Only the rules about bytes apply: [`ILLEGAL_CHARS`](crate::ILLEGAL_CHARS) and the ASCII
+/// `extra_illegal` characters (but not those in `allow`) get the `replacement`, C0 control
+/// bytes the `control_replacement`, names of dots only the `reserved_replacement`, and
+/// names are cut to `max_length` bytes (unless `truncation` is `None`), without splitting
+/// a UTF-8 sequence. The rules that need to know the characters, such as `charset` and
+/// the Windows rules, are left to [`sanitize_with_options`](crate::sanitize_with_options).
+/// Should a replacement be rejected itself, the name is sanitized again without
+/// replacements.
+///
+/// ```
+/// let options = sanitize_filename::Options::new().with_replacement("_");
+/// let name = sanitize_filename::sanitize_bytes(b"r\xe9sum\xe9/2024.txt", &options);
+/// assert_eq!(&name[..], b"r\xe9sum\xe9_2024.txt");
+/// ```
+pub fn sanitize_bytes<'n>(name: &'n [u8], options: &Options) -> Cow<'n, [u8]> {
+    let replacement: &str = &options.replacement;
+    let control_replacement = options
+        .control_replacement
+        .as_deref()
+        .unwrap_or(replacement);
+    let reserved_replacement = options
+        .reserved_replacement
+        .as_deref()
+        .unwrap_or(replacement);
+
+    let sanitized = sanitize_pass(
+        name,
+        options,
+        [replacement, control_replacement, reserved_replacement],
+    );
+    if is_clean(&sanitized, options) {
+        return sanitized;
+    }
+    Cow::Owned(sanitize_pass(&sanitized, options, [""; 3]).into_owned())
+}
+
+// The replacements are those of illegal bytes, control bytes and reserved names.
+fn sanitize_pass<'n>(name: &'n [u8], options: &Options, replacements: [&str; 3]) -> Cow<'n, [u8]> {
+    let [replacement, control_replacement, reserved_replacement] = replacements;
+    let mut name = if name.iter().any(|&b| is_rejected(b, options)) {
+        let mut replaced = Vec::with_capacity(name.len());
+        for &b in name {
+            if is_control(b) {
+                replaced.extend_from_slice(control_replacement.as_bytes());
+            } else if is_rejected(b, options) {
+                replaced.extend_from_slice(replacement.as_bytes());
+            } else {
+                replaced.push(b);
+            }
+        }
+        Cow::Owned(replaced)
+    } else {
+        Cow::Borrowed(name)
+    };
+
+    if is_dots(&name) {
+        name = Cow::Owned(reserved_replacement.as_bytes().to_vec());
+    }
+    if let Some(max) = length_limit(options) {
+        if name.len() > max {
+            let end = utf8_boundary(&name, max);
+            name = match name {
+                Cow::Borrowed(name) => Cow::Borrowed(&name[..end]),
+                Cow::Owned(mut name) => {
+                    name.truncate(end);
+                    Cow::Owned(name)
+                }
+            };
+        }
+    }
+    name
+}
+
+fn is_clean(name: &[u8], options: &Options) -> bool {
+    !name.iter().any(|&b| is_rejected(b, options))
+        && !is_dots(name)
+        && length_limit(options).map_or(true, |max| name.len() <= max)
+}
+
+fn is_dots(name: &[u8]) -> bool {
+    !name.is_empty() && name.iter().all(|&b| b == b'.')
+}
+
+fn is_control(b: u8) -> bool {
+    b.is_ascii() && is_control_char(char::from(b))
+}
+
+fn is_rejected(b: u8, options: &Options) -> bool {
+    if !b.is_ascii() {
+        return false;
+    }
+    let c = char::from(b);
+    is_control_char(c)
+        || (is_illegal_char(c) || options.extra_illegal.contains(&c)) && !options.allow.contains(&c)
+}
+
+// The largest end at or below `max` that does not split a valid UTF-8 sequence; bytes
+// that are not part of one can be cut anywhere.
+fn utf8_boundary(name: &[u8], max: usize) -> usize {
+    // Only a continuation byte can be in the middle of a sequence.
+    if name[max] & 0xc0 != 0x80 {
+        return max;
+    }
+    let Some(start) = (max.saturating_sub(3)..max)
+        .rev()
+        .find(|&idx| name[idx] & 0xc0 != 0x80)
+    else {
+        return max;
+    };
+    let end = name.len().min(start + 4);
+    let valid = match str::from_utf8(&name[start..end]) {
+        Ok(valid) => valid,
+        Err(err) => str::from_utf8(&name[start..start + err.valid_up_to()]).unwrap_or_default(),
+    };
+    match valid.chars().next() {
+        Some(c) if start + c.len_utf8() > max => start,
+        _ => max,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use super::sanitize_bytes;
+
+    #[test]
+    fn it_sanitizes_bytes() {
+        let options = crate::Options::new().with_replacement("_");
+
+        assert!(matches!(
+            sanitize_bytes(b"caf\xe9.txt", &options),
+            Cow::Borrowed(b"caf\xe9.txt")
+        ));
+        assert_eq!(&sanitize_bytes(b"a/\xff\x01b", &options)[..], b"a_\xff_b");
+        assert_eq!(
+            &sanitize_bytes(b"a\x01b", &options.clone().with_control_replacement(""))[..],
+            b"ab"
+        );
+        assert_eq!(&sanitize_bytes(b"..", &options)[..], b"_");
+        assert_eq!(
+            &sanitize_bytes(b"a:b", &options.clone().with_replacement("/"))[..],
+            b"ab"
+        );
+        assert_eq!(
+            &sanitize_bytes(b"a:b", &options.clone().with_allow(&[':']))[..],
+            b"a:b"
+        );
+
+        let short = options.clone().with_max_length(4);
+        assert_eq!(&sanitize_bytes("abcé".as_bytes(), &short)[..], b"abc");
+        assert_eq!(
+            &sanitize_bytes("abé!".as_bytes(), &short)[..],
+            "abé".as_bytes()
+        );
+        assert_eq!(
+            &sanitize_bytes(b"\xff\xff\xff\xff\xff", &short)[..],
+            b"\xff\xff\xff\xff"
+        );
+        assert_eq!(&sanitize_bytes(b"abc\xc3\xff", &short)[..], b"abc\xc3");
+    }
+}
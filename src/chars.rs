@@ -0,0 +1,133 @@
+use crate::lookup::Lookup;
+use crate::{replace_each_char, Options};
+
+/// Sanitizes a stream of characters lazily, for building names without an intermediate
+/// `String`, e.g. with `String::extend` or straight into a fixed buffer.
+///
+/// Only the rules that look at one character at a time apply: illegal, control and
+/// `extra_illegal` characters, `charset`, `allowlist` and `ascii_only` (with `allow`), the
+/// character-level security rules (bidi controls, shell metacharacters, expansion
+/// characters and confusables), and `replace_whitespace`, with the replacements and
+/// replacer of the options. The rules about the whole name need all of it and are left
+/// out: reserved and Windows device names, trailing dots and spaces, leading dots and
+/// hyphens, trimming and collapsing, deny patterns, extensions, truncation, minimum
+/// lengths and affixes. Collect the characters and run them through
+/// [`sanitize_with_options`](crate::sanitize_with_options) when those matter, or check
+/// the result with [`is_sanitized_with_options`](crate::is_sanitized_with_options).
+///
+/// The substitute of each character is buffered until it has been yielded, in one buffer
+/// that is reused for the whole name.
+///
+/// ```
+/// use sanitize_filename::{Options, SanitizedChars};
+///
+/// let options = Options::new().with_replacement("_");
+/// let mut name = String::from("report-");
+/// name.extend(SanitizedChars::new("2024/05: final?".chars(), &options));
+/// assert_eq!(name, "report-2024_05_ final_");
+/// ```
+#[derive(Clone, Debug)]
+pub struct SanitizedChars<'a, I> {
+    chars: I,
+    options: &'a Options<'a>,
+    lookup: Lookup,
+    // The rest of the substitute of the last character, from `next` on.
+    pending: String,
+    next: usize,
+}
+
+impl<'a, I: Iterator<Item = char>> SanitizedChars<'a, I> {
+    pub fn new(chars: I, options: &'a Options<'a>) -> Self {
+        SanitizedChars {
+            chars,
+            options,
+            lookup: Lookup::from(options),
+            pending: String::new(),
+            next: 0,
+        }
+    }
+}
+
+impl<'a, I: Iterator<Item = char>> Iterator for SanitizedChars<'a, I> {
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        loop {
+            if let Some(c) = self.pending[self.next..].chars().next() {
+                self.next += c.len_utf8();
+                return Some(c);
+            }
+            let c = self.chars.next()?;
+            let mut buffer = [0; 4];
+            let replaced = replace_each_char(
+                c.encode_utf8(&mut buffer),
+                self.options,
+                &self.lookup,
+                false,
+            );
+            let whitespace = self.options.replace_whitespace;
+            self.pending.clear();
+            self.next = 0;
+            self.pending
+                .extend(replaced.chars().map(|c| match whitespace {
+                    Some(separator) if c.is_whitespace() => separator,
+                    _ => c,
+                }));
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        // Characters can be replaced with nothing, or with any number of others.
+        let pending = self.pending[self.next..].chars().count();
+        (pending, None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SanitizedChars;
+
+    #[test]
+    fn it_matches_the_character_rules() {
+        let options = crate::Options::new()
+            .with_replacement("__")
+            .with_control_replacement("")
+            .with_replace_whitespace(Some('-'))
+            .with_strip_bidi(true);
+        let sanitize = |name: &str| SanitizedChars::new(name.chars(), &options).collect::<String>();
+
+        for name in [
+            "a/b:c",
+            "x\u{1}y",
+            "two words",
+            "\u{202e}txt.exe",
+            "plain.txt",
+        ] {
+            assert_eq!(
+                sanitize(name),
+                crate::sanitize_with_options(name, options.clone()),
+                "{:?}",
+                name
+            );
+        }
+        // Whole-name rules are left out.
+        assert_eq!(sanitize(".."), "..");
+        #[cfg(feature = "unicode")]
+        assert_eq!(
+            SanitizedChars::new(
+                "café ½".chars(),
+                &crate::Options::new().with_charset(crate::Charset::Portable)
+            )
+            .collect::<String>(),
+            "cafe"
+        );
+        assert_eq!(
+            SanitizedChars::new(
+                "a:b".chars(),
+                &crate::Options::new().with_hex_escape_replacer()
+            )
+            .collect::<String>(),
+            "a%3Ab"
+        );
+    }
+}
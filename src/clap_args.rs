@@ -0,0 +1,357 @@
+use crate::{
+    Charset, ExtensionNormalization, Fallback, LeadingDotPolicy, LengthUnit, MinLengthStrategy,
+    Options, OptionsForCheck, OptionsOverride, Replacer, RuleSet, Security, SuffixStyle,
+    TruncationStrategy,
+};
+
+/// The sanitization flags of the `sanitize-filename` binary, for embedding into other
+/// clap-based command lines with `#[command(flatten)]`.
+///
+/// Flags that are not given leave the corresponding [`Options`] default untouched.
+#[derive(Clone, Debug, Default, PartialEq, Eq, clap::Args)]
+pub struct SanitizeArgs {
+    /// Replacement for removed characters and names
+    #[arg(short = 'r', long, visible_alias = "replace", value_name = "STR")]
+    pub replacement: Option<String>,
+    /// Replacement for control characters
+    #[arg(long, value_name = "STR")]
+    pub control_replacement: Option<String>,
+    /// Replacement for reserved names such as `..` and `con`
+    #[arg(long, value_name = "STR")]
+    pub reserved_replacement: Option<String>,
+    /// Replacement for the trailing dots and spaces that Windows strips
+    #[arg(long, value_name = "STR")]
+    pub trailing_replacement: Option<String>,
+    /// The name to use instead when nothing would be left of a name
+    #[arg(long, value_name = "NAME")]
+    pub empty_fallback: Option<String>,
+    /// Start every file name with this, within --max-length
+    #[arg(long, value_name = "STR")]
+    pub prefix: Option<String>,
+    /// End every file name with this, within --max-length
+    #[arg(long, value_name = "STR")]
+    pub suffix: Option<String>,
+    /// Handle file names for Windows
+    #[arg(long, overrides_with = "no_windows")]
+    pub windows: bool,
+    /// Do not apply the Windows-only rules
+    #[arg(long, overrides_with = "windows")]
+    pub no_windows: bool,
+    /// Replace Windows device names such as `con`, even without --windows
+    #[arg(long, overrides_with = "no_windows_reserved_names")]
+    pub windows_reserved_names: bool,
+    /// Do not replace Windows device names, even with --windows
+    #[arg(long, overrides_with = "windows_reserved_names")]
+    pub no_windows_reserved_names: bool,
+    /// Replace the trailing dots and spaces that Windows strips, even without --windows
+    #[arg(long, overrides_with = "no_windows_trailing")]
+    pub windows_trailing: bool,
+    /// Do not replace trailing dots and spaces, even with --windows
+    #[arg(long, overrides_with = "windows_trailing")]
+    pub no_windows_trailing: bool,
+    /// Truncate file names (to 255 bytes unless --max-length is given)
+    #[arg(long, overrides_with = "no_truncate")]
+    pub truncate: bool,
+    /// Do not truncate file names, as --truncation none
+    #[arg(long, overrides_with = "truncate")]
+    pub no_truncate: bool,
+    /// Which version of the rules to apply
+    #[arg(long, value_enum, value_name = "RULE_SET")]
+    pub rule_set: Option<RuleSet>,
+    /// What to produce when nothing of a name survives sanitization
+    #[arg(long, value_enum, value_name = "FALLBACK")]
+    pub fallback: Option<Fallback>,
+    /// Replace names that cloud sync clients refuse, such as desktop.ini and .DS_Store
+    #[arg(long)]
+    pub block_sync_junk: bool,
+    /// Replace each run of consecutive replacements with a single one
+    #[arg(long)]
+    pub collapse_replacements: bool,
+    /// Replace each run of whitespace with a single space
+    #[arg(long)]
+    pub collapse_whitespace: bool,
+    /// Trim leading and trailing whitespace, on every platform
+    #[arg(long)]
+    pub trim: bool,
+    /// Sanitize each output again until it no longer changes
+    #[arg(long)]
+    pub fixed_point: bool,
+    /// Rewrite spaces and other whitespace to this character
+    #[arg(long, value_name = "CHAR")]
+    pub replace_whitespace: Option<char>,
+    /// Replace punctuation with its fullwidth look-alike
+    #[arg(long)]
+    pub fullwidth: bool,
+    /// Replace characters with the %XX escapes of their bytes
+    #[arg(long, conflicts_with = "fullwidth")]
+    pub hex_escape: bool,
+    /// The length to truncate file names to (in bytes unless --length-unit is given)
+    #[arg(long, value_name = "LENGTH")]
+    pub max_length: Option<usize>,
+    /// How --max-length is counted
+    #[arg(long, value_enum, value_name = "UNIT")]
+    pub length_unit: Option<LengthUnit>,
+    /// Which part of long file names to cut, if any
+    #[arg(long, value_enum, value_name = "STRATEGY")]
+    pub truncation: Option<TruncationStrategy>,
+    /// The length to bring shorter file names up to (in bytes unless --length-unit is given)
+    #[arg(long, value_name = "LENGTH")]
+    pub min_length: Option<usize>,
+    /// How file names shorter than --min-length are lengthened
+    #[arg(long, value_enum, value_name = "STRATEGY")]
+    pub min_length_strategy: Option<MinLengthStrategy>,
+    /// What to do with a leading dot, which hides files
+    #[arg(long, value_enum, value_name = "POLICY")]
+    pub leading_dot: Option<LeadingDotPolicy>,
+    /// Which characters may appear in file names
+    #[arg(long, value_enum, value_name = "CHARSET")]
+    pub charset: Option<Charset>,
+    /// Also replace each of these characters
+    #[arg(long, value_name = "CHARS")]
+    pub extra_illegal: Option<String>,
+    /// Keep each of these characters despite --charset and --security
+    #[arg(long, value_name = "CHARS")]
+    pub allow: Option<String>,
+    /// Replace every character but these (and --allow)
+    #[arg(long, value_name = "CHARS")]
+    pub allowlist: Option<String>,
+    /// Replace every character that is not ASCII (but --allow)
+    #[arg(long)]
+    pub ascii_only: bool,
+    /// With --windows, also replace this name, as `con` is
+    #[arg(long = "reserved-name", value_name = "NAME")]
+    pub reserved_names: Vec<String>,
+    /// Keep only this extension (repeatable), stripping others
+    #[arg(long = "allow-extension", value_name = "EXT")]
+    pub allowed_extensions: Vec<String>,
+    /// With --allow-extension, the extension that replaces the others instead
+    #[arg(long, value_name = "EXT")]
+    pub default_extension: Option<String>,
+    /// How to spell extensions
+    #[arg(long, value_enum, value_name = "NORMALIZATION")]
+    pub normalize_extension: Option<ExtensionNormalization>,
+    /// Keep at most this many extensions, replacing the dots in front of the others
+    #[arg(long, value_name = "N")]
+    pub max_extensions: Option<usize>,
+    /// Replace the matches of this regular expression
+    #[cfg(feature = "regex")]
+    #[arg(long = "deny", value_name = "REGEX", value_parser = crate::DenyPattern::new)]
+    pub deny_patterns: Vec<crate::DenyPattern>,
+    /// Which bundle of security rules to apply
+    #[arg(long, value_enum, value_name = "PRESET")]
+    pub security: Option<Security>,
+    /// How to tell colliding names apart
+    #[arg(long, value_enum, value_name = "STYLE")]
+    pub suffix_style: Option<SuffixStyle>,
+    /// Treat longer "extensions" as part of the stem when keeping extensions
+    #[arg(long, value_name = "CHARS")]
+    pub max_extension_len: Option<usize>,
+}
+
+fn flag(on: bool, off: bool) -> Option<bool> {
+    match (on, off) {
+        (true, _) => Some(true),
+        (_, true) => Some(false),
+        _ => None,
+    }
+}
+
+impl SanitizeArgs {
+    /// The flags that were given, as overrides for [`Options::merge`].
+    pub fn to_override(&self) -> OptionsOverride<'_> {
+        let overrides = OptionsOverride {
+            windows: flag(self.windows, self.no_windows),
+            windows_reserved_names: flag(
+                self.windows_reserved_names,
+                self.no_windows_reserved_names,
+            )
+            .map(Some),
+            windows_trailing: flag(self.windows_trailing, self.no_windows_trailing).map(Some),
+            replacement: self.replacement.as_deref().map(Into::into),
+            control_replacement: self.control_replacement.as_deref().map(|r| Some(r.into())),
+            reserved_replacement: self.reserved_replacement.as_deref().map(|r| Some(r.into())),
+            trailing_replacement: self.trailing_replacement.as_deref().map(|r| Some(r.into())),
+            empty_fallback: self.empty_fallback.as_deref().map(|r| Some(r.into())),
+            prefix: self.prefix.as_deref().map(Into::into),
+            suffix: self.suffix.as_deref().map(Into::into),
+            rule_set: self.rule_set,
+            fallback: self.fallback,
+            block_sync_junk: self.block_sync_junk.then_some(true),
+            collapse_replacements: self.collapse_replacements.then_some(true),
+            collapse_whitespace: self.collapse_whitespace.then_some(true),
+            trim: self.trim.then_some(true),
+            fixed_point: self.fixed_point.then_some(true),
+            replacer: (self.fullwidth.then(Replacer::fullwidth))
+                .or_else(|| self.hex_escape.then(Replacer::hex_escape)),
+            replace_whitespace: self.replace_whitespace.map(Some),
+            max_length: self.max_length,
+            length_unit: self.length_unit,
+            truncation: self.truncation(),
+            min_length: self.min_length.map(Some),
+            min_length_strategy: self.min_length_strategy,
+            leading_dot: self.leading_dot,
+            charset: self.charset,
+            extra_illegal: self
+                .extra_illegal
+                .as_ref()
+                .map(|chars| chars.chars().collect()),
+            allow: self.allow.as_ref().map(|chars| chars.chars().collect()),
+            allowlist: (self.allowlist.as_ref()).map(|chars| Some(chars.chars().collect())),
+            ascii_only: self.ascii_only.then_some(true),
+            reserved_names: (!self.reserved_names.is_empty()).then(|| {
+                self.reserved_names
+                    .iter()
+                    .map(|name| name.as_str().into())
+                    .collect()
+            }),
+            allowed_extensions: (!self.allowed_extensions.is_empty()).then(|| {
+                Some(
+                    (self.allowed_extensions.iter())
+                        .map(|extension| extension.as_str().into())
+                        .collect(),
+                )
+            }),
+            default_extension: (self.default_extension.as_deref()).map(|ext| Some(ext.into())),
+            normalize_extension: self.normalize_extension,
+            max_extensions: self.max_extensions.map(Some),
+            #[cfg(feature = "regex")]
+            deny_patterns: (!self.deny_patterns.is_empty()).then(|| self.deny_patterns[..].into()),
+            suffix_style: self.suffix_style,
+            max_extension_len: self.max_extension_len.map(Some),
+            ..OptionsOverride::new()
+        };
+        match self.security {
+            Some(security) => overrides.with_security(security),
+            None => overrides,
+        }
+    }
+
+    // --no-truncate wins over --truncation, and --truncate picks cutting the end unless
+    // --truncation says otherwise.
+    fn truncation(&self) -> Option<TruncationStrategy> {
+        match (flag(self.truncate, self.no_truncate), self.truncation) {
+            (Some(false), _) => Some(TruncationStrategy::None),
+            (Some(true), None | Some(TruncationStrategy::None)) => Some(TruncationStrategy::CutEnd),
+            (_, truncation) => truncation,
+        }
+    }
+
+    /// Applies the given flags on top of `options`.
+    pub fn apply_to<'a>(&'a self, options: &mut Options<'a>) {
+        *options = std::mem::take(options).merge(self.to_override());
+    }
+
+    pub fn to_options(&self) -> Options<'_> {
+        let mut options = Options::default();
+        self.apply_to(&mut options);
+        options
+    }
+
+    pub fn to_check_options(&self) -> OptionsForCheck {
+        let mut options = OptionsForCheck::default();
+        if let Some(windows) = flag(self.windows, self.no_windows) {
+            options.windows = windows;
+        }
+        options.windows_reserved_names =
+            flag(self.windows_reserved_names, self.no_windows_reserved_names);
+        options.windows_trailing = flag(self.windows_trailing, self.no_windows_trailing);
+        if let Some(truncation) = self.truncation() {
+            options.truncation = truncation;
+        }
+        if let Some(rule_set) = self.rule_set {
+            options.rule_set = rule_set;
+        }
+        if self.block_sync_junk {
+            options.block_sync_junk = true;
+        }
+        if let Some(max_length) = self.max_length {
+            options.max_length = max_length;
+        }
+        if let Some(min_length) = self.min_length {
+            options.min_length = Some(min_length);
+        }
+        if let Some(length_unit) = self.length_unit {
+            options.length_unit = length_unit;
+        }
+        if let Some(charset) = self.charset {
+            options.charset = charset;
+        }
+        if let Some(leading_dot) = self.leading_dot {
+            options.leading_dot = leading_dot;
+        }
+        if let Some(extra_illegal) = &self.extra_illegal {
+            options.extra_illegal = extra_illegal.chars().collect();
+        }
+        if let Some(allow) = &self.allow {
+            options.allow = allow.chars().collect();
+        }
+        if let Some(allowlist) = &self.allowlist {
+            options.allowlist = Some(allowlist.chars().collect());
+        }
+        if self.ascii_only {
+            options.ascii_only = true;
+        }
+        if !self.reserved_names.is_empty() {
+            options.reserved_names = self.reserved_names.clone();
+        }
+        if !self.allowed_extensions.is_empty() {
+            let default = self.default_extension.iter();
+            options.allowed_extensions = Some(
+                self.allowed_extensions
+                    .iter()
+                    .chain(default)
+                    .cloned()
+                    .collect(),
+            );
+        }
+        if let Some(normalize_extension) = self.normalize_extension {
+            options.normalize_extension = normalize_extension;
+        }
+        if self.max_extensions.is_some() {
+            options.max_extensions = self.max_extensions;
+        }
+        #[cfg(feature = "regex")]
+        if !self.deny_patterns.is_empty() {
+            options.deny_patterns = self.deny_patterns.clone();
+        }
+        if let Some(security) = self.security {
+            options = options.with_security(security);
+        }
+        options
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    #[derive(Parser)]
+    struct Cli {
+        #[command(flatten)]
+        sanitize: super::SanitizeArgs,
+    }
+
+    #[test]
+    fn it_maps_flags() {
+        let cli = Cli::parse_from([
+            "app",
+            "--replace",
+            "_",
+            "--windows",
+            "--no-truncate",
+            "--rule-set",
+            "v1",
+        ]);
+        let options = cli.sanitize.to_options();
+        assert_eq!(options.replacement, "_");
+        assert!(options.windows);
+        assert_eq!(options.truncation, crate::TruncationStrategy::None);
+        assert_eq!(options.rule_set, crate::RuleSet::V1);
+
+        let cli = Cli::parse_from(["app", "--windows", "--no-windows"]);
+        assert!(!cli.sanitize.to_check_options().windows);
+
+        let cli = Cli::parse_from(["app"]);
+        assert_eq!(cli.sanitize.to_options().replacement, "");
+    }
+}
@@ -0,0 +1,160 @@
+use std::borrow::Cow;
+
+use crate::os_str::decode_utf8;
+use crate::url::percent_decode;
+use crate::{Options, SanitizedFileName};
+
+/// Extracts the file name that a `Content-Disposition` header value suggests, as in
+/// RFC 6266, and sanitizes it:
+///
+/// ```
+/// use sanitize_filename::{filename_from_content_disposition, Options};
+///
+/// let options = Options::new().with_replacement("_");
+/// let header = r#"attachment; filename="Q3 report?.pdf"; filename*=UTF-8''Q3%20r%C3%A9sum%C3%A9.pdf"#;
+/// let name = filename_from_content_disposition(header, &options).unwrap();
+/// assert_eq!(name, "Q3 résumé.pdf");
+/// ```
+///
+/// The `filename*` parameter (RFC 5987, in UTF-8 or ISO-8859-1) wins over `filename`,
+/// which can be a token or a quoted string. Only the part after the last `/` or `\` is
+/// kept, as browsers do, so the name cannot carry directories. Returns `None` if there is
+/// no name, or nothing is left of it.
+pub fn filename_from_content_disposition(
+    header_value: &str,
+    options: &Options,
+) -> Option<SanitizedFileName> {
+    let mut filename = None;
+    let mut extended = None;
+    for (name, value) in parameters(header_value) {
+        if name.eq_ignore_ascii_case("filename*") {
+            extended = extended.or_else(|| decode_ext_value(&value, &options.replacement));
+        } else if name.eq_ignore_ascii_case("filename") {
+            filename = filename.or(Some(value));
+        }
+    }
+
+    let name = extended.or(filename.map(Cow::into_owned))?;
+    let name = name.rsplit(['/', '\\']).next().unwrap_or_default();
+    let name = SanitizedFileName::new(name, options);
+    (!name.is_empty()).then_some(name)
+}
+
+// The parameters after the disposition type, with their quoted strings unescaped.
+fn parameters(header_value: &str) -> Vec<(&str, Cow<'_, str>)> {
+    let mut parameters = Vec::new();
+    let mut rest = match header_value.find(';') {
+        Some(idx) => &header_value[idx + 1..],
+        None => return parameters,
+    };
+    while !rest.is_empty() {
+        let end = rest.find([';', '=']).unwrap_or(rest.len());
+        let name = rest[..end].trim();
+        if !rest[end..].starts_with('=') {
+            rest = rest.get(end + 1..).unwrap_or_default();
+            continue;
+        }
+        let value = rest[end + 1..].trim_start();
+        let (value, after) = match value.strip_prefix('"') {
+            Some(quoted) => unquote(quoted),
+            None => {
+                let end = value.find(';').unwrap_or(value.len());
+                (Cow::Borrowed(value[..end].trim_end()), &value[end..])
+            }
+        };
+        parameters.push((name, value));
+        // Skip whatever follows the value up to the next parameter.
+        rest = match after.find(';') {
+            Some(idx) => &after[idx + 1..],
+            None => "",
+        };
+    }
+    parameters
+}
+
+// The contents of the quoted string that `quoted` starts, after the opening quote, and
+// what follows it.
+fn unquote(quoted: &str) -> (Cow<'_, str>, &str) {
+    let mut unquoted = String::new();
+    let mut escaped = false;
+    for (idx, c) in quoted.char_indices() {
+        match c {
+            _ if escaped => {
+                unquoted.push(c);
+                escaped = false;
+            }
+            '\\' => escaped = true,
+            '"' => return (Cow::Owned(unquoted), &quoted[idx + 1..]),
+            _ => unquoted.push(c),
+        }
+    }
+    // An unterminated string runs to the end.
+    (Cow::Owned(unquoted), "")
+}
+
+// Decodes a `charset'language'value` of RFC 5987, or `None` for other charsets.
+fn decode_ext_value(value: &str, replacement: &str) -> Option<String> {
+    let mut parts = value.splitn(3, '\'');
+    let (charset, _language, encoded) = (parts.next()?, parts.next()?, parts.next()?);
+    let bytes = percent_decode(encoded);
+    if charset.eq_ignore_ascii_case("utf-8") {
+        Some(decode_utf8(&bytes, replacement))
+    } else if charset.eq_ignore_ascii_case("iso-8859-1") {
+        Some(bytes.into_iter().map(char::from).collect())
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::filename_from_content_disposition;
+
+    #[test]
+    fn it_parses_content_disposition() {
+        let options = crate::Options::new().with_replacement("_");
+        let name = |header: &str| {
+            filename_from_content_disposition(header, &options).map(|name| name.into_string())
+        };
+
+        assert_eq!(
+            name("attachment; filename=report.pdf").as_deref(),
+            Some("report.pdf")
+        );
+        assert_eq!(
+            name(r#"attachment; filename="a \"b\"; c.txt"; size=3"#).as_deref(),
+            Some("a _b_; c.txt")
+        );
+        assert_eq!(
+            name("inline; FILENAME*=utf-8'en'%E2%82%AC%20rates.csv; filename=rates.csv").as_deref(),
+            Some("€ rates.csv")
+        );
+        assert_eq!(
+            name("attachment; filename*=iso-8859-1''caf%E9.txt").as_deref(),
+            Some("café.txt")
+        );
+        assert_eq!(
+            name("attachment; filename*=koi8-r''x; filename=fallback.txt").as_deref(),
+            Some("fallback.txt")
+        );
+        assert_eq!(
+            name(r#"attachment; filename="../../etc/passwd""#).as_deref(),
+            Some("passwd")
+        );
+        assert_eq!(
+            name("attachment; filename*=UTF-8''C%3A%5CUsers%5Cevil.exe").as_deref(),
+            Some("evil.exe")
+        );
+        assert_eq!(
+            name("attachment; filename*=UTF-8''a%FFb").as_deref(),
+            Some("a_b")
+        );
+        assert_eq!(name("attachment").as_deref(), None);
+        assert_eq!(name("attachment; size=3; filename=").as_deref(), None);
+        assert_eq!(name(r#"attachment; filename="??""#).as_deref(), Some("__"));
+        assert_eq!(
+            name(r#"attachment; filename="unterminated"#).as_deref(),
+            Some("unterminated")
+        );
+    }
+}
@@ -0,0 +1,221 @@
+use std::borrow::Cow;
+
+use crate::{sanitize_with_options, Options};
+
+/// The encoding of a file name given as raw bytes, for [`sanitize_bytes_with_encoding`].
+///
+/// Any [`encoding_rs::Encoding`] converts into a `NameEncoding`, e.g.
+/// `encoding_rs::SHIFT_JIS` for names from Japanese Windows (CP932).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum NameEncoding {
+    Utf8,
+    /// The original IBM PC code page, which zip tools use for names without the UTF-8 flag.
+    Cp437,
+    Other(&'static encoding_rs::Encoding),
+}
+
+impl NameEncoding {
+    /// Decodes `bytes`, replacing malformed sequences with U+FFFD.
+    pub fn decode<'b>(&self, bytes: &'b [u8]) -> Cow<'b, str> {
+        match *self {
+            NameEncoding::Utf8 => String::from_utf8_lossy(bytes),
+            NameEncoding::Cp437 if bytes.is_ascii() => {
+                Cow::Borrowed(std::str::from_utf8(bytes).unwrap())
+            }
+            NameEncoding::Cp437 => Cow::Owned(
+                bytes
+                    .iter()
+                    .map(|&b| match b {
+                        0x00..=0x7F => b as char,
+                        _ => CP437_HIGH[usize::from(b - 0x80)],
+                    })
+                    .collect(),
+            ),
+            NameEncoding::Other(encoding) => encoding.decode_without_bom_handling(bytes).0,
+        }
+    }
+}
+
+impl From<&'static encoding_rs::Encoding> for NameEncoding {
+    fn from(encoding: &'static encoding_rs::Encoding) -> Self {
+        NameEncoding::Other(encoding)
+    }
+}
+
+// 0x80..=0xFF of CP437; the low half is ASCII (0x01..=0x1F are kept as control characters
+// rather than decoded to their glyphs, so that they are sanitized away).
+const CP437_HIGH: [char; 128] = [
+    'Ç', 'ü', 'é', 'â', 'ä', 'à', 'å', 'ç', 'ê', 'ë', 'è', 'ï', 'î', 'ì', 'Ä', 'Å', //
+    'É', 'æ', 'Æ', 'ô', 'ö', 'ò', 'û', 'ù', 'ÿ', 'Ö', 'Ü', '¢', '£', '¥', '₧', 'ƒ', //
+    'á', 'í', 'ó', 'ú', 'ñ', 'Ñ', 'ª', 'º', '¿', '⌐', '¬', '½', '¼', '¡', '«', '»', //
+    '░', '▒', '▓', '│', '┤', '╡', '╢', '╖', '╕', '╣', '║', '╗', '╝', '╜', '╛', '┐', //
+    '└', '┴', '┬', '├', '─', '┼', '╞', '╟', '╚', '╔', '╩', '╦', '╠', '═', '╬', '╧', //
+    '╨', '╤', '╥', '╙', '╘', '╒', '╓', '╫', '╪', '┘', '┌', '█', '▄', '▌', '▐', '▀', //
+    'α', 'ß', 'Γ', 'π', 'Σ', 'σ', 'µ', 'τ', 'Φ', 'Θ', 'Ω', 'δ', '∞', 'φ', 'ε', '∩', //
+    '≡', '±', '≥', '≤', '⌠', '⌡', '÷', '≈', '°', '∙', '·', '√', 'ⁿ', '²', '■', '\u{A0}',
+];
+
+/// Decodes a file name stored as `bytes` in `encoding`, such as the name of a zip entry
+/// created on Windows, and sanitizes it.
+pub fn sanitize_bytes_with_encoding<E: Into<NameEncoding>>(
+    bytes: &[u8],
+    encoding: E,
+    options: &Options,
+) -> String {
+    sanitize_with_options(encoding.into().decode(bytes), options.borrowed())
+}
+
+/// The result of [`detect_encoding`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EncodingGuess {
+    pub encoding: NameEncoding,
+    /// From 0 (a coin toss) to 1 (certain).
+    pub confidence: f32,
+}
+
+// How plausible each non-ASCII character of `bytes` is in a file name when decoded as
+// `encoding`, on average, or `None` if `bytes` is not valid in `encoding`.
+fn plausibility(bytes: &[u8], encoding: NameEncoding) -> Option<f32> {
+    if let NameEncoding::Other(other) = encoding {
+        other.decode_without_bom_handling_and_without_replacement(bytes)?;
+    }
+    let (mut total, mut count) = (0.0, 0);
+    let mut rest = bytes.iter().copied();
+    while let Some(b) = rest.next() {
+        if b.is_ascii() {
+            continue;
+        }
+        let score = match encoding {
+            // Accented letters
+            NameEncoding::Cp437 if b <= 0xA5 => 0.6,
+            // Box drawing, Greek and math symbols
+            NameEncoding::Cp437 => 0.05,
+            // Half-width katakana
+            NameEncoding::Other(other)
+                if other == encoding_rs::SHIFT_JIS && (0xA1..=0xDF).contains(&b) =>
+            {
+                0.3
+            }
+            NameEncoding::Other(other) if other == encoding_rs::SHIFT_JIS => {
+                rest.next();
+                match b {
+                    // Hiragana and katakana
+                    0x82 | 0x83 => 1.0,
+                    // Kanji
+                    0x88..=0x9F | 0xE0..=0xEA => 0.8,
+                    _ => 0.3,
+                }
+            }
+            NameEncoding::Other(other) if other == encoding_rs::GBK && b == 0x80 => 0.1,
+            NameEncoding::Other(other) if other == encoding_rs::GBK => {
+                let trail = rest.next().unwrap_or(0);
+                match b {
+                    // The common hanzi of GB 2312
+                    0xB0..=0xF7 if trail >= 0xA1 => 1.0,
+                    // The GBK extensions are rare
+                    _ => 0.3,
+                }
+            }
+            _ => 1.0,
+        };
+        total += score;
+        count += 1;
+    }
+    Some(if count == 0 {
+        1.0
+    } else {
+        total / count as f32
+    })
+}
+
+/// Guesses whether the raw file name `bytes` (e.g. of a zip entry without the UTF-8 flag)
+/// are UTF-8, CP437, Shift-JIS (CP932) or GBK (CP936).
+///
+/// The guess is based on which characters are plausible in a name, so it gets better
+/// with longer names; an extraction tool would typically use it as the default and let
+/// the user pick another encoding when the confidence is low.
+pub fn detect_encoding(bytes: &[u8]) -> EncodingGuess {
+    if bytes.is_ascii() || std::str::from_utf8(bytes).is_ok() {
+        let confidence = if bytes.is_ascii() { 1.0 } else { 0.95 };
+        return EncodingGuess {
+            encoding: NameEncoding::Utf8,
+            confidence,
+        };
+    }
+    let candidates = [
+        NameEncoding::Other(encoding_rs::SHIFT_JIS),
+        NameEncoding::Other(encoding_rs::GBK),
+        NameEncoding::Cp437,
+    ];
+    let scores = candidates
+        .iter()
+        .filter_map(|&encoding| plausibility(bytes, encoding).map(|score| (encoding, score)))
+        .collect::<Vec<_>>();
+    let total = scores.iter().map(|&(_, score)| score).sum::<f32>();
+    // CP437 decodes anything, so there is always a candidate.
+    let (encoding, best) =
+        scores
+            .into_iter()
+            .fold((NameEncoding::Cp437, f32::MIN), |best, candidate| {
+                if candidate.1 > best.1 {
+                    candidate
+                } else {
+                    best
+                }
+            });
+    EncodingGuess {
+        encoding,
+        confidence: best / total,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{detect_encoding, sanitize_bytes_with_encoding, NameEncoding};
+
+    #[test]
+    fn it_decodes_legacy_names() {
+        let options = crate::Options::default();
+
+        assert_eq!(
+            sanitize_bytes_with_encoding(b"r\x82sum\x82/:.txt", NameEncoding::Cp437, &options),
+            "résumé.txt"
+        );
+        assert_eq!(
+            sanitize_bytes_with_encoding(
+                b"\x93\xfa\x96\x7b?.txt",
+                encoding_rs::SHIFT_JIS,
+                &options
+            ),
+            "日本.txt"
+        );
+        assert_eq!(
+            sanitize_bytes_with_encoding(b"a\xffb", NameEncoding::Utf8, &options),
+            "a\u{FFFD}b"
+        );
+        assert_eq!(NameEncoding::Cp437.decode(b"\xe0\xff"), "α\u{A0}");
+    }
+
+    #[test]
+    fn it_detects_encodings() {
+        let guess = |bytes: &[u8]| detect_encoding(bytes).encoding;
+
+        assert_eq!(guess(b"plain.txt"), NameEncoding::Utf8);
+        assert_eq!(detect_encoding(b"plain.txt").confidence, 1.0);
+        assert_eq!(guess("résumé.txt".as_bytes()), NameEncoding::Utf8);
+        assert_eq!(guess(b"r\x82sum\x82.txt"), NameEncoding::Cp437);
+        // ひらがな.txt
+        assert_eq!(
+            guess(b"\x82\xd0\x82\xe7\x82\xaa\x82\xc8.txt"),
+            NameEncoding::Other(encoding_rs::SHIFT_JIS)
+        );
+        // 中文.txt
+        assert_eq!(
+            guess(b"\xd6\xd0\xce\xc4.txt"),
+            NameEncoding::Other(encoding_rs::GBK)
+        );
+
+        let confidence = detect_encoding(b"\xd6\xd0\xce\xc4.txt").confidence;
+        assert!(confidence > 0.0 && confidence < 1.0);
+    }
+}
@@ -0,0 +1,136 @@
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+
+use crate::{sanitize_os_str, sanitize_with_options, Options};
+
+/// Sanitizing as a method of the string types, for code that handles many names:
+///
+/// ```
+/// use sanitize_filename::{Options, Sanitize};
+///
+/// assert_eq!("weird:name".sanitized(), "weirdname");
+/// let options = Options::new().with_replacement("_");
+/// assert_eq!("weird:name".sanitized_with(&options), "weird_name");
+/// ```
+///
+/// A `Path` is taken as a whole path: only its file name is sanitized, as
+/// [`SanitizePath::with_sanitized_file_name`] does.
+pub trait Sanitize {
+    type Output;
+
+    /// Sanitizes `self` with the default [`Options`].
+    fn sanitized(&self) -> Self::Output {
+        self.sanitized_with(&Options::default())
+    }
+
+    fn sanitized_with(&self, options: &Options) -> Self::Output;
+}
+
+impl Sanitize for str {
+    type Output = String;
+
+    fn sanitized_with(&self, options: &Options) -> String {
+        sanitize_with_options(self, options.borrowed())
+    }
+}
+
+impl Sanitize for String {
+    type Output = String;
+
+    fn sanitized_with(&self, options: &Options) -> String {
+        self.as_str().sanitized_with(options)
+    }
+}
+
+/// Names that are not valid Unicode are handled as in [`sanitize_os_str`].
+impl Sanitize for OsStr {
+    type Output = OsString;
+
+    fn sanitized_with(&self, options: &Options) -> OsString {
+        sanitize_os_str(self, options)
+    }
+}
+
+impl Sanitize for OsString {
+    type Output = OsString;
+
+    fn sanitized_with(&self, options: &Options) -> OsString {
+        self.as_os_str().sanitized_with(options)
+    }
+}
+
+impl Sanitize for Path {
+    type Output = PathBuf;
+
+    fn sanitized_with(&self, options: &Options) -> PathBuf {
+        self.with_sanitized_file_name(options)
+    }
+}
+
+impl Sanitize for PathBuf {
+    type Output = PathBuf;
+
+    fn sanitized_with(&self, options: &Options) -> PathBuf {
+        self.as_path().sanitized_with(options)
+    }
+}
+
+/// Sanitizing the file name of a path, leaving its directories as they are.
+pub trait SanitizePath {
+    /// Returns the path with its file name sanitized, or removed if nothing is left of it,
+    /// e.g. for the destination of a download:
+    ///
+    /// ```
+    /// use std::path::Path;
+    /// use sanitize_filename::{Options, SanitizePath};
+    ///
+    /// let options = Options::new().with_replacement("_");
+    /// let path = Path::new("downloads").join("report: final?.pdf");
+    /// assert_eq!(
+    ///     path.with_sanitized_file_name(&options),
+    ///     Path::new("downloads").join("report_ final_.pdf")
+    /// );
+    /// ```
+    ///
+    /// Paths without a file name, such as `/` or ones ending in `..`, are returned as they
+    /// are.
+    fn with_sanitized_file_name(&self, options: &Options) -> PathBuf;
+}
+
+impl SanitizePath for Path {
+    fn with_sanitized_file_name(&self, options: &Options) -> PathBuf {
+        match self.file_name() {
+            Some(name) => self.with_file_name(sanitize_os_str(name, options)),
+            None => self.to_path_buf(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::{OsStr, OsString};
+    use std::path::{Path, PathBuf};
+
+    use super::{Sanitize, SanitizePath};
+
+    #[test]
+    fn it_sanitizes_as_a_method() {
+        let options = crate::Options::new().with_replacement("_");
+
+        assert_eq!("a:b".sanitized(), crate::sanitize("a:b"));
+        assert_eq!(String::from("a:b").sanitized_with(&options), "a_b");
+        assert_eq!(OsStr::new("a?b").sanitized_with(&options), "a_b");
+        assert_eq!(OsString::from("a?b").sanitized(), "ab");
+
+        let dir = Path::new("some").join("dir");
+        assert_eq!(dir.join("a|b").sanitized_with(&options), dir.join("a_b"));
+        assert_eq!(
+            PathBuf::from("x")
+                .join("a|b")
+                .with_sanitized_file_name(&options),
+            Path::new("x").join("a_b")
+        );
+        assert_eq!(dir.join("..").sanitized(), dir.join(".."));
+        assert_eq!(dir.join("???").sanitized(), dir.join(""));
+    }
+}
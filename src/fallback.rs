@@ -0,0 +1,120 @@
+/// What to produce when nothing of a name survives sanitization, e.g. a name made only of
+/// control characters, or `..`.
+///
+/// The fallbacks encode the bytes of the original name, so the result is unique and can
+/// be turned back into the original with [`Fallback::decode`] (unless it had to be
+/// truncated).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum Fallback {
+    /// Return the empty string (or the replacements), as without a fallback.
+    #[default]
+    None,
+    /// Lowercase base32 (RFC 4648) without padding, which also survives case-insensitive
+    /// file systems.
+    Base32,
+    /// URL-safe base64 (RFC 4648) without padding; shorter, but case-sensitive.
+    #[cfg_attr(feature = "clap", value(name = "base64url"))]
+    Base64Url,
+}
+
+const BASE32: &[u8; 32] = b"abcdefghijklmnopqrstuvwxyz234567";
+const BASE64URL: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+impl Fallback {
+    fn alphabet(&self) -> Option<(&'static [u8], u32)> {
+        match self {
+            Fallback::None => None,
+            Fallback::Base32 => Some((BASE32, 5)),
+            Fallback::Base64Url => Some((BASE64URL, 6)),
+        }
+    }
+
+    /// Encodes `bytes`; returns an empty string with [`Fallback::None`].
+    pub fn encode(&self, bytes: &[u8]) -> String {
+        let (alphabet, bits) = match self.alphabet() {
+            Some(alphabet) => alphabet,
+            None => return String::new(),
+        };
+        let mask = (1 << bits) - 1;
+        let mut out = String::with_capacity((bytes.len() * 8 + bits as usize - 1) / bits as usize);
+        let (mut buffer, mut buffered) = (0u32, 0);
+        for &b in bytes {
+            buffer = (buffer << 8) | u32::from(b);
+            buffered += 8;
+            while buffered >= bits {
+                buffered -= bits;
+                out.push(alphabet[((buffer >> buffered) & mask) as usize] as char);
+            }
+        }
+        if buffered > 0 {
+            out.push(alphabet[((buffer << (bits - buffered)) & mask) as usize] as char);
+        }
+        out
+    }
+
+    /// Decodes a name produced by [`encode`](Fallback::encode), or returns `None` if it
+    /// is not a valid encoding.
+    pub fn decode(&self, name: &str) -> Option<Vec<u8>> {
+        let (alphabet, bits) = self.alphabet()?;
+        let mut out = Vec::with_capacity(name.len() * bits as usize / 8);
+        let (mut buffer, mut buffered) = (0u32, 0);
+        for c in name.bytes() {
+            let value = alphabet.iter().position(|&a| a == c)? as u32;
+            buffer = (buffer << bits) | value;
+            buffered += bits;
+            if buffered >= 8 {
+                buffered -= 8;
+                out.push((buffer >> buffered) as u8);
+            }
+        }
+        // The leftover bits are padding and must be zero.
+        if buffer & ((1 << buffered) - 1) != 0 {
+            return None;
+        }
+        Some(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Fallback;
+
+    #[test]
+    fn it_encodes_reversibly() {
+        assert_eq!(Fallback::Base32.encode(b"foobar"), "mzxw6ytboi");
+        assert_eq!(Fallback::Base64Url.encode(b"\xfb\xff?"), "-_8_");
+        assert_eq!(Fallback::None.encode(b"foobar"), "");
+
+        for fallback in [Fallback::Base32, Fallback::Base64Url] {
+            for len in 0..8 {
+                let bytes = (0..len)
+                    .map(|b: u8| b.wrapping_mul(37).wrapping_add(200))
+                    .collect::<Vec<u8>>();
+                let encoded = fallback.encode(&bytes);
+                assert_eq!(fallback.decode(&encoded), Some(bytes));
+            }
+            assert_eq!(fallback.decode("a?"), None);
+        }
+        assert_eq!(Fallback::Base32.decode("mb"), None);
+    }
+
+    #[test]
+    fn it_falls_back_when_nothing_survives() {
+        let options = crate::Options::new().with_fallback(Fallback::Base32);
+
+        assert_eq!(crate::sanitize_with_options("..", options.clone()), "fyxa");
+        assert_eq!(
+            crate::sanitize_with_options("??", options.clone().with_replacement("_")),
+            "h47q"
+        );
+        assert_eq!(crate::sanitize_with_options("a?", options.clone()), "a");
+        assert_eq!(
+            crate::sanitize_with_options("__", options.clone().with_replacement("_")),
+            "__"
+        );
+        assert_eq!(crate::sanitize_with_options("", options), "");
+    }
+}
@@ -0,0 +1,91 @@
+use arbitrary::{Arbitrary, Result, Unstructured};
+
+use crate::{Options, RiskFactor, RiskReport, SanitizedFileName, ViolationFlags};
+
+/// Generates non-empty names sanitized with the default [`Options`], so each value also
+/// passes the default check (`"a/b".parse()` and the like).
+impl<'a> Arbitrary<'a> for SanitizedFileName {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let name = SanitizedFileName::new(<&str>::arbitrary(u)?, &Options::default());
+        if name.as_str().is_empty() {
+            return Ok(SanitizedFileName::new("_", &Options::default()));
+        }
+        Ok(name)
+    }
+
+    fn size_hint(depth: usize) -> (usize, Option<usize>) {
+        <&str>::size_hint(depth)
+    }
+}
+
+/// Generates any combination of the defined flags.
+impl<'a> Arbitrary<'a> for ViolationFlags {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let bits = u32::arbitrary(u)?;
+        let mut flags = ViolationFlags::empty();
+        for (idx, (flag, _)) in ViolationFlags::NAMES.iter().enumerate() {
+            if bits & (1 << idx) != 0 {
+                flags |= *flag;
+            }
+        }
+        Ok(flags)
+    }
+
+    fn size_hint(depth: usize) -> (usize, Option<usize>) {
+        u32::size_hint(depth)
+    }
+}
+
+/// Generates reports like [`risk_report`](crate::risk_report) returns them: the factors
+/// in declaration order without duplicates, and the score matching them.
+impl<'a> Arbitrary<'a> for RiskReport {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let mut factors = Vec::<RiskFactor>::arbitrary(u)?;
+        factors.sort_unstable_by_key(|&factor| factor as u8);
+        factors.dedup();
+        let score = factors.iter().map(|factor| factor.weight()).sum();
+        Ok(RiskReport { score, factors })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arbitrary::{Arbitrary, Unstructured};
+
+    use crate::{RiskReport, SanitizedFileName, ViolationFlags};
+
+    #[test]
+    fn it_generates_valid_values() {
+        let mut seed = 0x2545_f491_4f6c_dd1du64;
+        let mut data = Vec::with_capacity(4096);
+        for _ in 0..4096 {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            data.push(seed as u8);
+        }
+        data.extend_from_slice(b"con/..\\a\x01b?. ");
+
+        let mut u = Unstructured::new(&data);
+        while !u.is_empty() {
+            let name = SanitizedFileName::arbitrary(&mut u).unwrap();
+            assert!(!name.as_str().is_empty());
+            assert!(name.as_str().parse::<SanitizedFileName>().is_ok());
+
+            let flags = ViolationFlags::arbitrary(&mut u).unwrap();
+            assert_eq!(flags.bits() >> ViolationFlags::NAMES.len(), 0);
+
+            let report = RiskReport::arbitrary(&mut u).unwrap();
+            assert!(report
+                .factors
+                .windows(2)
+                .all(|w| (w[0] as u8) < (w[1] as u8)));
+            let score = report
+                .factors
+                .iter()
+                .map(|factor| factor.weight())
+                .sum::<u32>();
+            assert_eq!(report.score, score);
+        }
+    }
+}
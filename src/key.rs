@@ -0,0 +1,80 @@
+use unicode_normalization::UnicodeNormalization;
+
+use crate::{sanitize_with_options, Options};
+
+/// Produces a normalized comparison key for `name`.
+///
+/// The key is the sanitized name case-folded and in NFC, with runs of whitespace
+/// collapsed into a single space and leading/trailing whitespace removed. Two names with
+/// the same key would collide on a case-insensitive, normalization-insensitive
+/// filesystem, so `Straße` and `STRASSE` get the same key, as do a precomposed `é` and
+/// `e` followed by a combining accent. The key is meant for dedup indexes and caches,
+/// not for display.
+///
+/// The key only depends on `name` and `options`, not on the crate's features, so keys
+/// can be persisted. They change only if the sanitizer itself changes what it produces
+/// for `name`. Requires the `unicode` feature.
+pub fn canonical_key<S: AsRef<str>>(name: S, options: &Options) -> String {
+    let sanitized = sanitize_with_options(name, options.clone());
+
+    let mut key = String::with_capacity(sanitized.len());
+    let mut pending_space = false;
+    for c in fold(&sanitized).chars() {
+        if c.is_whitespace() {
+            pending_space = !key.is_empty();
+            continue;
+        }
+        if pending_space {
+            key.push(' ');
+            pending_space = false;
+        }
+        key.push(c);
+    }
+    key
+}
+
+// Canonical caseless matching: decompose, case-fold, then recompose. Lowercasing the
+// uppercase form gives the full case folding (`ß` and `ẞ` become `ss`, final `ς` becomes
+// `σ`), which `to_lowercase` alone does not.
+pub(crate) fn fold(name: &str) -> String {
+    name.nfd()
+        .flat_map(char::to_uppercase)
+        .flat_map(char::to_lowercase)
+        .nfc()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::canonical_key;
+
+    #[test]
+    fn it_builds_keys() {
+        let options = crate::Options {
+            windows: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            canonical_key("  Résumé \t Final.PDF ", &options),
+            "résumé final.pdf"
+        );
+        assert_eq!(
+            canonical_key("Re\u{0301}sume\u{0301}.pdf", &options),
+            canonical_key("RÉSUMÉ.pdf", &options)
+        );
+        assert_eq!(
+            canonical_key("a:b.txt", &options),
+            canonical_key("AB.txt", &options)
+        );
+        assert_eq!(canonical_key("Straße", &options), "strasse");
+        assert_eq!(
+            canonical_key("Straße", &options),
+            canonical_key("STRASSE", &options)
+        );
+        assert_eq!(
+            canonical_key("ΣΟΦΟΣ", &options),
+            canonical_key("σοφος", &options)
+        );
+    }
+}
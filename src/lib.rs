@@ -1,283 +1,4787 @@
-use std::sync::OnceLock;
+use std::borrow::Cow;
+use std::fmt;
+use std::ops::RangeInclusive;
 
-extern crate regex;
-use regex::{Regex, RegexBuilder};
+use lookup::{CharSet, Lookup};
+use replacer::push_substitute;
+use risk::{has_double_extension, is_bidi_control, is_confusable};
+use rules::{has_windows_trailing, is_control_char, is_illegal_char, is_windows_reserved};
+use security::{
+    defuse_double_extension, is_expansion_char, is_shell_metachar, replace_unsafe_chars,
+    strip_unsafe_prefix, SecurityRules,
+};
+use violation::first_violation;
 
-static ILLEGAL_RE: OnceLock<Regex> = OnceLock::new();
-static CONTROL_RE: OnceLock<Regex> = OnceLock::new();
-static RESERVED_RE: OnceLock<Regex> = OnceLock::new();
-static WINDOWS_RESERVED_RE: OnceLock<Regex> = OnceLock::new();
-static WINDOWS_TRAILING_RE: OnceLock<Regex> = OnceLock::new();
+mod analyze;
+mod batch;
+mod builder;
+#[cfg(feature = "bstr")]
+mod byte_str;
+mod bytes;
+mod chars;
+#[cfg(feature = "clap")]
+mod clap_args;
+#[cfg(feature = "http")]
+mod content_disposition;
+#[cfg(feature = "encoding_rs")]
+mod encoding;
+mod ext;
+mod fallback;
+#[cfg(feature = "arbitrary")]
+mod fuzz;
+#[cfg(feature = "unicode")]
+mod key;
+mod lookup;
+mod name;
+mod os_str;
+mod path;
+mod pattern;
+#[cfg(feature = "walkdir")]
+mod plan;
+#[cfg(feature = "policy")]
+mod policy;
+mod punycode;
+mod quarantine;
+#[cfg(feature = "unicode")]
+mod registry;
+mod replacer;
+mod reversible;
+mod risk;
+mod rule;
+pub mod rules;
+mod sanitizer;
+mod security;
+mod stem;
+mod substring;
+mod template;
+mod unc;
+mod url;
+mod violation;
+mod writer;
 
-fn illegal_re() -> &'static Regex {
-    ILLEGAL_RE.get_or_init(|| Regex::new(r#"[/\?<>\\:\*\|":]"#).unwrap())
+pub use analyze::{analyze, analyze_with_options, Issue};
+pub use batch::{
+    next_available_name, sanitize_many, sanitize_many_unique, sanitize_unique, SanitizeIter,
+    SuffixStyle,
+};
+pub use builder::FileNameBuilder;
+#[cfg(feature = "bstr")]
+pub use byte_str::{is_sanitized_bstr, sanitize_bstr};
+pub use bytes::sanitize_bytes;
+pub use chars::SanitizedChars;
+#[cfg(feature = "clap")]
+pub use clap_args::SanitizeArgs;
+#[cfg(feature = "http")]
+pub use content_disposition::filename_from_content_disposition;
+#[cfg(feature = "encoding_rs")]
+pub use encoding::{detect_encoding, sanitize_bytes_with_encoding, EncodingGuess, NameEncoding};
+pub use ext::{Sanitize, SanitizePath};
+pub use fallback::Fallback;
+#[cfg(feature = "unicode")]
+pub use key::canonical_key;
+pub use name::{NotSanitized, SanitizedFileName};
+pub use os_str::sanitize_os_str;
+pub use path::{
+    flatten_path, max_path_len, sanitize_in_dir, sanitize_path, sanitize_path_components,
+    split_path, DepthPolicy, PathError, PathLimit,
+};
+pub use pattern::DenyPattern;
+#[cfg(feature = "walkdir")]
+pub use plan::{plan_tree, Conflict, Rename, RenamePlan};
+#[cfg(feature = "policy")]
+pub use policy::PolicyError;
+pub use punycode::{from_ascii_name, to_ascii_name};
+pub use quarantine::{classify, Classification, RejectReason};
+#[cfg(feature = "unicode")]
+pub use registry::NameRegistry;
+pub use replacer::Replacer;
+pub use reversible::{decode, encode, DecodeError};
+pub use risk::{risk_report, RiskFactor, RiskLevel, RiskReport};
+pub use rule::{
+    IllegalChars, Pipeline, ReservedNames, Rule, Truncate, WindowsReserved, WindowsTrailing,
+};
+pub use sanitizer::Sanitizer;
+pub use security::Security;
+pub use stem::sanitize_stem;
+pub use substring::DenySubstrings;
+pub use template::{format_filename, TemplateError, TemplateValues};
+pub use unc::{sanitize_unc_path, UncPath};
+pub use url::filename_from_url;
+pub use violation::{
+    violation_flags, violations, why_not_sanitized, SanitizeError, Violation, ViolationFlags,
+};
+pub use writer::SanitizingWriter;
+
+/// Characters that are replaced wherever they appear in a name.
+pub const ILLEGAL_CHARS: &[char] = &['/', '?', '<', '>', '\\', ':', '*', '|', '"'];
+
+/// The POSIX portable filename character set, `A-Z`, `a-z`, `0-9`, `.`, `_` and `-`, e.g.
+/// for [`Options::with_allowlist`].
+pub const PORTABLE_CHARS: &[char] = &[
+    'A', 'B', 'C', 'D', 'E', 'F', 'G', 'H', 'I', 'J', 'K', 'L', 'M', 'N', 'O', 'P', 'Q', 'R', 'S',
+    'T', 'U', 'V', 'W', 'X', 'Y', 'Z', 'a', 'b', 'c', 'd', 'e', 'f', 'g', 'h', 'i', 'j', 'k', 'l',
+    'm', 'n', 'o', 'p', 'q', 'r', 's', 't', 'u', 'v', 'w', 'x', 'y', 'z', '0', '1', '2', '3', '4',
+    '5', '6', '7', '8', '9', '.', '_', '-',
+];
+
+/// Ranges of control characters (C0 and C1) that are replaced wherever they appear in a
+/// name.
+pub const CONTROL_CHARS: &[RangeInclusive<char>] = &['\u{0}'..='\u{1f}', '\u{80}'..='\u{9f}'];
+
+/// Device names reserved by Windows, matched case-insensitively against the whole name
+/// or the part before the first dot (`con`, `CON.txt`) when the Windows rules are on.
+///
+/// `RuleSet::V1` only knows the names up to `lpt9`; the console and clock devices and the
+/// superscript ports (`COM¹`, matched with only the ASCII letters ignoring case) came later.
+pub const WINDOWS_RESERVED: &[&str] = &[
+    "con", "prn", "aux", "nul", "com0", "com1", "com2", "com3", "com4", "com5", "com6", "com7",
+    "com8", "com9", "lpt0", "lpt1", "lpt2", "lpt3", "lpt4", "lpt5", "lpt6", "lpt7", "lpt8", "lpt9",
+    "clock$", "conin$", "conout$", "com¹", "com²", "com³", "lpt¹", "lpt²", "lpt³",
+];
+
+// How many of the `WINDOWS_RESERVED` names `RuleSet::V1` matches.
+pub(crate) const V1_WINDOWS_RESERVED: usize = 24;
+
+/// Characters that Windows strips from the end of a name, which are therefore trimmed
+/// there when the Windows rules are on.
+pub const WINDOWS_TRAILING_CHARS: &[char] = &['.', ' '];
+
+/// Characters with a special meaning to POSIX shells, replaced when `strip_shell_metachars`
+/// is on (along with leading hyphens), for names that end up in scripts.
+pub const SHELL_METACHARS: &[char] = &[
+    '$', '`', '&', ';', '(', ')', '!', '[', ']', '{', '}', '\'', '~', '#',
+];
+
+/// Legal characters that shells, Windows and Office expand or treat specially (`~` for
+/// the home directory and in `~$` lock files, `$` and `%` for variables, `` ` `` and `!`),
+/// replaced when `strip_expansion_chars` is on.
+pub const EXPANSION_CHARS: &[char] = &['~', '$', '%', '`', '!'];
+
+/// Extensions with a more usual spelling, as `(alias, canonical)` pairs, that
+/// [`ExtensionNormalization::Canonical`] rewrites.
+pub const EXTENSION_ALIASES: &[(&str, &str)] = &[
+    ("jpeg", "jpg"),
+    ("jpe", "jpg"),
+    ("jfif", "jpg"),
+    ("tif", "tiff"),
+    ("htm", "html"),
+    ("mpeg", "mpg"),
+    ("yml", "yaml"),
+    ("aif", "aiff"),
+];
+
+/// Names that cloud sync clients (OneDrive, Dropbox, iCloud, ...) refuse to upload or
+/// treat as system files, matched case-insensitively against the whole name when
+/// `block_sync_junk` is on.
+pub const SYNC_JUNK_NAMES: &[&str] = &[
+    "desktop.ini",
+    "thumbs.db",
+    "ehthumbs.db",
+    "ehthumbs_vista.db",
+    ".ds_store",
+    "icon\r",
+];
+
+// `.`, `..` and other names made only of dots.
+pub(crate) fn is_reserved(name: &str) -> bool {
+    !name.is_empty() && name.bytes().all(|b| b == b'.')
 }
 
-fn control_re() -> &'static Regex {
-    CONTROL_RE.get_or_init(|| Regex::new(r#"[\x00-\x1f\x80-\x9f]"#).unwrap())
+// Spaces, NBSPs and other whitespace make an effectively invisible name.
+pub(crate) fn is_whitespace_only(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(char::is_whitespace)
 }
 
-fn reserved_re() -> &'static Regex {
-    RESERVED_RE.get_or_init(|| Regex::new(r#"^\.+$"#).unwrap())
+pub(crate) fn is_sync_junk(name: &str) -> bool {
+    SYNC_JUNK_NAMES
+        .iter()
+        .any(|junk| junk.eq_ignore_ascii_case(name))
 }
 
-fn windows_reserved_re() -> &'static Regex {
-    WINDOWS_RESERVED_RE.get_or_init(|| {
-        RegexBuilder::new(r#"(?i)^(con|prn|aux|nul|com[0-9]|lpt[0-9])(\..*)?$"#)
-            .case_insensitive(true)
-            .build()
-            .unwrap()
+// Like `is_windows_reserved`, for a list of names that may have extensions of their own.
+pub(crate) fn is_listed_reserved<S: AsRef<str>>(name: &str, reserved_names: &[S]) -> bool {
+    reserved_names.iter().any(|reserved| {
+        let reserved = reserved.as_ref();
+        match name.get(..reserved.len()) {
+            Some(stem) if !reserved.is_empty() && stem.eq_ignore_ascii_case(reserved) => {
+                let rest = &name[reserved.len()..];
+                (rest.is_empty() || rest.starts_with('.')) && !rest.contains('\n')
+            }
+            _ => false,
+        }
     })
 }
 
-fn windows_trailing_re() -> &'static Regex {
-    WINDOWS_TRAILING_RE.get_or_init(|| Regex::new(r#"[\. ]+$"#).unwrap())
+/// Selects which version of the sanitization rules to apply.
+///
+/// `V1` is frozen: its output for a given input and set of options will never change
+/// across releases, which makes it suitable for content-addressed storage and other
+/// persisted mappings. `Latest` picks up rule improvements as they are released.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum RuleSet {
+    V1,
+    #[default]
+    Latest,
 }
 
-#[derive(Clone)]
-pub struct Options<'a> {
-    pub windows: bool,
-    pub truncate: bool,
-    pub replacement: &'a str,
+/// Which characters may appear in a name, beyond the rules that always apply.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum Charset {
+    /// Any character that is not illegal or a control character.
+    #[default]
+    Any,
+    /// The POSIX portable filename character set, `A-Z`, `a-z`, `0-9`, `.`, `_` and `-`,
+    /// with no leading `.` or `-` and no trailing `.`. With the `unicode` feature accented
+    /// letters lose their accents; other characters, and all characters outside of ASCII
+    /// without the feature, are replaced.
+    Portable,
 }
 
-impl<'a> Default for Options<'a> {
-    fn default() -> Self {
-        Options {
-            windows: cfg!(windows),
-            truncate: true,
-            replacement: "",
+/// How name lengths are counted against [`Options::max_length`] and
+/// [`OptionsForCheck::max_length`].
+///
+/// File systems differ: ext4 and most Unix file systems limit names to 255 bytes, NTFS
+/// and exFAT to 255 UTF-16 code units, and HFS+ and APFS to 255 characters.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum LengthUnit {
+    /// UTF-8 bytes, as `str::len` counts them.
+    #[default]
+    Bytes,
+    /// Unicode scalar values, as `str::chars` yields them.
+    Chars,
+    /// UTF-16 code units, as Windows counts them.
+    Utf16,
+}
+
+impl LengthUnit {
+    /// The length of `name` in this unit.
+    ///
+    /// ```
+    /// use sanitize_filename::LengthUnit;
+    ///
+    /// assert_eq!(LengthUnit::Bytes.measure("日本.txt"), 10);
+    /// assert_eq!(LengthUnit::Chars.measure("日本.txt"), 6);
+    /// assert_eq!(LengthUnit::Utf16.measure("🎉.txt"), 6);
+    /// ```
+    pub fn measure(self, name: &str) -> usize {
+        match self {
+            LengthUnit::Bytes => name.len(),
+            LengthUnit::Chars => name.chars().count(),
+            LengthUnit::Utf16 => name.encode_utf16().count(),
+        }
+    }
+
+    // The unit in error messages.
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            LengthUnit::Bytes => "bytes",
+            LengthUnit::Chars => "characters",
+            LengthUnit::Utf16 => "UTF-16 code units",
         }
     }
 }
 
-pub fn sanitize<S: AsRef<str>>(name: S) -> String {
-    sanitize_with_options(name, Options::default())
+/// How [`Options::truncation`] shortens names that are longer than `max_length`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum TruncationStrategy {
+    /// Cuts the end of the name, extension included.
+    #[default]
+    CutEnd,
+    /// Cuts the end of the stem, so `very-long-title….mp4` keeps its `.mp4`. Names whose
+    /// extension alone is too long are cut at the end.
+    PreserveExtension,
+    /// Leaves long names as they are, for no length limit at all.
+    None,
 }
 
-pub fn sanitize_with_options<S: AsRef<str>>(name: S, options: Options) -> String {
-    let Options {
-        windows,
-        truncate,
-        replacement,
-    } = options;
-    let name = name.as_ref();
-
-    let name = illegal_re().replace_all(&name, replacement);
-    let name = control_re().replace_all(&name, replacement);
-    let name = reserved_re().replace(&name, replacement);
-
-    let collect = |name: ::std::borrow::Cow<str>| {
-        if truncate && name.len() > 255 {
-            let mut end = 255;
-            loop {
-                if name.is_char_boundary(end) {
-                    break;
-                }
-                end -= 1;
-            }
-            String::from(&name[..end])
-        } else {
-            String::from(name)
+impl TruncationStrategy {
+    pub(crate) const fn truncates(self) -> bool {
+        !matches!(self, TruncationStrategy::None)
+    }
+}
+
+/// How [`Options::min_length`] brings names that are too short up to length.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum MinLengthStrategy {
+    /// Pads the end of the stem with `_`, so `a.txt` becomes `a__.txt`. Empty names are
+    /// padded too, unless there is an [`Options::empty_fallback`] to sanitize instead.
+    #[default]
+    Pad,
+    /// Sanitizes the [`Options::empty_fallback`] instead, leaving nothing if there is none
+    /// or it is too short itself.
+    Fallback,
+}
+
+/// How [`Options::normalize_extension`] spells extensions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum ExtensionNormalization {
+    /// Keeps extensions as they are.
+    #[default]
+    Keep,
+    /// Lowercases extensions and trims the whitespace around them: `.JPG ` becomes `.jpg`.
+    Lowercase,
+    /// Lowercases and trims extensions, and rewrites the [`EXTENSION_ALIASES`]: `.JPEG`
+    /// becomes `.jpg`.
+    Canonical,
+}
+
+/// What [`Options::leading_dot`] does with a leading dot, which hides the file on
+/// Unix-like systems.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum LeadingDotPolicy {
+    /// Keeps leading dots, so `.bashrc` stays a hidden file.
+    #[default]
+    Allow,
+    /// Strips leading dots: `.bashrc` becomes `bashrc`.
+    Strip,
+    /// Prepends `_` to names with a leading dot: `.bashrc` becomes `_.bashrc`.
+    Prefix,
+}
+
+impl LeadingDotPolicy {
+    // `Strip` where the deprecated `strip_hidden_prefix` was on, and `Allow` instead of
+    // `Strip` where it was off.
+    const fn stripped(self, strip: bool) -> Self {
+        match (strip, self) {
+            (true, _) => LeadingDotPolicy::Strip,
+            (false, LeadingDotPolicy::Strip) => LeadingDotPolicy::Allow,
+            (false, leading_dot) => leading_dot,
         }
-    };
+    }
+}
+
+pub(crate) fn is_portable_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-')
+}
 
-    if windows {
-        let name = windows_reserved_re().replace(&name, replacement);
-        let name = windows_trailing_re().replace(&name, replacement);
-        collect(name)
+pub(crate) fn has_portable_edges(name: &str) -> bool {
+    !name.starts_with(['.', '-']) && !name.ends_with('.')
+}
+
+// Replaces the characters outside of `Charset::Portable`, leaving the edges to
+// `trim_portable_edges`.
+fn to_portable<'n>(
+    name: Cow<'n, str>,
+    replacement: &str,
+    replacer: Option<&Replacer>,
+    allow: &CharSet,
+) -> Cow<'n, str> {
+    let kept = |c: char| is_portable_char(c) || allow.contains(c);
+    if name.chars().all(kept) {
+        name
     } else {
-        collect(name)
+        let mut out = String::with_capacity(name.len());
+        for c in name.chars() {
+            if kept(c) {
+                out.push(c);
+                continue;
+            }
+            match unaccented(c) {
+                Some(base) => out.push_str(&base),
+                None => push_substitute(&mut out, c, replacement, replacer),
+            }
+        }
+        Cow::Owned(out)
     }
 }
 
-#[derive(Clone)]
-pub struct OptionsForCheck {
-    pub windows: bool,
-    pub truncate: bool,
+// `c` without its accents if that leaves only portable characters: `é` becomes `e`, but
+// `ß` or `日` have no such spelling.
+#[cfg(feature = "unicode")]
+fn unaccented(c: char) -> Option<String> {
+    let mut base = String::new();
+    let mut portable = true;
+    unicode_normalization::char::decompose_canonical(c, |d| {
+        if is_portable_char(d) {
+            base.push(d);
+        } else if !unicode_normalization::char::is_combining_mark(d) {
+            portable = false;
+        }
+    });
+    Some(base).filter(|base| portable && !base.is_empty())
 }
 
-impl Default for OptionsForCheck {
-    fn default() -> Self {
-        OptionsForCheck {
-            windows: cfg!(windows),
-            truncate: true,
+// Folding accents needs the Unicode tables, so without them every character outside of
+// the portable set is replaced.
+#[cfg(not(feature = "unicode"))]
+fn unaccented(_: char) -> Option<String> {
+    None
+}
+
+// Replaces every character that `kept` rejects.
+fn keep_chars<'n, F: Fn(char) -> bool>(
+    name: Cow<'n, str>,
+    kept: F,
+    replacement: &str,
+    replacer: Option<&Replacer>,
+) -> Cow<'n, str> {
+    if name.chars().all(&kept) {
+        return name;
+    }
+    let mut out = String::with_capacity(name.len());
+    for c in name.chars() {
+        if kept(c) {
+            out.push(c);
+        } else {
+            push_substitute(&mut out, c, replacement, replacer);
         }
     }
+    Cow::Owned(out)
 }
 
-pub fn is_sanitized<S: AsRef<str>>(name: S) -> bool {
-    is_sanitized_with_options(name, OptionsForCheck::default())
+fn trim_portable_edges(name: Cow<'_, str>) -> Cow<'_, str> {
+    if has_portable_edges(&name) {
+        return name;
+    }
+    let trimmed = name
+        .trim_start_matches(['.', '-'])
+        .trim_end_matches('.')
+        .to_owned();
+    Cow::Owned(trimmed)
 }
 
-pub fn is_sanitized_with_options<S: AsRef<str>>(name: S, options: OptionsForCheck) -> bool {
-    let OptionsForCheck { windows, truncate } = options;
-    let name = name.as_ref();
+// Cuts `name` to at most `max` bytes without splitting a character.
+pub(crate) fn truncate_to(name: &str, max: usize) -> &str {
+    if name.len() <= max {
+        return name;
+    }
+    let mut end = max;
+    while !name.is_char_boundary(end) {
+        end -= 1;
+    }
+    &name[..end]
+}
 
-    if illegal_re().is_match(&name) {
-        return false;
+// Cuts `name` to at most `max` of `unit` without splitting a character.
+pub(crate) fn truncate_in(name: &str, max: usize, unit: LengthUnit) -> &str {
+    let end = match unit {
+        LengthUnit::Bytes => return truncate_to(name, max),
+        LengthUnit::Chars => name.char_indices().nth(max).map(|(idx, _)| idx),
+        LengthUnit::Utf16 => {
+            let mut units = 0;
+            name.char_indices()
+                .find(|&(_, c)| {
+                    units += c.len_utf16();
+                    units > max
+                })
+                .map(|(idx, _)| idx)
+        }
+    };
+    &name[..end.unwrap_or(name.len())]
+}
+
+// Splits `name` into its stem and its extension, including the dot; a leading dot does
+// not start an extension, and neither does one followed by more than
+// `options.max_extension_len` characters.
+pub(crate) fn split_extension<'n>(name: &'n str, options: &Options) -> (&'n str, &'n str) {
+    split_extension_within(name, options.max_extension_len)
+}
+
+fn split_extension_within(name: &str, max_extension_len: Option<usize>) -> (&str, &str) {
+    match name.rfind('.') {
+        Some(idx) if idx > 0 => {
+            let (stem, extension) = name.split_at(idx);
+            match max_extension_len {
+                Some(max) if extension.chars().count() - 1 > max => (name, ""),
+                _ => (stem, extension),
+            }
+        }
+        _ => (name, ""),
     }
-    if control_re().is_match(&name) {
-        return false;
+}
+
+// Whether `name` ends with one of `allowed` (given with or without the dot, ignoring
+// ASCII case), or has no extension at all.
+pub(crate) fn has_allowed_extension<S: AsRef<str>>(name: &str, allowed: &[S]) -> bool {
+    if split_extension_within(name, None).1.len() <= 1 {
+        return true;
     }
-    if reserved_re().is_match(&name) {
-        return false;
+    allowed.iter().any(|extension| {
+        let extension = extension.as_ref().trim_start_matches('.');
+        let start = name.len().checked_sub(extension.len() + 1);
+        match start.and_then(|start| name.get(start..)) {
+            Some(tail) if !extension.is_empty() && tail.len() < name.len() => {
+                tail.starts_with('.') && tail[1..].eq_ignore_ascii_case(extension)
+            }
+            _ => false,
+        }
+    })
+}
+
+// Replaces the extension of `name` with `default` unless it is allowed, or strips
+// extensions until one is.
+fn keep_allowed_extension<'n, S: AsRef<str>>(
+    name: Cow<'n, str>,
+    allowed: &[S],
+    default: Option<&str>,
+) -> Cow<'n, str> {
+    if has_allowed_extension(&name, allowed) {
+        return name;
     }
-    if truncate && name.len() > 255 {
-        return false;
+    let mut out = split_extension_within(&name, None).0.to_owned();
+    match default {
+        Some(default) => {
+            out.push('.');
+            out.push_str(default.trim_start_matches('.'));
+        }
+        None => {
+            while !has_allowed_extension(&out, allowed) {
+                let stem = split_extension_within(&out, None).0.len();
+                out.truncate(stem);
+            }
+        }
     }
-    if windows {
-        if windows_reserved_re().is_match(&name) {
-            return false;
+    Cow::Owned(out)
+}
+
+// How many extensions `name` has, i.e. its dots other than a leading one.
+pub(crate) fn extension_count(name: &str) -> usize {
+    name.match_indices('.').filter(|&(idx, _)| idx > 0).count()
+}
+
+// `name` with the dots in front of all but its last `max` extensions replaced, or `None`
+// if it has no more extensions than that.
+fn capped_extensions(name: &str, max: usize, replacement: &str) -> Option<String> {
+    let excess = extension_count(name)
+        .checked_sub(max)
+        .filter(|&excess| excess > 0)?;
+    let mut out = String::with_capacity(name.len() + excess * replacement.len());
+    let mut replaced = 0;
+    for (idx, c) in name.char_indices() {
+        if c == '.' && idx > 0 && replaced < excess {
+            out.push_str(replacement);
+            replaced += 1;
+        } else {
+            out.push(c);
         }
-        if windows_trailing_re().is_match(&name) {
-            return false;
+    }
+    Some(out)
+}
+
+// `name` with its extension spelled as `normalization` says, or `None` if it is already.
+pub(crate) fn normalized_extension(
+    name: &str,
+    normalization: ExtensionNormalization,
+) -> Option<String> {
+    if normalization == ExtensionNormalization::Keep {
+        return None;
+    }
+    let (stem, extension) = split_extension_within(name, None);
+    let mut extension = extension.get(1..)?.trim().to_lowercase();
+    if extension.is_empty() {
+        return None;
+    }
+    if normalization == ExtensionNormalization::Canonical {
+        if let Some(&(_, canonical)) =
+            (EXTENSION_ALIASES.iter()).find(|&&(alias, _)| alias == extension)
+        {
+            extension = canonical.to_owned();
         }
     }
+    let normalized = format!("{}.{}", stem, extension);
+    (normalized != name).then_some(normalized)
+}
 
-    return true;
+// Shortens the stem of an overlong `name` so that it fits within `max` with its extension,
+// or returns `None` if the extension leaves no room for any of the stem.
+fn truncate_stem(
+    name: &str,
+    max: usize,
+    unit: LengthUnit,
+    max_extension_len: Option<usize>,
+) -> Option<String> {
+    if unit.measure(name) <= max {
+        return None;
+    }
+    let (stem, extension) = split_extension_within(name, max_extension_len);
+    let stem = truncate_in(stem, max.checked_sub(unit.measure(extension))?, unit);
+    if stem.is_empty() {
+        return None;
+    }
+    Some(format!("{}{}", stem, extension))
 }
 
-#[cfg(test)]
-mod tests {
+// The length limit implied by `options`, if any.
+pub(crate) fn length_limit(options: &Options) -> Option<usize> {
+    if options.truncation.truncates() {
+        Some(options.max_length)
+    } else {
+        None
+    }
+}
 
-    // From https://github.com/parshap/node-sanitize-filename/blob/master/test.js
-    static NAMES: &'static [&'static str] = &[
-        "the quick brown fox jumped over the lazy dog",
-        "résumé",
-        "hello\u{0000}world",
-        "hello\nworld",
-        "semi;colon.js",
-        ";leading-semi.js",
-        "slash\\.js",
-        "slash/.js",
-        "col:on.js",
-        "star*.js",
-        "question?.js",
-        "quote\".js",
-        "singlequote'.js",
-        "brack<e>ts.js",
-        "p|pes.js",
-        "plus+.js",
-        "'five and six<seven'.js",
-        " space at front",
-        "space at end ",
-        ".period",
-        "period.",
-        "relative/path/to/some/dir",
-        "/abs/path/to/some/dir",
-        "~/.\u{0000}notssh/authorized_keys",
-        "",
-        "h?w",
-        "h/w",
-        "h*w",
-        ".",
-        "..",
-        "./",
-        "../",
-        "/..",
-        "/../",
-        "*.|.",
-        "./",
-        "./foobar",
-        "../foobar",
-        "../../foobar",
-        "./././foobar",
-        "|*.what",
-        "LPT9.asdf",
-        "foobar...",
-    ];
+/// Options for [`sanitize_with_options`].
+///
+/// The replacement can be borrowed for one-off calls (`"_".into()`) or owned, so an
+/// `Options<'static>` can be built from runtime configuration and stored in long-lived
+/// structs; see [`Options::into_owned`].
+///
+/// New options may be added in minor releases, so outside of this crate `Options` is
+/// built from [`Options::new`] (or `default()`) and the `with_*` setters. Setters take
+/// the same values on `Options`, [`OptionsForCheck`] and [`OptionsOverride`], so chains
+/// can be copied between them; options that can be unset also have a `without_*` setter.
+///
+/// ```
+/// let options = sanitize_filename::Options::new()
+///     .with_windows(true)
+///     .with_replacement("_");
+/// assert_eq!(sanitize_filename::sanitize_with_options("a:b.", options), "a_b_");
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+#[non_exhaustive]
+pub struct Options<'a> {
+    pub windows: bool,
+    /// Whether to replace Windows device names such as `con`, and `reserved_names` (as
+    /// `windows` by default).
+    pub windows_reserved_names: Option<bool>,
+    /// Whether to replace the trailing dots and spaces that Windows strips (as `windows` by
+    /// default).
+    pub windows_trailing: Option<bool>,
+    /// Replaces illegal characters, and anything that has no replacement of its own below.
+    pub replacement: Cow<'a, str>,
+    /// Picks a substitute per replaced character instead of `replacement`; see
+    /// [`Replacer`].
+    #[cfg_attr(feature = "schemars", schemars(skip))]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub replacer: Option<Replacer>,
+    /// Replaces control characters instead of `replacement`, e.g. `""` to drop them.
+    pub control_replacement: Option<Cow<'a, str>>,
+    /// Replaces whole names that are reserved (`..`, `con`, sync junk, whitespace only)
+    /// instead of `replacement`.
+    pub reserved_replacement: Option<Cow<'a, str>>,
+    /// Replaces the trailing dots and spaces that Windows strips instead of
+    /// `replacement`, e.g. `""` to trim them.
+    pub trailing_replacement: Option<Cow<'a, str>>,
+    /// The name to sanitize instead when nothing would be left of a name, e.g.
+    /// `"untitled"` for `"???"`.
+    pub empty_fallback: Option<Cow<'a, str>>,
+    /// Put at the start of every sanitized name, within `max_length` (none by default).
+    pub prefix: Cow<'a, str>,
+    /// Put at the end of every sanitized name, within `max_length` (none by default).
+    pub suffix: Cow<'a, str>,
+    pub rule_set: RuleSet,
+    pub fallback: Fallback,
+    /// Replace the names in [`SYNC_JUNK_NAMES`] (off by default).
+    pub block_sync_junk: bool,
+    /// The length that `truncation` cuts names to (255 by default, counted in
+    /// `length_unit`), e.g. 143 bytes for eCryptfs or a database column limit. Set
+    /// `truncation` to [`TruncationStrategy::None`] for no limit at all.
+    pub max_length: usize,
+    /// The length, in `length_unit`, that shorter names are brought up to as
+    /// `min_length_strategy` says (none by default).
+    pub min_length: Option<usize>,
+    pub min_length_strategy: MinLengthStrategy,
+    pub charset: Charset,
+    /// Replace each run of consecutive `replacement`s with a single one, so that `a///b` becomes
+    /// `a_b` rather than `a___b` (off by default).
+    pub collapse_replacements: bool,
+    /// Replace each run of whitespace, including ones left by removing characters between
+    /// words, with a single space (off by default).
+    pub collapse_whitespace: bool,
+    /// Trim leading and trailing whitespace, which `windows` only does for trailing spaces
+    /// (off by default).
+    pub trim: bool,
+    /// Rewrite each space or other whitespace character to this one, e.g. `_` for
+    /// `My_Great_File.pdf` (kept by default).
+    pub replace_whitespace: Option<char>,
+    /// Replace bidirectional controls such as RTLO, which can disguise extensions.
+    pub strip_bidi: bool,
+    /// Replace the dot in front of a fake extension (`invoice.pdf.exe`).
+    pub defuse_double_extensions: bool,
+    /// What to do with a leading dot, which hides the file on Unix-like systems (kept by
+    /// default).
+    pub leading_dot: LeadingDotPolicy,
+    /// Replace [`SHELL_METACHARS`] and strip leading hyphens.
+    pub strip_shell_metachars: bool,
+    /// Replace [`EXPANSION_CHARS`].
+    pub strip_expansion_chars: bool,
+    /// Replace characters that look like ASCII with that ASCII character.
+    pub fold_confusables: bool,
+    /// The most components that path functions such as [`sanitize_path_components`]
+    /// accept (any number by default); see `depth_policy`.
+    pub max_depth: Option<usize>,
+    pub depth_policy: DepthPolicy,
+    /// The character that [`flatten_path`] puts between the components of a path (`_` by
+    /// default, which is also used instead of a joiner that names may not have, such as
+    /// `/`).
+    pub joiner: char,
+    /// How names are told apart when they collide, e.g. in [`next_available_name`].
+    pub suffix_style: SuffixStyle,
+    /// Treat a final "extension" longer than this many characters as part of the stem, e.g.
+    /// when keeping extensions while numbering or shortening names (any length by default).
+    pub max_extension_len: Option<usize>,
+    /// The longest full path that path-length checks allow; see [`max_path_len`].
+    pub path_limit: PathLimit,
+    /// How `max_length` and `path_limit` are counted (in bytes by default).
+    pub length_unit: LengthUnit,
+    /// Which part of names longer than `max_length` is cut (the end by default), if any.
+    pub truncation: TruncationStrategy,
+    /// Characters to replace on top of [`ILLEGAL_CHARS`], e.g. `#` and `%` for URLs.
+    pub extra_illegal: Cow<'a, [char]>,
+    /// Characters to keep despite `charset` and the security rules, e.g. `+` and `~` under
+    /// [`Charset::Portable`]. Illegal and control characters are always replaced.
+    pub allow: Cow<'a, [char]>,
+    /// The only characters to keep (with `allow`), if any; see [`Options::with_allowlist`].
+    pub allowlist: Option<Cow<'a, [char]>>,
+    /// Replace every character that is not ASCII (unless in `allow`).
+    pub ascii_only: bool,
+    /// Names to replace on top of [`WINDOWS_RESERVED`] with `windows`, matched the same
+    /// way: ignoring ASCII case, and with any extension (`desktop.ini` also covers
+    /// `Desktop.ini.bak`).
+    pub reserved_names: Cow<'a, [Cow<'a, str>]>,
+    /// The only extensions to keep, if any; see [`Options::with_allowed_extensions`].
+    pub allowed_extensions: Option<Cow<'a, [Cow<'a, str>]>>,
+    /// The extension that replaces the ones not in `allowed_extensions`, which are
+    /// stripped otherwise.
+    pub default_extension: Option<Cow<'a, str>>,
+    /// How extensions are spelled (as given by default).
+    pub normalize_extension: ExtensionNormalization,
+    /// The most extensions a name keeps, e.g. 1 for just the last one; the dots in front of
+    /// the others are replaced (any number by default).
+    pub max_extensions: Option<usize>,
+    /// Patterns whose matches are replaced; see [`DenyPattern`].
+    #[cfg_attr(feature = "schemars", schemars(with = "Vec<String>"))]
+    pub deny_patterns: Cow<'a, [DenyPattern]>,
+    /// Substrings whose matches are replaced; see [`DenySubstrings`].
+    #[cfg_attr(feature = "schemars", schemars(with = "Option<Vec<String>>"))]
+    pub deny_substrings: Option<DenySubstrings>,
+    /// Sanitizes the output again until it no longer changes, so that sanitizing twice
+    /// gives the same name as sanitizing once; see [`Options::with_fixed_point`].
+    pub fixed_point: bool,
+}
 
-    static NAMES_CLEANED: &'static [&'static str] = &[
-        "the quick brown fox jumped over the lazy dog",
-        "résumé",
-        "helloworld",
-        "helloworld",
-        "semi;colon.js",
-        ";leading-semi.js",
-        "slash.js",
-        "slash.js",
-        "colon.js",
-        "star.js",
-        "question.js",
-        "quote.js",
-        "singlequote'.js",
-        "brackets.js",
-        "ppes.js",
-        "plus+.js",
-        "'five and sixseven'.js",
-        " space at front",
-        "space at end",
-        ".period",
-        "period",
-        "relativepathtosomedir",
-        "abspathtosomedir",
-        "~.notsshauthorized_keys",
-        "",
-        "hw",
-        "hw",
-        "hw",
-        "",
-        "",
-        "",
-        "",
-        "",
-        "",
-        "",
-        "",
-        ".foobar",
-        "..foobar",
-        "....foobar",
-        "...foobar",
-        ".what",
-        "",
-        "foobar",
-    ];
+impl<'a> Default for Options<'a> {
+    fn default() -> Self {
+        Options::new()
+    }
+}
 
-    static NAMES_IS_SANITIZED: &'static [bool] = &[
-        true, true, false, false, true, true, false, false, false, false, false, false, true,
-        false, false, true, false, true, false, true, false, false, false, false, true, false,
-        false, false, false, false, false, false, false, false, false, false, false, false, false,
-        false, false, false, false,
-    ];
+impl<'a> Options<'a> {
+    /// The default options, usable in `const` and `static` items.
+    pub const fn new() -> Self {
+        Options {
+            windows: cfg!(windows),
+            windows_reserved_names: None,
+            windows_trailing: None,
+            replacement: Cow::Borrowed(""),
+            replacer: None,
+            control_replacement: None,
+            reserved_replacement: None,
+            trailing_replacement: None,
+            empty_fallback: None,
+            prefix: Cow::Borrowed(""),
+            suffix: Cow::Borrowed(""),
+            rule_set: RuleSet::Latest,
+            fallback: Fallback::None,
+            block_sync_junk: false,
+            max_length: 255,
+            min_length: None,
+            min_length_strategy: MinLengthStrategy::Pad,
+            charset: Charset::Any,
+            collapse_replacements: false,
+            collapse_whitespace: false,
+            trim: false,
+            replace_whitespace: None,
+            strip_bidi: false,
+            defuse_double_extensions: false,
+            leading_dot: LeadingDotPolicy::Allow,
+            strip_shell_metachars: false,
+            strip_expansion_chars: false,
+            fold_confusables: false,
+            max_depth: None,
+            depth_policy: DepthPolicy::Flatten,
+            joiner: '_',
+            suffix_style: SuffixStyle::Parenthesized,
+            max_extension_len: None,
+            path_limit: PathLimit::Auto,
+            length_unit: LengthUnit::Bytes,
+            truncation: TruncationStrategy::CutEnd,
+            extra_illegal: Cow::Borrowed(&[]),
+            allow: Cow::Borrowed(&[]),
+            allowlist: None,
+            ascii_only: false,
+            reserved_names: Cow::Borrowed(&[]),
+            allowed_extensions: None,
+            default_extension: None,
+            normalize_extension: ExtensionNormalization::Keep,
+            max_extensions: None,
+            deny_patterns: Cow::Borrowed(&[]),
+            deny_substrings: None,
+            fixed_point: false,
+        }
+    }
 
-    #[test]
-    fn it_works() {
-        // sanitize
-        let options = super::Options {
-            windows: true,
-            truncate: true,
-            replacement: "",
-        };
+    /// Detaches these options from any borrowed data.
+    pub fn into_owned(self) -> Options<'static> {
+        Options {
+            windows: self.windows,
+            windows_reserved_names: self.windows_reserved_names,
+            windows_trailing: self.windows_trailing,
+            replacement: Cow::Owned(self.replacement.into_owned()),
+            replacer: self.replacer,
+            control_replacement: self.control_replacement.map(|r| Cow::Owned(r.into_owned())),
+            reserved_replacement: self
+                .reserved_replacement
+                .map(|r| Cow::Owned(r.into_owned())),
+            trailing_replacement: self
+                .trailing_replacement
+                .map(|r| Cow::Owned(r.into_owned())),
+            empty_fallback: self.empty_fallback.map(|r| Cow::Owned(r.into_owned())),
+            prefix: Cow::Owned(self.prefix.into_owned()),
+            suffix: Cow::Owned(self.suffix.into_owned()),
+            rule_set: self.rule_set,
+            fallback: self.fallback,
+            block_sync_junk: self.block_sync_junk,
+            max_length: self.max_length,
+            min_length: self.min_length,
+            min_length_strategy: self.min_length_strategy,
+            charset: self.charset,
+            collapse_replacements: self.collapse_replacements,
+            collapse_whitespace: self.collapse_whitespace,
+            trim: self.trim,
+            replace_whitespace: self.replace_whitespace,
+            strip_bidi: self.strip_bidi,
+            defuse_double_extensions: self.defuse_double_extensions,
+            leading_dot: self.leading_dot,
+            strip_shell_metachars: self.strip_shell_metachars,
+            strip_expansion_chars: self.strip_expansion_chars,
+            fold_confusables: self.fold_confusables,
+            max_depth: self.max_depth,
+            depth_policy: self.depth_policy,
+            joiner: self.joiner,
+            suffix_style: self.suffix_style,
+            max_extension_len: self.max_extension_len,
+            path_limit: self.path_limit,
+            length_unit: self.length_unit,
+            truncation: self.truncation,
+            extra_illegal: Cow::Owned(self.extra_illegal.into_owned()),
+            allow: Cow::Owned(self.allow.into_owned()),
+            allowlist: self.allowlist.map(|chars| Cow::Owned(chars.into_owned())),
+            ascii_only: self.ascii_only,
+            reserved_names: self
+                .reserved_names
+                .iter()
+                .map(|name| Cow::Owned(name.clone().into_owned()))
+                .collect(),
+            allowed_extensions: self.allowed_extensions.map(|extensions| {
+                extensions
+                    .iter()
+                    .map(|extension| Cow::Owned(extension.clone().into_owned()))
+                    .collect()
+            }),
+            default_extension: (self.default_extension).map(|ext| Cow::Owned(ext.into_owned())),
+            normalize_extension: self.normalize_extension,
+            max_extensions: self.max_extensions,
+            deny_patterns: Cow::Owned(self.deny_patterns.into_owned()),
+            deny_substrings: self.deny_substrings,
+            fixed_point: self.fixed_point,
+        }
+    }
 
-        for (idx, name) in NAMES.iter().enumerate() {
-            assert_eq!(
-                super::sanitize_with_options(name, options.clone()),
-                NAMES_CLEANED[idx]
-            );
+    /// Borrows these options, which is cheap even when they own their data.
+    pub fn borrowed(&self) -> Options<'_> {
+        Options {
+            windows: self.windows,
+            windows_reserved_names: self.windows_reserved_names,
+            windows_trailing: self.windows_trailing,
+            replacement: Cow::Borrowed(&self.replacement),
+            replacer: self.replacer.clone(),
+            control_replacement: self.control_replacement.as_deref().map(Cow::Borrowed),
+            reserved_replacement: self.reserved_replacement.as_deref().map(Cow::Borrowed),
+            trailing_replacement: self.trailing_replacement.as_deref().map(Cow::Borrowed),
+            empty_fallback: self.empty_fallback.as_deref().map(Cow::Borrowed),
+            prefix: Cow::Borrowed(&self.prefix),
+            suffix: Cow::Borrowed(&self.suffix),
+            rule_set: self.rule_set,
+            fallback: self.fallback,
+            block_sync_junk: self.block_sync_junk,
+            max_length: self.max_length,
+            min_length: self.min_length,
+            min_length_strategy: self.min_length_strategy,
+            charset: self.charset,
+            collapse_replacements: self.collapse_replacements,
+            collapse_whitespace: self.collapse_whitespace,
+            trim: self.trim,
+            replace_whitespace: self.replace_whitespace,
+            strip_bidi: self.strip_bidi,
+            defuse_double_extensions: self.defuse_double_extensions,
+            leading_dot: self.leading_dot,
+            strip_shell_metachars: self.strip_shell_metachars,
+            strip_expansion_chars: self.strip_expansion_chars,
+            fold_confusables: self.fold_confusables,
+            max_depth: self.max_depth,
+            depth_policy: self.depth_policy,
+            joiner: self.joiner,
+            suffix_style: self.suffix_style,
+            max_extension_len: self.max_extension_len,
+            path_limit: self.path_limit,
+            length_unit: self.length_unit,
+            truncation: self.truncation,
+            extra_illegal: Cow::Borrowed(&self.extra_illegal),
+            allow: Cow::Borrowed(&self.allow),
+            allowlist: self.allowlist.as_deref().map(Cow::Borrowed),
+            ascii_only: self.ascii_only,
+            reserved_names: Cow::Borrowed(&self.reserved_names),
+            allowed_extensions: self.allowed_extensions.as_deref().map(Cow::Borrowed),
+            default_extension: self.default_extension.as_deref().map(Cow::Borrowed),
+            normalize_extension: self.normalize_extension,
+            max_extensions: self.max_extensions,
+            deny_patterns: Cow::Borrowed(&self.deny_patterns),
+            deny_substrings: self.deny_substrings.clone(),
+            fixed_point: self.fixed_point,
         }
+    }
 
-        let long = ::std::iter::repeat('a').take(300).collect::<String>();
-        let shorter = ::std::iter::repeat('a').take(255).collect::<String>();
-        assert_eq!(super::sanitize_with_options(long, options.clone()), shorter);
+    pub const fn with_windows(mut self, windows: bool) -> Self {
+        self.windows = windows;
+        self
+    }
 
-        // is_sanitized
-        let options = super::OptionsForCheck {
-            windows: true,
-            truncate: true,
+    pub const fn with_windows_reserved_names(
+        mut self,
+        windows_reserved_names: Option<bool>,
+    ) -> Self {
+        self.windows_reserved_names = windows_reserved_names;
+        self
+    }
+
+    pub const fn with_windows_trailing(mut self, windows_trailing: Option<bool>) -> Self {
+        self.windows_trailing = windows_trailing;
+        self
+    }
+
+    /// Turns truncation on (cutting the end, unless `truncation` says otherwise) or off.
+    #[deprecated(note = "use `with_truncation`, with `TruncationStrategy::None` for no limit")]
+    pub const fn with_truncate(mut self, truncate: bool) -> Self {
+        self.truncation = match (truncate, self.truncation) {
+            (false, _) => TruncationStrategy::None,
+            (true, TruncationStrategy::None) => TruncationStrategy::CutEnd,
+            (true, truncation) => truncation,
         };
+        self
+    }
 
-        for (idx, name) in NAMES.iter().enumerate() {
-            assert_eq!(
-                super::is_sanitized_with_options(name, options.clone()),
-                NAMES_IS_SANITIZED[idx]
-            );
-        }
+    pub fn with_replacement<R: Into<Cow<'a, str>>>(mut self, replacement: R) -> Self {
+        self.replacement = replacement.into();
+        self
+    }
 
-        let long = ::std::iter::repeat('a').take(300).collect::<String>();
-        assert_eq!(
-            super::is_sanitized_with_options(long, options.clone()),
-            false
-        );
+    /// Substitutes each replaced character with what `replace` returns for it, or with
+    /// the replacement when it returns `None`.
+    ///
+    /// ```
+    /// use std::borrow::Cow;
+    ///
+    /// let options = sanitize_filename::Options::new()
+    ///     .with_replacement("_")
+    ///     .with_replacer(|c| match c {
+    ///         ':' => Some(Cow::Borrowed("-")),
+    ///         '?' => Some(Cow::Borrowed("")),
+    ///         _ => None,
+    ///     });
+    /// let name = sanitize_filename::sanitize_with_options("12:30 why?\u{7}.txt", options);
+    /// assert_eq!(name, "12-30 why_.txt");
+    /// ```
+    pub fn with_replacer<F>(mut self, replace: F) -> Self
+    where
+        F: Fn(char) -> Option<Cow<'static, str>> + Send + Sync + 'static,
+    {
+        self.replacer = Some(Replacer::new(replace));
+        self
+    }
+
+    /// Substitutes punctuation with its fullwidth form, as [`Replacer::fullwidth`] does.
+    pub fn with_fullwidth_replacer(mut self) -> Self {
+        self.replacer = Some(Replacer::fullwidth());
+        self
+    }
+
+    /// Substitutes characters with `%XX` escapes, as [`Replacer::hex_escape`] does.
+    pub fn with_hex_escape_replacer(mut self) -> Self {
+        self.replacer = Some(Replacer::hex_escape());
+        self
+    }
+
+    pub fn with_control_replacement<R: Into<Cow<'a, str>>>(
+        mut self,
+        control_replacement: R,
+    ) -> Self {
+        self.control_replacement = Some(control_replacement.into());
+        self
+    }
+
+    pub fn with_reserved_replacement<R: Into<Cow<'a, str>>>(
+        mut self,
+        reserved_replacement: R,
+    ) -> Self {
+        self.reserved_replacement = Some(reserved_replacement.into());
+        self
+    }
+
+    pub fn with_trailing_replacement<R: Into<Cow<'a, str>>>(
+        mut self,
+        trailing_replacement: R,
+    ) -> Self {
+        self.trailing_replacement = Some(trailing_replacement.into());
+        self
+    }
+
+    /// Sanitizes `empty_fallback` instead of names of which nothing would be left, so
+    /// that the result is only empty if nothing would be left of `empty_fallback` either,
+    /// as of `.` or `??`; [`Sanitizer::try_new`] rejects such a fallback.
+    ///
+    /// ```
+    /// let options = sanitize_filename::Options::new().with_empty_fallback("untitled");
+    /// assert_eq!(sanitize_filename::sanitize_with_options("???", options.clone()), "untitled");
+    /// assert_eq!(sanitize_filename::sanitize_with_options("a?", options), "a");
+    /// ```
+    pub fn with_empty_fallback<R: Into<Cow<'a, str>>>(mut self, empty_fallback: R) -> Self {
+        self.empty_fallback = Some(empty_fallback.into());
+        self
+    }
+
+    /// Starts every sanitized name with `prefix`, e.g. `upload_`, which counts against
+    /// `max_length`: the rest of the name is cut to fit. A name that already starts with
+    /// `prefix` is not given it again, so sanitizing stays idempotent, and names of which
+    /// nothing would be left stay empty. The characters of `prefix` are replaced as those
+    /// of names are, under any rule set; [`Sanitizer::try_new`] rejects affixes that need
+    /// it, or that leave no room within `max_length`.
+    ///
+    /// ```
+    /// let options = sanitize_filename::Options::new()
+    ///     .with_prefix("upload_")
+    ///     .with_suffix(".part")
+    ///     .with_max_length(20);
+    /// let sanitize = |name| sanitize_filename::sanitize_with_options(name, options.clone());
+    /// assert_eq!(sanitize("a?b.txt"), "upload_ab.txt.part");
+    /// assert_eq!(sanitize("upload_ab.txt.part"), "upload_ab.txt.part");
+    /// assert_eq!(sanitize("0123456789abcdef"), "upload_01234567.part");
+    /// ```
+    pub fn with_prefix<R: Into<Cow<'a, str>>>(mut self, prefix: R) -> Self {
+        self.prefix = prefix.into();
+        self
+    }
+
+    /// Ends every sanitized name with `suffix`, e.g. `.part`, as
+    /// [`with_prefix`](Options::with_prefix) does at the start.
+    pub fn with_suffix<R: Into<Cow<'a, str>>>(mut self, suffix: R) -> Self {
+        self.suffix = suffix.into();
+        self
+    }
+
+    pub const fn with_rule_set(mut self, rule_set: RuleSet) -> Self {
+        self.rule_set = rule_set;
+        self
+    }
+
+    pub const fn with_fallback(mut self, fallback: Fallback) -> Self {
+        self.fallback = fallback;
+        self
+    }
+
+    pub const fn with_block_sync_junk(mut self, block_sync_junk: bool) -> Self {
+        self.block_sync_junk = block_sync_junk;
+        self
+    }
+
+    pub const fn with_max_length(mut self, max_length: usize) -> Self {
+        self.max_length = max_length;
+        self
+    }
+
+    /// Lengthens names shorter than `min_length` (counted in `length_unit`) as
+    /// `min_length_strategy` says, for backends that reject empty or one-character names.
+    ///
+    /// ```
+    /// let options = sanitize_filename::Options::new().with_min_length(3);
+    /// assert_eq!(sanitize_filename::sanitize_with_options("a.txt", options.clone()), "a.txt");
+    /// assert_eq!(sanitize_filename::sanitize_with_options("a", options.clone()), "a__");
+    /// assert_eq!(sanitize_filename::sanitize_with_options("?", options), "___");
+    /// ```
+    pub const fn with_min_length(mut self, min_length: usize) -> Self {
+        self.min_length = Some(min_length);
+        self
+    }
+
+    pub const fn without_min_length(mut self) -> Self {
+        self.min_length = None;
+        self
+    }
+
+    pub const fn with_min_length_strategy(
+        mut self,
+        min_length_strategy: MinLengthStrategy,
+    ) -> Self {
+        self.min_length_strategy = min_length_strategy;
+        self
+    }
+
+    pub const fn with_charset(mut self, charset: Charset) -> Self {
+        self.charset = charset;
+        self
+    }
+
+    pub const fn with_collapse_replacements(mut self, collapse_replacements: bool) -> Self {
+        self.collapse_replacements = collapse_replacements;
+        self
+    }
+
+    pub const fn with_collapse_whitespace(mut self, collapse_whitespace: bool) -> Self {
+        self.collapse_whitespace = collapse_whitespace;
+        self
+    }
+
+    pub const fn with_trim(mut self, trim: bool) -> Self {
+        self.trim = trim;
+        self
+    }
+
+    pub const fn with_replace_whitespace(mut self, replace_whitespace: Option<char>) -> Self {
+        self.replace_whitespace = replace_whitespace;
+        self
+    }
+
+    pub const fn with_strip_bidi(mut self, strip_bidi: bool) -> Self {
+        self.strip_bidi = strip_bidi;
+        self
+    }
+
+    pub const fn with_defuse_double_extensions(mut self, defuse_double_extensions: bool) -> Self {
+        self.defuse_double_extensions = defuse_double_extensions;
+        self
+    }
+
+    /// Strips leading dots (`LeadingDotPolicy::Strip`), or keeps them again.
+    #[deprecated(note = "use `with_leading_dot`, with `LeadingDotPolicy::Strip`")]
+    pub const fn with_strip_hidden_prefix(mut self, strip_hidden_prefix: bool) -> Self {
+        self.leading_dot = self.leading_dot.stripped(strip_hidden_prefix);
+        self
+    }
+
+    pub const fn with_leading_dot(mut self, leading_dot: LeadingDotPolicy) -> Self {
+        self.leading_dot = leading_dot;
+        self
+    }
+
+    pub const fn with_strip_shell_metachars(mut self, strip_shell_metachars: bool) -> Self {
+        self.strip_shell_metachars = strip_shell_metachars;
+        self
+    }
+
+    pub const fn with_strip_expansion_chars(mut self, strip_expansion_chars: bool) -> Self {
+        self.strip_expansion_chars = strip_expansion_chars;
+        self
+    }
+
+    pub const fn with_fold_confusables(mut self, fold_confusables: bool) -> Self {
+        self.fold_confusables = fold_confusables;
+        self
+    }
+
+    pub const fn with_max_depth(mut self, max_depth: Option<usize>) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    pub const fn with_depth_policy(mut self, depth_policy: DepthPolicy) -> Self {
+        self.depth_policy = depth_policy;
+        self
+    }
+
+    pub const fn with_joiner(mut self, joiner: char) -> Self {
+        self.joiner = joiner;
+        self
+    }
+
+    pub const fn with_suffix_style(mut self, suffix_style: SuffixStyle) -> Self {
+        self.suffix_style = suffix_style;
+        self
+    }
+
+    pub const fn with_max_extension_len(mut self, max_extension_len: Option<usize>) -> Self {
+        self.max_extension_len = max_extension_len;
+        self
+    }
+
+    pub const fn with_path_limit(mut self, path_limit: PathLimit) -> Self {
+        self.path_limit = path_limit;
+        self
+    }
+
+    pub const fn with_length_unit(mut self, length_unit: LengthUnit) -> Self {
+        self.length_unit = length_unit;
+        self
+    }
+
+    pub const fn with_truncation(mut self, truncation: TruncationStrategy) -> Self {
+        self.truncation = truncation;
+        self
+    }
+
+    /// Also replaces `extra_illegal`, and rejects it when checking.
+    ///
+    /// ```
+    /// const URL_UNSAFE: &[char] = &['#', '%', '&'];
+    ///
+    /// let options = sanitize_filename::Options::new()
+    ///     .with_replacement("_")
+    ///     .with_extra_illegal(URL_UNSAFE);
+    /// assert_eq!(sanitize_filename::sanitize_with_options("50% off#1.txt", options), "50_ off_1.txt");
+    /// ```
+    pub fn with_extra_illegal<C: Into<Cow<'a, [char]>>>(mut self, extra_illegal: C) -> Self {
+        self.extra_illegal = extra_illegal.into();
+        self
+    }
+
+    pub fn with_allow<C: Into<Cow<'a, [char]>>>(mut self, allow: C) -> Self {
+        self.allow = allow.into();
+        self
+    }
+
+    /// Replaces every character that is not in `allowlist` (or `allow`), rather than only
+    /// the ones that are known to be unsafe, e.g. for uploads to systems that are strict
+    /// about names. The other rules still apply on top, so `..` is replaced even though
+    /// [`PORTABLE_CHARS`] has `.`.
+    ///
+    /// ```
+    /// use sanitize_filename::{sanitize_with_options, Options, PORTABLE_CHARS};
+    ///
+    /// let options = Options::new().with_replacement("_").with_allowlist(PORTABLE_CHARS);
+    /// assert_eq!(sanitize_with_options("naïve résumé (1).pdf", options), "na_ve_r_sum___1_.pdf");
+    /// ```
+    ///
+    /// The replacement is removed instead if it is not allowed itself.
+    pub fn with_allowlist<C: Into<Cow<'a, [char]>>>(mut self, allowlist: C) -> Self {
+        self.allowlist = Some(allowlist.into());
+        self
+    }
+
+    pub fn without_allowlist(mut self) -> Self {
+        self.allowlist = None;
+        self
+    }
+
+    /// Replaces every character that is not ASCII (or in `allow`), for legacy systems, FTP
+    /// servers and tools that mishandle UTF-8 names. Unlike [`Charset::Portable`], this
+    /// does not fold accents, and keeps ASCII punctuation that is not illegal.
+    ///
+    /// ```
+    /// use sanitize_filename::{sanitize_with_options, Options};
+    ///
+    /// let options = Options::new().with_replacement("_").with_ascii_only(true);
+    /// assert_eq!(sanitize_with_options("Café #1 (日本).txt", options), "Caf_ #1 (__).txt");
+    /// ```
+    pub const fn with_ascii_only(mut self, ascii_only: bool) -> Self {
+        self.ascii_only = ascii_only;
+        self
+    }
+
+    /// Also replaces `reserved_names` with the Windows rules, and rejects them when
+    /// checking.
+    ///
+    /// ```
+    /// let options = sanitize_filename::Options::new()
+    ///     .with_windows(true)
+    ///     .with_replacement("_")
+    ///     .with_reserved_names(["desktop.ini", "ehthumbs.db"]);
+    /// assert_eq!(sanitize_filename::sanitize_with_options("Desktop.ini", options.clone()), "_");
+    /// assert_eq!(sanitize_filename::sanitize_with_options("desktop.ini.txt", options.clone()), "_");
+    /// assert_eq!(sanitize_filename::sanitize_with_options("desktop.txt", options), "desktop.txt");
+    /// ```
+    pub fn with_reserved_names<I, S>(mut self, reserved_names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<Cow<'a, str>>,
+    {
+        self.reserved_names = reserved_names.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Keeps only names with one of these extensions (given with or without the dot, and
+    /// matched ignoring ASCII case, so `jpg` also allows `.JPG` and `tar.gz` allows
+    /// `.tar.gz`), replacing other extensions with the
+    /// [`default_extension`](Options::with_default_extension) or stripping them. Names
+    /// without an extension are left alone.
+    ///
+    /// ```
+    /// use sanitize_filename::{sanitize_with_options, Options};
+    ///
+    /// let options = Options::new().with_allowed_extensions(["jpg", "png", "pdf"]);
+    /// assert_eq!(sanitize_with_options("photo.JPG", options.clone()), "photo.JPG");
+    /// assert_eq!(sanitize_with_options("payload.pdf.exe", options.clone()), "payload.pdf");
+    /// let options = options.with_default_extension("bin");
+    /// assert_eq!(sanitize_with_options("payload.exe", options), "payload.bin");
+    /// ```
+    pub fn with_allowed_extensions<I, S>(mut self, allowed_extensions: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<Cow<'a, str>>,
+    {
+        self.allowed_extensions = Some(allowed_extensions.into_iter().map(Into::into).collect());
+        self
+    }
+
+    pub fn without_allowed_extensions(mut self) -> Self {
+        self.allowed_extensions = None;
+        self
+    }
+
+    pub fn with_default_extension<S: Into<Cow<'a, str>>>(mut self, default_extension: S) -> Self {
+        self.default_extension = Some(default_extension.into());
+        self
+    }
+
+    /// Lowercases and trims extensions (`photo.JPG ` becomes `photo.jpg`) and, with
+    /// [`ExtensionNormalization::Canonical`], spells each of the [`EXTENSION_ALIASES`] the
+    /// usual way (`scan.TIF` becomes `scan.tiff`), so that stored names are consistent for
+    /// content-type detection.
+    ///
+    /// ```
+    /// use sanitize_filename::{sanitize_with_options, ExtensionNormalization, Options};
+    ///
+    /// let options = Options::new().with_normalize_extension(ExtensionNormalization::Lowercase);
+    /// assert_eq!(sanitize_with_options("Photo.JPEG ", options.clone()), "Photo.jpeg");
+    /// let options = options.with_normalize_extension(ExtensionNormalization::Canonical);
+    /// assert_eq!(sanitize_with_options("Photo.JPEG ", options), "Photo.jpg");
+    /// ```
+    pub const fn with_normalize_extension(
+        mut self,
+        normalize_extension: ExtensionNormalization,
+    ) -> Self {
+        self.normalize_extension = normalize_extension;
+        self
+    }
+
+    /// Keeps at most `max_extensions` extensions, replacing the dots in front of the others
+    /// so that systems which route on "the" extension see a predictable one.
+    ///
+    /// ```
+    /// let options = sanitize_filename::Options::new()
+    ///     .with_replacement("_")
+    ///     .with_max_extensions(1);
+    /// assert_eq!(
+    ///     sanitize_filename::sanitize_with_options("report.v2.final.pdf.exe", options),
+    ///     "report_v2_final_pdf.exe"
+    /// );
+    /// ```
+    pub const fn with_max_extensions(mut self, max_extensions: usize) -> Self {
+        self.max_extensions = Some(max_extensions);
+        self
+    }
+
+    pub const fn without_max_extensions(mut self) -> Self {
+        self.max_extensions = None;
+        self
+    }
+
+    /// Replaces the matches of each of `deny_patterns` (with the `regex` feature), and
+    /// rejects names with matches when checking; see [`DenyPattern::new`].
+    pub fn with_deny_patterns<I: IntoIterator<Item = DenyPattern>>(
+        mut self,
+        deny_patterns: I,
+    ) -> Self {
+        self.deny_patterns = deny_patterns.into_iter().collect();
+        self
+    }
+
+    /// Replaces the matches of `deny_substrings` (with the `aho-corasick` feature), and
+    /// rejects names with matches when checking; see [`DenySubstrings::new`].
+    pub fn with_deny_substrings(mut self, deny_substrings: DenySubstrings) -> Self {
+        self.deny_substrings = Some(deny_substrings);
+        self
+    }
+
+    pub fn without_deny_substrings(mut self) -> Self {
+        self.deny_substrings = None;
+        self
+    }
+
+    /// Sanitizes the output again, a bounded number of times, until it no longer changes,
+    /// so that sanitizing a sanitized name always leaves it alone. Names that never settle
+    /// become the first of their passes that is sanitized, and are then left alone, or
+    /// else what `empty_fallback` sanitizes to.
+    ///
+    /// Most options already give such output, but some combinations do not, e.g. a
+    /// leading `-` that is only exposed once an illegal character before it is removed.
+    ///
+    /// ```
+    /// use sanitize_filename::{sanitize_with_options, Options};
+    ///
+    /// let options = Options::new().with_fixed_point(true);
+    /// let once = sanitize_with_options("?-rf", options.clone());
+    /// assert_eq!(sanitize_with_options(&once, options), once);
+    /// ```
+    pub const fn with_fixed_point(mut self, fixed_point: bool) -> Self {
+        self.fixed_point = fixed_point;
+        self
+    }
+
+    /// Sets all the security rules as in `security`.
+    pub const fn with_security(mut self, security: Security) -> Self {
+        let rules = security.rules();
+        self.strip_bidi = rules.strip_bidi;
+        self.defuse_double_extensions = rules.defuse_double_extensions;
+        self.leading_dot = self.leading_dot.stripped(rules.strip_hidden_prefix);
+        self.strip_shell_metachars = rules.strip_shell_metachars;
+        self.strip_expansion_chars = rules.strip_expansion_chars;
+        self.fold_confusables = rules.fold_confusables;
+        self
+    }
+
+    /// A profile following common digital-preservation (e.g. BagIt) guidance: portable
+    /// ASCII characters only, with `_` for everything else including spaces, at most 128
+    /// bytes so that paths stay well within common limits, and the Windows and sync-junk
+    /// rules on so names survive being moved between systems.
+    pub const fn archival() -> Self {
+        Options {
+            windows: true,
+            windows_reserved_names: None,
+            windows_trailing: None,
+            replacement: Cow::Borrowed("_"),
+            replacer: None,
+            control_replacement: None,
+            reserved_replacement: None,
+            trailing_replacement: None,
+            empty_fallback: None,
+            prefix: Cow::Borrowed(""),
+            suffix: Cow::Borrowed(""),
+            rule_set: RuleSet::Latest,
+            fallback: Fallback::None,
+            block_sync_junk: true,
+            max_length: 128,
+            min_length: None,
+            min_length_strategy: MinLengthStrategy::Pad,
+            charset: Charset::Portable,
+            collapse_replacements: false,
+            collapse_whitespace: false,
+            trim: false,
+            replace_whitespace: None,
+            strip_bidi: false,
+            defuse_double_extensions: false,
+            leading_dot: LeadingDotPolicy::Allow,
+            strip_shell_metachars: false,
+            strip_expansion_chars: false,
+            fold_confusables: false,
+            max_depth: None,
+            depth_policy: DepthPolicy::Flatten,
+            joiner: '_',
+            suffix_style: SuffixStyle::Parenthesized,
+            max_extension_len: None,
+            path_limit: PathLimit::Auto,
+            length_unit: LengthUnit::Bytes,
+            truncation: TruncationStrategy::CutEnd,
+            extra_illegal: Cow::Borrowed(&[]),
+            allow: Cow::Borrowed(&[]),
+            allowlist: None,
+            ascii_only: false,
+            reserved_names: Cow::Borrowed(&[]),
+            allowed_extensions: None,
+            default_extension: None,
+            normalize_extension: ExtensionNormalization::Keep,
+            max_extensions: None,
+            deny_patterns: Cow::Borrowed(&[]),
+            deny_substrings: None,
+            fixed_point: false,
+        }
+    }
+
+    /// The Windows rules, whatever the platform.
+    pub const fn windows() -> Self {
+        Options::new().with_windows(true)
+    }
+
+    /// The rules of Linux, macOS and other Unix-like systems only, whatever the platform.
+    pub const fn posix() -> Self {
+        Options::new().with_windows(false)
+    }
+
+    /// Names that are valid on Windows, macOS and Linux alike: the Windows rules, which
+    /// cover the illegal characters of the others, and at most 255 bytes, which fits the
+    /// 255 UTF-16 units of NTFS and HFS+ as well.
+    pub const fn portable() -> Self {
+        Options::new()
+            .with_windows(true)
+            .with_max_length(255)
+            .with_length_unit(LengthUnit::Bytes)
+    }
+
+    /// Nothing but `A-Z a-z 0-9 . _ -` and spaces, with `_` for everything else, no
+    /// leading or trailing whitespace, and the Windows rules on. Accented letters lose their
+    /// accents with the `unicode` feature, as in [`Charset::Portable`].
+    ///
+    /// ```
+    /// let options = sanitize_filename::Options::strict();
+    /// let name = sanitize_filename::sanitize_with_options(" Q3 report (v2).pdf", options);
+    /// assert_eq!(name, "Q3 report _v2_.pdf");
+    /// ```
+    pub const fn strict() -> Self {
+        Options {
+            windows: true,
+            windows_reserved_names: None,
+            windows_trailing: None,
+            replacement: Cow::Borrowed("_"),
+            replacer: None,
+            control_replacement: None,
+            reserved_replacement: None,
+            trailing_replacement: None,
+            empty_fallback: None,
+            prefix: Cow::Borrowed(""),
+            suffix: Cow::Borrowed(""),
+            rule_set: RuleSet::Latest,
+            fallback: Fallback::None,
+            block_sync_junk: false,
+            max_length: 255,
+            min_length: None,
+            min_length_strategy: MinLengthStrategy::Pad,
+            charset: Charset::Portable,
+            collapse_replacements: false,
+            collapse_whitespace: false,
+            trim: true,
+            replace_whitespace: None,
+            strip_bidi: false,
+            defuse_double_extensions: false,
+            leading_dot: LeadingDotPolicy::Allow,
+            strip_shell_metachars: false,
+            strip_expansion_chars: false,
+            fold_confusables: false,
+            max_depth: None,
+            depth_policy: DepthPolicy::Flatten,
+            joiner: '_',
+            suffix_style: SuffixStyle::Parenthesized,
+            max_extension_len: None,
+            path_limit: PathLimit::Auto,
+            length_unit: LengthUnit::Bytes,
+            truncation: TruncationStrategy::CutEnd,
+            extra_illegal: Cow::Borrowed(&[]),
+            allow: Cow::Borrowed(&[' ']),
+            allowlist: None,
+            ascii_only: false,
+            reserved_names: Cow::Borrowed(&[]),
+            allowed_extensions: None,
+            default_extension: None,
+            normalize_extension: ExtensionNormalization::Keep,
+            max_extensions: None,
+            deny_patterns: Cow::Borrowed(&[]),
+            deny_substrings: None,
+            fixed_point: false,
+        }
+    }
+
+    /// Applies the options that are set in `overrides` on top of these options, e.g. a
+    /// user's configuration on top of a preset.
+    pub fn merge(mut self, overrides: OptionsOverride<'a>) -> Self {
+        if let Some(windows) = overrides.windows {
+            self.windows = windows;
+        }
+        if let Some(windows_reserved_names) = overrides.windows_reserved_names {
+            self.windows_reserved_names = windows_reserved_names;
+        }
+        if let Some(windows_trailing) = overrides.windows_trailing {
+            self.windows_trailing = windows_trailing;
+        }
+        if let Some(replacement) = overrides.replacement {
+            self.replacement = replacement;
+        }
+        if let Some(replacer) = overrides.replacer {
+            self.replacer = Some(replacer);
+        }
+        if let Some(control_replacement) = overrides.control_replacement {
+            self.control_replacement = control_replacement;
+        }
+        if let Some(reserved_replacement) = overrides.reserved_replacement {
+            self.reserved_replacement = reserved_replacement;
+        }
+        if let Some(trailing_replacement) = overrides.trailing_replacement {
+            self.trailing_replacement = trailing_replacement;
+        }
+        if let Some(empty_fallback) = overrides.empty_fallback {
+            self.empty_fallback = empty_fallback;
+        }
+        if let Some(prefix) = overrides.prefix {
+            self.prefix = prefix;
+        }
+        if let Some(suffix) = overrides.suffix {
+            self.suffix = suffix;
+        }
+        if let Some(rule_set) = overrides.rule_set {
+            self.rule_set = rule_set;
+        }
+        if let Some(fallback) = overrides.fallback {
+            self.fallback = fallback;
+        }
+        if let Some(block_sync_junk) = overrides.block_sync_junk {
+            self.block_sync_junk = block_sync_junk;
+        }
+        if let Some(max_length) = overrides.max_length {
+            self.max_length = max_length;
+        }
+        if let Some(min_length) = overrides.min_length {
+            self.min_length = min_length;
+        }
+        if let Some(min_length_strategy) = overrides.min_length_strategy {
+            self.min_length_strategy = min_length_strategy;
+        }
+        if let Some(charset) = overrides.charset {
+            self.charset = charset;
+        }
+        if let Some(collapse_replacements) = overrides.collapse_replacements {
+            self.collapse_replacements = collapse_replacements;
+        }
+        if let Some(collapse_whitespace) = overrides.collapse_whitespace {
+            self.collapse_whitespace = collapse_whitespace;
+        }
+        if let Some(trim) = overrides.trim {
+            self.trim = trim;
+        }
+        if let Some(replace_whitespace) = overrides.replace_whitespace {
+            self.replace_whitespace = replace_whitespace;
+        }
+        if let Some(strip_bidi) = overrides.strip_bidi {
+            self.strip_bidi = strip_bidi;
+        }
+        if let Some(defuse_double_extensions) = overrides.defuse_double_extensions {
+            self.defuse_double_extensions = defuse_double_extensions;
+        }
+        if let Some(leading_dot) = overrides.leading_dot {
+            self.leading_dot = leading_dot;
+        }
+        if let Some(strip_shell_metachars) = overrides.strip_shell_metachars {
+            self.strip_shell_metachars = strip_shell_metachars;
+        }
+        if let Some(strip_expansion_chars) = overrides.strip_expansion_chars {
+            self.strip_expansion_chars = strip_expansion_chars;
+        }
+        if let Some(fold_confusables) = overrides.fold_confusables {
+            self.fold_confusables = fold_confusables;
+        }
+        if let Some(max_depth) = overrides.max_depth {
+            self.max_depth = max_depth;
+        }
+        if let Some(depth_policy) = overrides.depth_policy {
+            self.depth_policy = depth_policy;
+        }
+        if let Some(joiner) = overrides.joiner {
+            self.joiner = joiner;
+        }
+        if let Some(suffix_style) = overrides.suffix_style {
+            self.suffix_style = suffix_style;
+        }
+        if let Some(max_extension_len) = overrides.max_extension_len {
+            self.max_extension_len = max_extension_len;
+        }
+        if let Some(path_limit) = overrides.path_limit {
+            self.path_limit = path_limit;
+        }
+        if let Some(length_unit) = overrides.length_unit {
+            self.length_unit = length_unit;
+        }
+        if let Some(truncation) = overrides.truncation {
+            self.truncation = truncation;
+        }
+        if let Some(extra_illegal) = overrides.extra_illegal {
+            self.extra_illegal = extra_illegal;
+        }
+        if let Some(allow) = overrides.allow {
+            self.allow = allow;
+        }
+        if let Some(allowlist) = overrides.allowlist {
+            self.allowlist = allowlist;
+        }
+        if let Some(ascii_only) = overrides.ascii_only {
+            self.ascii_only = ascii_only;
+        }
+        if let Some(reserved_names) = overrides.reserved_names {
+            self.reserved_names = reserved_names;
+        }
+        if let Some(allowed_extensions) = overrides.allowed_extensions {
+            self.allowed_extensions = allowed_extensions;
+        }
+        if let Some(default_extension) = overrides.default_extension {
+            self.default_extension = default_extension;
+        }
+        if let Some(normalize_extension) = overrides.normalize_extension {
+            self.normalize_extension = normalize_extension;
+        }
+        if let Some(max_extensions) = overrides.max_extensions {
+            self.max_extensions = max_extensions;
+        }
+        if let Some(deny_patterns) = overrides.deny_patterns {
+            self.deny_patterns = deny_patterns;
+        }
+        if let Some(deny_substrings) = overrides.deny_substrings {
+            self.deny_substrings = deny_substrings;
+        }
+        if let Some(fixed_point) = overrides.fixed_point {
+            self.fixed_point = fixed_point;
+        }
+        self
+    }
+}
+
+/// A partial set of [`Options`], for [`Options::merge`]; options that are `None` are left
+/// as they are.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+#[non_exhaustive]
+pub struct OptionsOverride<'a> {
+    pub windows: Option<bool>,
+    pub windows_reserved_names: Option<Option<bool>>,
+    pub windows_trailing: Option<Option<bool>>,
+    pub replacement: Option<Cow<'a, str>>,
+    #[cfg_attr(feature = "schemars", schemars(skip))]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub replacer: Option<Replacer>,
+    pub control_replacement: Option<Option<Cow<'a, str>>>,
+    pub reserved_replacement: Option<Option<Cow<'a, str>>>,
+    pub trailing_replacement: Option<Option<Cow<'a, str>>>,
+    pub empty_fallback: Option<Option<Cow<'a, str>>>,
+    pub prefix: Option<Cow<'a, str>>,
+    pub suffix: Option<Cow<'a, str>>,
+    pub rule_set: Option<RuleSet>,
+    pub fallback: Option<Fallback>,
+    pub block_sync_junk: Option<bool>,
+    pub max_length: Option<usize>,
+    pub min_length: Option<Option<usize>>,
+    pub min_length_strategy: Option<MinLengthStrategy>,
+    pub charset: Option<Charset>,
+    pub collapse_replacements: Option<bool>,
+    pub collapse_whitespace: Option<bool>,
+    pub trim: Option<bool>,
+    pub replace_whitespace: Option<Option<char>>,
+    pub strip_bidi: Option<bool>,
+    pub defuse_double_extensions: Option<bool>,
+    pub leading_dot: Option<LeadingDotPolicy>,
+    pub strip_shell_metachars: Option<bool>,
+    pub strip_expansion_chars: Option<bool>,
+    pub fold_confusables: Option<bool>,
+    pub max_depth: Option<Option<usize>>,
+    pub depth_policy: Option<DepthPolicy>,
+    pub joiner: Option<char>,
+    pub suffix_style: Option<SuffixStyle>,
+    pub max_extension_len: Option<Option<usize>>,
+    pub path_limit: Option<PathLimit>,
+    pub length_unit: Option<LengthUnit>,
+    pub truncation: Option<TruncationStrategy>,
+    pub extra_illegal: Option<Cow<'a, [char]>>,
+    pub allow: Option<Cow<'a, [char]>>,
+    pub allowlist: Option<Option<Cow<'a, [char]>>>,
+    pub ascii_only: Option<bool>,
+    pub reserved_names: Option<Cow<'a, [Cow<'a, str>]>>,
+    pub allowed_extensions: Option<Option<Cow<'a, [Cow<'a, str>]>>>,
+    pub default_extension: Option<Option<Cow<'a, str>>>,
+    pub normalize_extension: Option<ExtensionNormalization>,
+    pub max_extensions: Option<Option<usize>>,
+    #[cfg_attr(feature = "schemars", schemars(with = "Option<Vec<String>>"))]
+    pub deny_patterns: Option<Cow<'a, [DenyPattern]>>,
+    #[cfg_attr(feature = "schemars", schemars(with = "Option<Option<Vec<String>>>"))]
+    pub deny_substrings: Option<Option<DenySubstrings>>,
+    pub fixed_point: Option<bool>,
+}
+
+impl<'a> OptionsOverride<'a> {
+    /// Overrides nothing.
+    pub const fn new() -> Self {
+        OptionsOverride {
+            windows: None,
+            windows_reserved_names: None,
+            windows_trailing: None,
+            replacement: None,
+            replacer: None,
+            control_replacement: None,
+            reserved_replacement: None,
+            trailing_replacement: None,
+            empty_fallback: None,
+            prefix: None,
+            suffix: None,
+            rule_set: None,
+            fallback: None,
+            block_sync_junk: None,
+            max_length: None,
+            min_length: None,
+            min_length_strategy: None,
+            charset: None,
+            collapse_replacements: None,
+            collapse_whitespace: None,
+            trim: None,
+            replace_whitespace: None,
+            strip_bidi: None,
+            defuse_double_extensions: None,
+            leading_dot: None,
+            strip_shell_metachars: None,
+            strip_expansion_chars: None,
+            fold_confusables: None,
+            max_depth: None,
+            depth_policy: None,
+            joiner: None,
+            suffix_style: None,
+            max_extension_len: None,
+            path_limit: None,
+            length_unit: None,
+            truncation: None,
+            extra_illegal: None,
+            allow: None,
+            allowlist: None,
+            ascii_only: None,
+            reserved_names: None,
+            allowed_extensions: None,
+            default_extension: None,
+            normalize_extension: None,
+            max_extensions: None,
+            deny_patterns: None,
+            deny_substrings: None,
+            fixed_point: None,
+        }
+    }
+
+    /// Detaches these overrides from any borrowed data, like [`Options::into_owned`].
+    pub fn into_owned(self) -> OptionsOverride<'static> {
+        OptionsOverride {
+            windows: self.windows,
+            windows_reserved_names: self.windows_reserved_names,
+            windows_trailing: self.windows_trailing,
+            replacement: self.replacement.map(|r| Cow::Owned(r.into_owned())),
+            replacer: self.replacer,
+            control_replacement: self
+                .control_replacement
+                .map(|r| r.map(|r| Cow::Owned(r.into_owned()))),
+            reserved_replacement: self
+                .reserved_replacement
+                .map(|r| r.map(|r| Cow::Owned(r.into_owned()))),
+            trailing_replacement: self
+                .trailing_replacement
+                .map(|r| r.map(|r| Cow::Owned(r.into_owned()))),
+            empty_fallback: self
+                .empty_fallback
+                .map(|r| r.map(|r| Cow::Owned(r.into_owned()))),
+            prefix: self.prefix.map(|r| Cow::Owned(r.into_owned())),
+            suffix: self.suffix.map(|r| Cow::Owned(r.into_owned())),
+            rule_set: self.rule_set,
+            fallback: self.fallback,
+            block_sync_junk: self.block_sync_junk,
+            max_length: self.max_length,
+            min_length: self.min_length,
+            min_length_strategy: self.min_length_strategy,
+            charset: self.charset,
+            collapse_replacements: self.collapse_replacements,
+            collapse_whitespace: self.collapse_whitespace,
+            trim: self.trim,
+            replace_whitespace: self.replace_whitespace,
+            strip_bidi: self.strip_bidi,
+            defuse_double_extensions: self.defuse_double_extensions,
+            leading_dot: self.leading_dot,
+            strip_shell_metachars: self.strip_shell_metachars,
+            strip_expansion_chars: self.strip_expansion_chars,
+            fold_confusables: self.fold_confusables,
+            max_depth: self.max_depth,
+            depth_policy: self.depth_policy,
+            joiner: self.joiner,
+            suffix_style: self.suffix_style,
+            max_extension_len: self.max_extension_len,
+            path_limit: self.path_limit,
+            length_unit: self.length_unit,
+            truncation: self.truncation,
+            extra_illegal: self
+                .extra_illegal
+                .map(|chars| Cow::Owned(chars.into_owned())),
+            allow: self.allow.map(|chars| Cow::Owned(chars.into_owned())),
+            allowlist: self
+                .allowlist
+                .map(|chars| chars.map(|chars| Cow::Owned(chars.into_owned()))),
+            ascii_only: self.ascii_only,
+            reserved_names: self.reserved_names.map(|names| {
+                names
+                    .iter()
+                    .map(|name| Cow::Owned(name.clone().into_owned()))
+                    .collect()
+            }),
+            allowed_extensions: self.allowed_extensions.map(|extensions| {
+                extensions.map(|extensions| {
+                    extensions
+                        .iter()
+                        .map(|extension| Cow::Owned(extension.clone().into_owned()))
+                        .collect()
+                })
+            }),
+            default_extension: (self.default_extension)
+                .map(|ext| ext.map(|ext| Cow::Owned(ext.into_owned()))),
+            normalize_extension: self.normalize_extension,
+            max_extensions: self.max_extensions,
+            deny_patterns: (self.deny_patterns).map(|patterns| Cow::Owned(patterns.into_owned())),
+            deny_substrings: self.deny_substrings,
+            fixed_point: self.fixed_point,
+        }
+    }
+
+    pub const fn with_windows(mut self, windows: bool) -> Self {
+        self.windows = Some(windows);
+        self
+    }
+
+    pub const fn with_windows_reserved_names(
+        mut self,
+        windows_reserved_names: Option<bool>,
+    ) -> Self {
+        self.windows_reserved_names = Some(windows_reserved_names);
+        self
+    }
+
+    pub const fn with_windows_trailing(mut self, windows_trailing: Option<bool>) -> Self {
+        self.windows_trailing = Some(windows_trailing);
+        self
+    }
+
+    /// Turns truncation on (cutting the end, unless `truncation` says otherwise) or off.
+    #[deprecated(note = "use `with_truncation`, with `TruncationStrategy::None` for no limit")]
+    pub const fn with_truncate(mut self, truncate: bool) -> Self {
+        self.truncation = match (truncate, self.truncation) {
+            (false, _) => Some(TruncationStrategy::None),
+            (true, None | Some(TruncationStrategy::None)) => Some(TruncationStrategy::CutEnd),
+            (true, truncation) => truncation,
+        };
+        self
+    }
+
+    pub fn with_replacement<R: Into<Cow<'a, str>>>(mut self, replacement: R) -> Self {
+        self.replacement = Some(replacement.into());
+        self
+    }
+
+    pub fn with_replacer(mut self, replacer: Replacer) -> Self {
+        self.replacer = Some(replacer);
+        self
+    }
+
+    pub fn with_control_replacement<R: Into<Cow<'a, str>>>(
+        mut self,
+        control_replacement: R,
+    ) -> Self {
+        self.control_replacement = Some(Some(control_replacement.into()));
+        self
+    }
+
+    pub fn with_reserved_replacement<R: Into<Cow<'a, str>>>(
+        mut self,
+        reserved_replacement: R,
+    ) -> Self {
+        self.reserved_replacement = Some(Some(reserved_replacement.into()));
+        self
+    }
+
+    pub fn with_trailing_replacement<R: Into<Cow<'a, str>>>(
+        mut self,
+        trailing_replacement: R,
+    ) -> Self {
+        self.trailing_replacement = Some(Some(trailing_replacement.into()));
+        self
+    }
+
+    pub fn with_empty_fallback<R: Into<Cow<'a, str>>>(mut self, empty_fallback: R) -> Self {
+        self.empty_fallback = Some(Some(empty_fallback.into()));
+        self
+    }
+
+    pub fn with_prefix<R: Into<Cow<'a, str>>>(mut self, prefix: R) -> Self {
+        self.prefix = Some(prefix.into());
+        self
+    }
+
+    pub fn with_suffix<R: Into<Cow<'a, str>>>(mut self, suffix: R) -> Self {
+        self.suffix = Some(suffix.into());
+        self
+    }
+
+    pub const fn with_rule_set(mut self, rule_set: RuleSet) -> Self {
+        self.rule_set = Some(rule_set);
+        self
+    }
+
+    pub const fn with_fallback(mut self, fallback: Fallback) -> Self {
+        self.fallback = Some(fallback);
+        self
+    }
+
+    pub const fn with_block_sync_junk(mut self, block_sync_junk: bool) -> Self {
+        self.block_sync_junk = Some(block_sync_junk);
+        self
+    }
+
+    pub const fn with_max_length(mut self, max_length: usize) -> Self {
+        self.max_length = Some(max_length);
+        self
+    }
+
+    pub const fn with_min_length(mut self, min_length: usize) -> Self {
+        self.min_length = Some(Some(min_length));
+        self
+    }
+
+    /// Unsets `min_length` when merged.
+    pub const fn without_min_length(mut self) -> Self {
+        self.min_length = Some(None);
+        self
+    }
+
+    pub const fn with_min_length_strategy(
+        mut self,
+        min_length_strategy: MinLengthStrategy,
+    ) -> Self {
+        self.min_length_strategy = Some(min_length_strategy);
+        self
+    }
+
+    pub const fn with_charset(mut self, charset: Charset) -> Self {
+        self.charset = Some(charset);
+        self
+    }
+
+    pub const fn with_collapse_replacements(mut self, collapse_replacements: bool) -> Self {
+        self.collapse_replacements = Some(collapse_replacements);
+        self
+    }
+
+    pub const fn with_collapse_whitespace(mut self, collapse_whitespace: bool) -> Self {
+        self.collapse_whitespace = Some(collapse_whitespace);
+        self
+    }
+
+    pub const fn with_trim(mut self, trim: bool) -> Self {
+        self.trim = Some(trim);
+        self
+    }
+
+    pub const fn with_replace_whitespace(mut self, replace_whitespace: Option<char>) -> Self {
+        self.replace_whitespace = Some(replace_whitespace);
+        self
+    }
+
+    pub const fn with_strip_bidi(mut self, strip_bidi: bool) -> Self {
+        self.strip_bidi = Some(strip_bidi);
+        self
+    }
+
+    pub const fn with_defuse_double_extensions(mut self, defuse_double_extensions: bool) -> Self {
+        self.defuse_double_extensions = Some(defuse_double_extensions);
+        self
+    }
+
+    /// Strips leading dots (`LeadingDotPolicy::Strip`), or keeps them again.
+    #[deprecated(note = "use `with_leading_dot`, with `LeadingDotPolicy::Strip`")]
+    pub const fn with_strip_hidden_prefix(mut self, strip_hidden_prefix: bool) -> Self {
+        self.leading_dot = match (strip_hidden_prefix, self.leading_dot) {
+            (true, _) => Some(LeadingDotPolicy::Strip),
+            (false, Some(leading_dot)) => Some(leading_dot.stripped(false)),
+            (false, None) => None,
+        };
+        self
+    }
+
+    pub const fn with_leading_dot(mut self, leading_dot: LeadingDotPolicy) -> Self {
+        self.leading_dot = Some(leading_dot);
+        self
+    }
+
+    pub const fn with_strip_shell_metachars(mut self, strip_shell_metachars: bool) -> Self {
+        self.strip_shell_metachars = Some(strip_shell_metachars);
+        self
+    }
+
+    pub const fn with_strip_expansion_chars(mut self, strip_expansion_chars: bool) -> Self {
+        self.strip_expansion_chars = Some(strip_expansion_chars);
+        self
+    }
+
+    pub const fn with_fold_confusables(mut self, fold_confusables: bool) -> Self {
+        self.fold_confusables = Some(fold_confusables);
+        self
+    }
+
+    pub const fn with_max_depth(mut self, max_depth: Option<usize>) -> Self {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    pub const fn with_depth_policy(mut self, depth_policy: DepthPolicy) -> Self {
+        self.depth_policy = Some(depth_policy);
+        self
+    }
+
+    pub const fn with_joiner(mut self, joiner: char) -> Self {
+        self.joiner = Some(joiner);
+        self
+    }
+
+    pub const fn with_suffix_style(mut self, suffix_style: SuffixStyle) -> Self {
+        self.suffix_style = Some(suffix_style);
+        self
+    }
+
+    pub const fn with_max_extension_len(mut self, max_extension_len: Option<usize>) -> Self {
+        self.max_extension_len = Some(max_extension_len);
+        self
+    }
+
+    pub const fn with_path_limit(mut self, path_limit: PathLimit) -> Self {
+        self.path_limit = Some(path_limit);
+        self
+    }
+
+    pub const fn with_length_unit(mut self, length_unit: LengthUnit) -> Self {
+        self.length_unit = Some(length_unit);
+        self
+    }
+
+    pub const fn with_truncation(mut self, truncation: TruncationStrategy) -> Self {
+        self.truncation = Some(truncation);
+        self
+    }
+
+    pub fn with_extra_illegal<C: Into<Cow<'a, [char]>>>(mut self, extra_illegal: C) -> Self {
+        self.extra_illegal = Some(extra_illegal.into());
+        self
+    }
+
+    pub fn with_allow<C: Into<Cow<'a, [char]>>>(mut self, allow: C) -> Self {
+        self.allow = Some(allow.into());
+        self
+    }
+
+    pub fn with_allowlist<C: Into<Cow<'a, [char]>>>(mut self, allowlist: C) -> Self {
+        self.allowlist = Some(Some(allowlist.into()));
+        self
+    }
+
+    /// Unsets `allowlist` when merged.
+    pub fn without_allowlist(mut self) -> Self {
+        self.allowlist = Some(None);
+        self
+    }
+
+    pub const fn with_ascii_only(mut self, ascii_only: bool) -> Self {
+        self.ascii_only = Some(ascii_only);
+        self
+    }
+
+    pub fn with_reserved_names<I, S>(mut self, reserved_names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<Cow<'a, str>>,
+    {
+        self.reserved_names = Some(reserved_names.into_iter().map(Into::into).collect());
+        self
+    }
+
+    pub fn with_allowed_extensions<I, S>(mut self, allowed_extensions: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<Cow<'a, str>>,
+    {
+        let allowed_extensions = allowed_extensions.into_iter().map(Into::into).collect();
+        self.allowed_extensions = Some(Some(allowed_extensions));
+        self
+    }
+
+    /// Unsets `allowed_extensions` when merged.
+    pub fn without_allowed_extensions(mut self) -> Self {
+        self.allowed_extensions = Some(None);
+        self
+    }
+
+    pub fn with_default_extension<S: Into<Cow<'a, str>>>(mut self, default_extension: S) -> Self {
+        self.default_extension = Some(Some(default_extension.into()));
+        self
+    }
+
+    pub const fn with_normalize_extension(
+        mut self,
+        normalize_extension: ExtensionNormalization,
+    ) -> Self {
+        self.normalize_extension = Some(normalize_extension);
+        self
+    }
+
+    pub const fn with_max_extensions(mut self, max_extensions: usize) -> Self {
+        self.max_extensions = Some(Some(max_extensions));
+        self
+    }
+
+    /// Unsets `max_extensions` when merged.
+    pub const fn without_max_extensions(mut self) -> Self {
+        self.max_extensions = Some(None);
+        self
+    }
+
+    pub fn with_deny_patterns<I: IntoIterator<Item = DenyPattern>>(
+        mut self,
+        deny_patterns: I,
+    ) -> Self {
+        self.deny_patterns = Some(deny_patterns.into_iter().collect());
+        self
+    }
+
+    pub fn with_deny_substrings(mut self, deny_substrings: DenySubstrings) -> Self {
+        self.deny_substrings = Some(Some(deny_substrings));
+        self
+    }
+
+    /// Unsets `deny_substrings` when merged.
+    pub fn without_deny_substrings(mut self) -> Self {
+        self.deny_substrings = Some(None);
+        self
+    }
+
+    pub const fn with_fixed_point(mut self, fixed_point: bool) -> Self {
+        self.fixed_point = Some(fixed_point);
+        self
+    }
+
+    /// Sets all the security rules as in `security`.
+    pub const fn with_security(mut self, security: Security) -> Self {
+        let rules = security.rules();
+        self.strip_bidi = Some(rules.strip_bidi);
+        self.defuse_double_extensions = Some(rules.defuse_double_extensions);
+        self.leading_dot = match (rules.strip_hidden_prefix, self.leading_dot) {
+            (true, _) => Some(LeadingDotPolicy::Strip),
+            (false, Some(leading_dot)) => Some(leading_dot.stripped(false)),
+            (false, None) => None,
+        };
+        self.strip_shell_metachars = Some(rules.strip_shell_metachars);
+        self.strip_expansion_chars = Some(rules.strip_expansion_chars);
+        self.fold_confusables = Some(rules.fold_confusables);
+        self
+    }
+}
+
+pub fn sanitize<S: AsRef<str>>(name: S) -> String {
+    sanitize_with_options(name, Options::default())
+}
+
+// Replaces illegal and control characters in a single pass, allocating the output once.
+//
+// Rejected characters used to be replaced by two passes, illegal characters first and
+// then control characters, so control characters inside a replacement for an illegal
+// character are themselves replaced. `illegal_replacement` preserves that.
+fn replace_chars<'n>(
+    name: &'n str,
+    replacement: &str,
+    control_replacement: &str,
+    replacer: Option<&Replacer>,
+    extra_illegal: &CharSet,
+) -> Cow<'n, str> {
+    let is_illegal_char = |c: char| is_illegal_char(c) || extra_illegal.contains(c);
+    let rejected = |c: char| is_illegal_char(c) || is_control_char(c);
+    let first = match name.find(rejected) {
+        Some(first) => first,
+        None => return Cow::Borrowed(name),
+    };
+
+    let illegal_replacement = replace_control_chars(replacement, control_replacement);
+    if let Some(replacer) = replacer {
+        let mut out = String::with_capacity(name.len());
+        out.push_str(&name[..first]);
+        for c in name[first..].chars() {
+            if is_illegal_char(c) {
+                push_substitute(&mut out, c, &illegal_replacement, Some(replacer));
+            } else if is_control_char(c) {
+                push_substitute(&mut out, c, control_replacement, Some(replacer));
+            } else {
+                out.push(c);
+            }
+        }
+        return Cow::Owned(out);
+    }
+
+    let len = first
+        + name[first..]
+            .chars()
+            .map(|c| {
+                if is_illegal_char(c) {
+                    illegal_replacement.len()
+                } else if is_control_char(c) {
+                    control_replacement.len()
+                } else {
+                    c.len_utf8()
+                }
+            })
+            .sum::<usize>();
+
+    let mut out = String::with_capacity(len);
+    out.push_str(&name[..first]);
+    for c in name[first..].chars() {
+        if is_illegal_char(c) {
+            out.push_str(&illegal_replacement);
+        } else if is_control_char(c) {
+            out.push_str(control_replacement);
+        } else {
+            out.push(c);
+        }
+    }
+    debug_assert_eq!(out.len(), len);
+    Cow::Owned(out)
+}
+
+fn replace_control_chars<'n>(name: &'n str, replacement: &str) -> Cow<'n, str> {
+    if !name.chars().any(is_control_char) {
+        return Cow::Borrowed(name);
+    }
+    let len = name
+        .chars()
+        .map(|c| {
+            if is_control_char(c) {
+                replacement.len()
+            } else {
+                c.len_utf8()
+            }
+        })
+        .sum();
+    let mut out = String::with_capacity(len);
+    for c in name.chars() {
+        if is_control_char(c) {
+            out.push_str(replacement);
+        } else {
+            out.push(c);
+        }
+    }
+    Cow::Owned(out)
+}
+
+// Whether nothing of `original` is left in `sanitized` but replacements.
+pub(crate) fn is_unsalvageable(original: &str, sanitized: &str, replacement: &str) -> bool {
+    if original.is_empty() || original == sanitized {
+        return false;
+    }
+    sanitized.is_empty()
+        || (!replacement.is_empty() && sanitized.split(replacement).all(str::is_empty))
+}
+
+// Replaces each run of consecutive `replacement`s in `name` with a single one.
+fn collapse_runs<'n>(name: Cow<'n, str>, replacement: &str) -> Cow<'n, str> {
+    if replacement.is_empty() || !name.contains(&*replacement.repeat(2)) {
+        return name;
+    }
+    let mut out = String::with_capacity(name.len());
+    let mut rest = &*name;
+    while let Some(idx) = rest.find(replacement) {
+        out.push_str(&rest[..idx]);
+        out.push_str(replacement);
+        rest = &rest[idx..];
+        while let Some(after) = rest.strip_prefix(replacement) {
+            rest = after;
+        }
+    }
+    out.push_str(rest);
+    Cow::Owned(out)
+}
+
+// Replaces each run of two or more whitespace characters in `name` with a single space.
+fn collapse_whitespace_runs(name: Cow<str>) -> Cow<str> {
+    let is_run = |(a, b): (char, char)| a.is_whitespace() && b.is_whitespace();
+    if !name.chars().zip(name.chars().skip(1)).any(is_run) {
+        return name;
+    }
+    let mut out = String::with_capacity(name.len());
+    for c in name.chars() {
+        if !c.is_whitespace() {
+            out.push(c);
+        } else if out.ends_with(char::is_whitespace) {
+            out.pop();
+            out.push(' ');
+        } else {
+            out.push(c);
+        }
+    }
+    Cow::Owned(out)
+}
+
+pub fn sanitize_with_options<S: AsRef<str>>(name: S, options: Options) -> String {
+    Sanitizer::new(options).sanitize(name)
+}
+
+/// Sanitizes `name` where it is, as [`sanitize_with_options`] would, reusing its buffer: a
+/// name that only loses characters at its ends (trailing dots with `windows`, or its end
+/// when truncated) is shortened without allocating, and other names are copied back into
+/// its capacity. For many names, [`Sanitizer::sanitize_in_place`] also saves deriving
+/// the checks each time.
+///
+/// ```
+/// let options = sanitize_filename::Options::new().with_windows(true);
+/// let mut name = String::from("report.pdf. ");
+/// sanitize_filename::sanitize_in_place(&mut name, &options);
+/// assert_eq!(name, "report.pdf");
+/// ```
+pub fn sanitize_in_place(name: &mut String, options: &Options) {
+    Sanitizer::new(options.borrowed()).sanitize_in_place(name)
+}
+
+/// Writes `name` sanitized as [`sanitize_with_options`] would into `out`, e.g. the end of
+/// an existing `String`, a formatter or a fixed buffer, without an intermediate `String`
+/// when sanitizing only removes characters at the ends of the name.
+///
+/// ```
+/// let mut path = String::from("uploads/");
+/// let options = sanitize_filename::Options::new();
+/// sanitize_filename::sanitize_to("a/b.txt", &options, &mut path).unwrap();
+/// assert_eq!(path, "uploads/ab.txt");
+/// ```
+pub fn sanitize_to<W: fmt::Write>(name: &str, options: &Options, out: &mut W) -> fmt::Result {
+    Sanitizer::new(options.borrowed()).sanitize_to(name, out)
+}
+
+/// Rejects `name` if it is not sanitized with the default options, instead of rewriting
+/// it; see [`try_sanitize_with_options`].
+pub fn try_sanitize(name: &str) -> Result<Cow<'_, str>, SanitizeError> {
+    try_sanitize_with_options(name, Options::default())
+}
+
+/// Rejects `name` with the first rule it breaks under `options`, rather than replacing
+/// what is wrong with it.
+///
+/// Names that break no rule come back as [`sanitize_with_options`] gives them, borrowed
+/// when it leaves them alone; only the tidying options such as [`Options::with_trim`] and
+/// [`Options::with_collapse_whitespace`] still change them.
+///
+/// ```
+/// use sanitize_filename::{try_sanitize_with_options, Options, Violation};
+///
+/// let options = Options::new().with_trim(true);
+/// assert_eq!(try_sanitize_with_options("report.pdf ", options.clone()).unwrap(), "report.pdf");
+/// let err = try_sanitize_with_options("a:b.pdf", options).unwrap_err();
+/// assert_eq!(err.0, Violation::IllegalChar { offset: 1, c: ':' });
+/// assert_eq!(err.to_string(), "not a sanitized file name: illegal character ':' at byte 1");
+/// ```
+pub fn try_sanitize_with_options<'n>(
+    name: &'n str,
+    options: Options,
+) -> Result<Cow<'n, str>, SanitizeError> {
+    let sanitizer = Sanitizer::new(options);
+    if let Some(violation) = first_violation(name, sanitizer.check_options()) {
+        return Err(SanitizeError(violation));
+    }
+    let sanitized = sanitizer.sanitize(name);
+    Ok(if sanitized == name {
+        Cow::Borrowed(name)
+    } else {
+        Cow::Owned(sanitized)
+    })
+}
+
+// Sanitizes `name` without `empty_fallback`, where `check` and `lookup` are built from
+// `options`. What is left of `name` comes back borrowed.
+pub(crate) fn sanitize_checked<'n>(
+    name: &'n str,
+    options: Options,
+    check: &OptionsForCheck,
+    lookup: &Lookup,
+) -> Cow<'n, str> {
+    let name = sanitize_once(name, options.borrowed(), lookup);
+    if options.rule_set == RuleSet::V1 || check_name(&name, check, lookup) {
+        return name;
+    }
+
+    // A replacement can be rejected itself (`"??"`, `"con"`), and cutting a name can
+    // expose a trailing space; sanitize the output again without any replacement, and
+    // give up on the name if even that does not converge, leaving it to `empty_fallback`.
+    let mut plain = options.with_replacement("");
+    plain.replacer = None;
+    plain.control_replacement = None;
+    plain.reserved_replacement = None;
+    plain.trailing_replacement = None;
+    let mut name = name.into_owned();
+    for _ in 0..4 {
+        name = sanitize_once(&name, plain.borrowed(), lookup).into_owned();
+        if check_name(&name, check, lookup) {
+            return Cow::Owned(name);
+        }
+    }
+    Cow::Borrowed("")
+}
+
+// Replaces the characters that `options` reject, each on its own, where `lookup` is built
+// from `options`; with `edges`, also trims the edges that `Charset::Portable` rejects,
+// which only whole names have.
+pub(crate) fn replace_each_char<'n>(
+    name: &'n str,
+    options: &Options,
+    lookup: &Lookup,
+    edges: bool,
+) -> Cow<'n, str> {
+    let replacement: &str = &options.replacement;
+    let control_replacement = options
+        .control_replacement
+        .as_deref()
+        .unwrap_or(replacement);
+    let replacer = options.replacer.as_ref();
+    let allow = &lookup.allow;
+    let security = SecurityRules {
+        strip_bidi: options.strip_bidi,
+        defuse_double_extensions: options.defuse_double_extensions,
+        strip_hidden_prefix: options.leading_dot == LeadingDotPolicy::Strip,
+        strip_shell_metachars: options.strip_shell_metachars,
+        strip_expansion_chars: options.strip_expansion_chars,
+        fold_confusables: options.fold_confusables,
+    };
+    let extra_illegal = &lookup.extra_illegal;
+    let mut name = match replace_unsafe_chars(name, replacement, replacer, allow, &security) {
+        Cow::Borrowed(name) => replace_chars(
+            name,
+            replacement,
+            control_replacement,
+            replacer,
+            extra_illegal,
+        ),
+        Cow::Owned(name) => {
+            let replaced = match replace_chars(
+                &name,
+                replacement,
+                control_replacement,
+                replacer,
+                extra_illegal,
+            ) {
+                Cow::Owned(replaced) => Some(replaced),
+                Cow::Borrowed(_) => None,
+            };
+            Cow::Owned(replaced.unwrap_or(name))
+        }
+    };
+    if options.charset == Charset::Portable {
+        name = to_portable(name, replacement, replacer, allow);
+        if edges {
+            name = trim_portable_edges(name);
+        }
+    }
+    if let Some(allowlist) = &lookup.allowlist {
+        let kept = |c: char| allowlist.contains(c) || allow.contains(c);
+        name = keep_chars(name, kept, replacement, replacer);
+    }
+    if options.ascii_only {
+        let kept = |c: char| c.is_ascii() || allow.contains(c);
+        name = keep_chars(name, kept, replacement, replacer);
+    }
+    name
+}
+
+// Replaces what `options` reject in `part`, a piece of a name that `FileNameBuilder`
+// assembles: its characters, denied patterns and substrings, and whitespace. The rules
+// for whole names, from reserved names to affixes and `min_length`, are left to the
+// assembled name.
+pub(crate) fn sanitize_part(part: &str, options: &Options) -> String {
+    let replacement: &str = &options.replacement;
+    let mut part = replace_each_char(part, options, &Lookup::from(options), false);
+    for pattern in options.deny_patterns.iter() {
+        part = pattern.replace_all(part, replacement);
+    }
+    if let Some(deny_substrings) = &options.deny_substrings {
+        part = deny_substrings.replace_all(part, replacement);
+    }
+    if options.collapse_replacements {
+        part = collapse_runs(part, replacement);
+    }
+    if options.collapse_whitespace {
+        part = collapse_whitespace_runs(part);
+    }
+    if let Some(separator) = options.replace_whitespace {
+        if part.contains(char::is_whitespace) {
+            part = Cow::Owned(
+                part.chars()
+                    .map(|c| if c.is_whitespace() { separator } else { c })
+                    .collect(),
+            );
+        }
+    }
+    part.into_owned()
+}
+
+// How long `options.prefix` and `options.suffix` are together, once their characters are
+// replaced, in `options.length_unit`.
+pub(crate) fn affixes_len(options: &Options) -> usize {
+    let unit = options.length_unit;
+    let lookup = Lookup::from(options);
+    unit.measure(&replace_each_char(&options.prefix, options, &lookup, false))
+        + unit.measure(&replace_each_char(&options.suffix, options, &lookup, false))
+}
+
+// Replaced whole names are owned, so that a borrowed result is always what is left of
+// `original`.
+fn sanitize_once<'n>(original: &'n str, mut options: Options, lookup: &Lookup) -> Cow<'n, str> {
+    // The affixes are put back at the end, so they go through the character rules too,
+    // whatever the rule set: `../` must not make names climb out of their directory.
+    if let Cow::Owned(prefix) = replace_each_char(&options.prefix, &options, lookup, false) {
+        options.prefix = Cow::Owned(prefix);
+    }
+    if let Cow::Owned(suffix) = replace_each_char(&options.suffix, &options, lookup, false) {
+        options.suffix = Cow::Owned(suffix);
+    }
+    // They are not added twice to names that carry them already, unless nothing else is
+    // left of the name; the rest of the name makes do with what length they leave.
+    let stripped = original.strip_prefix(&*options.prefix).unwrap_or(original);
+    let stripped = stripped.strip_suffix(&*options.suffix).unwrap_or(stripped);
+    let original = if stripped.is_empty() {
+        original
+    } else {
+        stripped
+    };
+    // Before the characters are replaced, as `Icon\r` would otherwise become `Icon`.
+    let replaced = (!(options.block_sync_junk && is_sync_junk(original)))
+        .then(|| replace_each_char(original, &options, lookup, true));
+
+    let Options {
+        windows,
+        windows_reserved_names,
+        windows_trailing,
+        replacement,
+        replacer: _,
+        control_replacement: _,
+        reserved_replacement,
+        trailing_replacement,
+        empty_fallback,
+        prefix,
+        suffix,
+        rule_set,
+        fallback,
+        block_sync_junk: _,
+        max_length,
+        min_length,
+        min_length_strategy,
+        charset,
+        collapse_replacements,
+        collapse_whitespace,
+        trim,
+        replace_whitespace,
+        strip_bidi: _,
+        defuse_double_extensions,
+        leading_dot,
+        strip_shell_metachars,
+        strip_expansion_chars: _,
+        fold_confusables: _,
+        max_depth: _,
+        depth_policy: _,
+        joiner: _,
+        suffix_style: _,
+        max_extension_len,
+        path_limit: _,
+        length_unit,
+        truncation,
+        extra_illegal: _,
+        allow: _,
+        allowlist: _,
+        ascii_only: _,
+        reserved_names: _,
+        allowed_extensions,
+        default_extension,
+        normalize_extension,
+        max_extensions,
+        deny_patterns,
+        deny_substrings,
+        fixed_point: _,
+    } = options;
+    let replacement: &str = &replacement;
+    let reserved_replacement = reserved_replacement.as_deref().unwrap_or(replacement);
+    let trailing_replacement = trailing_replacement.as_deref().unwrap_or(replacement);
+
+    let affixes = length_unit.measure(&prefix) + length_unit.measure(&suffix);
+    let max_length = max_length.saturating_sub(affixes);
+    let min_length = min_length.map(|min_length| min_length.saturating_sub(affixes));
+
+    let mut name = match replaced {
+        Some(name) => name,
+        None => Cow::Owned(reserved_replacement.to_owned()),
+    };
+    for pattern in deny_patterns.iter() {
+        name = pattern.replace_all(name, replacement);
+    }
+    if let Some(deny_substrings) = &deny_substrings {
+        name = deny_substrings.replace_all(name, replacement);
+    }
+    if collapse_replacements {
+        name = collapse_runs(name, replacement);
+    }
+    if collapse_whitespace {
+        name = collapse_whitespace_runs(name);
+    }
+    // Names of only whitespace get the reserved replacement instead.
+    if trim && !(rule_set == RuleSet::Latest && is_whitespace_only(&name)) {
+        name = match name {
+            Cow::Borrowed(name) => Cow::Borrowed(name.trim()),
+            Cow::Owned(name) if name.trim().len() == name.len() => Cow::Owned(name),
+            Cow::Owned(name) => Cow::Owned(name.trim().to_owned()),
+        };
+    }
+    if is_reserved(&name) {
+        name = Cow::Owned(reserved_replacement.to_owned());
+    } else {
+        let strip_dots = leading_dot == LeadingDotPolicy::Strip;
+        name = strip_unsafe_prefix(name, strip_dots, strip_shell_metachars);
+        if leading_dot == LeadingDotPolicy::Prefix && name.starts_with('.') {
+            name = Cow::Owned(format!("_{}", name));
+        }
+    }
+    if rule_set == RuleSet::Latest && is_whitespace_only(&name) {
+        name = Cow::Owned(reserved_replacement.to_owned());
+    }
+    if let Some(separator) = replace_whitespace {
+        if name.contains(char::is_whitespace) {
+            name = Cow::Owned(
+                name.chars()
+                    .map(|c| if c.is_whitespace() { separator } else { c })
+                    .collect(),
+            );
+        }
+    }
+
+    if let Some(capped) = max_extensions.and_then(|max| capped_extensions(&name, max, replacement))
+    {
+        name = Cow::Owned(capped);
+    }
+    if let Some(normalized) = normalized_extension(&name, normalize_extension) {
+        name = Cow::Owned(normalized);
+    }
+    if let Some(allowed_extensions) = &allowed_extensions {
+        name = keep_allowed_extension(name, allowed_extensions, default_extension.as_deref());
+    }
+
+    if windows_reserved_names.unwrap_or(windows)
+        && (is_windows_reserved(&name, rule_set) || lookup.reserved_names.contains(&name))
+    {
+        name = Cow::Owned(reserved_replacement.to_owned());
+    }
+    if windows_trailing.unwrap_or(windows) {
+        name = WindowsTrailing::new(trailing_replacement).apply(name);
+    }
+
+    if defuse_double_extensions {
+        name = defuse_double_extension(name, replacement);
+    }
+
+    if fallback != Fallback::None && is_unsalvageable(original, &name, replacement) {
+        name = Cow::Owned(fallback.encode(original.as_bytes()));
+    }
+
+    if truncation == TruncationStrategy::PreserveExtension {
+        if let Some(shortened) = truncate_stem(&name, max_length, length_unit, max_extension_len) {
+            name = Cow::Owned(shortened);
+        }
+    }
+    let end = if truncation.truncates() {
+        truncate_in(&name, max_length, length_unit).len()
+    } else {
+        name.len()
+    };
+    let mut name = match name {
+        Cow::Borrowed(name) => Cow::Borrowed(&name[..end]),
+        Cow::Owned(mut name) => {
+            name.truncate(end);
+            Cow::Owned(name)
+        }
+    };
+    // Truncating can expose a trailing dot again.
+    if charset == Charset::Portable && name.ends_with('.') {
+        name = trim_portable_edges(name);
+    }
+    if trim {
+        name = match name {
+            Cow::Borrowed(name) => Cow::Borrowed(name.trim_end()),
+            Cow::Owned(mut name) => {
+                name.truncate(name.trim_end().len());
+                Cow::Owned(name)
+            }
+        };
+    }
+
+    match min_length {
+        Some(min_length) if length_unit.measure(&name) < min_length => {
+            match min_length_strategy {
+                // Empty names are left to `empty_fallback`, which is padded in turn.
+                MinLengthStrategy::Pad if name.is_empty() && empty_fallback.is_some() => {}
+                MinLengthStrategy::Pad => {
+                    let padding = "_".repeat(min_length - length_unit.measure(&name));
+                    let (stem, extension) = split_extension_within(&name, max_extension_len);
+                    name = Cow::Owned(format!("{}{}{}", stem, padding, extension));
+                }
+                MinLengthStrategy::Fallback => name = Cow::Borrowed(""),
+            }
+        }
+        _ => {}
+    }
+    if !name.is_empty() && affixes > 0 {
+        name = Cow::Owned(format!("{}{}{}", prefix, name, suffix));
+    }
+    name
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+#[non_exhaustive]
+pub struct OptionsForCheck {
+    pub windows: bool,
+    pub windows_reserved_names: Option<bool>,
+    pub windows_trailing: Option<bool>,
+    /// Whether longer names than `max_length` fail the check (unless
+    /// [`TruncationStrategy::None`]); which part sanitizing would cut makes no difference.
+    pub truncation: TruncationStrategy,
+    pub rule_set: RuleSet,
+    pub block_sync_junk: bool,
+    /// The length, in `length_unit`, that longer names fail the check at, with `truncation`.
+    pub max_length: usize,
+    /// The length, in `length_unit`, that shorter names fail the check at.
+    pub min_length: Option<usize>,
+    pub charset: Charset,
+    pub strip_bidi: bool,
+    pub defuse_double_extensions: bool,
+    pub leading_dot: LeadingDotPolicy,
+    pub strip_shell_metachars: bool,
+    pub strip_expansion_chars: bool,
+    pub fold_confusables: bool,
+    /// How `max_length` and `path_limit` are counted (in bytes by default).
+    pub length_unit: LengthUnit,
+    /// Characters to reject on top of [`ILLEGAL_CHARS`].
+    pub extra_illegal: Vec<char>,
+    /// Characters to accept despite `charset` and the security rules.
+    pub allow: Vec<char>,
+    /// The only characters to accept (with `allow`), if any.
+    pub allowlist: Option<Vec<char>>,
+    /// Reject characters that are not ASCII (unless in `allow`).
+    pub ascii_only: bool,
+    /// Names to reject on top of [`WINDOWS_RESERVED`] with `windows`.
+    pub reserved_names: Vec<String>,
+    /// The only extensions to accept, if any, as [`Options::allowed_extensions`] along
+    /// with the `default_extension`.
+    pub allowed_extensions: Option<Vec<String>>,
+    /// How extensions must be normalized, as [`Options::normalize_extension`] does.
+    pub normalize_extension: ExtensionNormalization,
+    /// The most extensions to accept, if limited.
+    pub max_extensions: Option<usize>,
+    /// Patterns that names must not match.
+    #[cfg_attr(feature = "schemars", schemars(with = "Vec<String>"))]
+    pub deny_patterns: Vec<DenyPattern>,
+    /// Substrings that names must not contain.
+    #[cfg_attr(feature = "schemars", schemars(with = "Option<Vec<String>>"))]
+    pub deny_substrings: Option<DenySubstrings>,
+}
+
+impl Default for OptionsForCheck {
+    fn default() -> Self {
+        OptionsForCheck::new()
+    }
+}
+
+impl OptionsForCheck {
+    /// The default options, usable in `const` and `static` items.
+    pub const fn new() -> Self {
+        OptionsForCheck {
+            windows: cfg!(windows),
+            windows_reserved_names: None,
+            windows_trailing: None,
+            truncation: TruncationStrategy::CutEnd,
+            rule_set: RuleSet::Latest,
+            block_sync_junk: false,
+            max_length: 255,
+            min_length: None,
+            charset: Charset::Any,
+            strip_bidi: false,
+            defuse_double_extensions: false,
+            leading_dot: LeadingDotPolicy::Allow,
+            strip_shell_metachars: false,
+            strip_expansion_chars: false,
+            fold_confusables: false,
+            length_unit: LengthUnit::Bytes,
+            extra_illegal: Vec::new(),
+            allow: Vec::new(),
+            allowlist: None,
+            ascii_only: false,
+            reserved_names: Vec::new(),
+            allowed_extensions: None,
+            normalize_extension: ExtensionNormalization::Keep,
+            max_extensions: None,
+            deny_patterns: Vec::new(),
+            deny_substrings: None,
+        }
+    }
+
+    pub const fn with_windows(mut self, windows: bool) -> Self {
+        self.windows = windows;
+        self
+    }
+
+    pub const fn with_windows_reserved_names(
+        mut self,
+        windows_reserved_names: Option<bool>,
+    ) -> Self {
+        self.windows_reserved_names = windows_reserved_names;
+        self
+    }
+
+    pub const fn with_windows_trailing(mut self, windows_trailing: Option<bool>) -> Self {
+        self.windows_trailing = windows_trailing;
+        self
+    }
+
+    /// Turns truncation on (cutting the end, unless `truncation` says otherwise) or off.
+    #[deprecated(note = "use `with_truncation`, with `TruncationStrategy::None` for no limit")]
+    pub const fn with_truncate(mut self, truncate: bool) -> Self {
+        self.truncation = match (truncate, self.truncation) {
+            (false, _) => TruncationStrategy::None,
+            (true, TruncationStrategy::None) => TruncationStrategy::CutEnd,
+            (true, truncation) => truncation,
+        };
+        self
+    }
+
+    pub const fn with_truncation(mut self, truncation: TruncationStrategy) -> Self {
+        self.truncation = truncation;
+        self
+    }
+
+    pub const fn with_rule_set(mut self, rule_set: RuleSet) -> Self {
+        self.rule_set = rule_set;
+        self
+    }
+
+    pub const fn with_block_sync_junk(mut self, block_sync_junk: bool) -> Self {
+        self.block_sync_junk = block_sync_junk;
+        self
+    }
+
+    pub const fn with_max_length(mut self, max_length: usize) -> Self {
+        self.max_length = max_length;
+        self
+    }
+
+    pub const fn with_min_length(mut self, min_length: usize) -> Self {
+        self.min_length = Some(min_length);
+        self
+    }
+
+    pub const fn without_min_length(mut self) -> Self {
+        self.min_length = None;
+        self
+    }
+
+    pub const fn with_charset(mut self, charset: Charset) -> Self {
+        self.charset = charset;
+        self
+    }
+
+    pub const fn with_strip_bidi(mut self, strip_bidi: bool) -> Self {
+        self.strip_bidi = strip_bidi;
+        self
+    }
+
+    pub const fn with_defuse_double_extensions(mut self, defuse_double_extensions: bool) -> Self {
+        self.defuse_double_extensions = defuse_double_extensions;
+        self
+    }
+
+    /// Strips leading dots (`LeadingDotPolicy::Strip`), or keeps them again.
+    #[deprecated(note = "use `with_leading_dot`, with `LeadingDotPolicy::Strip`")]
+    pub const fn with_strip_hidden_prefix(mut self, strip_hidden_prefix: bool) -> Self {
+        self.leading_dot = self.leading_dot.stripped(strip_hidden_prefix);
+        self
+    }
+
+    pub const fn with_leading_dot(mut self, leading_dot: LeadingDotPolicy) -> Self {
+        self.leading_dot = leading_dot;
+        self
+    }
+
+    pub const fn with_strip_shell_metachars(mut self, strip_shell_metachars: bool) -> Self {
+        self.strip_shell_metachars = strip_shell_metachars;
+        self
+    }
+
+    pub const fn with_strip_expansion_chars(mut self, strip_expansion_chars: bool) -> Self {
+        self.strip_expansion_chars = strip_expansion_chars;
+        self
+    }
+
+    pub const fn with_fold_confusables(mut self, fold_confusables: bool) -> Self {
+        self.fold_confusables = fold_confusables;
+        self
+    }
+
+    pub const fn with_length_unit(mut self, length_unit: LengthUnit) -> Self {
+        self.length_unit = length_unit;
+        self
+    }
+
+    pub fn with_extra_illegal<C: Into<Vec<char>>>(mut self, extra_illegal: C) -> Self {
+        self.extra_illegal = extra_illegal.into();
+        self
+    }
+
+    pub fn with_allow<C: Into<Vec<char>>>(mut self, allow: C) -> Self {
+        self.allow = allow.into();
+        self
+    }
+
+    pub fn with_allowlist<C: Into<Vec<char>>>(mut self, allowlist: C) -> Self {
+        self.allowlist = Some(allowlist.into());
+        self
+    }
+
+    pub fn without_allowlist(mut self) -> Self {
+        self.allowlist = None;
+        self
+    }
+
+    pub const fn with_ascii_only(mut self, ascii_only: bool) -> Self {
+        self.ascii_only = ascii_only;
+        self
+    }
+
+    pub fn with_reserved_names<I, S>(mut self, reserved_names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.reserved_names = reserved_names.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn with_allowed_extensions<I, S>(mut self, allowed_extensions: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.allowed_extensions = Some(allowed_extensions.into_iter().map(Into::into).collect());
+        self
+    }
+
+    pub fn without_allowed_extensions(mut self) -> Self {
+        self.allowed_extensions = None;
+        self
+    }
+
+    pub const fn with_normalize_extension(
+        mut self,
+        normalize_extension: ExtensionNormalization,
+    ) -> Self {
+        self.normalize_extension = normalize_extension;
+        self
+    }
+
+    pub const fn with_max_extensions(mut self, max_extensions: usize) -> Self {
+        self.max_extensions = Some(max_extensions);
+        self
+    }
+
+    pub const fn without_max_extensions(mut self) -> Self {
+        self.max_extensions = None;
+        self
+    }
+
+    pub fn with_deny_patterns<I: IntoIterator<Item = DenyPattern>>(
+        mut self,
+        deny_patterns: I,
+    ) -> Self {
+        self.deny_patterns = deny_patterns.into_iter().collect();
+        self
+    }
+
+    pub fn with_deny_substrings(mut self, deny_substrings: DenySubstrings) -> Self {
+        self.deny_substrings = Some(deny_substrings);
+        self
+    }
+
+    pub fn without_deny_substrings(mut self) -> Self {
+        self.deny_substrings = None;
+        self
+    }
+
+    /// Sets all the security rules as in `security`.
+    pub const fn with_security(mut self, security: Security) -> Self {
+        let rules = security.rules();
+        self.strip_bidi = rules.strip_bidi;
+        self.defuse_double_extensions = rules.defuse_double_extensions;
+        self.leading_dot = self.leading_dot.stripped(rules.strip_hidden_prefix);
+        self.strip_shell_metachars = rules.strip_shell_metachars;
+        self.strip_expansion_chars = rules.strip_expansion_chars;
+        self.fold_confusables = rules.fold_confusables;
+        self
+    }
+}
+
+impl<'a> From<&Options<'a>> for OptionsForCheck {
+    /// The checks matching what [`sanitize_with_options`] enforces with `options`.
+    fn from(options: &Options<'a>) -> Self {
+        OptionsForCheck {
+            windows: options.windows,
+            windows_reserved_names: options.windows_reserved_names,
+            windows_trailing: options.windows_trailing,
+            truncation: options.truncation,
+            rule_set: options.rule_set,
+            block_sync_junk: options.block_sync_junk,
+            max_length: options.max_length,
+            min_length: options.min_length,
+            charset: options.charset,
+            strip_bidi: options.strip_bidi,
+            defuse_double_extensions: options.defuse_double_extensions,
+            leading_dot: options.leading_dot,
+            strip_shell_metachars: options.strip_shell_metachars,
+            strip_expansion_chars: options.strip_expansion_chars,
+            fold_confusables: options.fold_confusables,
+            length_unit: options.length_unit,
+            extra_illegal: options.extra_illegal.to_vec(),
+            allow: options.allow.to_vec(),
+            allowlist: options.allowlist.as_deref().map(<[char]>::to_vec),
+            ascii_only: options.ascii_only,
+            reserved_names: options
+                .reserved_names
+                .iter()
+                .map(|name| name.clone().into_owned())
+                .collect(),
+            allowed_extensions: options.allowed_extensions.as_ref().map(|extensions| {
+                (extensions.iter())
+                    .chain(&options.default_extension)
+                    .map(|extension| extension.clone().into_owned())
+                    .collect()
+            }),
+            normalize_extension: options.normalize_extension,
+            max_extensions: options.max_extensions,
+            deny_patterns: options.deny_patterns.to_vec(),
+            deny_substrings: options.deny_substrings.clone(),
+        }
+    }
+}
+
+pub fn is_sanitized<S: AsRef<str>>(name: S) -> bool {
+    is_sanitized_with_options(name, OptionsForCheck::default())
+}
+
+pub fn is_sanitized_with_options<S: AsRef<str>>(name: S, options: OptionsForCheck) -> bool {
+    check_name(name.as_ref(), &options, &Lookup::from(&options))
+}
+
+// Where `lookup` is built from `options`.
+pub(crate) fn check_name(name: &str, options: &OptionsForCheck, lookup: &Lookup) -> bool {
+    let &OptionsForCheck {
+        windows,
+        windows_reserved_names,
+        windows_trailing,
+        truncation,
+        rule_set,
+        block_sync_junk,
+        max_length,
+        min_length,
+        charset,
+        strip_bidi,
+        defuse_double_extensions,
+        leading_dot,
+        strip_shell_metachars,
+        strip_expansion_chars,
+        fold_confusables,
+        length_unit,
+        ascii_only,
+        normalize_extension,
+        max_extensions,
+        extra_illegal: _,
+        allow: _,
+        allowlist: _,
+        reserved_names: _,
+        ref allowed_extensions,
+        ref deny_patterns,
+        ref deny_substrings,
+    } = options;
+    let Lookup {
+        extra_illegal,
+        allow,
+        allowlist,
+        reserved_names,
+    } = lookup;
+
+    if block_sync_junk && is_sync_junk(name) {
+        return false;
+    }
+    if name
+        .chars()
+        .any(|c| is_illegal_char(c) || is_control_char(c) || extra_illegal.contains(c))
+    {
+        return false;
+    }
+    if is_reserved(name) || (rule_set == RuleSet::Latest && is_whitespace_only(name)) {
+        return false;
+    }
+    if name.chars().filter(|&c| !allow.contains(c)).any(|c| {
+        (strip_bidi && is_bidi_control(c))
+            || (strip_shell_metachars && is_shell_metachar(c))
+            || (strip_expansion_chars && is_expansion_char(c))
+            || (fold_confusables && is_confusable(c))
+    }) {
+        return false;
+    }
+    if (leading_dot != LeadingDotPolicy::Allow && name.starts_with('.'))
+        || (strip_shell_metachars && name.starts_with('-'))
+    {
+        return false;
+    }
+    if defuse_double_extensions && has_double_extension(name) {
+        return false;
+    }
+    if truncation.truncates() && length_unit.measure(name) > max_length {
+        return false;
+    }
+    if min_length.is_some_and(|min_length| length_unit.measure(name) < min_length) {
+        return false;
+    }
+    if charset == Charset::Portable
+        && !(name
+            .chars()
+            .all(|c| is_portable_char(c) || allow.contains(c))
+            && has_portable_edges(name))
+    {
+        return false;
+    }
+    if allowlist.as_ref().is_some_and(|allowlist| {
+        name.chars()
+            .any(|c| !allowlist.contains(c) && !allow.contains(c))
+    }) {
+        return false;
+    }
+    if ascii_only && name.chars().any(|c| !c.is_ascii() && !allow.contains(c)) {
+        return false;
+    }
+    if (allowed_extensions.as_ref())
+        .is_some_and(|extensions| !has_allowed_extension(name, extensions))
+    {
+        return false;
+    }
+    if normalized_extension(name, normalize_extension).is_some() {
+        return false;
+    }
+    if max_extensions.is_some_and(|max| extension_count(name) > max) {
+        return false;
+    }
+    if deny_patterns.iter().any(|pattern| pattern.is_match(name)) {
+        return false;
+    }
+    if deny_substrings
+        .as_ref()
+        .is_some_and(|deny_substrings| deny_substrings.find_iter(name).next().is_some())
+    {
+        return false;
+    }
+    if windows_reserved_names.unwrap_or(windows)
+        && (is_windows_reserved(name, rule_set) || reserved_names.contains(name))
+    {
+        return false;
+    }
+    if windows_trailing.unwrap_or(windows) && has_windows_trailing(name) {
+        return false;
+    }
+
+    true
+}
+
+#[cfg(test)]
+mod tests {
+
+    // From https://github.com/parshap/node-sanitize-filename/blob/master/test.js
+    static NAMES: &[&str] = &[
+        "the quick brown fox jumped over the lazy dog",
+        "résumé",
+        "hello\u{0000}world",
+        "hello\nworld",
+        "semi;colon.js",
+        ";leading-semi.js",
+        "slash\\.js",
+        "slash/.js",
+        "col:on.js",
+        "star*.js",
+        "question?.js",
+        "quote\".js",
+        "singlequote'.js",
+        "brack<e>ts.js",
+        "p|pes.js",
+        "plus+.js",
+        "'five and six<seven'.js",
+        " space at front",
+        "space at end ",
+        ".period",
+        "period.",
+        "relative/path/to/some/dir",
+        "/abs/path/to/some/dir",
+        "~/.\u{0000}notssh/authorized_keys",
+        "",
+        "h?w",
+        "h/w",
+        "h*w",
+        ".",
+        "..",
+        "./",
+        "../",
+        "/..",
+        "/../",
+        "*.|.",
+        "./",
+        "./foobar",
+        "../foobar",
+        "../../foobar",
+        "./././foobar",
+        "|*.what",
+        "LPT9.asdf",
+        "foobar...",
+    ];
+
+    static NAMES_CLEANED: &[&str] = &[
+        "the quick brown fox jumped over the lazy dog",
+        "résumé",
+        "helloworld",
+        "helloworld",
+        "semi;colon.js",
+        ";leading-semi.js",
+        "slash.js",
+        "slash.js",
+        "colon.js",
+        "star.js",
+        "question.js",
+        "quote.js",
+        "singlequote'.js",
+        "brackets.js",
+        "ppes.js",
+        "plus+.js",
+        "'five and sixseven'.js",
+        " space at front",
+        "space at end",
+        ".period",
+        "period",
+        "relativepathtosomedir",
+        "abspathtosomedir",
+        "~.notsshauthorized_keys",
+        "",
+        "hw",
+        "hw",
+        "hw",
+        "",
+        "",
+        "",
+        "",
+        "",
+        "",
+        "",
+        "",
+        ".foobar",
+        "..foobar",
+        "....foobar",
+        "...foobar",
+        ".what",
+        "",
+        "foobar",
+    ];
+
+    static NAMES_IS_SANITIZED: &[bool] = &[
+        true, true, false, false, true, true, false, false, false, false, false, false, true,
+        false, false, true, false, true, false, true, false, false, false, false, true, false,
+        false, false, false, false, false, false, false, false, false, false, false, false, false,
+        false, false, false, false,
+    ];
+
+    #[test]
+    fn it_works() {
+        // sanitize
+        let options = super::Options {
+            windows: true,
+            truncation: super::TruncationStrategy::CutEnd,
+            replacement: "".into(),
+            rule_set: super::RuleSet::Latest,
+            ..Default::default()
+        };
+
+        for (idx, name) in NAMES.iter().enumerate() {
+            assert_eq!(
+                super::sanitize_with_options(name, options.clone()),
+                NAMES_CLEANED[idx]
+            );
+        }
+
+        let long = "a".repeat(300);
+        let shorter = "a".repeat(255);
+        assert_eq!(super::sanitize_with_options(long, options.clone()), shorter);
+
+        // is_sanitized
+        let options = super::OptionsForCheck {
+            windows: true,
+            truncation: super::TruncationStrategy::CutEnd,
+            rule_set: super::RuleSet::Latest,
+            ..Default::default()
+        };
+
+        for (idx, name) in NAMES.iter().enumerate() {
+            assert_eq!(
+                super::is_sanitized_with_options(name, options.clone()),
+                NAMES_IS_SANITIZED[idx]
+            );
+        }
+
+        let long = "a".repeat(300);
+        assert!(!super::is_sanitized_with_options(long, options.clone()));
+    }
+
+    #[test]
+    fn v1_is_frozen() {
+        let options = super::Options {
+            windows: true,
+            rule_set: super::RuleSet::V1,
+            ..Default::default()
+        };
+
+        for (idx, name) in NAMES.iter().enumerate() {
+            assert_eq!(
+                super::sanitize_with_options(name, options.clone()),
+                NAMES_CLEANED[idx]
+            );
+        }
+
+        let options = super::OptionsForCheck {
+            windows: true,
+            rule_set: super::RuleSet::V1,
+            ..Default::default()
+        };
+
+        for (idx, name) in NAMES.iter().enumerate() {
+            assert_eq!(
+                super::is_sanitized_with_options(name, options.clone()),
+                NAMES_IS_SANITIZED[idx]
+            );
+        }
+    }
+
+    // The regex-based implementation the hand-written rules replaced, kept as a reference
+    // for `RuleSet::V1`.
+    mod reference {
+        use regex::{Regex, RegexBuilder};
+        use std::sync::OnceLock;
+
+        pub fn illegal_re() -> &'static Regex {
+            static RE: OnceLock<Regex> = OnceLock::new();
+            RE.get_or_init(|| Regex::new(r#"[/\?<>\\:\*\|":]"#).unwrap())
+        }
+
+        pub fn control_re() -> &'static Regex {
+            static RE: OnceLock<Regex> = OnceLock::new();
+            RE.get_or_init(|| Regex::new(r#"[\x00-\x1f\x80-\x9f]"#).unwrap())
+        }
+
+        pub fn reserved_re() -> &'static Regex {
+            static RE: OnceLock<Regex> = OnceLock::new();
+            RE.get_or_init(|| Regex::new(r#"^\.+$"#).unwrap())
+        }
+
+        pub fn windows_reserved_re() -> &'static Regex {
+            static RE: OnceLock<Regex> = OnceLock::new();
+            RE.get_or_init(|| {
+                RegexBuilder::new(r#"(?i)^(con|prn|aux|nul|com[0-9]|lpt[0-9])(\..*)?$"#)
+                    .case_insensitive(true)
+                    .build()
+                    .unwrap()
+            })
+        }
+
+        pub fn windows_trailing_re() -> &'static Regex {
+            static RE: OnceLock<Regex> = OnceLock::new();
+            RE.get_or_init(|| Regex::new(r#"[\. ]+$"#).unwrap())
+        }
+
+        pub fn sanitize(name: &str, windows: bool, truncate: bool, replacement: &str) -> String {
+            let name = illegal_re().replace_all(name, replacement);
+            let name = control_re().replace_all(&name, replacement);
+            let mut name = reserved_re().replace(&name, replacement).into_owned();
+            if windows {
+                let reserved = windows_reserved_re().replace(&name, replacement);
+                name = windows_trailing_re()
+                    .replace(&reserved, replacement)
+                    .into_owned();
+            }
+            if truncate {
+                name = super::super::truncate_to(&name, 255).to_owned();
+            }
+            name
+        }
+    }
+
+    #[test]
+    fn tables_match_rules() {
+        for &c in super::ILLEGAL_CHARS {
+            assert!(reference::illegal_re().is_match(c.encode_utf8(&mut [0; 4])));
+        }
+        for c in (0..0x800).filter_map(char::from_u32) {
+            let s = c.encode_utf8(&mut [0; 4]).to_owned();
+            assert_eq!(
+                super::is_illegal_char(c),
+                reference::illegal_re().is_match(&s)
+            );
+            assert_eq!(
+                super::is_control_char(c),
+                reference::control_re().is_match(&s)
+            );
+        }
+        for name in NAMES
+            .iter()
+            .chain(&["COM1", "lpt9.txt", "con.a\nb", "nul.", "aux1", "...", ". ."])
+        {
+            assert_eq!(
+                super::is_reserved(name),
+                reference::reserved_re().is_match(name)
+            );
+            assert_eq!(
+                super::is_windows_reserved(name, super::RuleSet::V1),
+                reference::windows_reserved_re().is_match(name),
+                "{:?}",
+                name
+            );
+            assert_eq!(
+                super::has_windows_trailing(name),
+                reference::windows_trailing_re().is_match(name)
+            );
+        }
+    }
+
+    #[test]
+    fn it_matches_the_reference_implementation() {
+        let long = "é".repeat(200);
+        let extra = [
+            long.as_str(),
+            "con",
+            "CON.txt",
+            "com1:",
+            "a:b\u{1}c",
+            "lpt1. .",
+            "...",
+            "x\u{85}y",
+        ];
+        let replacements = ["", "_", "\n", ":", "a\u{1}b", ".", " ", "con", "é"];
+
+        for name in NAMES.iter().chain(&extra) {
+            for replacement in replacements {
+                for (windows, truncate) in [(true, true), (true, false), (false, true)] {
+                    let options = super::Options {
+                        windows,
+                        truncation: if truncate {
+                            super::TruncationStrategy::CutEnd
+                        } else {
+                            super::TruncationStrategy::None
+                        },
+                        replacement: replacement.into(),
+                        rule_set: super::RuleSet::V1,
+                        ..Default::default()
+                    };
+                    assert_eq!(
+                        super::sanitize_with_options(name, options),
+                        reference::sanitize(name, windows, truncate, replacement),
+                        "{:?} with {:?}",
+                        name,
+                        replacement
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn it_builds_options_in_const_context() {
+        static OPTIONS: super::Options = super::Options {
+            windows: true,
+            windows_reserved_names: None,
+            windows_trailing: None,
+            replacement: ::std::borrow::Cow::Borrowed(""),
+            replacer: None,
+            control_replacement: None,
+            reserved_replacement: None,
+            trailing_replacement: None,
+            empty_fallback: None,
+            prefix: ::std::borrow::Cow::Borrowed(""),
+            suffix: ::std::borrow::Cow::Borrowed(""),
+            rule_set: super::RuleSet::Latest,
+            fallback: super::Fallback::None,
+            block_sync_junk: false,
+            max_length: 255,
+            min_length: None,
+            min_length_strategy: super::MinLengthStrategy::Pad,
+            charset: super::Charset::Any,
+            collapse_replacements: false,
+            collapse_whitespace: false,
+            trim: false,
+            replace_whitespace: None,
+            strip_bidi: false,
+            defuse_double_extensions: false,
+            leading_dot: super::LeadingDotPolicy::Allow,
+            strip_shell_metachars: false,
+            strip_expansion_chars: false,
+            fold_confusables: false,
+            max_depth: None,
+            depth_policy: super::DepthPolicy::Flatten,
+            joiner: '_',
+            suffix_style: super::SuffixStyle::Parenthesized,
+            max_extension_len: None,
+            path_limit: super::PathLimit::Auto,
+            length_unit: super::LengthUnit::Bytes,
+            truncation: super::TruncationStrategy::CutEnd,
+            extra_illegal: ::std::borrow::Cow::Borrowed(&[]),
+            allow: ::std::borrow::Cow::Borrowed(&[]),
+            allowlist: None,
+            ascii_only: false,
+            reserved_names: ::std::borrow::Cow::Borrowed(&[]),
+            allowed_extensions: None,
+            default_extension: None,
+            normalize_extension: super::ExtensionNormalization::Keep,
+            max_extensions: None,
+            deny_patterns: ::std::borrow::Cow::Borrowed(&[]),
+            deny_substrings: None,
+            fixed_point: false,
+        };
+        static DEFAULT: super::Options = super::Options::new();
+        static CHAINED: super::Options = super::Options::new().with_windows(true);
+        const CHECK: super::OptionsForCheck = super::OptionsForCheck::new();
+
+        assert_eq!(
+            OPTIONS.clone(),
+            super::Options {
+                windows: true,
+                ..Default::default()
+            }
+        );
+        assert_eq!(CHECK, super::OptionsForCheck::default());
+        assert_eq!(DEFAULT.clone(), super::Options::default());
+        assert_eq!(CHAINED, OPTIONS);
+        assert!(format!("{:?}", OPTIONS).contains("windows: true"));
+    }
+
+    #[test]
+    fn it_owns_options() {
+        struct Config {
+            options: super::Options<'static>,
+        }
+
+        let replacement = String::from("_");
+        let config = Config {
+            options: super::Options {
+                replacement: replacement.as_str().into(),
+                ..Default::default()
+            }
+            .into_owned(),
+        };
+        drop(replacement);
+
+        assert_eq!(
+            super::sanitize_with_options("a?b", config.options.borrowed()),
+            "a_b"
+        );
+    }
+
+    #[test]
+    fn it_applies_the_archival_profile() {
+        let options = super::Options::archival();
+        let check = super::OptionsForCheck::from(&options);
+
+        for (name, archived) in [
+            #[cfg(feature = "unicode")]
+            ("Résumé (final).docx", "Resume__final_.docx"),
+            #[cfg(not(feature = "unicode"))]
+            ("Résumé (final).docx", "R_sum___final_.docx"),
+            ("..hidden file.", "hidden_file"),
+            ("-rf", "rf"),
+            ("日本.txt", "__.txt"),
+            ("con", "_"),
+            ("Thumbs.db", "_"),
+        ] {
+            assert_eq!(
+                super::sanitize_with_options(name, options.clone()),
+                archived
+            );
+            assert!(super::is_sanitized_with_options(archived, check.clone()));
+            assert!(!super::is_sanitized_with_options(name, check.clone()));
+        }
+
+        let long = format!("{}.{}", "a".repeat(127), "b".repeat(10));
+        assert_eq!(
+            super::sanitize_with_options(&long, options.clone()),
+            "a".repeat(127)
+        );
+    }
+
+    #[test]
+    fn it_limits_names_to_max_length() {
+        // eCryptfs allows 143 bytes.
+        let ecryptfs = super::Options::new().with_max_length(143);
+        assert_eq!(
+            super::sanitize_with_options("é".repeat(100), ecryptfs.clone()),
+            "é".repeat(71)
+        );
+
+        let check = super::OptionsForCheck::from(&ecryptfs);
+        assert!(super::is_sanitized_with_options(
+            "a".repeat(143),
+            check.clone()
+        ));
+        assert!(!super::is_sanitized_with_options("a".repeat(144), check));
+    }
+
+    #[test]
+    fn it_merges_overrides() {
+        let preset = super::Options::new()
+            .with_windows(true)
+            .with_replacement("_");
+
+        assert_eq!(preset.clone().merge(Default::default()), preset);
+        assert_eq!(
+            preset.merge(
+                super::OptionsOverride::new()
+                    .with_truncation(super::TruncationStrategy::None)
+                    .with_replacement("-")
+            ),
+            super::Options::new()
+                .with_windows(true)
+                .with_truncation(super::TruncationStrategy::None)
+                .with_replacement("-")
+        );
+
+        let limited = super::Options::new()
+            .with_min_length(3)
+            .with_max_extensions(1);
+        assert_eq!(
+            limited.clone().merge(
+                super::OptionsOverride::new()
+                    .without_min_length()
+                    .without_max_extensions()
+            ),
+            super::Options::new()
+        );
+        assert_eq!(
+            super::OptionsForCheck::from(&limited.without_min_length()),
+            super::OptionsForCheck::new()
+                .with_min_length(3)
+                .with_max_extensions(1)
+                .without_min_length()
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn it_round_trips_options() {
+        let options = super::Options::archival()
+            .with_replacement(String::from("-"))
+            .with_reserved_names(["desktop.ini"])
+            .with_replacer(|_| None);
+        let json = serde_json::to_string(&options).unwrap();
+        let parsed = serde_json::from_str::<super::Options>(&json).unwrap();
+        assert_eq!(
+            parsed,
+            super::Options {
+                replacer: None,
+                ..options.clone()
+            }
+        );
+
+        let parsed = serde_json::from_str::<super::Options>(
+            r#"{"windows": true, "replacement": "_", "charset": "Portable"}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            parsed,
+            super::Options::new()
+                .with_windows(true)
+                .with_replacement("_")
+                .with_charset(super::Charset::Portable)
+        );
+
+        let check = super::OptionsForCheck::from(&options);
+        let json = serde_json::to_string(&check).unwrap();
+        assert_eq!(
+            serde_json::from_str::<super::OptionsForCheck>(&json).unwrap(),
+            check
+        );
+    }
+
+    #[cfg(feature = "schemars")]
+    #[test]
+    fn it_describes_options() {
+        let schema = schemars::schema_for!(super::Options);
+        let properties = &schema.as_value()["properties"];
+        for field in [
+            "windows",
+            "windows_reserved_names",
+            "windows_trailing",
+            "replacement",
+            "prefix",
+            "suffix",
+            "rule_set",
+            "fallback",
+            "block_sync_junk",
+            "max_length",
+            "min_length",
+            "min_length_strategy",
+            "charset",
+            "collapse_replacements",
+            "collapse_whitespace",
+            "trim",
+            "replace_whitespace",
+            "strip_bidi",
+            "defuse_double_extensions",
+            "leading_dot",
+            "strip_shell_metachars",
+            "strip_expansion_chars",
+            "fold_confusables",
+            "max_depth",
+            "depth_policy",
+            "joiner",
+            "suffix_style",
+            "max_extension_len",
+            "path_limit",
+            "length_unit",
+            "truncation",
+            "extra_illegal",
+            "allow",
+            "allowlist",
+            "ascii_only",
+            "reserved_names",
+            "allowed_extensions",
+            "default_extension",
+            "normalize_extension",
+            "max_extensions",
+            "deny_patterns",
+            "deny_substrings",
+            "fixed_point",
+        ] {
+            assert!(properties.get(field).is_some(), "missing {}", field);
+        }
+    }
+
+    #[test]
+    fn it_falls_back_on_empty_names() {
+        let options = super::Options::new()
+            .with_windows(true)
+            .with_empty_fallback("untitled");
+        let sanitize = |name: &str| super::sanitize_with_options(name, options.clone());
+
+        assert_eq!(sanitize("..."), "untitled");
+        assert_eq!(sanitize("???"), "untitled");
+        assert_eq!(sanitize("con"), "untitled");
+        assert_eq!(sanitize(""), "untitled");
+        assert_eq!(sanitize("a?"), "a");
+        assert_eq!(
+            super::sanitize_with_options("???", options.clone().with_empty_fallback("nul?")),
+            ""
+        );
+        assert_eq!(
+            super::sanitize_with_options("???", options.with_fallback(super::Fallback::Base32)),
+            "h47t6"
+        );
+        assert_eq!(super::sanitize("???"), "");
+    }
+
+    #[test]
+    fn it_replaces_whitespace() {
+        let options = super::Options::new()
+            .with_windows(true)
+            .with_replacement("_")
+            .with_replace_whitespace(Some('_'));
+        let sanitize = |name: &str| super::sanitize_with_options(name, options.clone());
+
+        assert_eq!(sanitize("My Great File.pdf"), "My_Great_File.pdf");
+        assert_eq!(sanitize("a\u{a0}b\u{3000}c\td"), "a_b_c_d");
+        assert_eq!(sanitize("   "), "_");
+        assert_eq!(
+            super::sanitize_with_options("a b", options.clone().with_replace_whitespace(Some('/'))),
+            "ab"
+        );
+        assert_eq!(
+            super::sanitize_with_options("a b", options.with_replace_whitespace(None)),
+            "a b"
+        );
+    }
+
+    #[test]
+    fn it_collapses_whitespace() {
+        let options = super::Options::new().with_collapse_whitespace(true);
+        let sanitize = |name: &str| super::sanitize_with_options(name, options.clone());
+
+        assert_eq!(sanitize("a   b.txt"), "a b.txt");
+        assert_eq!(sanitize("a ? b.txt"), "a b.txt");
+        assert_eq!(sanitize("a\u{a0}\u{3000}b"), "a b");
+        assert_eq!(sanitize("a\u{a0}b"), "a\u{a0}b");
+        assert_eq!(
+            super::sanitize_with_options(
+                "a  b",
+                options.clone().with_replace_whitespace(Some('_'))
+            ),
+            "a_b"
+        );
+        assert_eq!(
+            super::sanitize_with_options("a  b", options.with_collapse_whitespace(false)),
+            "a  b"
+        );
+    }
+
+    #[test]
+    fn it_trims_whitespace() {
+        let options = super::Options::new()
+            .with_windows(false)
+            .with_replacement("_")
+            .with_trim(true);
+        let sanitize = |name: &str| super::sanitize_with_options(name, options.clone());
+
+        assert_eq!(sanitize(" space at front "), "space at front");
+        assert_eq!(sanitize("\u{3000}a\t"), "a_");
+        assert_eq!(sanitize(" .. "), "_");
+        assert_eq!(sanitize("   "), "_");
+        assert_eq!(
+            super::sanitize_with_options("abc d", options.clone().with_max_length(4)),
+            "abc"
+        );
+        assert_eq!(
+            super::sanitize_with_options(" a ", options.with_trim(false)),
+            " a "
+        );
+    }
+
+    #[test]
+    fn it_handles_leading_dots() {
+        let sanitize = |name: &str, leading_dot| {
+            let options = super::Options::new().with_leading_dot(leading_dot);
+            let check = super::OptionsForCheck::from(&options);
+            let sanitized = super::sanitize_with_options(name, options);
+            assert!(super::is_sanitized_with_options(&sanitized, check));
+            sanitized
+        };
+
+        assert_eq!(
+            sanitize(".bashrc", super::LeadingDotPolicy::Allow),
+            ".bashrc"
+        );
+        assert_eq!(
+            sanitize("..hidden", super::LeadingDotPolicy::Strip),
+            "hidden"
+        );
+        assert_eq!(
+            sanitize(".bashrc", super::LeadingDotPolicy::Prefix),
+            "_.bashrc"
+        );
+        assert_eq!(sanitize("..", super::LeadingDotPolicy::Prefix), "");
+        assert_eq!(sanitize("a.b", super::LeadingDotPolicy::Prefix), "a.b");
+        assert_eq!(
+            super::violations(
+                ".a",
+                super::OptionsForCheck::new().with_leading_dot(super::LeadingDotPolicy::Prefix)
+            ),
+            [super::Violation::HiddenPrefix]
+        );
+
+        use super::LeadingDotPolicy::{Allow, Prefix, Strip};
+        let options = super::Options::new().with_leading_dot(Prefix);
+        let paranoid = options.clone().with_security(super::Security::Paranoid);
+        assert_eq!(paranoid.leading_dot, Strip);
+        assert_eq!(
+            paranoid
+                .with_security(super::Security::Standard)
+                .leading_dot,
+            Allow
+        );
+        assert_eq!(
+            options
+                .clone()
+                .with_security(super::Security::Relaxed)
+                .leading_dot,
+            Prefix
+        );
+        #[allow(deprecated)]
+        {
+            let stripped = options.clone().with_strip_hidden_prefix(true);
+            assert_eq!(stripped.leading_dot, Strip);
+            assert_eq!(stripped.with_strip_hidden_prefix(false).leading_dot, Allow);
+            assert_eq!(options.with_strip_hidden_prefix(false).leading_dot, Prefix);
+            let overrides = super::OptionsOverride::new().with_strip_hidden_prefix(false);
+            assert_eq!(overrides.leading_dot, None);
+            let overrides = overrides.with_strip_hidden_prefix(true);
+            assert_eq!(overrides.leading_dot, Some(Strip));
+            assert_eq!(
+                overrides.with_strip_hidden_prefix(false).leading_dot,
+                Some(Allow)
+            );
+        }
+    }
+
+    #[test]
+    fn it_toggles_windows_rules_separately() {
+        let options = super::Options::new()
+            .with_windows(false)
+            .with_replacement("_")
+            .with_windows_reserved_names(Some(true));
+        let check = super::OptionsForCheck::from(&options);
+        let sanitize = |name: &str| super::sanitize_with_options(name, options.clone());
+
+        assert_eq!(sanitize("con.txt"), "_");
+        assert_eq!(sanitize("a. "), "a. ");
+        assert!(!super::is_sanitized_with_options("con", check.clone()));
+        assert!(super::is_sanitized_with_options("a.", check.clone()));
+
+        let options = options
+            .with_windows(true)
+            .with_windows_reserved_names(None)
+            .with_windows_trailing(Some(false));
+        let check = super::OptionsForCheck::from(&options);
+        assert_eq!(super::sanitize_with_options("con", options.clone()), "_");
+        assert_eq!(super::sanitize_with_options("a. ", options), "a. ");
+        assert_eq!(
+            super::violations("aux.", check),
+            [super::Violation::WindowsReserved]
+        );
+    }
+
+    #[test]
+    fn it_applies_presets() {
+        let sanitize = |name: &str, options| super::sanitize_with_options(name, options);
+
+        assert_eq!(sanitize("con. ", super::Options::windows()), "");
+        assert_eq!(sanitize("con. ", super::Options::posix()), "con. ");
+        assert_eq!(
+            sanitize("aux:1.txt", super::Options::portable()),
+            "aux1.txt"
+        );
+        assert_eq!(
+            sanitize(&"é".repeat(200), super::Options::portable()).len(),
+            254
+        );
+        assert_eq!(sanitize("a b&c.", super::Options::strict()), "a b_c");
+        assert_eq!(sanitize(" - x", super::Options::strict()), "x");
+    }
+
+    #[test]
+    fn it_collapses_replacements() {
+        let options = super::Options::new()
+            .with_replacement("_")
+            .with_collapse_replacements(true);
+        let sanitize = |name: &str| super::sanitize_with_options(name, options.clone());
+
+        assert_eq!(sanitize("a///b???c"), "a_b_c");
+        assert_eq!(sanitize("a_/b"), "a_b");
+        assert_eq!(sanitize("a/b"), "a_b");
+        assert_eq!(
+            super::sanitize_with_options("a//b", options.clone().with_replacement("--")),
+            "a--b"
+        );
+        assert_eq!(
+            super::sanitize_with_options("a///b???c", options.with_collapse_replacements(false)),
+            "a___b___c"
+        );
+    }
+
+    #[test]
+    fn it_replaces_per_category() {
+        let options = super::Options::new()
+            .with_windows(true)
+            .with_replacement("_")
+            .with_control_replacement("")
+            .with_reserved_replacement("reserved")
+            .with_trailing_replacement("");
+        let sanitize = |name: &str| super::sanitize_with_options(name, options.clone());
+
+        assert_eq!(sanitize("a:b\u{7}c"), "a_bc");
+        assert_eq!(sanitize("con.txt"), "reserved");
+        assert_eq!(sanitize(".."), "reserved");
+        assert_eq!(sanitize("notes. . "), "notes");
+        assert_eq!(sanitize("a\u{1}:b"), "a_b");
+
+        let overrides = super::OptionsOverride::new().with_control_replacement("-");
+        let merged = options.clone().merge(overrides);
+        assert_eq!(super::sanitize_with_options("a\u{1}b", merged), "a-b");
+        let owned = options.into_owned();
+        assert_eq!(owned.reserved_replacement.as_deref(), Some("reserved"));
+
+        let reserved = String::from("reserved");
+        let overrides = super::OptionsOverride::new().with_reserved_replacement(&reserved[..]);
+        let overrides = overrides.into_owned();
+        drop(reserved);
+        let merged = super::Options::new().merge(overrides);
+        assert_eq!(super::sanitize_with_options("..", merged), "reserved");
+    }
+
+    #[test]
+    fn it_applies_extra_illegal_and_allowed_chars() {
+        let options = super::Options::new()
+            .with_replacement("_")
+            .with_charset(super::Charset::Portable)
+            .with_extra_illegal(&['#', '%'][..])
+            .with_allow(&['+', '#', '/'][..]);
+        let check = super::OptionsForCheck::from(&options);
+        let sanitize = |name: &str| super::sanitize_with_options(name, options.clone());
+
+        assert_eq!(sanitize("c++ #1 50%/x.txt"), "c++__1_50__x.txt");
+        assert!(super::is_sanitized_with_options("c++.txt", check.clone()));
+        assert!(!super::is_sanitized_with_options("c#.txt", check.clone()));
+        assert_eq!(
+            super::violations("a#b", check),
+            [super::Violation::IllegalChar { offset: 1, c: '#' }]
+        );
+
+        let options = super::Options::new()
+            .with_replacement("_")
+            .with_security(super::Security::Paranoid)
+            .with_allow(vec!['$']);
+        assert_eq!(
+            super::sanitize_with_options("$HOME;x", options.into_owned()),
+            "$HOME_x"
+        );
+    }
+
+    #[test]
+    fn it_keeps_only_allowlisted_chars() {
+        let options = super::Options::new()
+            .with_replacement("_")
+            .with_allowlist(super::PORTABLE_CHARS);
+        let check = super::OptionsForCheck::from(&options);
+        let sanitize = |name: &str| super::sanitize_with_options(name, options.clone());
+
+        assert_eq!(sanitize("my file (1).txt"), "my_file__1_.txt");
+        assert_eq!(sanitize("日本.txt"), "__.txt");
+        assert_eq!(sanitize(".."), "_");
+        assert!(super::is_sanitized_with_options("a-b_c.txt", check.clone()));
+        assert!(!super::is_sanitized_with_options("a b", check.clone()));
+        assert_eq!(
+            super::violations("a+b", check),
+            [super::Violation::NotAllowlisted { offset: 1, c: '+' }]
+        );
+
+        let options = options.with_replacement(" ").with_allow(&['+'][..]);
+        assert_eq!(super::sanitize_with_options("c++ (x)", options), "c++x");
+    }
+
+    #[test]
+    fn it_keeps_only_ascii() {
+        let options = super::Options::new()
+            .with_replacement("_")
+            .with_ascii_only(true);
+        let check = super::OptionsForCheck::from(&options);
+        let sanitize = |name: &str| super::sanitize_with_options(name, options.clone());
+
+        assert_eq!(sanitize("résumé (final).pdf"), "r_sum_ (final).pdf");
+        assert_eq!(sanitize("🎉"), "_");
+        assert!(super::is_sanitized_with_options(
+            "a~b (1).txt",
+            check.clone()
+        ));
+        assert_eq!(
+            super::violations("naïve", check),
+            [super::Violation::NonAscii { offset: 2, c: 'ï' }]
+        );
+
+        // Substitutes that are not ASCII themselves are replaced in turn.
+        let fullwidth = options.clone().with_fullwidth_replacer();
+        assert_eq!(super::sanitize_with_options("a:b", fullwidth), "a_b");
+        let options = options.with_allow(&['é'][..]);
+        assert_eq!(super::sanitize_with_options("café€", options), "café_");
+    }
+
+    #[test]
+    fn it_keeps_only_allowed_extensions() {
+        let options = super::Options::new().with_allowed_extensions([".jpg", "png", "tar.gz"]);
+        let check = super::OptionsForCheck::from(&options);
+        let sanitize = |name: &str| super::sanitize_with_options(name, options.clone());
+
+        assert_eq!(sanitize("cat.PNG"), "cat.PNG");
+        assert_eq!(sanitize("src.tar.gz"), "src.tar.gz");
+        assert_eq!(sanitize("evil.jpg.exe"), "evil.jpg");
+        assert_eq!(sanitize("notes.v2.txt"), "notes");
+        assert_eq!(sanitize("README"), "README");
+        assert_eq!(sanitize(".gitignore"), ".gitignore");
+        assert_eq!(sanitize("a.p?ng"), "a.png");
+        assert!(super::is_sanitized_with_options("gz", check.clone()));
+        assert_eq!(
+            super::violations("a.gz", check),
+            [super::Violation::DisallowedExtension]
+        );
+
+        let options = options.with_default_extension(".bin");
+        let check = super::OptionsForCheck::from(&options);
+        assert_eq!(
+            super::sanitize_with_options("run.exe", options.clone()),
+            "run.bin"
+        );
+        assert_eq!(
+            super::sanitize_with_options("run.bin", options.clone()),
+            "run.bin"
+        );
+        assert!(super::is_sanitized_with_options("run.BIN", check));
+    }
+
+    #[test]
+    fn it_pads_short_names() {
+        let options = super::Options::new().with_windows(true).with_min_length(4);
+        let check = super::OptionsForCheck::from(&options);
+        let sanitize = |name: &str, options: super::Options| {
+            let sanitized = super::sanitize_with_options(name, options);
+            assert!(super::is_sanitized_with_options(&sanitized, check.clone()));
+            sanitized
+        };
+
+        assert_eq!(sanitize("a.txt", options.clone()), "a.txt");
+        assert_eq!(sanitize("a.b", options.clone()), "a_.b");
+        assert_eq!(sanitize("a. ", options.clone()), "a___");
+        assert_eq!(sanitize("con", options.clone()), "____");
+        assert_eq!(sanitize("", options.clone()), "____");
+        assert_eq!(
+            sanitize("?", options.clone().with_empty_fallback("untitled")),
+            "untitled"
+        );
+        assert_eq!(
+            sanitize("?", options.clone().with_empty_fallback("x")),
+            "x___"
+        );
+        assert_eq!(
+            super::violations("abc", check.clone()),
+            [super::Violation::TooShort {
+                len: 3,
+                unit: crate::LengthUnit::Bytes
+            }]
+        );
+
+        let fallback = options.with_min_length_strategy(super::MinLengthStrategy::Fallback);
+        assert_eq!(super::sanitize_with_options("ab", fallback.clone()), "");
+        assert_eq!(
+            sanitize("ab", fallback.clone().with_empty_fallback("untitled")),
+            "untitled"
+        );
+        assert_eq!(
+            super::sanitize_with_options("ab", fallback.with_empty_fallback("x")),
+            ""
+        );
+    }
+
+    #[test]
+    fn it_adds_prefix_and_suffix() {
+        let options = super::Options::new()
+            .with_windows(true)
+            .with_prefix("upload_")
+            .with_suffix(".part")
+            .with_max_length(16);
+        let sanitize = |name: &str, options: super::Options| {
+            let check = super::OptionsForCheck::from(&options);
+            let sanitized = super::sanitize_with_options(name, options.clone());
+            assert!(super::is_sanitized_with_options(&sanitized, check));
+            assert_eq!(super::sanitize_with_options(&sanitized, options), sanitized);
+            sanitized
+        };
+
+        assert_eq!(sanitize("a:b", options.clone()), "upload_ab.part");
+        assert_eq!(sanitize("report.pdf", options.clone()), "upload_repo.part");
+        assert_eq!(sanitize("upload_a.part", options.clone()), "upload_a.part");
+        assert_eq!(sanitize("con", options.clone()), "");
+        assert_eq!(sanitize("???", options.clone()), "");
+        assert_eq!(
+            sanitize(
+                "report.pdf",
+                options
+                    .clone()
+                    .with_truncation(super::TruncationStrategy::PreserveExtension)
+                    .with_max_length(17)
+            ),
+            "upload_r.pdf.part"
+        );
+        assert_eq!(
+            sanitize("a", options.clone().with_min_length(14)),
+            "upload_a_.part"
+        );
+        assert_eq!(sanitize("abc", options.clone().with_max_length(10)), "");
+        assert_eq!(sanitize("upload_", options.clone()), "upload_uplo.part");
+        assert_eq!(sanitize(".part", options.clone()), "upload_.par.part");
+        assert_eq!(
+            sanitize("x", options.clone().with_prefix("a/b:")),
+            "abx.part"
+        );
+        assert_eq!(
+            sanitize("x", options.clone().with_windows(false).with_suffix("/..")),
+            "upload_x.."
+        );
+
+        // Affixes are not part of the V1 rules, so they are replaced there too.
+        let v1 = super::Options::new().with_rule_set(super::RuleSet::V1);
+        let sanitize_v1 = |name: &str, options: super::Options| {
+            let sanitized = super::sanitize_with_options(name, options);
+            assert!(!sanitized.contains('/'), "{:?}", sanitized);
+            sanitized
+        };
+        assert_eq!(sanitize_v1("x", v1.clone().with_prefix("../")), "..x");
+        assert_eq!(sanitize_v1("x", v1.clone().with_suffix("/..")), "x..");
+        assert_eq!(
+            sanitize_v1("x", v1.with_prefix("/").with_replacement("_")),
+            "_x"
+        );
+
+        use super::{SanitizeError, Sanitizer, Violation};
+        assert!(Sanitizer::try_new(options.clone()).is_ok());
+        for (options, violation) in [
+            (
+                options.clone().with_prefix("../"),
+                Violation::IllegalChar { offset: 2, c: '/' },
+            ),
+            (
+                options.clone().with_suffix("a:b"),
+                Violation::IllegalChar { offset: 1, c: ':' },
+            ),
+            (
+                options.clone().with_max_length(12),
+                Violation::TooLong {
+                    len: 12,
+                    unit: crate::LengthUnit::Bytes,
+                },
+            ),
+        ] {
+            assert_eq!(
+                Sanitizer::try_new(options).unwrap_err(),
+                SanitizeError(violation)
+            );
+        }
+        let unlimited = options.with_max_length(12);
+        let unlimited = unlimited.with_truncation(super::TruncationStrategy::None);
+        assert!(Sanitizer::try_new(unlimited).is_ok());
+    }
+
+    #[test]
+    fn it_caps_extensions() {
+        let options = super::Options::new()
+            .with_replacement("_")
+            .with_max_extensions(1);
+        let check = super::OptionsForCheck::from(&options);
+        let sanitize = |name: &str, options: super::Options| {
+            let sanitized = super::sanitize_with_options(name, options);
+            assert!(super::is_sanitized_with_options(&sanitized, check.clone()));
+            sanitized
+        };
+
+        assert_eq!(
+            sanitize("report.v2.final.pdf.exe", options.clone()),
+            "report_v2_final_pdf.exe"
+        );
+        assert_eq!(sanitize("src.tar.gz", options.clone()), "src_tar.gz");
+        assert_eq!(sanitize(".bashrc.bak", options.clone()), ".bashrc.bak");
+        assert_eq!(
+            sanitize("a.b.c", options.clone().with_replacement("")),
+            "ab.c"
+        );
+        assert_eq!(
+            super::violations("a.b.c", check.clone()),
+            [super::Violation::TooManyExtensions]
+        );
+
+        let none = options.with_max_extensions(0);
+        assert_eq!(super::sanitize_with_options("a.txt", none.clone()), "a_txt");
+        assert_eq!(super::sanitize_with_options(".env", none), ".env");
+    }
+
+    #[test]
+    fn it_normalizes_extensions() {
+        use super::ExtensionNormalization::{Canonical, Lowercase};
+
+        let options = super::Options::new().with_normalize_extension(Lowercase);
+        let check = super::OptionsForCheck::from(&options);
+        let sanitize = |name: &str| super::sanitize_with_options(name, options.clone());
+
+        assert_eq!(sanitize("Photo.JPG "), "Photo.jpg");
+        assert_eq!(sanitize("src.tar.GZ"), "src.tar.gz");
+        assert_eq!(sanitize("Photo.jpeg"), "Photo.jpeg");
+        assert_eq!(sanitize("README"), "README");
+        assert_eq!(sanitize(".Profile"), ".Profile");
+        assert!(!super::is_sanitized_with_options("a.Txt", check.clone()));
+        assert_eq!(
+            super::violations("a. TXT", check),
+            [super::Violation::UnnormalizedExtension]
+        );
+
+        let options = options.with_normalize_extension(Canonical);
+        let sanitize = |name: &str| super::sanitize_with_options(name, options.clone());
+        assert_eq!(sanitize("Photo.JPEG"), "Photo.jpg");
+        assert_eq!(sanitize("scan.tif"), "scan.tiff");
+        assert_eq!(sanitize("page.HTML"), "page.html");
+        assert_eq!(
+            super::sanitize_with_options(
+                "Photo.jpe",
+                options.clone().with_allowed_extensions(["jpg"])
+            ),
+            "Photo.jpg"
+        );
+        assert!(!super::is_sanitized_with_options(
+            "a.yml",
+            super::OptionsForCheck::from(&options)
+        ));
+    }
+
+    #[test]
+    fn it_never_emits_rejected_names() {
+        let sanitize = |name: &str, options: super::Options| {
+            let check = super::OptionsForCheck::from(&options);
+            let sanitized = super::sanitize_with_options(name, options);
+            assert!(super::is_sanitized_with_options(&sanitized, check));
+            sanitized
+        };
+        let windows = super::Options::new().with_windows(true);
+
+        assert_eq!(
+            sanitize("a/b", windows.clone().with_replacement("??")),
+            "ab"
+        );
+        assert_eq!(sanitize("aux", windows.clone().with_replacement("con")), "");
+        assert_eq!(
+            sanitize("a:b", windows.clone().with_replacement(" ")),
+            "a b"
+        );
+        assert_eq!(sanitize("abc d", windows.clone().with_max_length(4)), "abc");
+        assert_eq!(
+            super::sanitize_with_options(
+                "a/b",
+                windows
+                    .with_replacement("??")
+                    .with_rule_set(super::RuleSet::V1)
+            ),
+            "a??b"
+        );
+    }
+
+    #[test]
+    fn it_replaces_listed_reserved_names() {
+        let options = super::Options::new()
+            .with_windows(true)
+            .with_replacement("_")
+            .with_reserved_names(vec![
+                String::from("desktop.ini"),
+                String::from("ehthumbs.db"),
+            ]);
+        let check = super::OptionsForCheck::from(&options);
+        let sanitize = |name: &str| super::sanitize_with_options(name, options.clone());
+
+        assert_eq!(sanitize("Desktop.INI"), "_");
+        assert_eq!(sanitize("ehthumbs.db.old"), "_");
+        assert_eq!(sanitize("ehthumbs.dbx"), "ehthumbs.dbx");
+        assert_eq!(sanitize("desktop"), "desktop");
+        assert_eq!(sanitize("con.txt"), "_");
+        assert!(!super::is_sanitized_with_options(
+            "DESKTOP.ini",
+            check.clone()
+        ));
+        assert!(super::is_sanitized_with_options(
+            "my desktop.ini",
+            check.clone()
+        ));
+        assert_eq!(
+            super::violations("desktop.ini", check.clone()),
+            [super::Violation::WindowsReserved]
+        );
+        assert!(super::violations("desktop.ini", check.with_windows(false)).is_empty());
+        assert_eq!(
+            super::sanitize_with_options("desktop.ini", options.into_owned().with_windows(false)),
+            "desktop.ini"
+        );
+    }
+
+    #[test]
+    fn it_preserves_extensions_when_truncating() {
+        let options = super::Options::new()
+            .with_max_length(12)
+            .with_truncation(super::TruncationStrategy::PreserveExtension);
+        let sanitize = |name: &str| super::sanitize_with_options(name, options.clone());
+
+        assert_eq!(sanitize("very-long-title.mp4"), "very-lon.mp4");
+        assert_eq!(sanitize("short.mp4"), "short.mp4");
+        assert_eq!(sanitize("a.extremely-long"), "a.extremely-");
+        assert_eq!(sanitize("ééééééé.txt"), "éééé.txt");
+        assert_eq!(
+            super::sanitize_with_options(
+                "very-long-title.mp4",
+                options
+                    .clone()
+                    .with_truncation(super::TruncationStrategy::None)
+            ),
+            "very-long-title.mp4"
+        );
+        assert_eq!(
+            super::sanitize_with_options(
+                "very-long-title.mp4",
+                options
+                    .clone()
+                    .with_truncation(super::TruncationStrategy::CutEnd)
+            ),
+            "very-long-ti"
+        );
+
+        #[allow(deprecated)]
+        {
+            let off = options.clone().with_truncate(false);
+            assert_eq!(off.truncation, super::TruncationStrategy::None);
+            assert_eq!(
+                off.with_truncate(true).truncation,
+                super::TruncationStrategy::CutEnd
+            );
+            assert_eq!(
+                options.with_truncate(true).truncation,
+                super::TruncationStrategy::PreserveExtension
+            );
+        }
+    }
+
+    #[test]
+    fn it_counts_length_units() {
+        use super::LengthUnit;
+
+        let name = "日本語🎉".repeat(3);
+        for (unit, sanitized) in [
+            (LengthUnit::Bytes, "日本語"),
+            (LengthUnit::Chars, "日本語🎉日本語🎉日本語"),
+            (LengthUnit::Utf16, "日本語🎉日本語🎉日"),
+        ] {
+            let options = super::Options::new()
+                .with_max_length(11)
+                .with_length_unit(unit);
+            assert_eq!(
+                super::sanitize_with_options(&name, options.clone()),
+                sanitized
+            );
+            assert!(super::is_sanitized_with_options(
+                sanitized,
+                (&options).into()
+            ));
+            assert!(!super::is_sanitized_with_options(&name, (&options).into()));
+        }
+
+        let options = super::Options::new()
+            .with_max_length(12)
+            .with_length_unit(LengthUnit::Chars);
+        let name = "ßßßßßßßß.txt";
+        assert_eq!(
+            super::next_available_name(name, &options, |taken| taken == name).unwrap(),
+            "ßßßß (2).txt"
+        );
+    }
+
+    // Every public entry point taking a name, on pseudo-random names built from the
+    // characters that the rules treat specially, under options at their extremes.
+    #[test]
+    fn it_never_panics() {
+        const PIECES: &[&str] = &[
+            "a",
+            "é",
+            "e\u{301}",
+            "日本",
+            "🦀",
+            "\u{FFFD}",
+            "\u{202E}",
+            "\u{200B}",
+            "\0",
+            "\r",
+            "\u{85}",
+            "/",
+            "\\",
+            ":",
+            ".",
+            "..",
+            " ",
+            "-",
+            "$",
+            "con",
+            "LPT1",
+            "xn--",
+            "Ａ",
+            "а",
+            ".exe",
+            ".pdf",
+            "desktop.ini",
+            "%",
+            "%C3",
+            "filename*=UTF-8''",
+            "; filename=\"",
+        ];
+        let mut state = 0x2545_F491_4F6C_DD1Du64;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+
+        let options = [
+            super::Options::new(),
+            super::Options::new()
+                .with_windows(true)
+                .with_replacement("\u{85}:/")
+                .with_replacer(|c| (c != '/').then(|| format!("{}\u{0}/", c).into()))
+                .with_extra_illegal(&['a', '.'][..])
+                .with_allow(&['/', '\u{202e}', 'а'][..])
+                .with_reserved_names(["a", "", "b.", "é"])
+                .with_collapse_replacements(true),
+            super::Options::new()
+                .with_max_length(0)
+                .with_replacement("日")
+                .with_length_unit(super::LengthUnit::Utf16)
+                .with_truncation(super::TruncationStrategy::PreserveExtension),
+            super::Options::new()
+                .with_max_length(1)
+                .with_truncation(super::TruncationStrategy::None)
+                .with_control_replacement("\u{1}")
+                .with_reserved_replacement("..")
+                .with_trailing_replacement(". "),
+            super::Options::archival()
+                .with_fallback(super::Fallback::Base64Url)
+                .with_truncation(super::TruncationStrategy::PreserveExtension),
+            super::Options::new()
+                .with_security(super::Security::Paranoid)
+                .with_block_sync_junk(true)
+                .with_fallback(super::Fallback::Base32)
+                .with_max_depth(Some(0))
+                .with_joiner('/')
+                .with_length_unit(super::LengthUnit::Chars),
+        ];
+        for _ in 0..2_000 {
+            let len = (next() % 24) as usize;
+            let name = (0..len)
+                .map(|_| PIECES[(next() % PIECES.len() as u64) as usize])
+                .collect::<String>();
+            // The same name as the OS could hand it over, with an invalid sequence in it.
+            #[cfg(any(unix, windows))]
+            let at = (next() % (name.len() as u64 + 1)) as usize;
+            #[cfg(unix)]
+            let os_name = {
+                use std::os::unix::ffi::OsStringExt;
+                let mut bytes = name.clone().into_bytes();
+                bytes.splice(at..at, [0xe2, 0x82, 0xff]);
+                std::ffi::OsString::from_vec(bytes)
+            };
+            #[cfg(windows)]
+            let os_name = {
+                use std::os::windows::ffi::OsStringExt;
+                let mut wide = name.encode_utf16().collect::<Vec<_>>();
+                wide.insert(at.min(wide.len()), 0xd800);
+                std::ffi::OsString::from_wide(&wide)
+            };
+            for options in &options {
+                let sanitized = super::sanitize_with_options(&name, options.clone());
+                assert!(
+                    options.rule_set == super::RuleSet::V1
+                        || super::is_sanitized_with_options(&sanitized, options.into()),
+                    "{:?} sanitized to {:?}",
+                    name,
+                    sanitized
+                );
+                super::violations(&name, options.into());
+                super::risk_report(&name);
+                #[cfg(feature = "unicode")]
+                super::canonical_key(&name, options);
+                super::to_ascii_name(&name, options);
+                super::from_ascii_name(&name);
+                super::flatten_path(&name, options);
+                let _ = super::split_path(&name, options);
+                let _ = super::sanitize_unc_path(&name, options);
+                super::classify(&name, options);
+                let _ = super::try_sanitize_with_options(&name, options.clone());
+                super::analyze_with_options(&name, options.into());
+                let _ = super::encode(&name, options.into());
+                let _ = super::decode(&name);
+                super::sanitize_stem(&name, options);
+                super::sanitize_bytes(name.as_bytes(), options);
+                super::filename_from_url(&name, options);
+                let _ = super::sanitize_path(&name, options);
+                let _ = super::sanitize_in_dir(&name, &name, options);
+                #[cfg(any(unix, windows))]
+                {
+                    assert!(super::sanitize_os_str(&os_name, options).to_str().is_some());
+                    let _ = super::sanitize_path(&os_name, options);
+                    let _ = super::sanitize_in_dir(&os_name, &name, options);
+                }
+                super::SanitizedChars::new(name.chars(), options).for_each(drop);
+                let mut in_place = name.clone();
+                super::sanitize_in_place(&mut in_place, options);
+                #[cfg(feature = "http")]
+                super::filename_from_content_disposition(&name, options);
+            }
+        }
+
+        let huge = "a/\u{301}.".repeat(100_000);
+        for options in &options {
+            super::sanitize_with_options(&huge, options.clone());
+            super::is_sanitized_with_options(&huge, options.into());
+            let _ = super::split_path(&huge, options);
+        }
+    }
+
+    #[test]
+    fn it_rejects_instead_of_replacing() {
+        use super::{try_sanitize, try_sanitize_with_options, SanitizeError, Violation};
+
+        assert!(matches!(
+            try_sanitize("report.pdf"),
+            Ok(std::borrow::Cow::Borrowed("report.pdf"))
+        ));
+        assert_eq!(
+            try_sanitize("a/b\u{1}"),
+            Err(SanitizeError(Violation::IllegalChar { offset: 1, c: '/' }))
+        );
+        assert_eq!(try_sanitize(".."), Err(SanitizeError(Violation::DotsOnly)));
+
+        let options = super::Options::new()
+            .with_windows(true)
+            .with_collapse_whitespace(true);
+        assert_eq!(
+            try_sanitize_with_options("LPT9.txt", options.clone()),
+            Err(SanitizeError(Violation::WindowsReserved))
+        );
+        assert_eq!(
+            try_sanitize_with_options("a  b.txt", options.clone()).unwrap(),
+            "a b.txt"
+        );
+        assert_eq!(
+            try_sanitize_with_options("notes. ", options)
+                .unwrap_err()
+                .to_string(),
+            "not a sanitized file name: trailing dot or space"
+        );
+    }
+
+    #[test]
+    fn it_reserves_console_and_superscript_devices() {
+        let options = super::Options::new()
+            .with_windows(true)
+            .with_replacement("_");
+        let check = super::OptionsForCheck::from(&options);
+        for name in [
+            "CLOCK$",
+            "conin$.txt",
+            "ConOut$",
+            "COM\u{b9}",
+            "com\u{b2}.log",
+            "LPT\u{b3}",
+        ] {
+            assert_eq!(super::sanitize_with_options(name, options.clone()), "_");
+            assert!(!super::is_sanitized_with_options(name, check.clone()));
+        }
+        for name in ["clock", "conin", "com\u{2074}", "lpt\u{b9}x", "\u{b9}com"] {
+            assert_eq!(super::sanitize_with_options(name, options.clone()), name);
+        }
+
+        // `RuleSet::V1` keeps its original table.
+        let v1 = options.with_rule_set(super::RuleSet::V1);
+        assert_eq!(super::sanitize_with_options("CLOCK$", v1.clone()), "CLOCK$");
+        assert!(super::is_sanitized_with_options("com\u{b9}", (&v1).into()));
     }
 }
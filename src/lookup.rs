@@ -0,0 +1,158 @@
+use std::collections::HashSet;
+
+use crate::{Options, OptionsForCheck};
+
+// The characters and reserved names of a set of options, indexed once so that the rules
+// that look at every character, or at every name, do not scan the lists each time.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Lookup {
+    pub(crate) extra_illegal: CharSet,
+    pub(crate) allow: CharSet,
+    pub(crate) allowlist: Option<CharSet>,
+    pub(crate) reserved_names: ReservedNameSet,
+}
+
+impl Lookup {
+    fn new<S: AsRef<str>>(
+        extra_illegal: &[char],
+        allow: &[char],
+        allowlist: Option<&[char]>,
+        reserved_names: &[S],
+    ) -> Self {
+        Lookup {
+            extra_illegal: CharSet::new(extra_illegal),
+            allow: CharSet::new(allow),
+            allowlist: allowlist.map(CharSet::new),
+            reserved_names: ReservedNameSet::new(reserved_names),
+        }
+    }
+}
+
+impl From<&Options<'_>> for Lookup {
+    fn from(options: &Options) -> Self {
+        Lookup::new(
+            &options.extra_illegal,
+            &options.allow,
+            options.allowlist.as_deref(),
+            &options.reserved_names,
+        )
+    }
+}
+
+impl From<&OptionsForCheck> for Lookup {
+    fn from(options: &OptionsForCheck) -> Self {
+        Lookup::new(
+            &options.extra_illegal,
+            &options.allow,
+            options.allowlist.as_deref(),
+            &options.reserved_names,
+        )
+    }
+}
+
+// A bitmap for ASCII, and a sorted list for the rest.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct CharSet {
+    ascii: u128,
+    other: Vec<char>,
+}
+
+impl CharSet {
+    fn new(chars: &[char]) -> Self {
+        let mut set = CharSet::default();
+        for &c in chars {
+            if c.is_ascii() {
+                set.ascii |= 1 << c as u32;
+            } else {
+                set.other.push(c);
+            }
+        }
+        set.other.sort_unstable();
+        set.other.dedup();
+        set
+    }
+
+    pub(crate) fn contains(&self, c: char) -> bool {
+        if c.is_ascii() {
+            self.ascii & (1 << c as u32) != 0
+        } else {
+            self.other.binary_search(&c).is_ok()
+        }
+    }
+}
+
+// Reserved names lowercased, matched as `is_listed_reserved` matches them: the whole name
+// or what comes before one of its dots, ignoring ASCII case.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct ReservedNameSet {
+    names: HashSet<Vec<u8>>,
+    longest: usize,
+}
+
+impl ReservedNameSet {
+    fn new<S: AsRef<str>>(names: &[S]) -> Self {
+        let names: HashSet<_> = names
+            .iter()
+            .map(|name| name.as_ref().as_bytes().to_ascii_lowercase())
+            .filter(|name| !name.is_empty())
+            .collect();
+        let longest = names.iter().map(Vec::len).max().unwrap_or(0);
+        ReservedNameSet { names, longest }
+    }
+
+    pub(crate) fn contains(&self, name: &str) -> bool {
+        if self.names.is_empty() {
+            return false;
+        }
+        // Only the start of the name, as long as the longest reserved name, can match, and
+        // only where no newline follows.
+        let bytes = name.as_bytes();
+        let head = bytes[..bytes.len().min(self.longest + 1)].to_ascii_lowercase();
+        let from = name.rfind('\n').map_or(0, |idx| idx + 1);
+        (from..=head.len().min(self.longest)).any(|end| {
+            (end == bytes.len() || bytes[end] == b'.') && self.names.contains(&head[..end])
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CharSet, ReservedNameSet};
+    use crate::is_listed_reserved;
+
+    #[test]
+    fn it_matches_the_linear_scans() {
+        let chars = ['#', 'é', '\u{7f}', '日', '#'];
+        let set = CharSet::new(&chars);
+        for c in ['#', 'é', '\u{7f}', '日', 'a', 'e', '\0', '本', char::MAX] {
+            assert_eq!(set.contains(c), chars.contains(&c), "{:?}", c);
+        }
+
+        let names = ["desktop.ini", "Thumbs.db", "", "é", "a."];
+        let set = ReservedNameSet::new(&names);
+        for name in [
+            "desktop.ini",
+            "DESKTOP.INI",
+            "desktop.ini.txt",
+            "desktop",
+            "thumbs.DB.x",
+            "thumbs.dbx",
+            "É",
+            "é.txt",
+            "a.",
+            "a..b",
+            "a",
+            "desktop.ini\n",
+            "desktop.ini.\nx",
+            "",
+            ".",
+        ] {
+            assert_eq!(
+                set.contains(name),
+                is_listed_reserved(name, &names),
+                "{:?}",
+                name
+            );
+        }
+    }
+}
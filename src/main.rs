@@ -1,60 +1,1766 @@
 extern crate sanitize_filename;
 
-fn main() -> Result<(), ::std::io::Error> {
-    
-    let mut input = None;
-    let mut set_replacement = false;
-    let mut replacement = None;
-    let mut truncate = None;
-    let mut windows = None;
-
-    for arg in ::std::env::args().skip(1) {
-        if set_replacement {
-            replacement = Some(arg);
-            set_replacement = false;
-        } else if arg == "-r" || arg == "--replace" { 
-            set_replacement = true;
-        } else if arg == "--truncate" {
-            truncate = Some(true);
-        } else if arg == "--no-truncate" {
-            truncate = Some(false);
-        } else if arg == "--windows" {
-            windows = Some(true);
-        } else if arg == "--no-windows" {
-            windows = Some(false);
-        } else if arg == "-" {
-            input = None;
-        } else {
-            input = Some(arg);
+use std::collections::BTreeMap;
+#[cfg(feature = "fs")]
+use std::collections::HashSet;
+use std::io::Write;
+#[cfg(feature = "fs")]
+use std::path::Path;
+
+use sanitize_filename::{
+    Charset, DenyPattern, DenySubstrings, ExtensionNormalization, Fallback, LeadingDotPolicy,
+    LengthUnit, MinLengthStrategy, Options, OptionsForCheck, OptionsOverride, Replacer, RuleSet,
+    Sanitizer, Security, SuffixStyle, TruncationStrategy,
+};
+
+const USAGE: &str = "\
+Usage: sanitize-filename [sanitize] [OPTIONS] [NAME]
+       sanitize-filename check [OPTIONS] [NAME...]
+       sanitize-filename rename [OPTIONS] DIR...
+       sanitize-filename report [OPTIONS] [NAME...]
+       sanitize-filename audit [OPTIONS] [ARCHIVE]
+
+Names are read from STDIN when none are given (or when NAME is `-`). Arguments after
+`--` are always names (or directories), even ones that start with `-` or that are
+command names, as in `sanitize-filename -- check`.
+
+Commands:
+    sanitize  Print the sanitized name (the default)
+    check     Print the names that are not sanitized; exits with 1 if there are any
+    rename    Rename the entries of each DIR to their sanitized names
+    report    Print the sanitized name and risk assessment of each name
+    audit     List the entries of a zip or tar ARCHIVE and which would be unsafe to extract; exits with 1 if there are any
+
+Options:
+    -r, --replace <r>          Replacement characters
+    --collapse-replacements    Replace each run of consecutive replacements with a single one
+    --collapse-whitespace      Replace each run of whitespace with a single space
+    --trim                     Trim leading and trailing whitespace, on every platform
+    --replace-whitespace <c>   Rewrite spaces and other whitespace to this character (e.g. _)
+    --fullwidth                Replace punctuation such as `:` and `?` with its fullwidth look-alike (`：`, `？`)
+    --hex-escape               Replace characters with the %XX escapes of their bytes (`:` with `%3A`)
+    --control-replacement <r>  Replacement for control characters (default: the -r replacement)
+    --reserved-replacement <r> Replacement for reserved names such as `..` and `con` (default: the -r replacement)
+    --trailing-replacement <r> Replacement for the trailing dots and spaces Windows strips (default: the -r replacement)
+    --empty-fallback <name>    The name to use instead when nothing would be left of a name (e.g. untitled)
+    --prefix <str>             Start every file name with this, within --max-length (e.g. upload_)
+    --suffix <str>             End every file name with this, within --max-length (e.g. .part)
+    --fixed-point              Sanitize each output again until it no longer changes
+    --windows, --no-windows    Whether to handle filenames for Windows
+    --[no-]windows-reserved-names
+                               Whether to replace Windows device names such as `con` (default: as --windows)
+    --[no-]windows-trailing    Whether to replace the trailing dots and spaces Windows strips (default: as --windows)
+    --truncate, --no-truncate  Whether to truncate file names (to 255 bytes by default); --no-truncate is --truncation none
+    --max-length <n>           The length to truncate file names to
+    --length-unit <unit>       How --max-length counts: bytes (default), chars, utf16
+    --truncation <strategy>    Which part of long names to cut: cut-end (default), preserve-extension, none
+    --min-length <n>           The length to bring shorter file names up to
+    --min-length-strategy <s>  How to lengthen short names: pad (default), fallback
+    --rule-set <v1|latest>     Which version of the rules to apply (default: latest)
+    --profile <name>           Start from a named rule profile: windows (or ntfs), posix, portable, strict, archival
+    --policy <file>            Start from the options of a policy file, in TOML (or JSON for .json)
+    --charset <any|portable>   Which characters to allow: any (default), or only A-Z a-z 0-9 . _ -
+    --extra-illegal <chars>    Also replace each of these characters (e.g. '#%&')
+    --allow <chars>            Keep each of these characters despite --charset and --security (e.g. '+~')
+    --allowlist <chars>        Replace every character but these (and --allow), e.g. 'abcdefghijklmnopqrstuvwxyz0123456789_'
+    --ascii-only               Replace every character that is not ASCII (but --allow)
+    --leading-dot <policy>     What to do with a leading dot, which hides files: allow (default), strip, prefix (with _)
+    --fallback <encoding>      Encode names of which nothing would be left: none (default), base32, base64url
+    --block-sync-junk          Also replace names that sync clients refuse (desktop.ini, .DS_Store, ...)
+    --reserved-name <name>     With --windows, also replace this name, as `con` is (repeatable)
+    --allow-extension <ext>    Keep only this extension (repeatable), stripping others (e.g. pdf)
+    --default-extension <ext>  With --allow-extension, the extension that replaces the others instead
+    --normalize-extension <n>  How to spell extensions: keep (default), lowercase, canonical (`.jpeg` as `.jpg`)
+    --max-extensions <n>       Keep at most this many extensions, replacing the dots in front of the others
+    --deny <regex>             Replace the matches of this regular expression, e.g. '^~\\$' (repeatable)
+    --deny-substring <str>     Replace each occurrence of this string (repeatable)
+    --security <preset>        Apply a bundle of security rules: relaxed (default), standard, paranoid
+    --lines                    Treat each line of the input as a separate name (empty lines too, so output line N is that of input line N)
+    -0, --null                 Treat the input as NUL-delimited names (output is NUL-delimited too)
+    --encoding <encoding>      Decode STDIN as utf8 (default), cp437, shift_jis or latin1 (windows-1252)
+    -j, --jobs <n>             How many threads to process names on (default: one per CPU); the output keeps the input order
+    -h, --help                 Print this help
+    --                         Treat every following argument as a NAME (or DIR)
+
+sanitize options:
+    --filter                   Only print names whose final path component is not sanitized
+    --with-fix                 With --filter, also print the proposed fix after a tab
+    --stats                    Print a summary of the names to STDERR at the end
+    --map-out <file>           Also write each name and its sanitized name to this file, in input order, as a JSON object per line with `name` and `sanitized`
+
+check options:
+    --stats                    Print a summary of the names to STDERR at the end
+
+rename options:
+    --dry-run                  Only print the renames that would be made
+    --on-conflict <policy>     When the new name is taken: skip (default), overwrite, number
+    --suffix-style <style>     How --on-conflict number tells names apart: parenthesized (default), underscore, zero-padded, hash
+    --max-extension-len <n>    With --on-conflict number, keep only extensions of at most n characters after the number
+    --watch                    Keep running and rename files as they are created in each DIR
+    --edit                     Review and edit the proposed renames in $VISUAL/$EDITOR first
+
+report options:
+    --json                     Print the report as JSON
+
+audit options:
+    --json                     Print the entries and their problems as JSON
+
+audit reads an uncompressed tar archive from STDIN when no ARCHIVE is given, and checks
+entry names with the Windows rules unless --no-windows or --profile is given.
+";
+
+#[derive(Clone, Copy, PartialEq)]
+enum Command {
+    Sanitize,
+    Check,
+    Rename,
+    Report,
+    Audit,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Records {
+    // The whole input is one name
+    Whole,
+    // One name per line
+    Lines,
+    // NUL-delimited names, as from `find -print0`
+    Null,
+}
+
+// The encoding of the names on STDIN; all but UTF-8 need the `encoding_rs` feature.
+#[derive(Clone, Copy, PartialEq)]
+enum InputEncoding {
+    Utf8,
+    Cp437,
+    ShiftJis,
+    Latin1,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum OnConflict {
+    Skip,
+    Overwrite,
+    Number,
+}
+
+struct Args {
+    command: Command,
+    options: Options<'static>,
+    records: Records,
+    encoding: InputEncoding,
+    inputs: Vec<String>,
+    filter: bool,
+    with_fix: bool,
+    dry_run: bool,
+    on_conflict: OnConflict,
+    watch: bool,
+    edit: bool,
+    json: bool,
+    map_out: Option<String>,
+    stats: bool,
+    jobs: usize,
+}
+
+impl Args {
+    // `sanitize` and `audit` take one name, the last one given.
+    fn push_input(&mut self, input: String) {
+        match self.command {
+            Command::Sanitize | Command::Audit => self.inputs = vec![input],
+            _ => self.inputs.push(input),
         }
     }
+}
+
+fn invalid_input(message: String) -> ::std::io::Error {
+    ::std::io::Error::new(::std::io::ErrorKind::InvalidInput, message)
+}
+
+fn parse_args<I: Iterator<Item = String>>(args: I) -> Result<Args, ::std::io::Error> {
+    let mut args = args.peekable();
+
+    let command = match args.peek().map(String::as_str) {
+        Some("sanitize") => Some(Command::Sanitize),
+        Some("check") => Some(Command::Check),
+        Some("rename") => Some(Command::Rename),
+        Some("report") => Some(Command::Report),
+        Some("audit") => Some(Command::Audit),
+        _ => None,
+    };
+    if command.is_some() {
+        args.next();
+    }
+    let command = command.unwrap_or(Command::Sanitize);
+
+    let mut parsed = Args {
+        command,
+        options: Options::default(),
+        records: Records::Whole,
+        encoding: InputEncoding::Utf8,
+        inputs: Vec::new(),
+        filter: false,
+        with_fix: false,
+        dry_run: false,
+        on_conflict: OnConflict::Skip,
+        watch: false,
+        edit: false,
+        json: false,
+        map_out: None,
+        stats: false,
+        jobs: ::std::thread::available_parallelism().map_or(1, |jobs| jobs.get()),
+    };
+
+    // Explicit flags win over the profile, wherever they appear.
+    let mut profile = None;
+    let mut overrides = OptionsOverride::new();
+    let mut deny_substrings = Vec::new();
+
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| invalid_input(format!("{} requires a value", arg)))
+        };
+        match (command, arg.as_str()) {
+            (_, "-h") | (_, "--help") => {
+                print!("{}", USAGE);
+                ::std::process::exit(0);
+            }
+            (_, "-r") | (_, "--replace") => overrides.replacement = Some(value()?.into()),
+            (_, "--control-replacement") => {
+                overrides.control_replacement = Some(Some(value()?.into()))
+            }
+            (_, "--reserved-replacement") => {
+                overrides.reserved_replacement = Some(Some(value()?.into()))
+            }
+            (_, "--trailing-replacement") => {
+                overrides.trailing_replacement = Some(Some(value()?.into()))
+            }
+            (_, "--empty-fallback") => overrides.empty_fallback = Some(Some(value()?.into())),
+            (_, "--prefix") => overrides.prefix = Some(value()?.into()),
+            (_, "--suffix") => overrides.suffix = Some(value()?.into()),
+            (_, "--rule-set") => {
+                let value = value()?;
+                overrides.rule_set = Some(match value.as_str() {
+                    "v1" => RuleSet::V1,
+                    "latest" => RuleSet::Latest,
+                    _ => return Err(invalid_input(format!("unknown rule set: {}", value))),
+                });
+            }
+            (_, "--fallback") => {
+                let value = value()?;
+                overrides.fallback = Some(match value.as_str() {
+                    "none" => Fallback::None,
+                    "base32" => Fallback::Base32,
+                    "base64url" => Fallback::Base64Url,
+                    _ => return Err(invalid_input(format!("unknown fallback: {}", value))),
+                });
+            }
+            (_, "--profile") => {
+                let value = value()?;
+                profile = Some(match value.as_str() {
+                    "windows" | "ntfs" => Options::windows(),
+                    "posix" => Options::posix(),
+                    "portable" => Options::portable(),
+                    "strict" => Options::strict(),
+                    "archival" => Options::archival(),
+                    _ => return Err(invalid_input(format!("unknown profile: {}", value))),
+                });
+            }
+            (_, "--policy") => profile = Some(read_policy(&value()?)?),
+            (_, "--block-sync-junk") => overrides.block_sync_junk = Some(true),
+            (_, "--collapse-replacements") => overrides.collapse_replacements = Some(true),
+            (_, "--collapse-whitespace") => overrides.collapse_whitespace = Some(true),
+            (_, "--trim") => overrides.trim = Some(true),
+            (_, "--ascii-only") => overrides.ascii_only = Some(true),
+            (_, "--fixed-point") => overrides.fixed_point = Some(true),
+            (_, "--fullwidth") => overrides.replacer = Some(Replacer::fullwidth()),
+            (_, "--hex-escape") => overrides.replacer = Some(Replacer::hex_escape()),
+            (_, "--security") => {
+                let value = value()?;
+                overrides = overrides.with_security(match value.as_str() {
+                    "relaxed" => Security::Relaxed,
+                    "standard" => Security::Standard,
+                    "paranoid" => Security::Paranoid,
+                    _ => return Err(invalid_input(format!("unknown security preset: {}", value))),
+                });
+            }
+            (_, "--replace-whitespace") => {
+                let value = value()?;
+                overrides.replace_whitespace = Some(Some(value.parse().map_err(|_| {
+                    invalid_input(format!("invalid whitespace replacement: {}", value))
+                })?));
+            }
+            (_, "--max-length") => {
+                let value = value()?;
+                overrides.max_length = Some(
+                    value
+                        .parse()
+                        .map_err(|_| invalid_input(format!("invalid length: {}", value)))?,
+                );
+            }
+            (_, "--length-unit") => {
+                let value = value()?;
+                overrides.length_unit = Some(match value.as_str() {
+                    "bytes" => LengthUnit::Bytes,
+                    "chars" => LengthUnit::Chars,
+                    "utf16" => LengthUnit::Utf16,
+                    _ => return Err(invalid_input(format!("unknown length unit: {}", value))),
+                });
+            }
+            (_, "--truncation") => {
+                let value = value()?;
+                overrides.truncation = Some(match value.as_str() {
+                    "cut-end" => TruncationStrategy::CutEnd,
+                    "preserve-extension" => TruncationStrategy::PreserveExtension,
+                    "none" => TruncationStrategy::None,
+                    _ => return Err(invalid_input(format!("unknown truncation: {}", value))),
+                });
+            }
+            (_, "--min-length") => {
+                let value = value()?;
+                overrides.min_length =
+                    Some(Some(value.parse().map_err(|_| {
+                        invalid_input(format!("invalid length: {}", value))
+                    })?));
+            }
+            (_, "--normalize-extension") => {
+                let value = value()?;
+                overrides.normalize_extension = Some(match value.as_str() {
+                    "keep" => ExtensionNormalization::Keep,
+                    "lowercase" => ExtensionNormalization::Lowercase,
+                    "canonical" => ExtensionNormalization::Canonical,
+                    _ => {
+                        return Err(invalid_input(format!(
+                            "unknown extension normalization: {}",
+                            value
+                        )))
+                    }
+                });
+            }
+            (_, "--max-extensions") => {
+                let value = value()?;
+                overrides.max_extensions = Some(Some(value.parse().map_err(|_| {
+                    invalid_input(format!("invalid number of extensions: {}", value))
+                })?));
+            }
+            (_, "--min-length-strategy") => {
+                let value = value()?;
+                overrides.min_length_strategy = Some(match value.as_str() {
+                    "pad" => MinLengthStrategy::Pad,
+                    "fallback" => MinLengthStrategy::Fallback,
+                    _ => {
+                        return Err(invalid_input(format!(
+                            "unknown min length strategy: {}",
+                            value
+                        )))
+                    }
+                });
+            }
+            (_, "--leading-dot") => {
+                let value = value()?;
+                overrides.leading_dot = Some(match value.as_str() {
+                    "allow" => LeadingDotPolicy::Allow,
+                    "strip" => LeadingDotPolicy::Strip,
+                    "prefix" => LeadingDotPolicy::Prefix,
+                    _ => {
+                        return Err(invalid_input(format!(
+                            "unknown leading dot policy: {}",
+                            value
+                        )))
+                    }
+                });
+            }
+            (_, "--extra-illegal") => {
+                overrides.extra_illegal = Some(value()?.chars().collect::<Vec<_>>().into())
+            }
+            (_, "--allow-extension") => overrides
+                .allowed_extensions
+                .get_or_insert_with(|| Some(Default::default()))
+                .get_or_insert_with(Default::default)
+                .to_mut()
+                .push(value()?.into()),
+            (_, "--default-extension") => overrides.default_extension = Some(Some(value()?.into())),
+            (_, "--reserved-name") => overrides
+                .reserved_names
+                .get_or_insert_with(Default::default)
+                .to_mut()
+                .push(value()?.into()),
+            (_, "--deny") => overrides
+                .deny_patterns
+                .get_or_insert_with(Default::default)
+                .to_mut()
+                .push(parse_deny_pattern(&value()?)?),
+            (_, "--deny-substring") => deny_substrings.push(value()?),
+            (_, "--allow") => overrides.allow = Some(value()?.chars().collect::<Vec<_>>().into()),
+            (_, "--allowlist") => {
+                overrides.allowlist = Some(Some(value()?.chars().collect::<Vec<_>>().into()))
+            }
+            (_, "--charset") => {
+                let value = value()?;
+                overrides.charset = Some(match value.as_str() {
+                    "any" => Charset::Any,
+                    "portable" => Charset::Portable,
+                    _ => return Err(invalid_input(format!("unknown charset: {}", value))),
+                });
+            }
+            (_, "--truncate") => {
+                if matches!(overrides.truncation, None | Some(TruncationStrategy::None)) {
+                    overrides.truncation = Some(TruncationStrategy::CutEnd);
+                }
+            }
+            (_, "--no-truncate") => overrides.truncation = Some(TruncationStrategy::None),
+            (_, "--windows") => overrides.windows = Some(true),
+            (_, "--no-windows") => overrides.windows = Some(false),
+            (_, "--windows-reserved-names") => overrides.windows_reserved_names = Some(Some(true)),
+            (_, "--no-windows-reserved-names") => {
+                overrides.windows_reserved_names = Some(Some(false))
+            }
+            (_, "--windows-trailing") => overrides.windows_trailing = Some(Some(true)),
+            (_, "--no-windows-trailing") => overrides.windows_trailing = Some(Some(false)),
+            (_, "--lines") => parsed.records = Records::Lines,
+            (_, "-0") | (_, "--null") => parsed.records = Records::Null,
+            (_, "--encoding") => {
+                let value = value()?;
+                parsed.encoding = match value.as_str() {
+                    "utf8" | "utf-8" => InputEncoding::Utf8,
+                    "cp437" => InputEncoding::Cp437,
+                    "shift_jis" | "cp932" => InputEncoding::ShiftJis,
+                    "latin1" | "windows-1252" => InputEncoding::Latin1,
+                    _ => return Err(invalid_input(format!("unknown encoding: {}", value))),
+                };
+            }
+            (_, "-j") | (_, "--jobs") => {
+                let value = value()?;
+                parsed.jobs = match value.parse() {
+                    Ok(jobs) if jobs > 0 => jobs,
+                    _ => return Err(invalid_input(format!("invalid number of jobs: {}", value))),
+                };
+            }
+            (Command::Sanitize, "--filter") => parsed.filter = true,
+            (Command::Sanitize, "--with-fix") => parsed.with_fix = true,
+            (Command::Sanitize, "--stats") | (Command::Check, "--stats") => parsed.stats = true,
+            (Command::Sanitize, "--map-out") => parsed.map_out = Some(value()?),
+            (Command::Rename, "--dry-run") => parsed.dry_run = true,
+            (Command::Rename, "--on-conflict") => {
+                let value = value()?;
+                parsed.on_conflict = match value.as_str() {
+                    "skip" => OnConflict::Skip,
+                    "overwrite" => OnConflict::Overwrite,
+                    "number" => OnConflict::Number,
+                    _ => return Err(invalid_input(format!("unknown conflict policy: {}", value))),
+                };
+            }
+            (Command::Rename, "--suffix-style") => {
+                let value = value()?;
+                overrides.suffix_style = Some(match value.as_str() {
+                    "parenthesized" => SuffixStyle::Parenthesized,
+                    "underscore" => SuffixStyle::Underscore,
+                    "zero-padded" => SuffixStyle::ZeroPadded,
+                    "hash" => SuffixStyle::Hash,
+                    _ => return Err(invalid_input(format!("unknown suffix style: {}", value))),
+                });
+            }
+            (Command::Rename, "--max-extension-len") => {
+                let value = value()?;
+                overrides.max_extension_len =
+                    Some(Some(value.parse().map_err(|_| {
+                        invalid_input(format!("invalid extension length: {}", value))
+                    })?));
+            }
+            (Command::Rename, "--watch") => parsed.watch = true,
+            (Command::Rename, "--edit") => parsed.edit = true,
+            (Command::Report, "--json") | (Command::Audit, "--json") => parsed.json = true,
+            (_, "-") => parsed.inputs.clear(),
+            (_, "--") => args.by_ref().for_each(|arg| parsed.push_input(arg)),
+            (_, flag) if flag.starts_with('-') && flag.len() > 1 => {
+                return Err(invalid_input(format!("unexpected argument: {}", flag)))
+            }
+            (_, _) => parsed.push_input(arg),
+        }
+    }
+    if !deny_substrings.is_empty() {
+        overrides.deny_substrings = Some(Some(build_deny_substrings(deny_substrings)?));
+    }
+
+    // Archives are usually extracted elsewhere, so audit for Windows unless told otherwise.
+    let audit_windows =
+        parsed.command == Command::Audit && profile.is_none() && overrides.windows.is_none();
+    parsed.options = profile.unwrap_or_default().merge(overrides);
+    if audit_windows {
+        parsed.options = parsed.options.with_windows(true);
+    }
+    if let Err(err) = Sanitizer::try_new(parsed.options.borrowed()) {
+        return Err(invalid_input(format!(
+            "invalid --empty-fallback, --prefix or --suffix: {}",
+            err.0
+        )));
+    }
+
+    if parsed.command == Command::Rename && !cfg!(feature = "fs") {
+        return Err(invalid_input(String::from(
+            "rename is not available in this build (enable the `fs` feature)",
+        )));
+    }
+
+    if parsed.command == Command::Audit && !cfg!(feature = "archive") {
+        return Err(invalid_input(String::from(
+            "audit is not available in this build (enable the `archive` feature)",
+        )));
+    }
+
+    if parsed.watch && !cfg!(feature = "notify") {
+        return Err(invalid_input(String::from(
+            "--watch is not available in this build (enable the `notify` feature)",
+        )));
+    }
+
+    if parsed.encoding != InputEncoding::Utf8 && !cfg!(feature = "encoding_rs") {
+        return Err(invalid_input(String::from(
+            "--encoding is not available in this build (enable the `encoding_rs` feature)",
+        )));
+    }
+
+    if parsed.watch && parsed.edit {
+        return Err(invalid_input(String::from(
+            "--watch and --edit cannot be combined",
+        )));
+    }
+
+    if parsed.command == Command::Rename && parsed.inputs.is_empty() {
+        return Err(invalid_input(String::from("rename requires a directory")));
+    }
+
+    Ok(parsed)
+}
+
+// With `--filter` and `check`, records are usually paths from `find`; only their final
+// component is checked and fixed.
+fn split_file_name(record: &str) -> (&str, &str) {
+    match record.rfind('/') {
+        Some(idx) => record.split_at(idx + 1),
+        None => ("", record),
+    }
+}
+
+#[cfg(feature = "encoding_rs")]
+fn decode_input(encoding: InputEncoding, input: Vec<u8>) -> Result<String, ::std::io::Error> {
+    use sanitize_filename::NameEncoding;
 
-    let input = if let Some(input) = input {
-        input
+    let encoding = match encoding {
+        InputEncoding::Utf8 => return decode_input_utf8(input),
+        InputEncoding::Cp437 => NameEncoding::Cp437,
+        InputEncoding::ShiftJis => NameEncoding::Other(encoding_rs::SHIFT_JIS),
+        InputEncoding::Latin1 => NameEncoding::Other(encoding_rs::WINDOWS_1252),
+    };
+    Ok(encoding.decode(&input).into_owned())
+}
+
+#[cfg(not(feature = "encoding_rs"))]
+fn decode_input(_: InputEncoding, input: Vec<u8>) -> Result<String, ::std::io::Error> {
+    decode_input_utf8(input)
+}
+
+fn decode_input_utf8(input: Vec<u8>) -> Result<String, ::std::io::Error> {
+    String::from_utf8(input).map_err(|_| {
+        ::std::io::Error::new(
+            ::std::io::ErrorKind::InvalidData,
+            "stream did not contain valid UTF-8 (see --encoding)",
+        )
+    })
+}
+
+#[cfg(feature = "regex")]
+fn parse_deny_pattern(pattern: &str) -> Result<DenyPattern, ::std::io::Error> {
+    DenyPattern::new(pattern).map_err(|err| invalid_input(err.to_string()))
+}
+
+#[cfg(not(feature = "regex"))]
+fn parse_deny_pattern(_: &str) -> Result<DenyPattern, ::std::io::Error> {
+    Err(invalid_input(String::from(
+        "--deny is not available in this build (enable the `regex` feature)",
+    )))
+}
+
+#[cfg(feature = "aho-corasick")]
+fn build_deny_substrings(substrings: Vec<String>) -> Result<DenySubstrings, ::std::io::Error> {
+    DenySubstrings::new(substrings).map_err(|err| invalid_input(err.to_string()))
+}
+
+#[cfg(not(feature = "aho-corasick"))]
+fn build_deny_substrings(_: Vec<String>) -> Result<DenySubstrings, ::std::io::Error> {
+    Err(invalid_input(String::from(
+        "--deny-substring is not available in this build (enable the `aho-corasick` feature)",
+    )))
+}
+
+#[cfg(feature = "policy")]
+fn read_policy(path: &str) -> Result<Options<'static>, ::std::io::Error> {
+    let policy = ::std::fs::read_to_string(path)?;
+    let options = if path.ends_with(".json") {
+        Options::from_json(&policy)
     } else {
-        let mut buffer = String::new();
-        ::std::io::Read::read_to_string(&mut ::std::io::stdin(), &mut buffer)?;
-        buffer
+        Options::from_toml(&policy)
     };
+    options.map_err(|err| invalid_input(format!("{}: {}", path, err)))
+}
+
+#[cfg(not(feature = "policy"))]
+fn read_policy(_: &str) -> Result<Options<'static>, ::std::io::Error> {
+    Err(invalid_input(String::from(
+        "--policy is not available in this build (enable the `policy` feature)",
+    )))
+}
+
+// The most records of STDIN that are processed at once.
+const CHUNK: usize = 8192;
 
-    let mut options = sanitize_filename::Options::default();
+// Calls `f` with the names to process, in order: the NAME arguments, or the records of
+// STDIN in chunks of those that have arrived (at most `CHUNK`), so that huge or endless
+// inputs are processed as they come in. Empty records are kept, so that output record N
+// is that of input record N.
+fn for_each_chunk<F>(args: &Args, mut f: F) -> Result<(), ::std::io::Error>
+where
+    F: FnMut(&[String]) -> Result<(), ::std::io::Error>,
+{
+    use std::io::{BufRead, Read};
 
-    if let Some(ref replacement) = replacement { 
-        options.replacement = replacement;
+    if !args.inputs.is_empty() {
+        return f(&args.inputs);
     }
 
-    if let Some(windows) = windows {
-        options.windows = windows;
+    let (records, encoding) = (args.records, args.encoding);
+    let delimiter = match records {
+        Records::Whole => {
+            let mut input = Vec::new();
+            ::std::io::stdin().read_to_end(&mut input)?;
+            return f(&[decode_input(encoding, input)?]);
+        }
+        Records::Lines => b'\n',
+        Records::Null => b'\0',
+    };
+
+    let read = move |stdin: &mut dyn BufRead| {
+        let mut record = Vec::new();
+        match stdin.read_until(delimiter, &mut record) {
+            Ok(0) => None,
+            Ok(_) => {
+                if record.last() == Some(&delimiter) {
+                    record.pop();
+                    if records == Records::Lines && record.last() == Some(&b'\r') {
+                        record.pop();
+                    }
+                }
+                Some(decode_input(encoding, record))
+            }
+            Err(err) => Some(Err(err)),
+        }
+    };
+
+    // Without threads, each record is processed as soon as it is read.
+    if !threads_available() {
+        let stdin = ::std::io::stdin();
+        let mut stdin = stdin.lock();
+        while let Some(name) = read(&mut stdin) {
+            f(&[name?])?;
+        }
+        return Ok(());
     }
 
-    if let Some(truncate) = truncate {
-        options.truncate = truncate;
+    // The reader runs ahead by at most a chunk.
+    let (sender, receiver) = ::std::sync::mpsc::sync_channel(CHUNK);
+    ::std::thread::spawn(move || {
+        let stdin = ::std::io::stdin();
+        let mut stdin = stdin.lock();
+        while let Some(name) = read(&mut stdin) {
+            let failed = name.is_err();
+            if sender.send(name).is_err() || failed {
+                break;
+            }
+        }
+    });
+
+    let mut names = Vec::with_capacity(CHUNK);
+    while let Ok(name) = receiver.recv() {
+        names.push(name?);
+        while names.len() < CHUNK {
+            match receiver.try_recv() {
+                Ok(name) => names.push(name?),
+                Err(_) => break,
+            }
+        }
+        f(&names)?;
+        names.clear();
     }
+    Ok(())
+}
 
-    let output = sanitize_filename::sanitize_with_options(input, options);
+// One line of `--map-out`.
+fn write_mapping<W: Write>(
+    out: &mut W,
+    name: &str,
+    sanitized: &str,
+) -> Result<(), ::std::io::Error> {
+    writeln!(
+        out,
+        "{{\"name\": {}, \"sanitized\": {}}}",
+        json_string(name),
+        json_string(sanitized)
+    )
+}
+
+fn delimiter(args: &Args) -> &'static str {
+    if args.records == Records::Null {
+        "\0"
+    } else {
+        "\n"
+    }
+}
 
-    println!("{}", &output);
+// What `--stats` prints: how messy a batch of names was.
+#[derive(Default)]
+struct Stats {
+    names: usize,
+    clean: usize,
+    changed: usize,
+    // The number of names with each kind of violation
+    violations: BTreeMap<&'static str, usize>,
+    shortest: Option<String>,
+    longest: Option<String>,
+}
 
+impl Stats {
+    fn record(&mut self, name: &str, sanitized: &str, check: &OptionsForCheck) {
+        let mut kinds = sanitize_filename::violations(name, check.clone())
+            .iter()
+            .map(|violation| violation.as_str())
+            .collect::<Vec<_>>();
+        self.names += 1;
+        if kinds.is_empty() {
+            self.clean += 1;
+        }
+        if sanitized != name {
+            self.changed += 1;
+        }
+        kinds.sort_unstable();
+        kinds.dedup();
+        for kind in kinds {
+            *self.violations.entry(kind).or_insert(0) += 1;
+        }
+        if self
+            .shortest
+            .as_ref()
+            .map_or(true, |s| sanitized.len() < s.len())
+        {
+            self.shortest = Some(sanitized.to_owned());
+        }
+        if self
+            .longest
+            .as_ref()
+            .map_or(true, |l| sanitized.len() > l.len())
+        {
+            self.longest = Some(sanitized.to_owned());
+        }
+    }
+
+    fn print(&self) {
+        eprintln!("names: {}", self.names);
+        eprintln!("clean: {}", self.clean);
+        eprintln!("changed: {}", self.changed);
+        if !self.violations.is_empty() {
+            eprintln!("violations:");
+            for (kind, count) in &self.violations {
+                eprintln!("  {}: {}", kind, count);
+            }
+        }
+        for (label, result) in [("shortest", &self.shortest), ("longest", &self.longest)] {
+            if let Some(result) = result {
+                eprintln!("{} result: {:?} ({} bytes)", label, result, result.len());
+            }
+        }
+    }
+}
+
+// Whether threads can be spawned, which they cannot on `wasm32-wasip1`.
+fn threads_available() -> bool {
+    !cfg!(target_os = "wasi") && ::std::thread::available_parallelism().is_ok()
+}
+
+// Maps `names` with `f` on `args.jobs` threads, keeping their order.
+fn par_map<T, F>(args: &Args, names: &[String], f: F) -> Vec<T>
+where
+    T: Send,
+    F: Fn(&str) -> T + Sync,
+{
+    if args.jobs <= 1 || names.len() < 2 || !threads_available() {
+        return names.iter().map(|name| f(name)).collect();
+    }
+    let chunk = (names.len() + args.jobs - 1) / args.jobs;
+    let f = &f;
+    ::std::thread::scope(|scope| {
+        let workers = names
+            .chunks(chunk)
+            .map(|chunk| scope.spawn(move || chunk.iter().map(|name| f(name)).collect::<Vec<_>>()))
+            .collect::<Vec<_>>();
+        workers
+            .into_iter()
+            .flat_map(|worker| {
+                worker
+                    .join()
+                    .unwrap_or_else(|panic| ::std::panic::resume_unwind(panic))
+            })
+            .collect()
+    })
+}
+
+fn sanitize(args: &Args) -> Result<(), ::std::io::Error> {
+    let sanitizer = Sanitizer::new(args.options.borrowed());
+    let check = sanitizer.check_options();
+    let mut stats = Stats::default();
+    let mut map_out = match &args.map_out {
+        Some(path) => Some(::std::io::BufWriter::new(::std::fs::File::create(path)?)),
+        None => None,
+    };
+    let stdout = ::std::io::stdout();
+    let mut out = ::std::io::BufWriter::new(stdout.lock());
+
+    for_each_chunk(args, |names| {
+        // With --filter, whether the final component is sanitized and its fix
+        let results = par_map(args, names, |name| {
+            if args.filter {
+                let (_, file_name) = split_file_name(name);
+                let fixed = sanitizer.sanitize(file_name);
+                let is_sanitized = sanitizer.is_sanitized(file_name);
+                (is_sanitized, fixed)
+            } else {
+                let output = sanitizer.sanitize(name);
+                (false, output)
+            }
+        });
+
+        for (name, (is_sanitized, output)) in names.iter().zip(results) {
+            if args.filter {
+                let (parent, file_name) = split_file_name(name);
+                if name.is_empty() || file_name == "." || file_name == ".." {
+                    continue;
+                }
+                if args.stats {
+                    stats.record(file_name, &output, check);
+                }
+                if let Some(map_out) = &mut map_out {
+                    write_mapping(map_out, name, &format!("{}{}", parent, output))?;
+                }
+                if is_sanitized {
+                    continue;
+                }
+                if args.with_fix {
+                    write!(out, "{}\t{}{}{}", name, parent, output, delimiter(args))?;
+                } else {
+                    write!(out, "{}{}", name, delimiter(args))?;
+                }
+            } else {
+                if args.stats {
+                    stats.record(name, &output, check);
+                }
+                if let Some(map_out) = &mut map_out {
+                    write_mapping(map_out, name, &output)?;
+                }
+                write!(out, "{}{}", output, delimiter(args))?;
+            }
+        }
+        out.flush()
+    })?;
+
+    if let Some(mut map_out) = map_out {
+        map_out.flush()?;
+    }
+    if args.stats {
+        stats.print();
+    }
+    Ok(())
+}
+
+fn check(args: &Args) -> Result<bool, ::std::io::Error> {
+    let sanitizer = Sanitizer::new(args.options.borrowed());
+    let check = sanitizer.check_options();
+    let stdout = ::std::io::stdout();
+    let mut out = ::std::io::BufWriter::new(stdout.lock());
+    let mut all_sanitized = true;
+    let mut stats = Stats::default();
+
+    for_each_chunk(args, |names| {
+        let results = par_map(args, names, |name| {
+            let (_, file_name) = split_file_name(name);
+            sanitizer.is_sanitized(file_name)
+        });
+
+        for (name, is_sanitized) in names.iter().zip(results) {
+            let (_, file_name) = split_file_name(name);
+            if name.is_empty() || file_name == "." || file_name == ".." {
+                continue;
+            }
+            if args.stats {
+                let fixed = sanitizer.sanitize(file_name);
+                stats.record(file_name, &fixed, check);
+            }
+            if !is_sanitized {
+                all_sanitized = false;
+                write!(out, "{}{}", name, delimiter(args))?;
+            }
+        }
+        out.flush()
+    })?;
+
+    if args.stats {
+        stats.print();
+    }
+    Ok(all_sanitized)
+}
+
+// The name `name` should be renamed to, applying the conflict policy against the names
+// for which `taken` returns true, or `None` to leave it alone.
+#[cfg(feature = "fs")]
+fn rename_target(
+    args: &Args,
+    dir: &Path,
+    name: &str,
+    taken: &dyn Fn(&str) -> bool,
+) -> Option<String> {
+    let sanitized = sanitize_filename::sanitize_with_options(name, args.options.borrowed());
+    if sanitized == name {
+        return None;
+    }
+    if sanitized.is_empty() {
+        eprintln!(
+            "skipping {:?}: nothing is left after sanitizing",
+            dir.join(name)
+        );
+        return None;
+    }
+    resolve_conflict(args, dir, name, sanitized, taken)
+}
+
+#[cfg(feature = "fs")]
+fn resolve_conflict(
+    args: &Args,
+    dir: &Path,
+    name: &str,
+    target: String,
+    taken: &dyn Fn(&str) -> bool,
+) -> Option<String> {
+    if !taken(&target) {
+        return Some(target);
+    }
+    match args.on_conflict {
+        OnConflict::Skip => {
+            eprintln!(
+                "skipping {:?}: {:?} already exists",
+                dir.join(name),
+                dir.join(&target)
+            );
+            None
+        }
+        OnConflict::Overwrite => Some(target),
+        OnConflict::Number => {
+            let next = sanitize_filename::next_available_name(&target, &args.options, taken);
+            if next.is_none() {
+                eprintln!("skipping {:?}: no free name left", dir.join(name));
+            }
+            next
+        }
+    }
+}
+
+#[cfg(feature = "fs")]
+fn apply_rename(args: &Args, dir: &Path, from: &str, to: &str) -> Result<(), ::std::io::Error> {
+    let (from, to) = (dir.join(from), dir.join(to));
+    println!("{} -> {}", from.display(), to.display());
+    if !args.dry_run {
+        ::std::fs::rename(&from, &to)?;
+    }
     Ok(())
+}
+
+#[cfg(feature = "fs")]
+fn rename_dir(args: &Args, dir: &Path) -> Result<(), ::std::io::Error> {
+    let mut entries = ::std::fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.file_name()))
+        .collect::<Result<Vec<_>, _>>()?;
+    entries.sort();
+
+    // Names that exist (or will exist) once the renames so far are applied.
+    let mut taken = entries
+        .iter()
+        .filter_map(|name| name.to_str().map(String::from))
+        .collect::<HashSet<_>>();
 
+    for entry in entries {
+        let name = match entry.to_str() {
+            Some(name) => name,
+            None => {
+                eprintln!("skipping non-UTF-8 name: {:?}", dir.join(&entry));
+                continue;
+            }
+        };
+        let target = rename_target(args, dir, name, &|candidate| taken.contains(candidate));
+        if let Some(target) = target {
+            apply_rename(args, dir, name, &target)?;
+            taken.remove(name);
+            taken.insert(target);
+        }
+    }
+
+    Ok(())
+}
+
+// A new directory in the temp directory that only the current user can enter (the temp
+// directory is per-user on Windows already), removed with its contents when dropped, so
+// that other users can neither plant a symlink in place of a file in it nor swap one.
+#[cfg(feature = "fs")]
+struct PrivateTempDir(::std::path::PathBuf);
+
+#[cfg(feature = "fs")]
+impl PrivateTempDir {
+    fn new() -> Result<Self, ::std::io::Error> {
+        use std::hash::{BuildHasher, Hasher};
+
+        let builder = ::std::fs::DirBuilder::new();
+        #[cfg(unix)]
+        let builder = {
+            let mut builder = builder;
+            ::std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+            builder
+        };
+        let mut attempt = 0;
+        loop {
+            // `RandomState` is seeded randomly, so the name cannot be guessed.
+            let mut hasher = ::std::collections::hash_map::RandomState::new().build_hasher();
+            hasher.write_u32(::std::process::id());
+            hasher.write_u32(attempt);
+            let path =
+                ::std::env::temp_dir().join(format!("sanitize-filename-{:016x}", hasher.finish()));
+            match builder.create(&path) {
+                Ok(()) => return Ok(PrivateTempDir(path)),
+                Err(err) if err.kind() == ::std::io::ErrorKind::AlreadyExists && attempt < 16 => {
+                    attempt += 1
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+}
+
+#[cfg(feature = "fs")]
+impl Drop for PrivateTempDir {
+    fn drop(&mut self) {
+        let _ = ::std::fs::remove_dir_all(&self.0);
+    }
+}
+
+// Lets the user review and edit the proposed renames of `dir` in their editor, then
+// applies whatever is left in the file.
+#[cfg(feature = "fs")]
+fn edit_dir(args: &Args, dir: &Path) -> Result<(), ::std::io::Error> {
+    let mut entries = ::std::fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.file_name()))
+        .collect::<Result<Vec<_>, _>>()?;
+    entries.sort();
+
+    let mut taken = entries
+        .iter()
+        .filter_map(|name| name.to_str().map(String::from))
+        .collect::<HashSet<_>>();
+
+    let mut table = String::from(
+        "# Proposed renames, one `original<TAB>new name` per line.\n\
+         # Edit the new names as needed; delete a line to leave that entry alone.\n",
+    );
+    let mut proposed = 0;
+    for entry in &entries {
+        let name = match entry.to_str() {
+            Some(name) => name,
+            None => continue,
+        };
+        if name.contains(['\t', '\n', '\r']) {
+            eprintln!(
+                "skipping {:?}: the name cannot be edited as a line",
+                dir.join(name)
+            );
+            continue;
+        }
+        // Proposals are resolved against each other so the table starts out conflict-free.
+        if let Some(target) = rename_target(args, dir, name, &|c| taken.contains(c)) {
+            table.push_str(&format!("{}\t{}\n", name, target));
+            taken.insert(target);
+            proposed += 1;
+        }
+    }
+    if proposed == 0 {
+        return Ok(());
+    }
+
+    let editor = ::std::env::var("VISUAL")
+        .or_else(|_| ::std::env::var("EDITOR"))
+        .unwrap_or_else(|_| String::from("vi"));
+    // The table is removed with its directory on every way out of this block.
+    let (status, edited) = {
+        let temp = PrivateTempDir::new()?;
+        let path = temp.0.join("renames.tsv");
+        ::std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)?
+            .write_all(table.as_bytes())?;
+
+        let mut words = editor.split_whitespace();
+        let status = ::std::process::Command::new(words.next().unwrap_or("vi"))
+            .args(words)
+            .arg(&path)
+            .status();
+        (status, ::std::fs::read_to_string(&path))
+    };
+    if !status?.success() {
+        return Err(::std::io::Error::new(
+            ::std::io::ErrorKind::Other,
+            format!("{} exited unsuccessfully; nothing was renamed", editor),
+        ));
+    }
+
+    let check = OptionsForCheck::from(&args.options);
+    let mut taken = entries
+        .iter()
+        .filter_map(|name| name.to_str().map(String::from))
+        .collect::<HashSet<_>>();
+
+    for line in edited?.lines() {
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (name, target) = match line.split_once('\t') {
+            Some((name, target)) if taken.contains(name) => (name, target),
+            _ => {
+                eprintln!("ignoring unrecognized line: {:?}", line);
+                continue;
+            }
+        };
+        if target == name {
+            continue;
+        }
+        if target.is_empty() || !sanitize_filename::is_sanitized_with_options(target, check.clone())
+        {
+            eprintln!(
+                "skipping {:?}: {:?} is not a sanitized name",
+                dir.join(name),
+                target
+            );
+            continue;
+        }
+        let target = resolve_conflict(args, dir, name, target.to_owned(), &|c| taken.contains(c));
+        if let Some(target) = target {
+            apply_rename(args, dir, name, &target)?;
+            taken.remove(name);
+            taken.insert(target);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "fs")]
+fn rename(args: &Args) -> Result<(), ::std::io::Error> {
+    if args.watch {
+        return watch(args);
+    }
+    if args.edit {
+        for dir in &args.inputs {
+            edit_dir(args, Path::new(dir))?;
+        }
+        return Ok(());
+    }
+    for dir in &args.inputs {
+        rename_dir(args, Path::new(dir))?;
+    }
+    Ok(())
+}
+
+// Renames files as they appear in the watched directories, until interrupted.
+#[cfg(feature = "notify")]
+fn watch(args: &Args) -> Result<(), ::std::io::Error> {
+    use notify::event::{EventKind, ModifyKind};
+    use notify::{RecursiveMode, Watcher};
+
+    let to_io = |error: notify::Error| ::std::io::Error::new(::std::io::ErrorKind::Other, error);
+
+    let (sender, events) = ::std::sync::mpsc::channel();
+    let mut watcher = notify::recommended_watcher(sender).map_err(to_io)?;
+    for dir in &args.inputs {
+        watcher
+            .watch(Path::new(dir), RecursiveMode::NonRecursive)
+            .map_err(to_io)?;
+    }
+
+    for event in events {
+        let event = match event {
+            Ok(event) => event,
+            Err(error) => {
+                eprintln!("watch error: {}", error);
+                continue;
+            }
+        };
+        if !matches!(
+            event.kind,
+            EventKind::Create(_) | EventKind::Modify(ModifyKind::Name(_))
+        ) {
+            continue;
+        }
+        // For renames within the directory, the last path is where the file is now.
+        let path = match event.paths.last() {
+            Some(path) if path.exists() => path,
+            _ => continue,
+        };
+        let (dir, name) = match (
+            path.parent(),
+            path.file_name().and_then(|name| name.to_str()),
+        ) {
+            (Some(dir), Some(name)) => (dir, name),
+            _ => continue,
+        };
+        let target = rename_target(args, dir, name, &|candidate| dir.join(candidate).exists());
+        if let Some(target) = target {
+            if let Err(error) = apply_rename(args, dir, name, &target) {
+                eprintln!("failed to rename {:?}: {}", path, error);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(all(feature = "fs", not(feature = "notify")))]
+fn watch(_: &Args) -> Result<(), ::std::io::Error> {
+    unreachable!("rejected while parsing arguments")
+}
+
+#[cfg(not(feature = "fs"))]
+fn rename(_: &Args) -> Result<(), ::std::io::Error> {
+    unreachable!("rejected while parsing arguments")
+}
+
+fn json_string(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn report(args: &Args) -> Result<(), ::std::io::Error> {
+    let check = OptionsForCheck::from(&args.options);
+    let stdout = ::std::io::stdout();
+    let mut out = ::std::io::BufWriter::new(stdout.lock());
+
+    if args.json {
+        writeln!(out, "[")?;
+    }
+    let mut first = true;
+
+    for_each_chunk(args, |names| {
+        let results = par_map(args, names, |name| {
+            (
+                sanitize_filename::sanitize_with_options(name, args.options.borrowed()),
+                sanitize_filename::is_sanitized_with_options(name, check.clone()),
+                sanitize_filename::risk_report(name),
+            )
+        });
+
+        for (name, (sanitized, is_sanitized, risk)) in names.iter().zip(results) {
+            let factors = risk
+                .factors
+                .iter()
+                .map(|factor| factor.as_str())
+                .collect::<Vec<_>>();
+
+            if args.json {
+                let factors = factors
+                    .iter()
+                    .map(|factor| json_string(factor))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                // The line is ended by the next entry, with a comma, or by the `]`.
+                write!(
+                    out,
+                    "{}  {{\"name\": {}, \"sanitized\": {}, \"is_sanitized\": {}, \"risk_score\": {}, \"risk_level\": {}, \"risk_factors\": [{}]}}",
+                    if first { "" } else { ",\n" },
+                    json_string(name),
+                    json_string(&sanitized),
+                    is_sanitized,
+                    risk.score,
+                    json_string(risk.level().as_str()),
+                    factors,
+                )?;
+                first = false;
+            } else {
+                writeln!(out, "{:?}", name)?;
+                writeln!(out, "  sanitized: {:?}", sanitized)?;
+                writeln!(out, "  is sanitized: {}", is_sanitized)?;
+                writeln!(
+                    out,
+                    "  risk: {} ({}){}{}",
+                    risk.level().as_str(),
+                    risk.score,
+                    if factors.is_empty() { "" } else { ": " },
+                    factors.join(", ")
+                )?;
+            }
+        }
+        out.flush()
+    })?;
+
+    if args.json {
+        if !first {
+            writeln!(out)?;
+        }
+        writeln!(out, "]")?;
+    }
+
+    out.flush()
+}
+
+// An entry of an archive, as listed by `audit`.
+#[cfg(feature = "archive")]
+struct ArchiveEntry {
+    name: String,
+    // The target of a symbolic or hard link
+    link: Option<String>,
+}
+
+#[cfg(feature = "archive")]
+fn invalid_archive(message: &str) -> ::std::io::Error {
+    ::std::io::Error::new(::std::io::ErrorKind::InvalidData, message)
+}
+
+// Reads the entry names of the archive at `path`, or of a tar archive on STDIN. Only the
+// headers are read: entry data is skipped, not decompressed.
+#[cfg(feature = "archive")]
+fn read_archive_entries(path: Option<&str>) -> Result<Vec<ArchiveEntry>, ::std::io::Error> {
+    use std::io::{Read, Seek, SeekFrom};
+
+    let mut head = Vec::with_capacity(512);
+    match path {
+        Some(path) => {
+            let mut file = ::std::fs::File::open(path)?;
+            (&mut file).take(512).read_to_end(&mut head)?;
+            check_archive_format(&head)?;
+            if head.starts_with(b"PK") {
+                return read_zip_entries(&mut file);
+            }
+            file.seek(SeekFrom::Start(0))?;
+            read_tar_entries(::std::io::BufReader::new(file))
+        }
+        None => {
+            let stdin = ::std::io::stdin();
+            let mut stdin = stdin.lock();
+            stdin.by_ref().take(512).read_to_end(&mut head)?;
+            check_archive_format(&head)?;
+            if head.starts_with(b"PK") {
+                return Err(invalid_archive(
+                    "zip archives cannot be read from STDIN; pass the path instead",
+                ));
+            }
+            read_tar_entries(::std::io::Cursor::new(head).chain(stdin))
+        }
+    }
+}
+
+#[cfg(feature = "archive")]
+fn check_archive_format(head: &[u8]) -> Result<(), ::std::io::Error> {
+    const COMPRESSED: &[&[u8]] = &[b"\x1f\x8b", b"BZh", b"\xfd7zXZ\0", b"\x28\xb5\x2f\xfd"];
+    if COMPRESSED.iter().any(|magic| head.starts_with(magic)) {
+        return Err(invalid_archive(
+            "compressed tar archives are not supported; decompress them first, e.g. \
+             `gzip -dc archive.tar.gz | sanitize-filename audit`",
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(feature = "archive")]
+fn le16(bytes: &[u8], at: usize) -> u64 {
+    u64::from(u16::from_le_bytes([bytes[at], bytes[at + 1]]))
+}
+
+#[cfg(feature = "archive")]
+fn le32(bytes: &[u8], at: usize) -> u64 {
+    let mut buf = [0; 4];
+    buf.copy_from_slice(&bytes[at..at + 4]);
+    u64::from(u32::from_le_bytes(buf))
+}
+
+#[cfg(feature = "archive")]
+fn le64(bytes: &[u8], at: usize) -> u64 {
+    let mut buf = [0; 8];
+    buf.copy_from_slice(&bytes[at..at + 8]);
+    u64::from_le_bytes(buf)
+}
+
+// Lists a zip archive from its central directory, found through the end of central
+// directory record (and its zip64 counterpart, for large archives).
+#[cfg(feature = "archive")]
+fn read_zip_entries(file: &mut ::std::fs::File) -> Result<Vec<ArchiveEntry>, ::std::io::Error> {
+    use std::io::{Read, Seek, SeekFrom};
+
+    const EOCD_LEN: usize = 22;
+    let len = file.seek(SeekFrom::End(0))?;
+    let tail_len = len.min(EOCD_LEN as u64 + 0xffff);
+    file.seek(SeekFrom::Start(len - tail_len))?;
+    let mut tail = vec![0; tail_len as usize];
+    file.read_exact(&mut tail)?;
+
+    let eocd = (0..(tail.len() + 1).saturating_sub(EOCD_LEN))
+        .rev()
+        .find(|&at| tail[at..].starts_with(b"PK\x05\x06"))
+        .ok_or_else(|| invalid_archive("not a zip archive: no end of central directory"))?;
+    let (mut count, mut offset) = (le16(&tail, eocd + 10), le32(&tail, eocd + 16));
+    if count == 0xffff || offset == 0xffff_ffff {
+        let locator = eocd
+            .checked_sub(20)
+            .filter(|&at| tail[at..].starts_with(b"PK\x06\x07"))
+            .ok_or_else(|| invalid_archive("invalid zip64 archive"))?;
+        file.seek(SeekFrom::Start(le64(&tail, locator + 8)))?;
+        let mut record = [0; 56];
+        file.read_exact(&mut record)?;
+        if !record.starts_with(b"PK\x06\x06") {
+            return Err(invalid_archive("invalid zip64 archive"));
+        }
+        count = le64(&record, 32);
+        offset = le64(&record, 48);
+    }
+
+    file.seek(SeekFrom::Start(offset))?;
+    let mut reader = ::std::io::BufReader::new(file);
+    let mut entries = Vec::new();
+    for _ in 0..count {
+        let mut header = [0; 46];
+        reader.read_exact(&mut header)?;
+        if !header.starts_with(b"PK\x01\x02") {
+            return Err(invalid_archive("invalid zip central directory"));
+        }
+        let mut name = vec![0; le16(&header, 28) as usize];
+        reader.read_exact(&mut name)?;
+        let rest = le16(&header, 30) + le16(&header, 32);
+        ::std::io::copy(&mut reader.by_ref().take(rest), &mut ::std::io::sink())?;
+        // Names are UTF-8 when bit 11 is set, and CP437 otherwise
+        let name = if le16(&header, 8) & 0x800 != 0 {
+            String::from_utf8_lossy(&name).into_owned()
+        } else {
+            decode_cp437(&name)
+        };
+        entries.push(ArchiveEntry { name, link: None });
+    }
+    Ok(entries)
+}
+
+#[cfg(all(feature = "archive", feature = "encoding_rs"))]
+fn decode_cp437(name: &[u8]) -> String {
+    sanitize_filename::NameEncoding::Cp437
+        .decode(name)
+        .into_owned()
+}
+
+#[cfg(all(feature = "archive", not(feature = "encoding_rs")))]
+fn decode_cp437(name: &[u8]) -> String {
+    String::from_utf8_lossy(name).into_owned()
+}
+
+// The bytes of a tar header field up to the first NUL.
+#[cfg(feature = "archive")]
+fn tar_string(field: &[u8]) -> String {
+    let end = field.iter().position(|&b| b == 0).unwrap_or(field.len());
+    String::from_utf8_lossy(&field[..end]).into_owned()
+}
+
+// A numeric tar header field: octal, or base-256 when the high bit is set (GNU).
+#[cfg(feature = "archive")]
+fn tar_number(field: &[u8]) -> Option<u64> {
+    if field[0] & 0x80 != 0 {
+        return field[1..]
+            .iter()
+            .try_fold(u64::from(field[0] & 0x7f), |n, &b| {
+                n.checked_mul(256).map(|n| n | u64::from(b))
+            });
+    }
+    let digits = tar_string(field);
+    let digits = digits.trim_matches(|c| c == ' ' || c == '\0');
+    if digits.is_empty() {
+        return Some(0);
+    }
+    u64::from_str_radix(digits, 8).ok()
+}
+
+// Reads a 512-byte block; `false` at the end of the input.
+#[cfg(feature = "archive")]
+fn read_tar_block<R: ::std::io::Read>(
+    reader: &mut R,
+    block: &mut [u8; 512],
+) -> Result<bool, ::std::io::Error> {
+    let mut filled = 0;
+    while filled < block.len() {
+        match reader.read(&mut block[filled..])? {
+            0 if filled == 0 => return Ok(false),
+            0 => return Err(::std::io::ErrorKind::UnexpectedEof.into()),
+            n => filled += n,
+        }
+    }
+    Ok(true)
+}
+
+// Reads (`keep`) or skips the data of an entry, padded to whole blocks.
+#[cfg(feature = "archive")]
+fn read_tar_data<R: ::std::io::Read>(
+    reader: &mut R,
+    size: u64,
+    keep: bool,
+) -> Result<Vec<u8>, ::std::io::Error> {
+    use std::io::Read;
+
+    // Long names and pax headers are small; anything bigger is not worth keeping.
+    const MAX_KEPT: u64 = 1 << 20;
+    let padded = size
+        .checked_add(511)
+        .ok_or_else(|| invalid_archive("invalid tar entry size"))?
+        / 512
+        * 512;
+    let mut data = Vec::new();
+    let read = if keep && size <= MAX_KEPT {
+        reader.by_ref().take(padded).read_to_end(&mut data)? as u64
+    } else {
+        ::std::io::copy(&mut reader.by_ref().take(padded), &mut ::std::io::sink())?
+    };
+    if read < padded {
+        return Err(::std::io::ErrorKind::UnexpectedEof.into());
+    }
+    data.truncate(size as usize);
+    Ok(data)
+}
+
+// Lists a tar archive (v7, ustar, GNU long names or pax) from its headers.
+#[cfg(feature = "archive")]
+fn read_tar_entries<R: ::std::io::Read>(
+    mut reader: R,
+) -> Result<Vec<ArchiveEntry>, ::std::io::Error> {
+    let mut entries = Vec::new();
+    let mut block = [0; 512];
+    let (mut long_name, mut long_link) = (None, None);
+
+    while read_tar_block(&mut reader, &mut block)? {
+        if block.iter().all(|&b| b == 0) {
+            break;
+        }
+        let checksum = block
+            .iter()
+            .enumerate()
+            .map(|(idx, &b)| {
+                if (148..156).contains(&idx) {
+                    32
+                } else {
+                    u64::from(b)
+                }
+            })
+            .sum::<u64>();
+        if tar_number(&block[148..156]) != Some(checksum) {
+            return Err(invalid_archive("not a zip or tar archive"));
+        }
+        let size = tar_number(&block[124..136])
+            .ok_or_else(|| invalid_archive("invalid tar entry size"))?;
+
+        match block[156] {
+            b'L' => long_name = Some(tar_string(&read_tar_data(&mut reader, size, true)?)),
+            b'K' => long_link = Some(tar_string(&read_tar_data(&mut reader, size, true)?)),
+            b'x' => {
+                let data = read_tar_data(&mut reader, size, true)?;
+                for (key, value) in pax_records(&data) {
+                    match key {
+                        "path" => long_name = Some(value.to_owned()),
+                        "linkpath" => long_link = Some(value.to_owned()),
+                        _ => {}
+                    }
+                }
+            }
+            // Global pax headers hold defaults, not an entry
+            b'g' => {
+                read_tar_data(&mut reader, size, false)?;
+            }
+            kind => {
+                read_tar_data(&mut reader, size, false)?;
+                let name = long_name.take().unwrap_or_else(|| {
+                    let name = tar_string(&block[..100]);
+                    match &block[257..262] == b"ustar" {
+                        true if block[345] != 0 => {
+                            format!("{}/{}", tar_string(&block[345..500]), name)
+                        }
+                        _ => name,
+                    }
+                });
+                let link = long_link.take();
+                let link = match kind {
+                    b'1' | b'2' => Some(link.unwrap_or_else(|| tar_string(&block[157..257]))),
+                    _ => None,
+                };
+                entries.push(ArchiveEntry { name, link });
+            }
+        }
+    }
+    Ok(entries)
+}
+
+// The `key=value` records of a pax extended header, each `<length> key=value\n`.
+#[cfg(feature = "archive")]
+fn pax_records(data: &[u8]) -> Vec<(&str, &str)> {
+    let mut records = Vec::new();
+    let mut rest = data;
+    while let Some(space) = rest.iter().position(|&b| b == b' ') {
+        let len = match ::std::str::from_utf8(&rest[..space])
+            .ok()
+            .and_then(|len| len.parse::<usize>().ok())
+        {
+            Some(len) if len > space && len <= rest.len() => len,
+            _ => break,
+        };
+        let record = &rest[space + 1..len];
+        let record = record.strip_suffix(b"\n").unwrap_or(record);
+        if let Ok(Some((key, value))) =
+            ::std::str::from_utf8(record).map(|record| record.split_once('='))
+        {
+            records.push((key, value));
+        }
+        rest = &rest[len..];
+    }
+    records
+}
+
+// Absolute POSIX paths as well as Windows root-relative and drive paths.
+#[cfg(feature = "archive")]
+fn is_absolute_path(path: &str) -> bool {
+    let bytes = path.as_bytes();
+    path.starts_with(['/', '\\'])
+        || (bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':')
+}
+
+// Why extracting `entry` could be unsafe, as snake_case identifiers; empty if it is not.
+#[cfg(feature = "archive")]
+fn audit_entry(entry: &ArchiveEntry, args: &Args, check: &OptionsForCheck) -> Vec<&'static str> {
+    let mut problems = Vec::new();
+    if is_absolute_path(&entry.name) {
+        problems.push("absolute_path");
+    }
+    // Extractors on Windows split on either separator
+    for component in entry.name.split(['/', '\\']) {
+        match component {
+            "" | "." => {}
+            ".." => problems.push("traversal"),
+            _ => problems.extend(
+                sanitize_filename::violations(component, check.clone())
+                    .iter()
+                    .map(|violation| violation.as_str()),
+            ),
+        }
+    }
+    if args.options.length_unit.measure(&entry.name)
+        > sanitize_filename::max_path_len(&args.options)
+    {
+        problems.push("path_too_long");
+    }
+    if let Some(link) = &entry.link {
+        if is_absolute_path(link) || link.split(['/', '\\']).any(|component| component == "..") {
+            problems.push("link_traversal");
+        }
+    }
+    problems.sort_unstable();
+    problems.dedup();
+    problems
+}
+
+#[cfg(feature = "archive")]
+fn audit(args: &Args) -> Result<bool, ::std::io::Error> {
+    let entries = read_archive_entries(args.inputs.first().map(String::as_str))?;
+    let check = OptionsForCheck::from(&args.options);
+    let stdout = ::std::io::stdout();
+    let mut out = ::std::io::BufWriter::new(stdout.lock());
+    let mut all_safe = true;
+
+    if args.json {
+        writeln!(out, "[")?;
+    }
+
+    for (idx, entry) in entries.iter().enumerate() {
+        let problems = audit_entry(entry, args, &check);
+        all_safe &= problems.is_empty();
+
+        if args.json {
+            let problems = problems
+                .iter()
+                .map(|problem| json_string(problem))
+                .collect::<Vec<_>>()
+                .join(", ");
+            writeln!(
+                out,
+                "  {{\"name\": {}, \"link\": {}, \"safe\": {}, \"problems\": [{}]}}{}",
+                json_string(&entry.name),
+                entry
+                    .link
+                    .as_deref()
+                    .map_or(String::from("null"), json_string),
+                problems.is_empty(),
+                problems,
+                if idx + 1 < entries.len() { "," } else { "" }
+            )?;
+        } else if problems.is_empty() {
+            writeln!(out, "{:?}: ok", entry.name)?;
+        } else {
+            writeln!(out, "{:?}: {}", entry.name, problems.join(", "))?;
+        }
+    }
+
+    if args.json {
+        writeln!(out, "]")?;
+    }
+
+    out.flush()?;
+    Ok(all_safe)
+}
+
+#[cfg(not(feature = "archive"))]
+fn audit(_: &Args) -> Result<bool, ::std::io::Error> {
+    unreachable!("rejected while parsing arguments")
+}
+
+fn main() -> Result<(), ::std::io::Error> {
+    let args = parse_args(::std::env::args().skip(1))?;
+
+    match args.command {
+        Command::Sanitize => sanitize(&args),
+        Command::Check => {
+            if !check(&args)? {
+                ::std::process::exit(1);
+            }
+            Ok(())
+        }
+        Command::Rename => rename(&args),
+        Command::Report => report(&args),
+        Command::Audit => {
+            if !audit(&args)? {
+                ::std::process::exit(1);
+            }
+            Ok(())
+        }
+    }
 }
@@ -0,0 +1,189 @@
+use std::borrow::Borrow;
+use std::ffi::OsStr;
+use std::fmt;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::{is_sanitized_with_options, sanitize_with_options, Options, OptionsForCheck};
+
+/// A file name that is known to be sanitized, so it can be passed on to path-building code
+/// without being checked again.
+///
+/// ```
+/// use std::path::Path;
+/// use sanitize_filename::SanitizedFileName;
+///
+/// let name = SanitizedFileName::new("a/b?.txt", &Default::default());
+/// assert_eq!(Path::new("uploads").join(&name), Path::new("uploads/ab.txt"));
+/// assert!("a/b".parse::<SanitizedFileName>().is_err());
+/// assert!(name.ends_with(".txt"));
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SanitizedFileName(String);
+
+/// The error returned when a name that is not sanitized is turned into a
+/// [`SanitizedFileName`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NotSanitized(pub String);
+
+impl fmt::Display for NotSanitized {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?} is not a sanitized file name", self.0)
+    }
+}
+
+impl std::error::Error for NotSanitized {}
+
+impl SanitizedFileName {
+    /// Sanitizes `name`.
+    pub fn new<S: AsRef<str>>(name: S, options: &Options) -> Self {
+        SanitizedFileName(sanitize_with_options(name, options.borrowed()))
+    }
+
+    /// Sanitizes `name` with the default options, where [`FromStr`] and [`TryFrom`] would
+    /// reject it.
+    pub fn coerce<S: AsRef<str>>(name: S) -> Self {
+        SanitizedFileName::new(name, &Options::default())
+    }
+
+    /// Accepts `name` as is if it is sanitized according to `options`.
+    pub fn try_new<S: Into<String>>(
+        name: S,
+        options: OptionsForCheck,
+    ) -> Result<Self, NotSanitized> {
+        let name = name.into();
+        if is_sanitized_with_options(&name, options) {
+            Ok(SanitizedFileName(name))
+        } else {
+            Err(NotSanitized(name))
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn into_string(self) -> String {
+        self.0
+    }
+}
+
+/// Accepts the name if it is sanitized with the default options.
+impl FromStr for SanitizedFileName {
+    type Err = NotSanitized;
+
+    fn from_str(name: &str) -> Result<Self, NotSanitized> {
+        SanitizedFileName::try_new(name, OptionsForCheck::default())
+    }
+}
+
+impl TryFrom<&str> for SanitizedFileName {
+    type Error = NotSanitized;
+
+    fn try_from(name: &str) -> Result<Self, NotSanitized> {
+        SanitizedFileName::try_new(name, OptionsForCheck::default())
+    }
+}
+
+impl TryFrom<String> for SanitizedFileName {
+    type Error = NotSanitized;
+
+    fn try_from(name: String) -> Result<Self, NotSanitized> {
+        SanitizedFileName::try_new(name, OptionsForCheck::default())
+    }
+}
+
+impl fmt::Display for SanitizedFileName {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Gives the `str` methods, so that code that takes a `&str` takes a `&SanitizedFileName`
+/// too.
+impl Deref for SanitizedFileName {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for SanitizedFileName {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<OsStr> for SanitizedFileName {
+    fn as_ref(&self) -> &OsStr {
+        self.0.as_ref()
+    }
+}
+
+impl AsRef<Path> for SanitizedFileName {
+    fn as_ref(&self) -> &Path {
+        self.0.as_ref()
+    }
+}
+
+impl Borrow<str> for SanitizedFileName {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl PartialEq<str> for SanitizedFileName {
+    fn eq(&self, other: &str) -> bool {
+        self.0 == other
+    }
+}
+
+impl PartialEq<&str> for SanitizedFileName {
+    fn eq(&self, other: &&str) -> bool {
+        self.0 == *other
+    }
+}
+
+impl From<SanitizedFileName> for String {
+    fn from(name: SanitizedFileName) -> String {
+        name.0
+    }
+}
+
+impl From<SanitizedFileName> for PathBuf {
+    fn from(name: SanitizedFileName) -> PathBuf {
+        name.0.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+
+    use super::{NotSanitized, SanitizedFileName};
+
+    #[test]
+    fn it_validates_names() {
+        let name = SanitizedFileName::new("x:y", &Default::default());
+        assert_eq!(name, "xy");
+        assert_eq!(name.to_string(), "xy");
+        assert!(name.starts_with('x'));
+        assert_eq!(name.len(), 2);
+        assert_eq!("xy".parse(), Ok(name.clone()));
+        assert_eq!(
+            SanitizedFileName::try_from("x:y".to_owned()),
+            Err(NotSanitized("x:y".to_owned()))
+        );
+        assert!(SanitizedFileName::try_from("x:y").is_err());
+        assert_eq!(SanitizedFileName::try_from("xy"), Ok(name.clone()));
+        assert_eq!(SanitizedFileName::coerce("x:y"), name);
+
+        let mut sizes = HashMap::new();
+        sizes.insert(name.clone(), 1);
+        assert_eq!(sizes.get("xy"), Some(&1));
+        assert_eq!(PathBuf::from(name), PathBuf::from("xy"));
+    }
+}
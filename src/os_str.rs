@@ -0,0 +1,114 @@
+use std::borrow::Cow;
+use std::ffi::{OsStr, OsString};
+use std::str;
+
+use crate::{sanitize_with_options, Options};
+
+/// Sanitizes a name as it comes from the OS, which need not be valid Unicode: names can
+/// hold invalid UTF-8 on Unix and unpaired surrogates on Windows.
+///
+/// Each invalid sequence is replaced with the [`Options::replacement`], so it is stripped
+/// by default, before the name is sanitized; the result is always valid Unicode. That
+/// saves going through `to_string_lossy`, which leaves U+FFFD in their place.
+///
+/// ```
+/// use std::ffi::OsStr;
+///
+/// let options = sanitize_filename::Options::new().with_replacement("_");
+/// let name = sanitize_filename::sanitize_os_str(OsStr::new("a:b.txt"), &options);
+/// assert_eq!(name, "a_b.txt");
+/// ```
+pub fn sanitize_os_str(name: &OsStr, options: &Options) -> OsString {
+    sanitize_with_options(decode(name, &options.replacement), options.borrowed()).into()
+}
+
+// `name` as a `str`, with each invalid sequence replaced with `replacement`.
+pub(crate) fn decode<'n>(name: &'n OsStr, replacement: &str) -> Cow<'n, str> {
+    match name.to_str() {
+        Some(name) => Cow::Borrowed(name),
+        None => Cow::Owned(decode_invalid(name, replacement)),
+    }
+}
+
+#[cfg(unix)]
+fn decode_invalid(name: &OsStr, replacement: &str) -> String {
+    use std::os::unix::ffi::OsStrExt;
+
+    decode_utf8(name.as_bytes(), replacement)
+}
+
+// `bytes` as UTF-8, with each invalid sequence replaced with `replacement`.
+pub(crate) fn decode_utf8(mut bytes: &[u8], replacement: &str) -> String {
+    let mut decoded = String::with_capacity(bytes.len());
+    loop {
+        match str::from_utf8(bytes) {
+            Ok(valid) => {
+                decoded.push_str(valid);
+                return decoded;
+            }
+            Err(err) => {
+                let (valid, rest) = bytes.split_at(err.valid_up_to());
+                decoded.push_str(str::from_utf8(valid).unwrap_or_default());
+                decoded.push_str(replacement);
+                bytes = &rest[err.error_len().unwrap_or(rest.len())..];
+            }
+        }
+    }
+}
+
+#[cfg(windows)]
+fn decode_invalid(name: &OsStr, replacement: &str) -> String {
+    use std::os::windows::ffi::OsStrExt;
+
+    let mut decoded = String::new();
+    for c in char::decode_utf16(name.encode_wide()) {
+        match c {
+            Ok(c) => decoded.push(c),
+            Err(_) => decoded.push_str(replacement),
+        }
+    }
+    decoded
+}
+
+#[cfg(not(any(unix, windows)))]
+fn decode_invalid(name: &OsStr, replacement: &str) -> String {
+    // Without access to the encoding, U+FFFD is all there is to go by.
+    name.to_string_lossy().replace('\u{FFFD}', replacement)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::OsStr;
+
+    use super::sanitize_os_str;
+
+    #[test]
+    fn it_sanitizes_os_strs() {
+        let options = crate::Options::new().with_replacement("_");
+        assert_eq!(sanitize_os_str(OsStr::new("a/b"), &options), "a_b");
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::ffi::OsStrExt;
+
+            let name = OsStr::from_bytes(b"caf\xe9 \xff\xfe:menu.txt");
+            assert_eq!(sanitize_os_str(name, &options), "caf_ ___menu.txt");
+            assert_eq!(
+                sanitize_os_str(name, &crate::Options::default()),
+                "caf menu.txt"
+            );
+            assert_eq!(
+                sanitize_os_str(OsStr::from_bytes(b"ab\xe2\x82"), &options),
+                "ab_"
+            );
+        }
+        #[cfg(windows)]
+        {
+            use std::ffi::OsString;
+            use std::os::windows::ffi::OsStringExt;
+
+            let name = OsString::from_wide(&[0x61, 0xd800, 0x3a, 0x62]);
+            assert_eq!(sanitize_os_str(&name, &options), "a__b");
+        }
+    }
+}
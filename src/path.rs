@@ -0,0 +1,598 @@
+use std::fmt;
+use std::path::{Component, Path, PathBuf};
+
+use crate::os_str::decode;
+use crate::{
+    length_limit, sanitize_part, sanitize_with_options, split_extension, FileNameBuilder,
+    LengthUnit, Options, TruncationStrategy,
+};
+
+/// What path functions do with a path that has more than [`Options::max_depth`]
+/// components.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum DepthPolicy {
+    /// Merge the deepest components into the last allowed one, as [`flatten_path`]
+    /// does.
+    #[default]
+    Flatten,
+    /// Fail with [`PathError::TooDeep`].
+    Error,
+}
+
+/// The longest full path allowed, for [`Options::path_limit`].
+///
+/// Limits are counted in [`Options::length_unit`], bytes by default. Windows counts UTF-16
+/// units, of which a path never has more than it has bytes, so counting bytes can only
+/// overestimate there: some paths that would fit are shortened or rejected, but the ones
+/// that are accepted always fit. [`LengthUnit::Utf16`] counts as Windows does.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PathLimit {
+    /// What the running system allows: on Windows, `Extended` if long paths are enabled
+    /// both in the registry (`LongPathsEnabled`) and in the manifest of the program, or
+    /// else `Classic`. Elsewhere, `Classic` with [`Options::windows`], or else 4096
+    /// (`PATH_MAX` on Linux).
+    #[default]
+    Auto,
+    /// `MAX_PATH`, 260 including the terminating NUL, which most Windows programs still
+    /// assume.
+    Classic,
+    /// 32767, for paths on Windows with long paths enabled.
+    Extended,
+    /// A given limit, e.g. for a server preparing names for other machines.
+    Custom(usize),
+}
+
+const CLASSIC_MAX_PATH: usize = 260;
+const EXTENDED_MAX_PATH: usize = 32_767;
+#[cfg(not(windows))]
+const POSIX_PATH_MAX: usize = 4096;
+
+#[cfg(windows)]
+fn long_paths_enabled() -> bool {
+    use std::ffi::c_void;
+    use std::sync::OnceLock;
+
+    extern "system" {
+        fn GetModuleHandleW(name: *const u16) -> *mut c_void;
+        fn GetProcAddress(module: *mut c_void, name: *const u8) -> *mut c_void;
+    }
+
+    static ENABLED: OnceLock<bool> = OnceLock::new();
+    *ENABLED.get_or_init(|| {
+        // `RtlAreLongPathsEnabled` checks both the registry and the manifest; it is looked
+        // up at run time as versions of Windows before 10 1607 do not have it.
+        let ntdll = "ntdll.dll\0".encode_utf16().collect::<Vec<_>>();
+        unsafe {
+            let module = GetModuleHandleW(ntdll.as_ptr());
+            if module.is_null() {
+                return false;
+            }
+            let proc = GetProcAddress(module, b"RtlAreLongPathsEnabled\0".as_ptr());
+            if proc.is_null() {
+                return false;
+            }
+            // SAFETY: ntdll declares `BOOLEAN NTAPI RtlAreLongPathsEnabled(VOID)`, which
+            // takes nothing and returns a `u8` under the `system` calling convention.
+            let are_long_paths_enabled: extern "system" fn() -> u8 = std::mem::transmute(proc);
+            are_long_paths_enabled() != 0
+        }
+    })
+}
+
+/// The longest full path, in `options.length_unit`, that `options.path_limit` allows on
+/// this system.
+pub fn max_path_len(options: &Options) -> usize {
+    match options.path_limit {
+        #[cfg(windows)]
+        PathLimit::Auto if long_paths_enabled() => EXTENDED_MAX_PATH,
+        #[cfg(not(windows))]
+        PathLimit::Auto if !options.windows => POSIX_PATH_MAX,
+        PathLimit::Auto | PathLimit::Classic => CLASSIC_MAX_PATH,
+        PathLimit::Extended => EXTENDED_MAX_PATH,
+        PathLimit::Custom(limit) => limit,
+    }
+}
+
+/// Sanitizes `name` for the directory `dir`, shortening it so that the full path fits in
+/// [`max_path_len`], and returns that path:
+///
+/// ```
+/// use std::path::Path;
+/// use sanitize_filename::{Options, PathLimit};
+///
+/// let options = Options::new().with_path_limit(PathLimit::Classic);
+/// let dir = Path::new("/").join("x".repeat(251));
+/// let path = sanitize_filename::sanitize_in_dir(&dir, "report: final.pdf", &options).unwrap();
+/// assert_eq!(path, dir.join("report"));
+/// ```
+///
+/// The limit counts the terminating NUL, as `MAX_PATH` and `PATH_MAX` do, and a separator
+/// between `dir` and the name. The name is shortened as `options.truncation` says, within
+/// `max_length` as well. Fails with [`PathError::DirTooLong`] if `dir` leaves no room for
+/// a name at all; the name can still be empty if nothing is left of it after sanitizing.
+pub fn sanitize_in_dir<P, S>(dir: P, name: S, options: &Options) -> Result<PathBuf, PathError>
+where
+    P: AsRef<Path>,
+    S: AsRef<str>,
+{
+    let dir = dir.as_ref();
+    let unit = options.length_unit;
+    let len = match unit {
+        LengthUnit::Bytes => dir.as_os_str().len(),
+        _ => unit.measure(&dir.to_string_lossy()),
+    };
+    // The separator is ASCII, so it is as long in any unit.
+    let separator = dir.join("_").as_os_str().len() - dir.as_os_str().len() - 1;
+    let max_len = max_path_len(options);
+    let budget = max_len.saturating_sub(len + separator + 1);
+    if budget == 0 {
+        return Err(PathError::DirTooLong { len, max_len, unit });
+    }
+
+    let mut name = sanitize_with_options(name.as_ref(), options.borrowed());
+    if unit.measure(&name) > budget {
+        let mut fitting = options.borrowed();
+        if !fitting.truncation.truncates() {
+            fitting.truncation = TruncationStrategy::CutEnd;
+        }
+        fitting.max_length = budget;
+        name = sanitize_with_options(name, fitting);
+    }
+    Ok(dir.join(name))
+}
+
+/// An error from the path functions.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PathError {
+    /// The path has more components than [`Options::max_depth`] allows.
+    TooDeep { depth: usize, max_depth: usize },
+    /// The path does not start with `\\`, for [`sanitize_unc_path`](crate::sanitize_unc_path).
+    NotUnc,
+    /// Nothing is left of the server name of a UNC path.
+    InvalidServer,
+    /// Nothing is left of the share name of a UNC path.
+    InvalidShare,
+    /// The directory leaves no room for a name within [`max_path_len`], for
+    /// [`sanitize_in_dir`].
+    DirTooLong {
+        len: usize,
+        max_len: usize,
+        unit: LengthUnit,
+    },
+}
+
+impl fmt::Display for PathError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PathError::TooDeep { depth, max_depth } => write!(
+                f,
+                "path has {} components, more than the maximum of {}",
+                depth, max_depth
+            ),
+            PathError::NotUnc => write!(f, r"path does not start with \\"),
+            PathError::InvalidServer => write!(f, "invalid server name"),
+            PathError::InvalidShare => write!(f, "invalid share name"),
+            PathError::DirTooLong { len, max_len, unit } => write!(
+                f,
+                "directory of {} {} leaves no room for a name within {}",
+                len,
+                unit.name(),
+                max_len
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PathError {}
+
+// Splits on both separators, as archive entries use `/` whatever system made them, and
+// skips the components that do not name anything.
+pub(crate) fn raw_components(path: &str) -> Vec<&str> {
+    path.split(['/', '\\'])
+        .filter(|component| !matches!(*component, "" | "." | ".."))
+        .collect()
+}
+
+/// Sanitizes each component of the path-ish `path`, such as the name of an archive
+/// entry, with `options`.
+///
+/// Both `/` and `\` separate components; empty, `.` and `..` components are dropped, as
+/// are components of which nothing is left after sanitizing. Paths deeper than
+/// `options.max_depth` are handled as `options.depth_policy` says (a `max_depth` of zero
+/// is treated as one):
+///
+/// ```
+/// use sanitize_filename::{sanitize_path_components, DepthPolicy, Options, PathError};
+///
+/// let options = Options::new().with_max_depth(Some(2)).with_replacement("_");
+/// assert_eq!(
+///     sanitize_path_components("a/../b/c:d", &options).unwrap(),
+///     ["a", "b_c_d"]
+/// );
+/// assert_eq!(
+///     sanitize_path_components("a/b/c", &options.with_depth_policy(DepthPolicy::Error)),
+///     Err(PathError::TooDeep { depth: 3, max_depth: 2 })
+/// );
+/// ```
+pub fn sanitize_path_components<S: AsRef<str>>(
+    path: S,
+    options: &Options,
+) -> Result<Vec<String>, PathError> {
+    sanitize_components(raw_components(path.as_ref()), options)
+}
+
+// Sanitizes each of `components`, handling paths that are too deep as
+// `options.depth_policy` says.
+fn sanitize_components<S: AsRef<str>>(
+    mut components: Vec<S>,
+    options: &Options,
+) -> Result<Vec<String>, PathError> {
+    let mut deepest = None;
+    if let Some(max_depth) = options.max_depth {
+        let max_depth = max_depth.max(1);
+        if components.len() > max_depth {
+            match options.depth_policy {
+                DepthPolicy::Error => {
+                    return Err(PathError::TooDeep {
+                        depth: components.len(),
+                        max_depth,
+                    })
+                }
+                DepthPolicy::Flatten => {
+                    let deep = components.split_off(max_depth - 1);
+                    let deep = deep.iter().map(AsRef::as_ref).collect::<Vec<_>>();
+                    deepest = Some(flatten_components(&deep, options))
+                }
+            }
+        }
+    }
+
+    let mut sanitized = components
+        .into_iter()
+        .map(|component| sanitize_with_options(component, options.borrowed()))
+        .collect::<Vec<_>>();
+    sanitized.extend(deepest);
+    sanitized.retain(|component| !component.is_empty());
+    Ok(sanitized)
+}
+
+/// Sanitizes each component of `path`, keeping the directory structure that [`sanitize`]
+/// would flatten (`a/b:c` gives `a/bc`, not `abc`):
+///
+/// ```
+/// use std::path::Path;
+///
+/// let options = sanitize_filename::Options::new().with_replacement("_");
+/// let path = Path::new("/srv").join("..").join("uploads").join("a?.txt");
+/// assert_eq!(
+///     sanitize_filename::sanitize_path(&path, &options).unwrap(),
+///     Path::new("srv").join("uploads").join("a_.txt")
+/// );
+/// ```
+///
+/// The result is always relative, so that it stays within the directory it is joined
+/// to: roots, prefixes such as `C:`, and `.` and `..` components are dropped, as are
+/// components of which nothing is left after sanitizing. Components that are not valid
+/// Unicode are handled as in [`sanitize_os_str`](crate::sanitize_os_str), and
+/// `options.max_depth` applies as in [`sanitize_path_components`]. Unlike there, only the
+/// separators of the running system separate components, so a `\` in a Unix name is
+/// sanitized as part of it.
+///
+/// [`sanitize`]: crate::sanitize
+pub fn sanitize_path<P: AsRef<Path>>(path: P, options: &Options) -> Result<PathBuf, PathError> {
+    let components = (path.as_ref().components())
+        .filter_map(|component| match component {
+            Component::Normal(name) => Some(decode(name, &options.replacement)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    Ok(sanitize_components(components, options)?
+        .into_iter()
+        .collect())
+}
+
+/// Sanitizes the path-ish `path` like [`sanitize_path_components`], but returns the
+/// directories and the final name separately, e.g. to store the directories as metadata
+/// instead of recreating them:
+///
+/// ```
+/// let options = sanitize_filename::Options::new();
+/// let (dirs, name) = sanitize_filename::split_path("photos/2024:06/img.jpg", &options).unwrap();
+/// assert_eq!(dirs, ["photos", "202406"]);
+/// assert_eq!(name, "img.jpg");
+/// ```
+///
+/// The last component is taken as the name even if `path` ends with a separator. The name
+/// is empty only if no component is left at all.
+pub fn split_path<S: AsRef<str>>(
+    path: S,
+    options: &Options,
+) -> Result<(Vec<String>, String), PathError> {
+    let mut dirs = sanitize_path_components(path, options)?;
+    let name = dirs.pop().unwrap_or_default();
+    Ok((dirs, name))
+}
+
+// Joins `components` into one name with `options.joiner`, keeping the extension of the
+// last one. When the result would be too long, the directories are shortened first,
+// deepest first, as in `FileNameBuilder`, which applies the whole-name rules once to the
+// joined name.
+fn flatten_components(components: &[&str], options: &Options) -> String {
+    let (name, dirs) = match components.split_last() {
+        Some(split) => split,
+        None => return String::new(),
+    };
+    let joiner = joiner(options);
+    let mut builder = FileNameBuilder::with_options(options.borrowed());
+    let limit = length_limit(options).unwrap_or(usize::MAX);
+    let unit = options.length_unit;
+    let mut len = 0;
+    for dir in dirs {
+        // The directories past the limit would be dropped anyway, so there is no need
+        // to make the builder shorten them one by one.
+        if len > limit {
+            break;
+        }
+        len += unit.measure(&sanitize_part(dir, options))
+            + unit.measure(joiner.encode_utf8(&mut [0; 4]));
+        builder.push(dir).push_separator(joiner);
+    }
+    let (stem, extension) = split_extension(name, options);
+    builder
+        .push_with_priority(stem, u32::MAX)
+        .set_extension(extension);
+    builder.build()
+}
+
+// `options.joiner`, or `_` if names may not have it.
+fn joiner(options: &Options) -> char {
+    let joiner = options.joiner;
+    let encoded = joiner.encode_utf8(&mut [0; 4]).to_owned();
+    if sanitize_part(&encoded, options) == encoded {
+        joiner
+    } else {
+        '_'
+    }
+}
+
+/// Turns the path-ish `path` into a single file name, joining its components with
+/// `options.joiner`:
+///
+/// ```
+/// let options = sanitize_filename::Options::new().with_joiner('—');
+/// assert_eq!(sanitize_filename::flatten_path("a/b/c.txt", &options), "a—b—c.txt");
+/// ```
+///
+/// Components are dropped as in [`sanitize_path_components`]. When the name would be too
+/// long, the directories are shortened before the last component, and its extension is
+/// kept.
+pub fn flatten_path<S: AsRef<str>>(path: S, options: &Options) -> String {
+    flatten_components(&raw_components(path.as_ref()), options)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use super::{
+        flatten_path, max_path_len, sanitize_in_dir, sanitize_path, sanitize_path_components,
+        split_path, DepthPolicy, PathError, PathLimit,
+    };
+
+    #[test]
+    fn it_limits_path_depth() {
+        let options = crate::Options::new().with_replacement("_");
+
+        assert_eq!(
+            sanitize_path_components("./a\\..//b/c?", &options).unwrap(),
+            ["a", "b", "c_"]
+        );
+        assert_eq!(
+            sanitize_path_components("a/b/c/d", &options.clone().with_max_depth(Some(2))).unwrap(),
+            ["a", "b_c_d"]
+        );
+        assert_eq!(
+            sanitize_path_components("a/b", &options.clone().with_max_depth(Some(0))).unwrap(),
+            ["a_b"]
+        );
+
+        let deep = "a/".repeat(10_000);
+        let flattened =
+            sanitize_path_components(&deep, &options.clone().with_max_depth(Some(16))).unwrap();
+        assert_eq!(flattened.len(), 16);
+        assert_eq!(flattened[15].len(), 255);
+
+        let strict = options
+            .with_max_depth(Some(16))
+            .with_depth_policy(DepthPolicy::Error);
+        assert_eq!(
+            sanitize_path_components(&deep, &strict),
+            Err(PathError::TooDeep {
+                depth: 10_000,
+                max_depth: 16
+            })
+        );
+        assert!(sanitize_path_components("a/b", &strict).is_ok());
+    }
+
+    #[test]
+    fn it_sanitizes_paths() {
+        let options = crate::Options::new().with_replacement("_");
+        let path: PathBuf = ["a", ".", "..", "b:c", "???", "d"].iter().collect();
+
+        assert_eq!(
+            sanitize_path(&path, &crate::Options::new()).unwrap(),
+            Path::new("a").join("bc").join("d")
+        );
+        assert_eq!(
+            sanitize_path(&path, &options).unwrap(),
+            ["a", "b_c", "___", "d"].iter().collect::<PathBuf>()
+        );
+        assert_eq!(
+            sanitize_path(Path::new("/"), &options).unwrap(),
+            PathBuf::new()
+        );
+        assert_eq!(
+            sanitize_path(&path, &options.clone().with_max_depth(Some(2))).unwrap(),
+            Path::new("a").join("b_c_____d")
+        );
+        assert!(sanitize_path(
+            &path,
+            &options
+                .with_max_depth(Some(2))
+                .with_depth_policy(DepthPolicy::Error)
+        )
+        .is_err());
+
+        #[cfg(unix)]
+        {
+            use std::ffi::OsStr;
+            use std::os::unix::ffi::OsStrExt;
+
+            let path = Path::new("x").join(OsStr::from_bytes(b"caf\xe9\\"));
+            assert_eq!(
+                sanitize_path(path, &crate::Options::new()).unwrap(),
+                Path::new("x").join("caf")
+            );
+        }
+    }
+
+    #[test]
+    fn it_fits_names_in_dirs() {
+        let options = crate::Options::new().with_path_limit(PathLimit::Custom(16));
+
+        let path = sanitize_in_dir("dir", "a:bcdefghijklmnop.txt", &options).unwrap();
+        assert_eq!(path, Path::new("dir").join("abcdefghijk"));
+        assert_eq!(path.as_os_str().len(), 15);
+        assert_eq!(
+            sanitize_in_dir("", "abcdefghijklmnopq", &options).unwrap(),
+            Path::new("abcdefghijklmno")
+        );
+        let preserving = options
+            .clone()
+            .with_truncation(crate::TruncationStrategy::PreserveExtension);
+        assert_eq!(
+            sanitize_in_dir("dir", "abcdefghijklmnop.txt", &preserving).unwrap(),
+            Path::new("dir").join("abcdefg.txt")
+        );
+        assert_eq!(
+            sanitize_in_dir(
+                "dir",
+                "café",
+                &options.clone().with_length_unit(crate::LengthUnit::Chars)
+            )
+            .unwrap(),
+            Path::new("dir").join("café")
+        );
+        let utf16 = options.clone().with_length_unit(crate::LengthUnit::Utf16);
+        assert_eq!(
+            sanitize_in_dir("dir", "日本語".repeat(4), &utf16).unwrap(),
+            Path::new("dir").join("日本語日本語日本語日本")
+        );
+        assert_eq!(
+            sanitize_in_dir("dir", "日本語".repeat(4), &options).unwrap(),
+            Path::new("dir").join("日本語")
+        );
+        assert_eq!(
+            sanitize_in_dir("日本語".repeat(4), "a", &utf16).unwrap(),
+            Path::new(&"日本語".repeat(4)).join("a")
+        );
+        assert_eq!(
+            sanitize_in_dir("d".repeat(14), "a", &options),
+            Err(PathError::DirTooLong {
+                len: 14,
+                max_len: 16,
+                unit: crate::LengthUnit::Bytes
+            })
+        );
+    }
+
+    #[test]
+    fn it_flattens_paths() {
+        let options = crate::Options::new();
+
+        assert_eq!(flatten_path("a/b/c.txt", &options), "a_b_c.txt");
+        assert_eq!(flatten_path("a\\../b?/.c", &options), "a_b_.c");
+        assert_eq!(flatten_path("a/?/b", &options), "a_b");
+        assert_eq!(
+            flatten_path("a/b", &options.clone().with_joiner('/')),
+            "a_b"
+        );
+        assert_eq!(
+            flatten_path("a/b/c.txt", &options.clone().with_joiner('.')),
+            "a.b.c.txt"
+        );
+        assert_eq!(
+            flatten_path("a/b/c.txt", &options.clone().with_prefix("up_")),
+            "up_a_b_c.txt"
+        );
+        assert_eq!(
+            flatten_path("con/b.", &options.clone().with_windows(true)),
+            "con_b"
+        );
+        assert_eq!(
+            crate::sanitize_path_components(
+                "a/b/c/d.txt",
+                &options.clone().with_prefix("up_").with_max_depth(Some(2))
+            )
+            .unwrap(),
+            ["up_a", "up_b_c_d.txt"]
+        );
+        assert_eq!(flatten_path("", &options), "");
+
+        let long = format!("{}/{}/report.pdf", "x".repeat(200), "y".repeat(200));
+        let flattened = flatten_path(&long, &options);
+        assert_eq!(flattened.len(), 255);
+        assert!(flattened.starts_with(&format!("{}_y", "x".repeat(200))));
+        assert!(flattened.ends_with("y_report.pdf"));
+    }
+
+    #[test]
+    fn it_splits_paths() {
+        let options = crate::Options::new().with_replacement("_");
+
+        assert_eq!(
+            split_path("/a/b?/c.txt", &options).unwrap(),
+            (vec!["a".to_owned(), "b_".to_owned()], "c.txt".to_owned())
+        );
+        assert_eq!(
+            split_path("a/", &options).unwrap(),
+            (vec![], "a".to_owned())
+        );
+        assert_eq!(
+            split_path("../..", &options).unwrap(),
+            (vec![], String::new())
+        );
+        assert!(split_path(
+            "a/b/c",
+            &options
+                .with_max_depth(Some(2))
+                .with_depth_policy(DepthPolicy::Error)
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn it_picks_path_limits() {
+        let options = crate::Options::new();
+
+        for (limit, len) in [
+            (PathLimit::Classic, 260),
+            (PathLimit::Extended, 32_767),
+            (PathLimit::Custom(1024), 1024),
+        ] {
+            assert_eq!(max_path_len(&options.clone().with_path_limit(limit)), len);
+        }
+        #[cfg(not(windows))]
+        {
+            assert_eq!(max_path_len(&options.clone().with_windows(false)), 4096);
+            assert_eq!(max_path_len(&options.with_windows(true)), 260);
+        }
+    }
+}
@@ -0,0 +1,175 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Range;
+
+#[cfg(feature = "regex")]
+type Inner = regex::Regex;
+// Patterns can only be compiled with the `regex` feature, so without it there are none.
+#[cfg(not(feature = "regex"))]
+type Inner = std::convert::Infallible;
+
+/// A regular expression that names must not match, for
+/// [`Options::with_deny_patterns`](crate::Options::with_deny_patterns), e.g. `^~\$` for
+/// Office lock files or `\.tmp$` for temporary files.
+///
+/// When sanitizing, each match is replaced with
+/// [`Options::replacement`](crate::Options::replacement); when checking, a name with a
+/// match is rejected. Patterns are built with the `regex` feature, and two patterns are
+/// equal when their sources are.
+#[derive(Clone)]
+pub struct DenyPattern(Inner);
+
+impl DenyPattern {
+    /// Compiles `pattern`, in the syntax of the [`regex`] crate.
+    ///
+    /// ```
+    /// use sanitize_filename::{sanitize_with_options, DenyPattern, Options};
+    ///
+    /// let options = Options::new().with_deny_patterns([
+    ///     DenyPattern::new(r"^~\$").unwrap(),
+    ///     DenyPattern::new(r"(?i)\.tmp$").unwrap(),
+    /// ]);
+    /// assert_eq!(sanitize_with_options("~$report.docx", options.clone()), "report.docx");
+    /// assert_eq!(sanitize_with_options("draft.TMP", options), "draft");
+    /// ```
+    #[cfg(feature = "regex")]
+    pub fn new(pattern: &str) -> Result<Self, regex::Error> {
+        regex::Regex::new(pattern).map(DenyPattern)
+    }
+
+    /// The source of this pattern.
+    pub fn as_str(&self) -> &str {
+        #[cfg(feature = "regex")]
+        {
+            self.0.as_str()
+        }
+        #[cfg(not(feature = "regex"))]
+        match self.0 {}
+    }
+
+    pub(crate) fn is_match(&self, name: &str) -> bool {
+        #[cfg(feature = "regex")]
+        {
+            self.0.is_match(name)
+        }
+        #[cfg(not(feature = "regex"))]
+        {
+            let _ = name;
+            match self.0 {}
+        }
+    }
+
+    // Where the first match in `name` is.
+    pub(crate) fn find(&self, name: &str) -> Option<Range<usize>> {
+        #[cfg(feature = "regex")]
+        {
+            self.0.find(name).map(|found| found.range())
+        }
+        #[cfg(not(feature = "regex"))]
+        {
+            let _ = name;
+            match self.0 {}
+        }
+    }
+
+    pub(crate) fn replace_all<'n>(&self, name: Cow<'n, str>, replacement: &str) -> Cow<'n, str> {
+        #[cfg(feature = "regex")]
+        {
+            match self.0.replace_all(&name, regex::NoExpand(replacement)) {
+                Cow::Borrowed(_) => name,
+                Cow::Owned(replaced) => Cow::Owned(replaced),
+            }
+        }
+        #[cfg(not(feature = "regex"))]
+        {
+            let _ = (name, replacement);
+            match self.0 {}
+        }
+    }
+}
+
+#[cfg(feature = "regex")]
+impl From<regex::Regex> for DenyPattern {
+    fn from(regex: regex::Regex) -> Self {
+        DenyPattern(regex)
+    }
+}
+
+impl fmt::Debug for DenyPattern {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("DenyPattern").field(&self.as_str()).finish()
+    }
+}
+
+impl PartialEq for DenyPattern {
+    fn eq(&self, other: &DenyPattern) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl Eq for DenyPattern {}
+
+impl Hash for DenyPattern {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state);
+    }
+}
+
+// As the source, which is compiled again when deserializing.
+#[cfg(feature = "serde")]
+impl serde::Serialize for DenyPattern {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for DenyPattern {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let pattern = <Cow<str>>::deserialize(deserializer)?;
+        #[cfg(feature = "regex")]
+        {
+            DenyPattern::new(&pattern).map_err(serde::de::Error::custom)
+        }
+        #[cfg(not(feature = "regex"))]
+        Err(serde::de::Error::custom(format!(
+            "deny pattern `{}` needs the `regex` feature",
+            pattern
+        )))
+    }
+}
+
+#[cfg(all(test, feature = "regex"))]
+mod tests {
+    use super::DenyPattern;
+
+    #[test]
+    fn it_denies_patterns() {
+        let options = crate::Options::new()
+            .with_replacement("_")
+            .with_deny_patterns([
+                DenyPattern::new(r"^~\$").unwrap(),
+                DenyPattern::new(r"\.(tmp|bak)$").unwrap(),
+            ]);
+        let check = crate::OptionsForCheck::from(&options);
+        let sanitize = |name: &str| crate::sanitize_with_options(name, options.clone());
+
+        assert_eq!(sanitize("~$notes.docx"), "_notes.docx");
+        assert_eq!(sanitize("notes.docx.bak"), "notes.docx_");
+        assert_eq!(sanitize("notes~$.tmp.txt"), "notes~$.tmp.txt");
+        assert!(!crate::is_sanitized_with_options("a.tmp", check.clone()));
+        assert_eq!(
+            crate::violations("a.tmp", check),
+            [crate::Violation::DeniedPattern { index: 1 }]
+        );
+
+        // Replacing a match must not leave another one behind.
+        let options = crate::Options::new().with_deny_patterns([DenyPattern::new("ab").unwrap()]);
+        assert_eq!(crate::sanitize_with_options("aabb", options), "");
+
+        let pattern = DenyPattern::new("x+").unwrap();
+        assert_eq!(pattern, DenyPattern::from(regex::Regex::new("x+").unwrap()));
+        assert_eq!(format!("{:?}", pattern), r#"DenyPattern("x+")"#);
+    }
+}
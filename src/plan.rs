@@ -0,0 +1,175 @@
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::{sanitize_with_options, Options};
+
+/// One rename of a [`RenamePlan`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Rename {
+    pub from: PathBuf,
+    pub to: PathBuf,
+}
+
+/// An entry of the tree that a [`RenamePlan`] leaves alone although it is not sanitized.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Conflict {
+    /// The sanitized name is already used by another entry of the same directory (or by
+    /// an earlier rename into it).
+    Taken { from: PathBuf, to: PathBuf },
+    /// Nothing is left of the name after sanitizing.
+    Empty(PathBuf),
+    /// The name is not valid UTF-8.
+    NotUnicode(PathBuf),
+}
+
+/// The renames that sanitize every name in a directory tree, as computed by
+/// [`plan_tree`].
+///
+/// The renames are ordered children first, and each one only changes the last component
+/// of its path, so `from` is where the entry is at the time it is renamed even when one of
+/// its parent directories is renamed later in the plan.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RenamePlan {
+    pub renames: Vec<Rename>,
+    pub conflicts: Vec<Conflict>,
+}
+
+impl RenamePlan {
+    pub fn is_empty(&self) -> bool {
+        self.renames.is_empty()
+    }
+
+    /// Performs the renames in order, stopping at the first error.
+    pub fn execute(&self) -> io::Result<()> {
+        for rename in &self.renames {
+            std::fs::rename(&rename.from, &rename.to)?;
+        }
+        Ok(())
+    }
+
+    /// Where `path`, given as it is before the plan is executed, ends up afterwards.
+    pub fn final_path<P: AsRef<Path>>(&self, path: P) -> PathBuf {
+        let mut path = path.as_ref().to_path_buf();
+        for rename in &self.renames {
+            if let Ok(rest) = path.strip_prefix(&rename.from) {
+                path = rename.to.join(rest);
+            }
+        }
+        path
+    }
+}
+
+/// Computes the renames that sanitize the name of every entry below `dir` (but not `dir`
+/// itself), without touching the file system.
+///
+/// Like the `rename` command of the binary, an entry whose sanitized name is already taken
+/// in its directory is left alone and reported as a [`Conflict`].
+pub fn plan_tree<P: AsRef<Path>>(dir: P, options: &Options) -> io::Result<RenamePlan> {
+    let entries = walkdir::WalkDir::new(dir)
+        .min_depth(1)
+        .contents_first(true)
+        .sort_by_file_name()
+        .into_iter()
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // Names that exist (or will exist) in each directory once the renames so far are
+    // applied.
+    let mut taken = HashMap::<&Path, HashSet<String>>::new();
+    for entry in &entries {
+        if let (Some(parent), Some(name)) = (entry.path().parent(), entry.file_name().to_str()) {
+            taken.entry(parent).or_default().insert(name.to_owned());
+        }
+    }
+
+    let mut plan = RenamePlan::default();
+    for entry in &entries {
+        let path = entry.path();
+        let name = match entry.file_name().to_str() {
+            Some(name) => name,
+            None => {
+                plan.conflicts
+                    .push(Conflict::NotUnicode(path.to_path_buf()));
+                continue;
+            }
+        };
+        let sanitized = sanitize_with_options(name, options.borrowed());
+        if sanitized == name {
+            continue;
+        }
+        if sanitized.is_empty() {
+            plan.conflicts.push(Conflict::Empty(path.to_path_buf()));
+            continue;
+        }
+        let to = path.with_file_name(&sanitized);
+        let siblings = path.parent().and_then(|parent| taken.get_mut(parent));
+        match siblings {
+            Some(siblings) if !siblings.contains(&sanitized) => {
+                siblings.remove(name);
+                siblings.insert(sanitized);
+                plan.renames.push(Rename {
+                    from: path.to_path_buf(),
+                    to,
+                });
+            }
+            _ => plan.conflicts.push(Conflict::Taken {
+                from: path.to_path_buf(),
+                to,
+            }),
+        }
+    }
+    Ok(plan)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{plan_tree, Conflict};
+
+    #[test]
+    fn it_plans_tree_renames() {
+        let root = std::env::temp_dir().join(format!("sanitize-plan-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("d?ir").join("su*b")).unwrap();
+        std::fs::write(root.join("d?ir").join("su*b").join("f:ile"), "").unwrap();
+        std::fs::write(root.join("a"), "").unwrap();
+        std::fs::write(root.join("a?"), "").unwrap();
+
+        let plan = plan_tree(&root, &Default::default()).unwrap();
+        assert_eq!(
+            plan.conflicts,
+            vec![Conflict::Taken {
+                from: root.join("a?"),
+                to: root.join("a"),
+            }]
+        );
+        let renames = plan
+            .renames
+            .iter()
+            .map(|rename| (rename.from.strip_prefix(&root).unwrap(), &rename.to))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            renames,
+            vec![
+                (
+                    "d?ir/su*b/f:ile".as_ref(),
+                    &root.join("d?ir").join("su*b").join("file")
+                ),
+                ("d?ir/su*b".as_ref(), &root.join("d?ir").join("sub")),
+                ("d?ir".as_ref(), &root.join("dir")),
+            ]
+        );
+        assert_eq!(
+            plan.final_path(root.join("d?ir").join("su*b").join("f:ile")),
+            root.join("dir").join("sub").join("file")
+        );
+
+        plan.execute().unwrap();
+        assert!(root.join("dir").join("sub").join("file").is_file());
+        assert!(plan_tree(&root, &Default::default()).unwrap().is_empty());
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,476 @@
+use std::fmt;
+
+use serde::Deserialize;
+use serde_json::{Map, Value};
+
+use crate::Options;
+
+/// An error from [`Options::from_json`] or [`Options::from_toml`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct PolicyError {
+    /// The line of the document that the error is on, counting from 1.
+    pub line: usize,
+    pub message: String,
+}
+
+impl PolicyError {
+    fn new<M: Into<String>>(line: usize, message: M) -> Self {
+        PolicyError {
+            line,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for PolicyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for PolicyError {}
+
+impl Options<'static> {
+    /// Parses a policy document in JSON, an object of the options to set:
+    ///
+    /// ```
+    /// let options = sanitize_filename::Options::from_json(r##"{
+    ///     "replacement": "_",
+    ///     "max_length": 143,
+    ///     "extra_illegal": ["#", "%"],
+    ///     "reserved_names": ["desktop.ini"]
+    /// }"##).unwrap();
+    /// assert_eq!(options.max_length, 143);
+    /// assert_eq!(sanitize_filename::sanitize_with_options("#1: 50%", options), "_1_ 50_");
+    /// ```
+    ///
+    /// Options that the document does not set keep their defaults, and options that do not
+    /// exist are an error, so that typos in hand-written policies do not go unnoticed.
+    pub fn from_json(json: &str) -> Result<Self, PolicyError> {
+        let fields = match serde_json::from_str::<Value>(json).map_err(json_error)? {
+            Value::Object(fields) => fields,
+            _ => return Err(PolicyError::new(1, "expected an object of options")),
+        };
+        let options = serde_json::from_str::<Options>(json).map_err(json_error)?;
+        check_fields(&fields, &options, |_| 1)?;
+        Ok(options)
+    }
+
+    /// Parses a policy document in TOML, with one `key = value` line per option to set:
+    ///
+    /// ```
+    /// let options = sanitize_filename::Options::from_toml(r#"
+    /// ## Shared drive policy
+    /// windows = true
+    /// replacement = "_"
+    /// charset = "Portable"
+    /// reserved_names = ["desktop.ini", "ehthumbs.db"]
+    /// path_limit = { Custom = 1024 }
+    /// "#).unwrap();
+    /// assert_eq!(sanitize_filename::sanitize_with_options("Desktop.ini", options), "_");
+    /// ```
+    ///
+    /// Only the part of TOML that options need is supported: strings, integers, booleans,
+    /// and arrays and inline tables of them, without `[table]` headers. Like [`from_json`](Options::from_json), options
+    /// that are missing keep their defaults and unknown options are an error.
+    pub fn from_toml(toml: &str) -> Result<Self, PolicyError> {
+        let mut parser = TomlParser::new(toml);
+        let fields = parser.parse()?;
+        let line_of = |key: &str| {
+            let line = parser.lines.iter().find(|(k, _)| k == key);
+            line.map_or(1, |(_, line)| *line)
+        };
+        // One option at a time first, so that errors point at their line.
+        for (key, value) in &fields {
+            let mut field = Map::new();
+            field.insert(key.clone(), value.clone());
+            Options::deserialize(Value::Object(field))
+                .map_err(|err| PolicyError::new(line_of(key), err.to_string()))?;
+        }
+        let options = Options::deserialize(Value::Object(fields.clone()))
+            .map_err(|err| PolicyError::new(1, err.to_string()))?;
+        check_fields(&fields, &options, line_of)?;
+        Ok(options)
+    }
+}
+
+// serde_json reports the position at the end of its messages.
+fn json_error(err: serde_json::Error) -> PolicyError {
+    let message = err.to_string();
+    let message = match message.rsplit_once(" at line ") {
+        Some((message, _)) => message.to_owned(),
+        None => message,
+    };
+    PolicyError::new(err.line().max(1), message)
+}
+
+// Rejects the fields of `fields` that are not options, which deserializing ignores.
+fn check_fields<F: Fn(&str) -> usize>(
+    fields: &Map<String, Value>,
+    options: &Options,
+    line_of: F,
+) -> Result<(), PolicyError> {
+    let known = match serde_json::to_value(options) {
+        Ok(Value::Object(known)) => known,
+        _ => return Ok(()),
+    };
+    match fields.keys().find(|key| !known.contains_key(*key)) {
+        Some(key) => Err(PolicyError::new(
+            line_of(key),
+            format!("unknown option `{}`", key),
+        )),
+        None => Ok(()),
+    }
+}
+
+// A parser for the subset of TOML that policies use: top-level keys, with inline tables
+// for the options whose values are enum variants with data.
+struct TomlParser<'t> {
+    chars: std::iter::Peekable<std::str::Chars<'t>>,
+    line: usize,
+    // The line of each key
+    lines: Vec<(String, usize)>,
+}
+
+impl<'t> TomlParser<'t> {
+    fn new(toml: &'t str) -> Self {
+        TomlParser {
+            chars: toml.chars().peekable(),
+            line: 1,
+            lines: Vec::new(),
+        }
+    }
+
+    fn error<M: Into<String>>(&self, message: M) -> PolicyError {
+        PolicyError::new(self.line, message)
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.chars.next();
+        if c == Some('\n') {
+            self.line += 1;
+        }
+        c
+    }
+
+    // Skips spaces and tabs, and with `newlines` also line breaks and comments.
+    fn skip_blank(&mut self, newlines: bool) {
+        while let Some(&c) = self.chars.peek() {
+            match c {
+                ' ' | '\t' => {}
+                '\r' | '\n' if newlines => {}
+                '#' if newlines => {
+                    while self.chars.peek().is_some_and(|&c| c != '\n') {
+                        self.next();
+                    }
+                    continue;
+                }
+                _ => break,
+            }
+            self.next();
+        }
+    }
+
+    fn parse(&mut self) -> Result<Map<String, Value>, PolicyError> {
+        let mut fields = Map::new();
+        loop {
+            self.skip_blank(true);
+            let c = match self.chars.peek() {
+                Some(&c) => c,
+                None => return Ok(fields),
+            };
+            if c == '[' {
+                return Err(self.error("table headers are not supported"));
+            }
+            let line = self.line;
+            let key = self.parse_key()?;
+            self.skip_blank(false);
+            if self.next() != Some('=') {
+                return Err(self.error(format!("expected `=` after `{}`", key)));
+            }
+            self.skip_blank(false);
+            let value = self.parse_value()?;
+            self.skip_blank(false);
+            if self.chars.peek() == Some(&'#') {
+                self.skip_blank(true);
+            } else {
+                match self.next() {
+                    None | Some('\n') => {}
+                    Some('\r') if self.next() == Some('\n') => {}
+                    _ => {
+                        return Err(PolicyError::new(
+                            line,
+                            "expected a line break after the value",
+                        ))
+                    }
+                }
+            }
+            if fields.insert(key.clone(), value).is_some() {
+                return Err(PolicyError::new(
+                    line,
+                    format!("duplicate option `{}`", key),
+                ));
+            }
+            self.lines.push((key, line));
+        }
+    }
+
+    fn parse_key(&mut self) -> Result<String, PolicyError> {
+        match self.chars.peek() {
+            Some('"') => return self.parse_basic_string(),
+            Some('\'') => return self.parse_literal_string(),
+            _ => {}
+        }
+        let mut key = String::new();
+        while let Some(&c) = self.chars.peek() {
+            if !(c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+                break;
+            }
+            key.push(c);
+            self.next();
+        }
+        if key.is_empty() {
+            return Err(self.error("expected an option name"));
+        }
+        Ok(key)
+    }
+
+    fn parse_value(&mut self) -> Result<Value, PolicyError> {
+        match self.chars.peek() {
+            Some('"') => self.parse_basic_string().map(Value::String),
+            Some('\'') => self.parse_literal_string().map(Value::String),
+            Some('[') => self.parse_array(),
+            Some('{') => self.parse_inline_table(),
+            Some('t') | Some('f') => {
+                let word = self.parse_word();
+                match word.as_str() {
+                    "true" => Ok(Value::Bool(true)),
+                    "false" => Ok(Value::Bool(false)),
+                    _ => Err(self.error(format!("invalid value `{}`", word))),
+                }
+            }
+            Some(c) if c.is_ascii_digit() || *c == '+' || *c == '-' => {
+                let word = self.parse_word();
+                let digits = word.trim_start_matches('+').replace('_', "");
+                match digits.parse::<i64>() {
+                    Ok(int) => Ok(Value::from(int)),
+                    Err(_) => Err(self.error(format!("invalid integer `{}`", word))),
+                }
+            }
+            _ => Err(self.error("expected a value")),
+        }
+    }
+
+    fn parse_word(&mut self) -> String {
+        let mut word = String::new();
+        while let Some(&c) = self.chars.peek() {
+            if !(c.is_ascii_alphanumeric() || matches!(c, '_' | '+' | '-' | '.')) {
+                break;
+            }
+            word.push(c);
+            self.next();
+        }
+        word
+    }
+
+    fn parse_array(&mut self) -> Result<Value, PolicyError> {
+        self.next();
+        let mut values = Vec::new();
+        loop {
+            self.skip_blank(true);
+            if self.chars.peek() == Some(&']') {
+                self.next();
+                return Ok(Value::Array(values));
+            }
+            values.push(self.parse_value()?);
+            self.skip_blank(true);
+            match self.next() {
+                Some(',') => {}
+                Some(']') => return Ok(Value::Array(values)),
+                _ => return Err(self.error("expected `,` or `]` in array")),
+            }
+        }
+    }
+
+    // On one line, without a trailing comma, as TOML has them.
+    fn parse_inline_table(&mut self) -> Result<Value, PolicyError> {
+        self.next();
+        let mut fields = Map::new();
+        self.skip_blank(false);
+        if self.chars.peek() == Some(&'}') {
+            self.next();
+            return Ok(Value::Object(fields));
+        }
+        loop {
+            self.skip_blank(false);
+            if self.chars.peek() == Some(&'}') {
+                return Err(self.error("trailing `,` in inline table"));
+            }
+            let key = self.parse_key()?;
+            self.skip_blank(false);
+            if self.next() != Some('=') {
+                return Err(self.error(format!("expected `=` after `{}`", key)));
+            }
+            self.skip_blank(false);
+            let value = self.parse_value()?;
+            if fields.insert(key.clone(), value).is_some() {
+                return Err(self.error(format!("duplicate key `{}`", key)));
+            }
+            self.skip_blank(false);
+            match self.chars.peek() {
+                Some(',') => {}
+                Some('}') => {
+                    self.next();
+                    return Ok(Value::Object(fields));
+                }
+                _ => return Err(self.error("expected `,` or `}` in inline table")),
+            }
+            self.next();
+        }
+    }
+
+    fn parse_literal_string(&mut self) -> Result<String, PolicyError> {
+        self.next();
+        let mut out = String::new();
+        loop {
+            match self.next() {
+                Some('\'') => return Ok(out),
+                Some('\n') | None => return Err(self.error("unterminated string")),
+                Some(c) => out.push(c),
+            }
+        }
+    }
+
+    fn parse_basic_string(&mut self) -> Result<String, PolicyError> {
+        self.next();
+        let mut out = String::new();
+        loop {
+            match self.next() {
+                Some('"') => return Ok(out),
+                Some('\\') => out.push(self.parse_escape()?),
+                Some('\n') | None => return Err(self.error("unterminated string")),
+                Some(c) => out.push(c),
+            }
+        }
+    }
+
+    fn parse_escape(&mut self) -> Result<char, PolicyError> {
+        let len = match self.next() {
+            Some('b') => return Ok('\u{8}'),
+            Some('t') => return Ok('\t'),
+            Some('n') => return Ok('\n'),
+            Some('f') => return Ok('\u{c}'),
+            Some('r') => return Ok('\r'),
+            Some('"') => return Ok('"'),
+            Some('\\') => return Ok('\\'),
+            Some('u') => 4,
+            Some('U') => 8,
+            _ => return Err(self.error("invalid escape in string")),
+        };
+        let hex = (0..len).filter_map(|_| self.next()).collect::<String>();
+        u32::from_str_radix(&hex, 16)
+            .ok()
+            .filter(|_| hex.len() == len)
+            .and_then(char::from_u32)
+            .ok_or_else(|| self.error(format!("invalid escape `{}`", hex)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Charset, Options, PathLimit};
+
+    #[test]
+    fn it_loads_policies() {
+        let options = Options::from_toml(
+            "# Upload policy\n\
+             windows = true\n\
+             replacement = '_' # for everything\n\
+             reserved_replacement = \"\\u0078\"\n\
+             max_length = 1_000\n\
+             charset = \"Portable\"\n\
+             extra_illegal = [\n  \"#\",\n  \"%\", # URLs\n]\n\
+             reserved_names = []\n",
+        )
+        .unwrap();
+        let expected = Options::new()
+            .with_windows(true)
+            .with_replacement("_")
+            .with_reserved_replacement("x")
+            .with_max_length(1000)
+            .with_charset(Charset::Portable)
+            .with_extra_illegal(&['#', '%'][..]);
+        assert_eq!(options, expected);
+        assert_eq!(
+            Options::from_json(
+                r##"{"windows": true, "replacement": "_", "reserved_replacement": "x",
+                "max_length": 1000, "charset": "Portable", "extra_illegal": ["#", "%"]}"##
+            ),
+            Ok(expected)
+        );
+        assert_eq!(Options::from_toml(""), Ok(Options::new()));
+
+        let error = |result: Result<Options, super::PolicyError>| {
+            let err = result.unwrap_err();
+            (err.line, err.message)
+        };
+        assert_eq!(
+            error(Options::from_toml("windows = true\nmax_lenght = 1")),
+            (2, String::from("unknown option `max_lenght`"))
+        );
+        assert_eq!(error(Options::from_toml("\nmax_length = \"long\"")).0, 2);
+        assert_eq!(
+            error(Options::from_toml("[options]")),
+            (1, String::from("table headers are not supported"))
+        );
+        assert_eq!(
+            error(Options::from_toml("a = 1\na = 2")),
+            (2, String::from("duplicate option `a`"))
+        );
+        assert_eq!(error(Options::from_toml("a = \"b")).0, 1);
+        assert_eq!(error(Options::from_json("{\n\"windows\": 1}")).0, 2);
+        assert_eq!(
+            error(Options::from_json("{\"windos\": true}")),
+            (1, String::from("unknown option `windos`"))
+        );
+        assert!(Options::from_json("[]").is_err());
+    }
+
+    #[test]
+    fn it_loads_inline_tables() {
+        let options = Options::from_toml(
+            "path_limit = { Custom = 1024 } # a NAS\n\
+             max_length = 64\n",
+        )
+        .unwrap();
+        assert_eq!(options.path_limit, PathLimit::Custom(1024));
+        assert_eq!(options.max_length, 64);
+        assert_eq!(
+            Options::from_toml("path_limit = {\"Custom\"=1024}")
+                .unwrap()
+                .path_limit,
+            PathLimit::Custom(1024)
+        );
+
+        let error = |toml: &str| {
+            let err = Options::from_toml(toml).unwrap_err();
+            (err.line, err.message)
+        };
+        assert_eq!(
+            error("path_limit = { Custom = 1024, }"),
+            (1, String::from("trailing `,` in inline table"))
+        );
+        assert_eq!(
+            error("windows = true\npath_limit = { Custom = 1024\n}"),
+            (2, String::from("expected `,` or `}` in inline table"))
+        );
+        assert_eq!(
+            error("path_limit = { Custom = 1, Custom = 2 }"),
+            (1, String::from("duplicate key `Custom`"))
+        );
+        assert_eq!(error("\npath_limit = { Custom = \"x\" }").0, 2);
+        assert_eq!(error("path_limit = { Huge = 1 }").0, 1);
+        assert_eq!(error("path_limit = {}").0, 1);
+    }
+}
@@ -0,0 +1,227 @@
+use crate::{length_limit, sanitize_with_options, Options};
+
+// The Bootstring parameters of Punycode (RFC 3492).
+const BASE: u64 = 36;
+const T_MIN: u64 = 1;
+const T_MAX: u64 = 26;
+const SKEW: u64 = 38;
+const DAMP: u64 = 700;
+const INITIAL_BIAS: u64 = 72;
+const INITIAL_N: u64 = 0x80;
+
+const PREFIX: &str = "xn--";
+
+fn adapt(delta: u64, points: u64, first: bool) -> u64 {
+    let mut delta = if first { delta / DAMP } else { delta / 2 };
+    delta += delta / points;
+    let mut k = 0;
+    while delta > ((BASE - T_MIN) * T_MAX) / 2 {
+        delta /= BASE - T_MIN;
+        k += BASE;
+    }
+    k + (BASE - T_MIN + 1) * delta / (delta + SKEW)
+}
+
+fn threshold(k: u64, bias: u64) -> u64 {
+    if k <= bias {
+        T_MIN
+    } else if k >= bias + T_MAX {
+        T_MAX
+    } else {
+        k - bias
+    }
+}
+
+fn encode_digit(d: u64) -> char {
+    match d {
+        0..=25 => (b'a' + d as u8) as char,
+        _ => (b'0' + (d - 26) as u8) as char,
+    }
+}
+
+fn decode_digit(c: char) -> Option<u64> {
+    match c {
+        'a'..='z' => Some(u64::from(c) - u64::from('a')),
+        'A'..='Z' => Some(u64::from(c) - u64::from('A')),
+        '0'..='9' => Some(u64::from(c) - u64::from('0') + 26),
+        _ => None,
+    }
+}
+
+fn encode(input: &str, out: &mut String) {
+    let input = input
+        .chars()
+        .map(|c| u64::from(u32::from(c)))
+        .collect::<Vec<_>>();
+    let basic = input.iter().filter(|&&c| c < INITIAL_N).count() as u64;
+    out.extend(
+        input
+            .iter()
+            .filter(|&&c| c < INITIAL_N)
+            .map(|&c| c as u8 as char),
+    );
+    if basic > 0 {
+        out.push('-');
+    }
+
+    let (mut n, mut delta, mut bias, mut handled) = (INITIAL_N, 0u64, INITIAL_BIAS, basic);
+    while (handled as usize) < input.len() {
+        let m = input.iter().copied().filter(|&c| c >= n).min().unwrap();
+        delta += (m - n) * (handled + 1);
+        n = m;
+        for &c in &input {
+            if c < n {
+                delta += 1;
+            }
+            if c == n {
+                let mut q = delta;
+                let mut k = BASE;
+                loop {
+                    let t = threshold(k, bias);
+                    if q < t {
+                        break;
+                    }
+                    out.push(encode_digit(t + (q - t) % (BASE - t)));
+                    q = (q - t) / (BASE - t);
+                    k += BASE;
+                }
+                out.push(encode_digit(q));
+                bias = adapt(delta, handled + 1, handled == basic);
+                delta = 0;
+                handled += 1;
+            }
+        }
+        delta += 1;
+        n += 1;
+    }
+}
+
+fn decode(input: &str) -> Option<String> {
+    let (basic, digits) = match input.rfind('-') {
+        Some(idx) => (&input[..idx], &input[idx + 1..]),
+        None => ("", input),
+    };
+    if !basic.is_ascii() {
+        return None;
+    }
+    let mut out = basic.chars().collect::<Vec<_>>();
+
+    let (mut n, mut i, mut bias) = (INITIAL_N, 0u64, INITIAL_BIAS);
+    let mut digits = digits.chars().peekable();
+    while digits.peek().is_some() {
+        let (old_i, mut w) = (i, 1u64);
+        let mut k = BASE;
+        loop {
+            let digit = decode_digit(digits.next()?)?;
+            i = i.checked_add(digit.checked_mul(w)?)?;
+            let t = threshold(k, bias);
+            if digit < t {
+                break;
+            }
+            w = w.checked_mul(BASE - t)?;
+            k += BASE;
+        }
+        let len = out.len() as u64 + 1;
+        bias = adapt(i - old_i, len, old_i == 0);
+        n = n.checked_add(i / len)?;
+        i %= len;
+        out.insert(i as usize, char::from_u32(u32::try_from(n).ok()?)?);
+        i += 1;
+    }
+    Some(out.into_iter().collect())
+}
+
+fn encode_labels(name: &str) -> String {
+    let mut out = String::with_capacity(name.len() * 2);
+    for (idx, label) in name.split('.').enumerate() {
+        if idx > 0 {
+            out.push('.');
+        }
+        if label.is_ascii() {
+            out.push_str(label);
+        } else {
+            out.push_str(PREFIX);
+            encode(label, &mut out);
+        }
+    }
+    out
+}
+
+/// Sanitizes `name` and makes it ASCII-only in a reversible way, for storage that only
+/// accepts ASCII names; see [`from_ascii_name`].
+///
+/// Like domain names in IDNA, each dot-separated part of the name that is not ASCII is
+/// replaced by `xn--` and its Punycode encoding, so extensions stay readable:
+///
+/// ```
+/// let name = sanitize_filename::to_ascii_name("Bücher?.pdf", &Default::default());
+/// assert_eq!(name, "xn--Bcher-kva.pdf");
+/// assert_eq!(sanitize_filename::from_ascii_name(&name).as_deref(), Some("Bücher.pdf"));
+/// ```
+///
+/// ASCII names are returned as sanitized, so an ASCII name that happens to have a part
+/// starting with `xn--` is not told apart from an encoded one. When the encoded name
+/// would exceed the length limit, characters are dropped from the end of the name before
+/// encoding, so the result can still be decoded.
+pub fn to_ascii_name<S: AsRef<str>>(name: S, options: &Options) -> String {
+    let mut name = sanitize_with_options(name, options.borrowed());
+    loop {
+        let encoded = encode_labels(&name);
+        match length_limit(options) {
+            Some(limit) if encoded.len() > limit => {
+                name.pop();
+            }
+            _ => return encoded,
+        }
+    }
+}
+
+/// Recovers the original name from the result of [`to_ascii_name`], or returns `None` if
+/// a part starting with `xn--` is not valid Punycode.
+pub fn from_ascii_name(name: &str) -> Option<String> {
+    let mut out = String::with_capacity(name.len());
+    for (idx, label) in name.split('.').enumerate() {
+        if idx > 0 {
+            out.push('.');
+        }
+        match label.get(..PREFIX.len()) {
+            Some(prefix) if prefix.eq_ignore_ascii_case(PREFIX) => {
+                out.push_str(&decode(&label[PREFIX.len()..])?)
+            }
+            _ => out.push_str(label),
+        }
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{from_ascii_name, to_ascii_name};
+
+    #[test]
+    fn it_round_trips_through_ascii() {
+        let options = crate::Options::default();
+
+        // Examples from RFC 3492 and IDNA
+        assert_eq!(to_ascii_name("bücher", &options), "xn--bcher-kva");
+        assert_eq!(to_ascii_name("münchen", &options), "xn--mnchen-3ya");
+        assert_eq!(
+            to_ascii_name("他们为什么不说中文", &options),
+            "xn--ihqwcrb4cv8a8dqg056pqjye"
+        );
+        assert_eq!(to_ascii_name("plain.txt", &options), "plain.txt");
+
+        for name in ["Résumé (final).tar.gz", ".забор", "a-ü-", "日本語", "x..y"] {
+            let encoded = to_ascii_name(name, &options);
+            assert!(encoded.is_ascii());
+            assert_eq!(from_ascii_name(&encoded).as_deref(), Some(name));
+        }
+        assert_eq!(from_ascii_name("xn--bcher-KVA").as_deref(), Some("bücher"));
+        assert_eq!(from_ascii_name("xn--99999999999"), None);
+
+        let long = "é".repeat(300);
+        let encoded = to_ascii_name(&long, &options);
+        assert!(encoded.len() <= 255);
+        assert!(long.starts_with(&from_ascii_name(&encoded).unwrap()));
+    }
+}
@@ -0,0 +1,133 @@
+use crate::risk::{risk_report, RiskFactor, RiskReport};
+use crate::{
+    is_sanitized_with_options, is_unsalvageable, length_limit, sanitize_with_options,
+    split_extension, truncate_in, Options, TruncationStrategy,
+};
+
+/// What [`classify`] makes of a name.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Classification {
+    /// The name is sanitized already.
+    Clean,
+    /// The name can be used once fixed, as given.
+    Fixable(String),
+    /// Nothing useful would come out of sanitizing the name.
+    Reject(RejectReason),
+}
+
+/// Why [`classify`] rejected a name.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum RejectReason {
+    /// Nothing but replacements would be left of the name (and there is no fallback).
+    Empty,
+    /// The extension alone does not fit in the length limit.
+    TooLong,
+    /// The name looks crafted to mislead: it has bidi controls, a fake extension or
+    /// confusable characters.
+    Disguised(RiskReport),
+}
+
+// The risk factors with no innocent explanation.
+const DISGUISES: [RiskFactor; 3] = [
+    RiskFactor::BidiControl,
+    RiskFactor::DoubleExtension,
+    RiskFactor::Confusable,
+];
+
+/// Sorts `name` into clean names, names that can be fixed, and names to reject, so an
+/// ingestion pipeline can send the hopeless ones to a quarantine instead of accepting
+/// whatever [`sanitize_with_options`] makes of them.
+///
+/// The fix differs from what `sanitize_with_options` returns in one way: a name that is
+/// too long has its stem shortened rather than its end cut off, so the extension is kept.
+///
+/// ```
+/// use sanitize_filename::{classify, Classification, RejectReason};
+///
+/// let options = sanitize_filename::Options::new();
+/// assert_eq!(classify("report.pdf", &options), Classification::Clean);
+/// assert_eq!(
+///     classify("report?.pdf", &options),
+///     Classification::Fixable("report.pdf".to_owned())
+/// );
+/// assert_eq!(classify("???", &options), Classification::Reject(RejectReason::Empty));
+/// ```
+pub fn classify<S: AsRef<str>>(name: S, options: &Options) -> Classification {
+    let name = name.as_ref();
+
+    let report = risk_report(name);
+    if DISGUISES.iter().any(|&factor| report.has(factor)) {
+        return Classification::Reject(RejectReason::Disguised(report));
+    }
+    if is_sanitized_with_options(name, options.into()) {
+        return Classification::Clean;
+    }
+
+    let fixed = sanitize_with_options(
+        name,
+        options.borrowed().with_truncation(TruncationStrategy::None),
+    );
+    if is_unsalvageable(name, &fixed, &options.replacement) {
+        return Classification::Reject(RejectReason::Empty);
+    }
+    match length_limit(options) {
+        Some(limit) if options.length_unit.measure(&fixed) > limit => {
+            let (stem, extension) = split_extension(&fixed, options);
+            let extension_len = options.length_unit.measure(extension);
+            if extension_len >= limit {
+                return Classification::Reject(RejectReason::TooLong);
+            }
+            let stem = truncate_in(stem, limit - extension_len, options.length_unit);
+            let fixed = format!("{}{}", stem, extension);
+            // Shortening may have exposed e.g. a trailing space before the extension.
+            Classification::Fixable(sanitize_with_options(fixed, options.borrowed()))
+        }
+        _ => Classification::Fixable(fixed),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{classify, Classification, RejectReason};
+
+    #[test]
+    fn it_classifies_names() {
+        let options = crate::Options::new().with_replacement("_");
+        let fixable = |name: &str| Classification::Fixable(name.to_owned());
+
+        assert_eq!(classify("notes.txt", &options), Classification::Clean);
+        assert_eq!(classify("a:b.txt", &options), fixable("a_b.txt"));
+        assert_eq!(
+            classify("..", &options),
+            Classification::Reject(RejectReason::Empty)
+        );
+        assert_eq!(
+            classify(
+                "..",
+                &options.clone().with_fallback(crate::Fallback::Base32)
+            ),
+            fixable("fyxa")
+        );
+        assert!(matches!(
+            classify("invoice.pdf.exe", &options),
+            Classification::Reject(RejectReason::Disguised(_))
+        ));
+
+        let long = format!("{}.tar.gz", "x".repeat(300));
+        assert_eq!(
+            classify(&long, &options),
+            fixable(&format!("{}.gz", "x".repeat(252)))
+        );
+        let options = options.with_max_length(4);
+        assert_eq!(
+            classify("a.json", &options),
+            Classification::Reject(RejectReason::TooLong)
+        );
+        assert_eq!(classify("abcdef.gz", &options), fixable("a.gz"));
+        assert_eq!(
+            classify("a.json", &options.clone().with_max_extension_len(Some(3))),
+            fixable("a.js")
+        );
+    }
+}
@@ -0,0 +1,193 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard};
+
+use crate::key::fold;
+use crate::{sanitize_unique, Options};
+
+/// Hands out unique sanitized names and remembers them, for long-running services such
+/// as upload handlers.
+///
+/// Names are compared the way case-insensitive, normalization-insensitive filesystems
+/// (NTFS, APFS) compare them, so `Résumé.pdf` and `re\u{301}sume\u{301}.PDF` collide,
+/// as do `Straße.txt` and `STRASSE.txt` (see [`canonical_key`](crate::canonical_key)).
+/// Requires the `unicode` feature. All methods take `&self` and are atomic, so a registry can be shared between threads
+/// (e.g. in an `Arc`) without two requests being issued the same name.
+///
+/// ```
+/// let registry = sanitize_filename::NameRegistry::new(Default::default());
+/// assert_eq!(registry.issue("report?.pdf").as_deref(), Some("report.pdf"));
+/// assert_eq!(registry.issue("REPORT.pdf").as_deref(), Some("REPORT (2).pdf"));
+/// ```
+#[derive(Debug)]
+pub struct NameRegistry<'a> {
+    options: Options<'a>,
+    // Folded name -> name as issued
+    names: Mutex<HashMap<String, String>>,
+}
+
+impl<'a> NameRegistry<'a> {
+    pub fn new(options: Options<'a>) -> Self {
+        NameRegistry {
+            options,
+            names: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Restores a registry from a previous [`snapshot`](NameRegistry::snapshot) (or any
+    /// list of names already in use).
+    pub fn from_snapshot<I, S>(options: Options<'a>, names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let registry = NameRegistry::new(options);
+        {
+            let mut issued = registry.lock();
+            for name in names {
+                let name = name.as_ref();
+                issued.insert(fold(name), name.to_owned());
+            }
+        }
+        registry
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, String>> {
+        // The map is never left half-updated, so a poisoned lock is still usable.
+        self.names
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Sanitizes `name`, makes it unique among the names issued so far and records it;
+    /// `None` if no variant of it is free, as in [`next_available_name`].
+    ///
+    /// [`next_available_name`]: crate::next_available_name
+    pub fn issue<S: AsRef<str>>(&self, name: S) -> Option<String> {
+        let mut issued = self.lock();
+        let unique = sanitize_unique(name, &self.options, |candidate| {
+            issued.contains_key(&fold(candidate))
+        })?;
+        issued.insert(fold(&unique), unique.clone());
+        Some(unique)
+    }
+
+    /// Records a name that is in use elsewhere, as is. Returns `false` if it collides with
+    /// a name already recorded.
+    pub fn reserve<S: AsRef<str>>(&self, name: S) -> bool {
+        let name = name.as_ref();
+        let mut issued = self.lock();
+        let key = fold(name);
+        if issued.contains_key(&key) {
+            return false;
+        }
+        issued.insert(key, name.to_owned());
+        true
+    }
+
+    /// Whether `name` collides with a recorded name.
+    pub fn contains<S: AsRef<str>>(&self, name: S) -> bool {
+        self.lock().contains_key(&fold(name.as_ref()))
+    }
+
+    /// Forgets the recorded name that `name` collides with, so it can be issued again.
+    /// Returns the name as it was recorded.
+    pub fn release<S: AsRef<str>>(&self, name: S) -> Option<String> {
+        self.lock().remove(&fold(name.as_ref()))
+    }
+
+    /// Forgets every recorded name for which `evict` returns true.
+    pub fn evict<F: FnMut(&str) -> bool>(&self, mut evict: F) {
+        self.lock().retain(|_, name| !evict(name));
+    }
+
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    /// The recorded names, sorted, e.g. to persist and later pass to
+    /// [`from_snapshot`](NameRegistry::from_snapshot).
+    pub fn snapshot(&self) -> Vec<String> {
+        let mut names = self.lock().values().cloned().collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::NameRegistry;
+
+    #[test]
+    fn it_issues_unique_names() {
+        let registry = NameRegistry::new(Default::default());
+
+        assert_eq!(registry.issue("Résumé.pdf").as_deref(), Some("Résumé.pdf"));
+        assert_eq!(
+            registry.issue("RÉSUMÉ.PDF").as_deref(),
+            Some("RÉSUMÉ (2).PDF")
+        );
+        assert_eq!(
+            registry.issue("résumé?.pdf").as_deref(),
+            Some("résumé (3).pdf")
+        );
+        assert!(registry.contains("RÉSUMÉ (2).pdf"));
+        assert!(!registry.reserve("résumé.PDF"));
+        assert!(registry.reserve("other"));
+
+        assert_eq!(
+            registry.release("RÉSUMÉ.PDF").as_deref(),
+            Some("Résumé.pdf")
+        );
+        assert_eq!(registry.issue("résumé.pdf").as_deref(), Some("résumé.pdf"));
+
+        registry.evict(|name| name.starts_with('o'));
+        let snapshot = registry.snapshot();
+        assert_eq!(snapshot.len(), 3);
+
+        let restored = NameRegistry::from_snapshot(Default::default(), &snapshot);
+        assert_eq!(
+            restored.issue("Résumé.pdf").as_deref(),
+            Some("Résumé (4).pdf")
+        );
+        assert_eq!(restored.issue("other").as_deref(), Some("other"));
+    }
+
+    #[test]
+    fn it_compares_normalized_names() {
+        let registry = NameRegistry::new(Default::default());
+
+        assert_eq!(registry.issue("Résumé.pdf").as_deref(), Some("Résumé.pdf"));
+        assert_eq!(
+            registry.issue("re\u{301}sume\u{301}.PDF").as_deref(),
+            Some("re\u{301}sume\u{301} (2).PDF")
+        );
+        assert!(registry.contains("RE\u{301}SUME\u{301}.pdf"));
+        assert_eq!(registry.issue("Straße.txt").as_deref(), Some("Straße.txt"));
+        assert!(registry.contains("STRASSE.txt"));
+    }
+
+    #[test]
+    fn it_is_shareable_between_threads() {
+        let registry = NameRegistry::new(Default::default());
+        let mut names = std::thread::scope(|scope| {
+            let handles = (0..8)
+                .map(|_| scope.spawn(|| registry.issue("upload.bin")))
+                .collect::<Vec<_>>();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap().unwrap())
+                .collect::<Vec<_>>()
+        });
+        names.sort();
+        names.dedup();
+        assert_eq!(names.len(), 8);
+    }
+}
@@ -0,0 +1,214 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+
+use crate::{is_control_char, is_illegal_char};
+
+type ReplaceFn = dyn Fn(char) -> Option<Cow<'static, str>> + Send + Sync;
+
+/// Picks the substitute of each character that sanitizing replaces, for
+/// [`Options::with_replacer`](crate::Options::with_replacer).
+///
+/// The function is called with each illegal or control character, each character outside
+/// of [`Charset::Portable`](crate::Charset::Portable) when that charset is selected, and
+/// each bidi control or shell metacharacter when those rules are on. Returning `None`
+/// falls back to [`Options::replacement`](crate::Options::replacement). Whole names that
+/// are replaced, such as reserved ones, always get the replacement.
+///
+/// Options holding the same `Replacer` (or clones of it) compare equal; two replacers
+/// built from identical closures do not.
+#[derive(Clone)]
+pub struct Replacer(Arc<ReplaceFn>);
+
+impl Replacer {
+    pub fn new<F>(replace: F) -> Self
+    where
+        F: Fn(char) -> Option<Cow<'static, str>> + Send + Sync + 'static,
+    {
+        Replacer(Arc::new(replace))
+    }
+
+    /// Substitutes ASCII punctuation, such as [`ILLEGAL_CHARS`](crate::ILLEGAL_CHARS),
+    /// with its fullwidth form (`:` with `：`, `?` with `？`, `/` with `／`), as yt-dlp and
+    /// browsers do, so that titles keep their punctuation. Other characters get the
+    /// replacement.
+    ///
+    /// The fullwidth forms are confusable with ASCII ones, so
+    /// [`Options::fold_confusables`](crate::Options::fold_confusables) rejects them.
+    ///
+    /// ```
+    /// let options = sanitize_filename::Options::new().with_fullwidth_replacer();
+    /// let name = sanitize_filename::sanitize_with_options("AC/DC: Live?", options);
+    /// assert_eq!(name, "AC／DC： Live？");
+    /// ```
+    pub fn fullwidth() -> Self {
+        Replacer::new(|c| {
+            if !c.is_ascii_punctuation() {
+                return None;
+            }
+            char::from_u32(c as u32 + 0xfee0).map(|c| Cow::Owned(c.to_string()))
+        })
+    }
+
+    /// Substitutes each character with the `%XX` escapes of its UTF-8 bytes (`:` with
+    /// `%3A`, `é` with `%C3%A9`), so that what a hostile name contained is kept rather
+    /// than dropped. `\xNN` escapes are not an option, as `\` is illegal itself.
+    ///
+    /// `%` is kept as it is, so names are not decoded unambiguously, and is rejected under
+    /// [`Charset::Portable`](crate::Charset::Portable) and
+    /// [`Options::strip_expansion_chars`](crate::Options::strip_expansion_chars), which then
+    /// replace the escapes again. [`encode`](crate::encode) escapes `%` too, for names that
+    /// must be decoded again.
+    ///
+    /// ```
+    /// let options = sanitize_filename::Options::new().with_hex_escape_replacer();
+    /// let name = sanitize_filename::sanitize_with_options("a:b\u{7}?.txt", options);
+    /// assert_eq!(name, "a%3Ab%07%3F.txt");
+    /// ```
+    pub fn hex_escape() -> Self {
+        Replacer::new(|c| {
+            let mut out = String::new();
+            for b in c.encode_utf8(&mut [0; 4]).bytes() {
+                out.push_str(&format!("%{:02X}", b));
+            }
+            Some(Cow::Owned(out))
+        })
+    }
+
+    // The substitute for `c`, without the illegal and control characters that the
+    // function may have returned.
+    pub(crate) fn substitute(&self, c: char) -> Option<Cow<'static, str>> {
+        let substitute = (self.0)(c)?;
+        if !substitute
+            .chars()
+            .any(|c| is_illegal_char(c) || is_control_char(c))
+        {
+            return Some(substitute);
+        }
+        Some(Cow::Owned(
+            substitute
+                .chars()
+                .filter(|&c| !is_illegal_char(c) && !is_control_char(c))
+                .collect(),
+        ))
+    }
+
+    fn addr(&self) -> *const () {
+        Arc::as_ptr(&self.0) as *const ()
+    }
+}
+
+impl fmt::Debug for Replacer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Replacer(..)")
+    }
+}
+
+impl PartialEq for Replacer {
+    fn eq(&self, other: &Replacer) -> bool {
+        self.addr() == other.addr()
+    }
+}
+
+impl Eq for Replacer {}
+
+impl Hash for Replacer {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.addr().hash(state);
+    }
+}
+
+// Pushes the substitute of `c` onto `out`.
+pub(crate) fn push_substitute(
+    out: &mut String,
+    c: char,
+    replacement: &str,
+    replacer: Option<&Replacer>,
+) {
+    match replacer.and_then(|replacer| replacer.substitute(c)) {
+        Some(substitute) => out.push_str(&substitute),
+        None => out.push_str(replacement),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use super::Replacer;
+
+    #[test]
+    fn it_replaces_per_character() {
+        let options = crate::Options::new()
+            .with_replacement("_")
+            .with_replacer(|c| match c {
+                ':' => Some(Cow::Borrowed("-")),
+                '?' => Some(Cow::Borrowed("")),
+                '*' => Some(Cow::Borrowed("/x")),
+                _ => None,
+            });
+        let sanitize = |name: &str| crate::sanitize_with_options(name, options.clone());
+
+        assert_eq!(sanitize("12:30 what?.txt"), "12-30 what.txt");
+        assert_eq!(sanitize("a\u{1}b|c"), "a_b_c");
+        assert_eq!(sanitize("a*b"), "axb");
+        #[cfg(feature = "unicode")]
+        assert_eq!(
+            crate::sanitize_with_options(
+                "né:e",
+                options
+                    .clone()
+                    .with_charset(crate::Charset::Portable)
+                    .with_replacer(|c| (c == ':').then_some(Cow::Borrowed("."))),
+            ),
+            "ne.e"
+        );
+
+        assert_eq!(
+            crate::sanitize_with_options(
+                "a<b>|\"c\"\\*\u{1}",
+                crate::Options::new()
+                    .with_replacement("_")
+                    .with_fullwidth_replacer()
+            ),
+            "a＜b＞｜＂c＂＼＊_"
+        );
+        assert_eq!(
+            crate::sanitize_with_options(
+                "a:b",
+                crate::Options::new()
+                    .with_fullwidth_replacer()
+                    .with_fold_confusables(true)
+            ),
+            "ab"
+        );
+
+        let replacer = Replacer::new(|_| None);
+        assert_eq!(replacer, replacer.clone());
+        assert_ne!(replacer, Replacer::new(|_| None));
+        assert_ne!(options, crate::Options::new().with_replacement("_"));
+    }
+
+    #[test]
+    fn it_hex_escapes() {
+        let options = crate::Options::new()
+            .with_windows(true)
+            .with_hex_escape_replacer();
+        let check = crate::OptionsForCheck::from(&options);
+        let sanitize = |name: &str, options: crate::Options| {
+            let sanitized = crate::sanitize_with_options(name, options);
+            assert!(crate::is_sanitized_with_options(&sanitized, check.clone()));
+            sanitized
+        };
+
+        assert_eq!(sanitize("a/b\\c", options.clone()), "a%2Fb%5Cc");
+        assert_eq!(sanitize("x\u{1b}[31m", options.clone()), "x%1B[31m");
+        assert_eq!(sanitize("100%.txt", options.clone()), "100%.txt");
+        assert_eq!(
+            sanitize("\u{202e}txt.exe", options.clone().with_strip_bidi(true)),
+            "%E2%80%AEtxt.exe"
+        );
+        assert_eq!(sanitize("con", options.clone().with_replacement("_")), "_");
+    }
+}
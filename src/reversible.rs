@@ -0,0 +1,176 @@
+use std::fmt;
+use std::fmt::Write;
+
+use crate::{analyze_with_options, OptionsForCheck, SanitizeError, Violation};
+
+/// Escapes `name` so that it passes the checks of `options`, in a way that [`decode`]
+/// undoes exactly, for sync tools and archivers that must get the original names back.
+///
+/// Each character that a check rejects is written as the `%XX` escapes of its UTF-8
+/// bytes, as are `%` itself and, where a rule is about more than one character, the
+/// characters it covers: the trailing dots and spaces that Windows strips, the stem of a
+/// reserved name, the dots of a name of only dots or a leading dot.
+///
+/// ```
+/// use sanitize_filename::{decode, encode, OptionsForCheck};
+///
+/// let options = OptionsForCheck::new().with_windows(true);
+/// let name = encode("Q3: 100% done.", options).unwrap();
+/// assert_eq!(name, "Q3%3A 100%25 done%2E");
+/// assert_eq!(decode(&name).unwrap(), "Q3: 100% done.");
+/// ```
+///
+/// Escaping only makes names longer, so names that are too long or too short fail with
+/// that violation, as do names under options that reject the escapes themselves, such as
+/// [`Charset::Portable`](crate::Charset::Portable) and `strip_expansion_chars`.
+pub fn encode<S: AsRef<str>>(name: S, options: OptionsForCheck) -> Result<String, SanitizeError> {
+    // Each character, and whether it is escaped
+    let mut chars: Vec<(char, bool)> = name.as_ref().chars().map(|c| (c, c == '%')).collect();
+    loop {
+        let mut encoded = String::new();
+        let mut offsets = Vec::with_capacity(chars.len());
+        for &(c, escaped) in &chars {
+            offsets.push(encoded.len());
+            if escaped {
+                let mut buffer = [0; 4];
+                for b in c.encode_utf8(&mut buffer).bytes() {
+                    let _ = write!(encoded, "%{:02X}", b);
+                }
+            } else {
+                encoded.push(c);
+            }
+        }
+
+        let issues = analyze_with_options(&encoded, options.clone());
+        let first = match issues.first() {
+            Some(issue) => issue.violation,
+            None => return Ok(encoded),
+        };
+        if let Violation::TooLong { .. } | Violation::TooShort { .. } = first {
+            return Err(SanitizeError(first));
+        }
+        let mut progress = false;
+        for issue in &issues {
+            for (idx, &start) in offsets.iter().enumerate() {
+                let (c, escaped) = &mut chars[idx];
+                if !*escaped && start < issue.range.end && issue.range.start < start + c.len_utf8()
+                {
+                    *escaped = true;
+                    progress = true;
+                }
+            }
+        }
+        if !progress {
+            return Err(SanitizeError(first));
+        }
+    }
+}
+
+/// The error returned by [`decode`] for a name that [`encode`] cannot have written.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct DecodeError {
+    /// The byte offset of the `%` of the escape that is invalid.
+    pub offset: usize,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid escape at byte {}", self.offset)
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+/// Recovers the name that [`encode`] escaped. Escapes must be `%` followed by two hex
+/// digits, and those in a row must spell out UTF-8.
+pub fn decode<S: AsRef<str>>(name: S) -> Result<String, DecodeError> {
+    let name = name.as_ref();
+    let mut decoded = Vec::with_capacity(name.len());
+    let bytes = name.as_bytes();
+    let mut idx = 0;
+    while idx < bytes.len() {
+        if bytes[idx] != b'%' {
+            decoded.push(bytes[idx]);
+            idx += 1;
+            continue;
+        }
+        let byte = name
+            .get(idx + 1..idx + 3)
+            .filter(|hex| hex.bytes().all(|b| b.is_ascii_hexdigit()))
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            .ok_or(DecodeError { offset: idx })?;
+        decoded.push(byte);
+        idx += 3;
+    }
+    String::from_utf8(decoded).map_err(|err| DecodeError {
+        offset: offset_of(name, err.utf8_error().valid_up_to()),
+    })
+}
+
+// The offset in `name` of the escape that decoded to byte `decoded` of the output.
+fn offset_of(name: &str, decoded: usize) -> usize {
+    let mut produced = 0;
+    let mut idx = 0;
+    let bytes = name.as_bytes();
+    while idx < bytes.len() && produced < decoded {
+        idx += if bytes[idx] == b'%' { 3 } else { 1 };
+        produced += 1;
+    }
+    idx
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode, encode, DecodeError};
+    use crate::{OptionsForCheck, Violation};
+
+    #[test]
+    fn it_round_trips() {
+        let windows = OptionsForCheck::new().with_windows(true);
+        let strict = windows
+            .clone()
+            .with_strip_bidi(true)
+            .with_leading_dot(crate::LeadingDotPolicy::Strip)
+            .with_strip_shell_metachars(true)
+            .with_defuse_double_extensions(true);
+
+        for name in [
+            "plain.txt",
+            "a/b\\c:d",
+            "100%",
+            "%41",
+            "..",
+            "con.txt",
+            "notes. .",
+            "x\u{1}\u{85}y",
+            "日本語?",
+            ".hidden",
+            "-rf $HOME",
+            "invoice.pdf.exe",
+            "\u{202e}txt",
+        ] {
+            for options in [windows.clone(), strict.clone()] {
+                let encoded = encode(name, options.clone()).unwrap();
+                assert!(crate::is_sanitized_with_options(&encoded, options));
+                assert_eq!(decode(&encoded).unwrap(), name, "{:?}", encoded);
+            }
+        }
+        assert_eq!(encode("..", windows.clone()).unwrap(), "%2E%2E");
+        assert_eq!(encode("con.txt", windows.clone()).unwrap(), "%63%6F%6E.txt");
+        assert_eq!(encode("é?", windows.clone()).unwrap(), "é%3F");
+
+        assert_eq!(
+            encode("a", windows.clone().with_min_length(2)),
+            Err(crate::SanitizeError(Violation::TooShort {
+                len: 1,
+                unit: crate::LengthUnit::Bytes
+            }))
+        );
+        let paranoid = OptionsForCheck::new().with_security(crate::Security::Paranoid);
+        assert!(encode("a:b", paranoid).is_err());
+
+        assert_eq!(decode("a%2"), Err(DecodeError { offset: 1 }));
+        assert_eq!(decode("a%zz"), Err(DecodeError { offset: 1 }));
+        assert_eq!(decode("ab%C3"), Err(DecodeError { offset: 2 }));
+    }
+}
@@ -0,0 +1,263 @@
+/// A suspicious trait found in a file name by [`risk_report`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum RiskFactor {
+    /// Bidirectional control characters such as RTLO (`U+202E`), which can make
+    /// `photo\u{202e}gpj.exe` display as `photoexe.jpg`.
+    BidiControl,
+    /// An executable extension hiding behind a harmless-looking one (`invoice.pdf.exe`).
+    DoubleExtension,
+    /// Runs of dots or an unusually high number of them (`a..b`, `x.y.z.w.v`).
+    ExcessiveDots,
+    /// A leading dot, which hides the file on Unix-like systems.
+    HiddenFile,
+    /// A dot-only name or a Windows reserved device name such as `con` or `lpt1.txt`.
+    ReservedName,
+    /// Longer than 255 bytes.
+    Overlong,
+    /// Characters that look like ASCII letters or punctuation but are not (Cyrillic `а`,
+    /// fullwidth `．`, ...).
+    Confusable,
+}
+
+impl RiskFactor {
+    /// A stable snake_case identifier, e.g. for reports.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RiskFactor::BidiControl => "bidi_control",
+            RiskFactor::DoubleExtension => "double_extension",
+            RiskFactor::ExcessiveDots => "excessive_dots",
+            RiskFactor::HiddenFile => "hidden_file",
+            RiskFactor::ReservedName => "reserved_name",
+            RiskFactor::Overlong => "overlong",
+            RiskFactor::Confusable => "confusable",
+        }
+    }
+
+    /// The contribution of this factor to [`RiskReport::score`].
+    pub fn weight(self) -> u32 {
+        match self {
+            RiskFactor::BidiControl => 50,
+            RiskFactor::DoubleExtension => 40,
+            RiskFactor::ReservedName => 30,
+            RiskFactor::Confusable => 25,
+            RiskFactor::Overlong => 20,
+            RiskFactor::HiddenFile => 10,
+            RiskFactor::ExcessiveDots => 10,
+        }
+    }
+}
+
+/// Coarse classification of a [`RiskReport::score`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum RiskLevel {
+    None,
+    Low,
+    Medium,
+    High,
+}
+
+impl RiskLevel {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RiskLevel::None => "none",
+            RiskLevel::Low => "low",
+            RiskLevel::Medium => "medium",
+            RiskLevel::High => "high",
+        }
+    }
+}
+
+/// The result of [`risk_report`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RiskReport {
+    /// Sum of the weights of all `factors`.
+    pub score: u32,
+    /// Every factor found, in declaration order of [`RiskFactor`], without duplicates.
+    pub factors: Vec<RiskFactor>,
+}
+
+impl RiskReport {
+    pub fn level(&self) -> RiskLevel {
+        match self.score {
+            0 => RiskLevel::None,
+            1..=24 => RiskLevel::Low,
+            25..=49 => RiskLevel::Medium,
+            _ => RiskLevel::High,
+        }
+    }
+
+    pub fn has(&self, factor: RiskFactor) -> bool {
+        self.factors.contains(&factor)
+    }
+}
+
+static EXECUTABLE_EXTENSIONS: &[&str] = &[
+    "app", "bat", "cmd", "com", "cpl", "dll", "exe", "hta", "jar", "js", "jse", "lnk", "msi",
+    "pif", "ps1", "scr", "sh", "vbe", "vbs", "wsf",
+];
+
+pub(crate) fn is_bidi_control(c: char) -> bool {
+    matches!(
+        c,
+        '\u{061c}' | '\u{200e}' | '\u{200f}' | '\u{202a}'..='\u{202e}' | '\u{2066}'..='\u{2069}'
+    )
+}
+
+pub(crate) fn is_confusable(c: char) -> bool {
+    confusable_ascii(c).is_some()
+}
+
+// The ASCII character that `c` is confusable with, if any.
+pub(crate) fn confusable_ascii(c: char) -> Option<char> {
+    let ascii = match c {
+        // Cyrillic letters that render like Latin ones
+        'а' => 'a',
+        'е' => 'e',
+        'о' => 'o',
+        'р' => 'p',
+        'с' => 'c',
+        'у' => 'y',
+        'х' => 'x',
+        'і' => 'i',
+        'ј' => 'j',
+        'ѕ' => 's',
+        'ԁ' => 'd',
+        'ԛ' => 'q',
+        'ԝ' => 'w',
+        'А' => 'A',
+        'В' => 'B',
+        'Е' => 'E',
+        'К' => 'K',
+        'М' => 'M',
+        'Н' => 'H',
+        'О' => 'O',
+        'Р' => 'P',
+        'С' => 'C',
+        'Т' => 'T',
+        'Х' => 'X',
+        'І' => 'I',
+        'Ј' => 'J',
+        'Ѕ' => 'S',
+        // Greek
+        'α' => 'a',
+        'ο' => 'o',
+        'ν' => 'v',
+        'ρ' => 'p',
+        'Α' => 'A',
+        'Β' => 'B',
+        'Ε' => 'E',
+        'Ζ' => 'Z',
+        'Η' => 'H',
+        'Ι' => 'I',
+        'Κ' => 'K',
+        'Μ' => 'M',
+        'Ν' => 'N',
+        'Ο' => 'O',
+        'Ρ' => 'P',
+        'Τ' => 'T',
+        'Υ' => 'Y',
+        'Χ' => 'X',
+        // One dot leaders, fraction and division slashes
+        '\u{2024}' => '.',
+        '\u{2044}' | '\u{2215}' => '/',
+        // Fullwidth ASCII
+        '\u{ff01}'..='\u{ff5e}' => char::from_u32(c as u32 - 0xfee0)?,
+        _ => return None,
+    };
+    Some(ascii)
+}
+
+pub(crate) fn has_double_extension(name: &str) -> bool {
+    double_extension_dot(name).is_some()
+}
+
+// The dot before the harmless-looking extension of a double extension, i.e. the first dot
+// of `invoice.pdf.exe`.
+pub(crate) fn double_extension_dot(name: &str) -> Option<usize> {
+    let last = name.rfind('.')?;
+    // The stem itself must be present, otherwise this is just a hidden file.
+    let dot = name[..last].rfind('.')?;
+    let previous = name[dot + 1..last].trim_end();
+    let is_double = EXECUTABLE_EXTENSIONS
+        .iter()
+        .any(|ext| ext.eq_ignore_ascii_case(&name[last + 1..]))
+        && (1..=4).contains(&previous.len())
+        && previous.chars().all(|c| c.is_ascii_alphanumeric());
+    if is_double {
+        Some(dot)
+    } else {
+        None
+    }
+}
+
+fn has_excessive_dots(name: &str) -> bool {
+    name.contains("..") || name.matches('.').count() > 3
+}
+
+/// Scores `name` for traits commonly abused to disguise malicious files.
+///
+/// The name is inspected as given; it is not sanitized first.
+pub fn risk_report<S: AsRef<str>>(name: S) -> RiskReport {
+    let name = name.as_ref();
+    let mut factors = Vec::new();
+
+    if name.chars().any(is_bidi_control) {
+        factors.push(RiskFactor::BidiControl);
+    }
+    if has_double_extension(name) {
+        factors.push(RiskFactor::DoubleExtension);
+    }
+    if has_excessive_dots(name) {
+        factors.push(RiskFactor::ExcessiveDots);
+    }
+    if name.starts_with('.') && !crate::is_reserved(name) {
+        factors.push(RiskFactor::HiddenFile);
+    }
+    if crate::is_reserved(name) || crate::is_windows_reserved(name, crate::RuleSet::Latest) {
+        factors.push(RiskFactor::ReservedName);
+    }
+    if name.len() > 255 {
+        factors.push(RiskFactor::Overlong);
+    }
+    if name.chars().any(is_confusable) {
+        factors.push(RiskFactor::Confusable);
+    }
+
+    RiskReport {
+        score: factors.iter().map(|f| f.weight()).sum(),
+        factors,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{risk_report, RiskFactor, RiskLevel};
+
+    #[test]
+    fn it_scores_names() {
+        let clean = risk_report("holiday photo.jpg");
+        assert!(clean.factors.is_empty());
+        assert_eq!(clean.level(), RiskLevel::None);
+
+        let rtlo = risk_report("photo\u{202e}gpj.exe");
+        assert_eq!(rtlo.factors, vec![RiskFactor::BidiControl]);
+        assert_eq!(rtlo.level(), RiskLevel::High);
+
+        assert!(risk_report("invoice.pdf.exe").has(RiskFactor::DoubleExtension));
+        assert!(risk_report("invoice.pdf   .EXE").has(RiskFactor::DoubleExtension));
+        assert!(!risk_report("archive.tar.gz").has(RiskFactor::DoubleExtension));
+        assert!(!risk_report(".exe").has(RiskFactor::DoubleExtension));
+
+        assert!(risk_report("a..b").has(RiskFactor::ExcessiveDots));
+        assert!(risk_report(".bashrc").has(RiskFactor::HiddenFile));
+        assert_eq!(
+            risk_report("..").factors,
+            vec![RiskFactor::ExcessiveDots, RiskFactor::ReservedName]
+        );
+        assert!(risk_report("LPT1.txt").has(RiskFactor::ReservedName));
+        assert!(risk_report("a".repeat(256)).has(RiskFactor::Overlong));
+        assert!(risk_report("pаypal.html").has(RiskFactor::Confusable));
+    }
+}
@@ -0,0 +1,392 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::lookup::CharSet;
+use crate::{
+    is_control_char, is_illegal_char, is_listed_reserved, is_reserved, is_windows_reserved,
+    replace_chars, truncate_in, LengthUnit, RuleSet, Sanitizer, WINDOWS_TRAILING_CHARS,
+};
+
+/// One step of sanitization: a rewrite of a name, and the check that its output passes.
+///
+/// The built-in steps are rules too — [`IllegalChars`], [`ReservedNames`],
+/// [`WindowsReserved`], [`WindowsTrailing`] and [`Truncate`] — and a [`Pipeline`] runs
+/// any mix of them and custom rules in the order they were added. A [`Sanitizer`] is a
+/// rule that runs the whole pipeline of its options, so custom rules can also go before
+/// or after it.
+///
+/// ```
+/// use std::borrow::Cow;
+/// use sanitize_filename::{Pipeline, Rule, Truncate};
+///
+/// struct Lowercase;
+///
+/// impl Rule for Lowercase {
+///     fn apply<'n>(&self, name: Cow<'n, str>) -> Cow<'n, str> {
+///         if self.check(&name) {
+///             name
+///         } else {
+///             Cow::Owned(name.to_lowercase())
+///         }
+///     }
+///
+///     fn check(&self, name: &str) -> bool {
+///         !name.chars().any(char::is_uppercase)
+///     }
+/// }
+///
+/// let pipeline = Pipeline::new().then(Lowercase).then(Truncate::new(8));
+/// assert_eq!(pipeline.sanitize("QUARTERLY.pdf"), "quarterl");
+/// assert!(!pipeline.is_sanitized("Q1.pdf"));
+/// ```
+pub trait Rule: Send + Sync {
+    /// Rewrites `name` so that it passes [`check`](Rule::check), borrowing it when it
+    /// already does.
+    fn apply<'n>(&self, name: Cow<'n, str>) -> Cow<'n, str>;
+
+    /// Whether `name` is left alone by this rule.
+    fn check(&self, name: &str) -> bool;
+}
+
+/// Replaces [`ILLEGAL_CHARS`](crate::ILLEGAL_CHARS) and control characters.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct IllegalChars<'a> {
+    replacement: Cow<'a, str>,
+}
+
+impl<'a> IllegalChars<'a> {
+    pub fn new<S: Into<Cow<'a, str>>>(replacement: S) -> Self {
+        IllegalChars {
+            replacement: replacement.into(),
+        }
+    }
+}
+
+impl Rule for IllegalChars<'_> {
+    fn apply<'n>(&self, name: Cow<'n, str>) -> Cow<'n, str> {
+        match name {
+            Cow::Borrowed(name) => replace_chars(
+                name,
+                &self.replacement,
+                &self.replacement,
+                None,
+                &CharSet::default(),
+            ),
+            Cow::Owned(name) => {
+                match replace_chars(
+                    &name,
+                    &self.replacement,
+                    &self.replacement,
+                    None,
+                    &CharSet::default(),
+                ) {
+                    Cow::Owned(replaced) => Cow::Owned(replaced),
+                    Cow::Borrowed(_) => Cow::Owned(name),
+                }
+            }
+        }
+    }
+
+    fn check(&self, name: &str) -> bool {
+        !name
+            .chars()
+            .any(|c| is_illegal_char(c) || is_control_char(c))
+    }
+}
+
+/// Replaces names of only dots, such as `.` and `..`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ReservedNames<'a> {
+    replacement: Cow<'a, str>,
+}
+
+impl<'a> ReservedNames<'a> {
+    pub fn new<S: Into<Cow<'a, str>>>(replacement: S) -> Self {
+        ReservedNames {
+            replacement: replacement.into(),
+        }
+    }
+}
+
+impl Rule for ReservedNames<'_> {
+    fn apply<'n>(&self, name: Cow<'n, str>) -> Cow<'n, str> {
+        if is_reserved(&name) {
+            Cow::Owned(self.replacement.clone().into_owned())
+        } else {
+            name
+        }
+    }
+
+    fn check(&self, name: &str) -> bool {
+        !is_reserved(name)
+    }
+}
+
+/// Replaces names that Windows reserves, such as `CON` and `nul.txt`, along with any
+/// listed in [`with_names`](WindowsReserved::with_names).
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct WindowsReserved<'a> {
+    pub(crate) replacement: Cow<'a, str>,
+    pub(crate) names: Cow<'a, [Cow<'a, str>]>,
+    pub(crate) rule_set: RuleSet,
+}
+
+impl<'a> WindowsReserved<'a> {
+    pub fn new<S: Into<Cow<'a, str>>>(replacement: S) -> Self {
+        WindowsReserved {
+            replacement: replacement.into(),
+            names: Cow::Borrowed(&[]),
+            rule_set: RuleSet::Latest,
+        }
+    }
+
+    /// Also replaces these names, as [`Options::reserved_names`](crate::Options) does.
+    pub fn with_names<I, S>(mut self, names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<Cow<'a, str>>,
+    {
+        self.names = names.into_iter().map(Into::into).collect();
+        self
+    }
+}
+
+impl Rule for WindowsReserved<'_> {
+    fn apply<'n>(&self, name: Cow<'n, str>) -> Cow<'n, str> {
+        if self.check(&name) {
+            name
+        } else {
+            Cow::Owned(self.replacement.clone().into_owned())
+        }
+    }
+
+    fn check(&self, name: &str) -> bool {
+        !is_windows_reserved(name, self.rule_set) && !is_listed_reserved(name, &self.names)
+    }
+}
+
+/// Replaces the dots and spaces that Windows strips from the end of names.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct WindowsTrailing<'a> {
+    pub(crate) replacement: Cow<'a, str>,
+}
+
+impl<'a> WindowsTrailing<'a> {
+    pub fn new<S: Into<Cow<'a, str>>>(replacement: S) -> Self {
+        WindowsTrailing {
+            replacement: replacement.into(),
+        }
+    }
+}
+
+impl Rule for WindowsTrailing<'_> {
+    fn apply<'n>(&self, name: Cow<'n, str>) -> Cow<'n, str> {
+        let trimmed = name.trim_end_matches(WINDOWS_TRAILING_CHARS).len();
+        if trimmed == name.len() {
+            return name;
+        }
+        let mut out = String::with_capacity(trimmed + self.replacement.len());
+        out.push_str(&name[..trimmed]);
+        out.push_str(&self.replacement);
+        Cow::Owned(out)
+    }
+
+    fn check(&self, name: &str) -> bool {
+        !name.ends_with(WINDOWS_TRAILING_CHARS)
+    }
+}
+
+/// Cuts names down to a maximum length, on a character boundary.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Truncate {
+    max_length: usize,
+    length_unit: LengthUnit,
+}
+
+impl Truncate {
+    /// Cuts names to `max_length` bytes.
+    pub fn new(max_length: usize) -> Self {
+        Truncate {
+            max_length,
+            length_unit: LengthUnit::Bytes,
+        }
+    }
+
+    pub fn with_length_unit(mut self, length_unit: LengthUnit) -> Self {
+        self.length_unit = length_unit;
+        self
+    }
+}
+
+impl Rule for Truncate {
+    fn apply<'n>(&self, name: Cow<'n, str>) -> Cow<'n, str> {
+        match name {
+            Cow::Borrowed(name) => {
+                Cow::Borrowed(truncate_in(name, self.max_length, self.length_unit))
+            }
+            Cow::Owned(mut name) => {
+                let end = truncate_in(&name, self.max_length, self.length_unit).len();
+                name.truncate(end);
+                Cow::Owned(name)
+            }
+        }
+    }
+
+    fn check(&self, name: &str) -> bool {
+        self.length_unit.measure(name) <= self.max_length
+    }
+}
+
+impl Rule for Sanitizer<'_> {
+    fn apply<'n>(&self, name: Cow<'n, str>) -> Cow<'n, str> {
+        Cow::Owned(self.sanitize(name))
+    }
+
+    fn check(&self, name: &str) -> bool {
+        self.is_sanitized(name)
+    }
+}
+
+/// Rules run one after another, in the order they were added.
+///
+/// A name is sanitized once every rule passes it, so a rule that undoes the work of an
+/// earlier one, e.g. by appending a reserved extension, is caught by
+/// [`is_sanitized`](Pipeline::is_sanitized). Cloning a pipeline shares its rules.
+///
+/// ```
+/// use sanitize_filename::{IllegalChars, Pipeline, Truncate, WindowsReserved, WindowsTrailing};
+///
+/// let pipeline = Pipeline::new()
+///     .then(IllegalChars::new("_"))
+///     .then(Truncate::new(12))
+///     .then(WindowsTrailing::new(""))
+///     .then(WindowsReserved::new("file").with_names(["desktop.ini"]));
+/// assert_eq!(pipeline.sanitize("a:b.txt"), "a_b.txt");
+/// assert_eq!(pipeline.sanitize("notes for now.txt"), "notes for no");
+/// assert_eq!(pipeline.sanitize("aux. "), "file");
+/// assert!(pipeline.is_sanitized("a_b.txt"));
+/// ```
+#[derive(Clone, Default)]
+pub struct Pipeline<'a> {
+    rules: Vec<Arc<dyn Rule + 'a>>,
+}
+
+impl<'a> Pipeline<'a> {
+    /// A pipeline without rules, which leaves every name alone.
+    pub fn new() -> Self {
+        Pipeline::default()
+    }
+
+    /// Adds `rule` to run after the rules already added.
+    pub fn then<R: Rule + 'a>(mut self, rule: R) -> Self {
+        self.rules.push(Arc::new(rule));
+        self
+    }
+
+    /// Adds `rule` to run before the rule at `index`, or last if `index` is the number of
+    /// rules.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is greater than the number of rules.
+    pub fn insert<R: Rule + 'a>(&mut self, index: usize, rule: R) {
+        self.rules.insert(index, Arc::new(rule));
+    }
+
+    /// The number of rules.
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    pub fn sanitize<S: AsRef<str>>(&self, name: S) -> String {
+        self.apply(Cow::Borrowed(name.as_ref())).into_owned()
+    }
+
+    pub fn is_sanitized<S: AsRef<str>>(&self, name: S) -> bool {
+        self.check(name.as_ref())
+    }
+}
+
+impl Rule for Pipeline<'_> {
+    fn apply<'n>(&self, name: Cow<'n, str>) -> Cow<'n, str> {
+        self.rules.iter().fold(name, |name, rule| rule.apply(name))
+    }
+
+    fn check(&self, name: &str) -> bool {
+        self.rules.iter().all(|rule| rule.check(name))
+    }
+}
+
+impl fmt::Debug for Pipeline<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Pipeline")
+            .field("rules", &self.rules.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use super::{
+        IllegalChars, Pipeline, ReservedNames, Rule, Truncate, WindowsReserved, WindowsTrailing,
+    };
+
+    struct Suffix(&'static str);
+
+    impl Rule for Suffix {
+        fn apply<'n>(&self, name: Cow<'n, str>) -> Cow<'n, str> {
+            if self.check(&name) {
+                name
+            } else {
+                Cow::Owned(format!("{}{}", name, self.0))
+            }
+        }
+
+        fn check(&self, name: &str) -> bool {
+            name.ends_with(self.0)
+        }
+    }
+
+    #[test]
+    fn it_runs_rules_in_order() {
+        assert!(matches!(
+            IllegalChars::new("_").apply(Cow::Borrowed("ok.txt")),
+            Cow::Borrowed("ok.txt")
+        ));
+        assert_eq!(IllegalChars::new("").apply("a\u{1}/b".into()), "ab");
+        assert_eq!(ReservedNames::new("dots").apply("..".into()), "dots");
+        assert!(ReservedNames::new("").check(".git"));
+        assert_eq!(WindowsReserved::new("x").apply("com1.log".into()), "x");
+        assert_eq!(WindowsTrailing::new("_").apply("a. .".into()), "a_");
+        assert_eq!(
+            Truncate::new(2)
+                .with_length_unit(crate::LengthUnit::Chars)
+                .apply("日本語".into()),
+            "日本"
+        );
+
+        // The suffix comes after truncation, so it survives it; the other way round the
+        // pipeline cannot converge and its output fails the check.
+        let suffix_last = Pipeline::new().then(Truncate::new(4)).then(Suffix(".md"));
+        assert_eq!(suffix_last.sanitize("readme"), "read.md");
+        let mut suffix_first = Pipeline::new().then(Truncate::new(4));
+        suffix_first.insert(0, Suffix(".md"));
+        assert_eq!(suffix_first.len(), 2);
+        assert_eq!(suffix_first.sanitize("readme"), "read");
+        assert!(!suffix_first.is_sanitized("read"));
+
+        let sanitizer = crate::Sanitizer::new(crate::Options::new().with_windows(true));
+        let pipeline = Pipeline::new().then(sanitizer).then(Suffix(".txt"));
+        assert_eq!(pipeline.sanitize("a:b"), "ab.txt");
+        assert!(pipeline.is_sanitized("ab.txt"));
+        assert!(!pipeline.is_sanitized("a:b.txt"));
+        assert!(Pipeline::new().is_empty());
+    }
+}
@@ -0,0 +1,85 @@
+//! The predicates behind the built-in rules, for policy engines of their own.
+//!
+//! These are the exact tests that sanitizing and checking use. Their signatures are
+//! stable, and so is what they match under [`RuleSet::V1`]; under [`RuleSet::Latest`] a
+//! minor release may match more, as the sanitization rules do, but never less.
+//!
+//! ```
+//! use sanitize_filename::rules::{has_windows_trailing, is_illegal_char, is_windows_reserved};
+//! use sanitize_filename::RuleSet;
+//!
+//! assert!("a:b".chars().any(is_illegal_char));
+//! assert!(is_windows_reserved("CON.txt", RuleSet::Latest));
+//! assert!(has_windows_trailing("notes. "));
+//! ```
+
+use crate::{RuleSet, V1_WINDOWS_RESERVED};
+
+pub use crate::{CONTROL_CHARS, ILLEGAL_CHARS, WINDOWS_RESERVED, WINDOWS_TRAILING_CHARS};
+
+/// Whether `c` is one of [`ILLEGAL_CHARS`].
+pub fn is_illegal_char(c: char) -> bool {
+    ILLEGAL_CHARS.contains(&c)
+}
+
+/// Whether `c` is in one of the [`CONTROL_CHARS`] ranges.
+pub fn is_control_char(c: char) -> bool {
+    CONTROL_CHARS.iter().any(|range| range.contains(&c))
+}
+
+/// Whether `name` is one of the [`WINDOWS_RESERVED`] device names known to `rule_set`,
+/// on its own or before the first dot, ignoring ASCII case.
+pub fn is_windows_reserved(name: &str, rule_set: RuleSet) -> bool {
+    let (stem, rest) = match name.find('.') {
+        Some(idx) => name.split_at(idx),
+        None => (name, ""),
+    };
+    let names = match rule_set {
+        RuleSet::V1 => &WINDOWS_RESERVED[..V1_WINDOWS_RESERVED],
+        RuleSet::Latest => WINDOWS_RESERVED,
+    };
+    // The rest of the name cannot span a line break, which only a replacement can
+    // introduce; `RuleSet::V1` has always behaved this way.
+    names
+        .iter()
+        .any(|reserved| reserved.eq_ignore_ascii_case(stem))
+        && !rest.contains('\n')
+}
+
+/// Whether `name` ends with one of the [`WINDOWS_TRAILING_CHARS`], which Windows strips.
+pub fn has_windows_trailing(name: &str) -> bool {
+    name.ends_with(WINDOWS_TRAILING_CHARS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{has_windows_trailing, is_control_char, is_illegal_char, is_windows_reserved};
+    use crate::{violation_flags, OptionsForCheck, RuleSet, ViolationFlags};
+
+    #[test]
+    fn it_agrees_with_the_checks() {
+        let options = OptionsForCheck::new().with_windows(true);
+        for name in [
+            "a:b", "a\u{7f}", "\u{85}", "nul.txt", "COM¹", "a. ", "ok.txt", "lpt",
+        ] {
+            let flags = violation_flags(name, options.clone());
+            assert_eq!(
+                name.chars().any(is_illegal_char),
+                flags.contains(ViolationFlags::ILLEGAL_CHAR)
+            );
+            assert_eq!(
+                name.chars().any(is_control_char),
+                flags.contains(ViolationFlags::CONTROL_CHAR)
+            );
+            assert_eq!(
+                is_windows_reserved(name, RuleSet::Latest),
+                flags.contains(ViolationFlags::WINDOWS_RESERVED)
+            );
+            assert_eq!(
+                has_windows_trailing(name),
+                flags.contains(ViolationFlags::WINDOWS_TRAILING)
+            );
+        }
+        assert!(!is_windows_reserved("COM¹", RuleSet::V1));
+    }
+}
@@ -0,0 +1,407 @@
+use std::borrow::Cow;
+use std::fmt;
+
+use crate::lookup::Lookup;
+use crate::violation::first_violation;
+use crate::{
+    check_name, replace_each_char, sanitize_checked, Options, OptionsForCheck, SanitizeError,
+    Violation,
+};
+
+// How many times `Options::fixed_point` sanitizes a name again before giving up on it.
+const MAX_PASSES: usize = 8;
+
+/// Sanitizes and checks many names with the same options, for hot loops.
+///
+/// [`sanitize_with_options`](crate::sanitize_with_options) derives the options that its
+/// output is checked against, and indexes `extra_illegal`, `allow`, `allowlist` and
+/// `reserved_names` for lookup, on every call; a `Sanitizer` does both once, when it is
+/// built. Deny patterns and substrings are compiled when they are added to the options,
+/// and cloning a `Sanitizer` shares them. A `Sanitizer` is `Send` and `Sync`, so one can
+/// serve several threads.
+///
+/// ```
+/// let sanitizer = sanitize_filename::Sanitizer::new(
+///     sanitize_filename::Options::new().with_replacement("_").with_windows(true),
+/// );
+/// assert_eq!(sanitizer.sanitize("con.txt"), "_");
+/// assert_eq!(sanitizer.sanitize("a:b"), "a_b");
+/// assert!(sanitizer.is_sanitized("a_b"));
+/// assert!(!sanitizer.is_sanitized("a:b"));
+/// ```
+#[derive(Clone, Debug)]
+pub struct Sanitizer<'a> {
+    options: Options<'a>,
+    check: OptionsForCheck,
+    lookup: Lookup,
+}
+
+impl<'a> Sanitizer<'a> {
+    pub fn new(options: Options<'a>) -> Self {
+        let check = OptionsForCheck::from(&options);
+        let lookup = Lookup::from(&options);
+        Sanitizer {
+            options,
+            check,
+            lookup,
+        }
+    }
+
+    /// Builds a `Sanitizer` as [`new`](Sanitizer::new) does, but rejects options that
+    /// would make its output empty or not what they say, with the first rule broken:
+    ///
+    /// - an [`Options::empty_fallback`] of which nothing would be left itself, such as `.`
+    ///   or `??`; with such a fallback, names of which nothing is left still sanitize to
+    ///   `""`.
+    /// - an [`Options::prefix`] or [`Options::suffix`] with characters that are replaced,
+    ///   such as the `/` of `../`, or that together leave no room within `max_length`.
+    ///
+    /// ```
+    /// use sanitize_filename::{Options, Sanitizer, Violation};
+    ///
+    /// let options = Options::new().with_empty_fallback("untitled");
+    /// assert_eq!(Sanitizer::try_new(options).unwrap().sanitize("???"), "untitled");
+    /// let err = Sanitizer::try_new(Options::new().with_empty_fallback("..")).unwrap_err();
+    /// assert_eq!(err.0, Violation::DotsOnly);
+    /// ```
+    pub fn try_new(options: Options<'a>) -> Result<Self, SanitizeError> {
+        let sanitizer = Sanitizer::new(options);
+        let options = &sanitizer.options;
+        for affix in [&options.prefix, &options.suffix] {
+            if replace_each_char(affix, options, &sanitizer.lookup, false) != **affix {
+                if let Some(violation) = first_violation(affix, &sanitizer.check) {
+                    return Err(SanitizeError(violation));
+                }
+            }
+        }
+        let unit = options.length_unit;
+        let affixes = unit.measure(&options.prefix) + unit.measure(&options.suffix);
+        if affixes > 0 && options.truncation.truncates() && affixes >= options.max_length {
+            return Err(SanitizeError(Violation::TooLong { len: affixes, unit }));
+        }
+        match &sanitizer.options.empty_fallback {
+            Some(empty_fallback) if sanitizer.sanitize_pass("").is_empty() => {
+                // Only an empty fallback breaks no rule of its own.
+                let violation = first_violation(empty_fallback, &sanitizer.check);
+                Err(SanitizeError(violation.unwrap_or(Violation::TooShort {
+                    len: 0,
+                    unit: options.length_unit,
+                })))
+            }
+            _ => Ok(sanitizer),
+        }
+    }
+
+    pub fn options(&self) -> &Options<'a> {
+        &self.options
+    }
+
+    /// The options that [`is_sanitized`](Sanitizer::is_sanitized) checks against.
+    pub fn check_options(&self) -> &OptionsForCheck {
+        &self.check
+    }
+
+    /// Sanitizes `name`, as [`sanitize_with_options`](crate::sanitize_with_options) does.
+    pub fn sanitize<S: AsRef<str>>(&self, name: S) -> String {
+        self.sanitize_cow(name.as_ref()).into_owned()
+    }
+
+    /// Sanitizes `name` where it is, as [`sanitize_in_place`](crate::sanitize_in_place)
+    /// does.
+    pub fn sanitize_in_place(&self, name: &mut String) {
+        let kept = match self.sanitize_cow(name) {
+            Cow::Borrowed(kept) => offset_in(name, kept).map(|start| start..start + kept.len()),
+            Cow::Owned(sanitized) => {
+                name.clear();
+                name.push_str(&sanitized);
+                return;
+            }
+        };
+        match kept {
+            Some(kept) => {
+                name.truncate(kept.end);
+                name.drain(..kept.start);
+            }
+            None => name.clear(),
+        }
+    }
+
+    /// Writes `name` sanitized into `out`, as [`sanitize_to`](crate::sanitize_to) does.
+    pub fn sanitize_to<W: fmt::Write>(&self, name: &str, out: &mut W) -> fmt::Result {
+        out.write_str(&self.sanitize_cow(name))
+    }
+
+    // What is left of `name` comes back borrowed.
+    fn sanitize_cow<'n>(&self, name: &'n str) -> Cow<'n, str> {
+        let first = self.sanitize_pass(name);
+        if !self.options.fixed_point {
+            return first;
+        }
+        // A pass can expose what the next one would remove, as `-.` loses its `-` and is
+        // then left as a reserved `.`. Names that keep changing, in a cycle or by growing,
+        // give up with the first of them that is already sanitized, which sanitizes to
+        // itself the same way.
+        let second = self.sanitize_pass(&first).into_owned();
+        if second == first {
+            return first;
+        }
+        let mut passes = vec![first.into_owned(), second];
+        while passes.len() <= MAX_PASSES {
+            let last = &passes[passes.len() - 1];
+            let next = self.sanitize_pass(last).into_owned();
+            if next == *last {
+                return Cow::Owned(next);
+            }
+            if passes.contains(&next) {
+                break;
+            }
+            passes.push(next);
+        }
+        if self.is_sanitized(name) {
+            return Cow::Borrowed(name);
+        }
+        match passes.into_iter().find(|pass| self.is_sanitized(pass)) {
+            Some(pass) => Cow::Owned(pass),
+            None => self.sanitize_pass(""),
+        }
+    }
+
+    fn sanitize_pass<'n>(&self, name: &'n str) -> Cow<'n, str> {
+        let name = sanitize_checked(name, self.options.borrowed(), &self.check, &self.lookup);
+        match &self.options.empty_fallback {
+            Some(empty_fallback) if name.is_empty() => Cow::Owned(
+                sanitize_checked(
+                    empty_fallback,
+                    self.options.borrowed(),
+                    &self.check,
+                    &self.lookup,
+                )
+                .into_owned(),
+            ),
+            _ => name,
+        }
+    }
+
+    /// Whether `name` is sanitized under these options, as
+    /// [`is_sanitized_with_options`](crate::is_sanitized_with_options) says.
+    pub fn is_sanitized<S: AsRef<str>>(&self, name: S) -> bool {
+        check_name(name.as_ref(), &self.check, &self.lookup)
+    }
+}
+
+// Where `part` starts in `whole`, if it is a slice of it.
+fn offset_in(whole: &str, part: &str) -> Option<usize> {
+    let start = (part.as_ptr() as usize).checked_sub(whole.as_ptr() as usize)?;
+    (start + part.len() <= whole.len()).then_some(start)
+}
+
+impl<'a> From<Options<'a>> for Sanitizer<'a> {
+    fn from(options: Options<'a>) -> Self {
+        Sanitizer::new(options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Sanitizer;
+
+    #[test]
+    fn it_matches_the_free_functions() {
+        let options = crate::Options::new()
+            .with_replacement("??")
+            .with_windows(true)
+            .with_empty_fallback("untitled")
+            .with_reserved_names(["desktop.ini"]);
+        let sanitizer = Sanitizer::new(options.clone());
+        let check = crate::OptionsForCheck::from(&options);
+
+        for name in [
+            "a?b",
+            "con",
+            "Desktop.ini",
+            "???",
+            "..",
+            "a. ",
+            "",
+            "report.pdf",
+        ] {
+            let sanitized = crate::sanitize_with_options(name, options.clone());
+            assert_eq!(sanitizer.sanitize(name), sanitized);
+            assert!(sanitizer.is_sanitized(&sanitized));
+            assert_eq!(
+                sanitizer.is_sanitized(name),
+                crate::is_sanitized_with_options(name, check.clone())
+            );
+        }
+        assert_eq!(sanitizer.sanitize("???"), "untitled");
+        assert_eq!(sanitizer.check_options(), &check);
+
+        let shared = std::sync::Arc::new(Sanitizer::from(options.into_owned()));
+        let handle = std::thread::spawn({
+            let shared = shared.clone();
+            move || shared.sanitize("a/b")
+        });
+        assert_eq!(handle.join().unwrap(), "ab");
+    }
+
+    #[test]
+    fn it_sanitizes_in_place() {
+        let options = crate::Options::new()
+            .with_windows(true)
+            .with_replacement("_")
+            .with_trim(true)
+            .with_empty_fallback("untitled")
+            .with_max_length(8);
+        let sanitizer = Sanitizer::new(options.clone());
+        for name in [
+            "report.pdf",
+            " a:b ",
+            "notes. ",
+            "con",
+            "???",
+            "",
+            "0123456789",
+            "\u{1}x",
+        ] {
+            let mut buffer = String::with_capacity(32);
+            buffer.push_str(name);
+            let before = buffer.as_ptr();
+            sanitizer.sanitize_in_place(&mut buffer);
+            assert_eq!(buffer, sanitizer.sanitize(name), "{:?}", name);
+            assert_eq!(buffer.as_ptr(), before);
+
+            let mut buffer = name.to_owned();
+            crate::sanitize_in_place(&mut buffer, &options);
+            assert_eq!(buffer, sanitizer.sanitize(name));
+        }
+    }
+
+    #[test]
+    fn it_writes_to_a_sink() {
+        use std::fmt::Write;
+
+        // Holds at most 8 bytes, as a stack buffer would.
+        struct Fixed(String);
+        impl Write for Fixed {
+            fn write_str(&mut self, s: &str) -> std::fmt::Result {
+                if self.0.len() + s.len() > 8 {
+                    return Err(std::fmt::Error);
+                }
+                self.0.push_str(s);
+                Ok(())
+            }
+        }
+
+        let sanitizer = Sanitizer::new(crate::Options::new().with_windows(true));
+        let mut out = String::from("dir/");
+        sanitizer.sanitize_to("a:b. ", &mut out).unwrap();
+        write!(out, "/").unwrap();
+        crate::sanitize_to("con", &crate::Options::new().with_windows(true), &mut out).unwrap();
+        assert_eq!(out, "dir/ab/");
+
+        let mut fixed = Fixed(String::new());
+        sanitizer.sanitize_to("report.pdf", &mut fixed).unwrap_err();
+        sanitizer.sanitize_to("a?.pdf", &mut fixed).unwrap();
+        assert_eq!(fixed.0, "a.pdf");
+    }
+
+    #[test]
+    fn it_rejects_fallbacks_of_which_nothing_is_left() {
+        use crate::{MinLengthStrategy, Options, SanitizeError, Violation};
+
+        let options = Options::new().with_windows(true);
+        for (empty_fallback, violation) in [
+            (
+                "",
+                Violation::TooShort {
+                    len: 0,
+                    unit: crate::LengthUnit::Bytes,
+                },
+            ),
+            (".", Violation::DotsOnly),
+            ("??", Violation::IllegalChar { offset: 0, c: '?' }),
+            ("nul?", Violation::IllegalChar { offset: 3, c: '?' }),
+        ] {
+            let options = options.clone().with_empty_fallback(empty_fallback);
+            assert_eq!(crate::sanitize_with_options("???", options.clone()), "");
+            assert_eq!(
+                Sanitizer::try_new(options).unwrap_err(),
+                SanitizeError(violation)
+            );
+        }
+
+        // Names that are still rejected once sanitized without any replacement give up on
+        // what is left of them, and so get the fallback too.
+        let short = options
+            .with_min_length(4)
+            .with_min_length_strategy(MinLengthStrategy::Fallback);
+        let err = Sanitizer::try_new(short.clone().with_empty_fallback("x")).unwrap_err();
+        assert_eq!(
+            err.0,
+            Violation::TooShort {
+                len: 1,
+                unit: crate::LengthUnit::Bytes
+            }
+        );
+        for options in [short.clone(), short.with_fixed_point(true)] {
+            let sanitizer = Sanitizer::try_new(options.with_empty_fallback("untitled")).unwrap();
+            assert_eq!(sanitizer.sanitize("ab"), "untitled");
+            assert_eq!(sanitizer.sanitize("a?"), "untitled");
+            assert_eq!(sanitizer.sanitize("abcd"), "abcd");
+        }
+        assert!(Sanitizer::try_new(Options::new()).is_ok());
+    }
+
+    #[test]
+    fn it_reaches_a_fixed_point() {
+        let v1 = crate::Options::new()
+            .with_rule_set(crate::RuleSet::V1)
+            .with_strip_shell_metachars(true);
+        let once = Sanitizer::new(v1.clone()).sanitize("-.");
+        assert_eq!(once, ".");
+        assert_eq!(Sanitizer::new(v1.clone()).sanitize(once), "");
+
+        for options in [
+            v1.clone(),
+            v1.with_windows(true).with_replacement(" "),
+            crate::Options::strict(),
+            crate::Options::windows().with_trailing_replacement("-"),
+            crate::Options::new()
+                .with_truncation(crate::TruncationStrategy::PreserveExtension)
+                .with_max_length(5),
+        ] {
+            let sanitizer = Sanitizer::new(options.with_fixed_point(true));
+            for name in [
+                "-.",
+                " -a",
+                "?.",
+                "\u{1}..",
+                ". ",
+                "a?b.tar.gz",
+                "con.txt",
+                "",
+            ] {
+                let once = sanitizer.sanitize(name);
+                assert_eq!(sanitizer.sanitize(&once), once, "{:?}", name);
+            }
+        }
+
+        // Each pass adds another `_` before the suffix, so this never settles.
+        let growing = crate::Options::new()
+            .with_replacement("_.")
+            .with_windows(true)
+            .with_suffix("_");
+        assert_eq!(Sanitizer::new(growing.clone()).sanitize("a?"), "a__._");
+        let sanitizer = Sanitizer::new(growing.with_fixed_point(true));
+        let once = sanitizer.sanitize("a?");
+        assert_eq!(once, "a__._");
+        assert!(sanitizer.is_sanitized(&once));
+        assert_eq!(sanitizer.sanitize(&once), once);
+        let sanitizer = Sanitizer::new(sanitizer.options().clone().with_empty_fallback("x"));
+        assert_eq!(
+            sanitizer.sanitize("?"),
+            sanitizer.sanitize(sanitizer.sanitize("?"))
+        );
+        assert_ne!(sanitizer.sanitize("?"), "");
+    }
+}
@@ -0,0 +1,209 @@
+use std::borrow::Cow;
+
+use crate::lookup::CharSet;
+use crate::replacer::push_substitute;
+use crate::risk::{confusable_ascii, double_extension_dot, is_bidi_control};
+use crate::{Replacer, EXPANSION_CHARS, SHELL_METACHARS};
+
+/// Named bundles of the security-oriented rules, for [`Options::with_security`] and
+/// [`OptionsForCheck::with_security`].
+///
+/// | rule                       | `Relaxed` | `Standard` | `Paranoid` |
+/// |----------------------------|-----------|------------|------------|
+/// | `strip_bidi`               |           | ✓          | ✓          |
+/// | `defuse_double_extensions` |           | ✓          | ✓          |
+/// | `leading_dot`: `Strip`     |           |            | ✓          |
+/// | `strip_shell_metachars`    |           |            | ✓          |
+/// | `strip_expansion_chars`    |           |            | ✓          |
+/// | `fold_confusables`         |           |            | ✓          |
+///
+/// `Relaxed` matches the defaults. `Paranoid` also folds Cyrillic and Greek letters that
+/// look like Latin ones, so it garbles names in those scripts; it is meant for upload
+/// handlers that expect Latin names. The other presets turn a `LeadingDotPolicy::Strip`
+/// back into `Allow`, and leave `Prefix` alone.
+///
+/// [`Options::with_security`]: crate::Options::with_security
+/// [`OptionsForCheck::with_security`]: crate::OptionsForCheck::with_security
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum Security {
+    #[default]
+    Relaxed,
+    Standard,
+    Paranoid,
+}
+
+// The rules of each preset, in the order of the table above.
+pub(crate) struct SecurityRules {
+    pub(crate) strip_bidi: bool,
+    pub(crate) defuse_double_extensions: bool,
+    pub(crate) strip_hidden_prefix: bool,
+    pub(crate) strip_shell_metachars: bool,
+    pub(crate) strip_expansion_chars: bool,
+    pub(crate) fold_confusables: bool,
+}
+
+impl Security {
+    pub(crate) const fn rules(self) -> SecurityRules {
+        let (standard, paranoid) = match self {
+            Security::Relaxed => (false, false),
+            Security::Standard => (true, false),
+            Security::Paranoid => (true, true),
+        };
+        SecurityRules {
+            strip_bidi: standard,
+            defuse_double_extensions: standard,
+            strip_hidden_prefix: paranoid,
+            strip_shell_metachars: paranoid,
+            strip_expansion_chars: paranoid,
+            fold_confusables: paranoid,
+        }
+    }
+}
+
+pub(crate) fn is_shell_metachar(c: char) -> bool {
+    SHELL_METACHARS.contains(&c)
+}
+
+pub(crate) fn is_expansion_char(c: char) -> bool {
+    EXPANSION_CHARS.contains(&c)
+}
+
+// Folds confusables and replaces bidi controls, shell metacharacters and expansion
+// characters, as enabled. Runs before the illegal characters are replaced, as e.g. a
+// fullwidth solidus folds to `/`.
+pub(crate) fn replace_unsafe_chars<'n>(
+    name: &'n str,
+    replacement: &str,
+    replacer: Option<&Replacer>,
+    allow: &CharSet,
+    rules: &SecurityRules,
+) -> Cow<'n, str> {
+    let stripped = |c: char| {
+        !allow.contains(c)
+            && ((rules.strip_bidi && is_bidi_control(c))
+                || (rules.strip_shell_metachars && is_shell_metachar(c))
+                || (rules.strip_expansion_chars && is_expansion_char(c)))
+    };
+    let folded = |c: char| {
+        if rules.fold_confusables && !allow.contains(c) {
+            confusable_ascii(c)
+        } else {
+            None
+        }
+    };
+    if !name.chars().any(|c| stripped(c) || folded(c).is_some()) {
+        return Cow::Borrowed(name);
+    }
+    let mut out = String::with_capacity(name.len());
+    for c in name.chars() {
+        if stripped(c) {
+            push_substitute(&mut out, c, replacement, replacer);
+        } else {
+            out.push(folded(c).unwrap_or(c));
+        }
+    }
+    Cow::Owned(out)
+}
+
+// Strips leading dots (hiding the file on Unix) and hyphens (read as options by most
+// commands), as enabled.
+pub(crate) fn strip_unsafe_prefix(name: Cow<'_, str>, dots: bool, hyphens: bool) -> Cow<'_, str> {
+    let unsafe_prefix = |c: char| (dots && c == '.') || (hyphens && c == '-');
+    if !name.starts_with(unsafe_prefix) {
+        return name;
+    }
+    Cow::Owned(name.trim_start_matches(unsafe_prefix).to_owned())
+}
+
+// Replaces the dot before a harmless-looking extension hiding an executable one, so
+// `invoice.pdf.exe` shows what it is: `invoice_pdf.exe`.
+pub(crate) fn defuse_double_extension<'n>(name: Cow<'n, str>, replacement: &str) -> Cow<'n, str> {
+    match double_extension_dot(&name) {
+        Some(dot) => {
+            let mut out = String::with_capacity(name.len() + replacement.len());
+            out.push_str(&name[..dot]);
+            out.push_str(replacement);
+            out.push_str(&name[dot + 1..]);
+            Cow::Owned(out)
+        }
+        None => name,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Security;
+
+    #[test]
+    fn it_applies_security_presets() {
+        let sanitize = |name: &str, security| {
+            let options = crate::Options::new()
+                .with_replacement("_")
+                .with_security(security);
+            crate::sanitize_with_options(name, options)
+        };
+        let is_sanitized = |name: &str, security| {
+            crate::is_sanitized_with_options(
+                name,
+                crate::OptionsForCheck::new().with_security(security),
+            )
+        };
+
+        for (name, relaxed, standard, paranoid) in [
+            (
+                "photo\u{202e}gpj.exe",
+                "photo\u{202e}gpj.exe",
+                "photo_gpj.exe",
+                "photo_gpj.exe",
+            ),
+            (
+                "invoice.pdf.exe",
+                "invoice.pdf.exe",
+                "invoice_pdf.exe",
+                "invoice_pdf.exe",
+            ),
+            (".bashrc", ".bashrc", ".bashrc", "bashrc"),
+            ("-rf $(id)", "-rf $(id)", "-rf $(id)", "rf __id_"),
+            ("раypal．com", "раypal．com", "раypal．com", "paypal.com"),
+            ("..", "_", "_", "_"),
+            ("100% ~$plan", "100% ~$plan", "100% ~$plan", "100_ __plan"),
+        ] {
+            assert_eq!(sanitize(name, Security::Relaxed), relaxed);
+            assert_eq!(sanitize(name, Security::Standard), standard);
+            assert_eq!(sanitize(name, Security::Paranoid), paranoid);
+            for (sanitized, security) in [
+                (standard, Security::Standard),
+                (paranoid, Security::Paranoid),
+            ] {
+                assert!(is_sanitized(sanitized, security));
+                assert_eq!(is_sanitized(name, security), name == sanitized);
+            }
+        }
+    }
+
+    #[test]
+    fn it_replaces_expansion_chars() {
+        let options = crate::Options::new()
+            .with_replacement("_")
+            .with_strip_expansion_chars(true);
+        let check = crate::OptionsForCheck::from(&options);
+        let sanitize = |name: &str| crate::sanitize_with_options(name, options.clone());
+
+        assert_eq!(sanitize("~$report.docx"), "__report.docx");
+        assert_eq!(sanitize("%TEMP%\u{60}id\u{60}!"), "_TEMP__id__");
+        // Unlike the shell rules, other punctuation and leading hyphens are left alone.
+        assert_eq!(sanitize("-a (1) & b;c.txt"), "-a (1) & b;c.txt");
+        assert_eq!(
+            crate::violations("a~b", check.clone()),
+            [crate::Violation::ExpansionChar { offset: 1, c: '~' }]
+        );
+        assert!(crate::is_sanitized_with_options("a(b)", check));
+        assert_eq!(
+            crate::sanitize_with_options("~a", options.with_allow(&['~'][..])),
+            "~a"
+        );
+    }
+}
@@ -0,0 +1,116 @@
+use crate::lookup::Lookup;
+use crate::{
+    is_sanitized_with_options, normalized_extension, replace_each_char, sanitize_with_options,
+    ExtensionNormalization, Options, TruncationStrategy,
+};
+
+/// Sanitizes the stem of `name` only, keeping its extensions as they are, so that titles
+/// can be cleaned up as much as the options say without ever losing a `.tar.gz`:
+///
+/// ```
+/// let options = sanitize_filename::Options::new()
+///     .with_replacement("_")
+///     .with_max_length(16);
+/// let name = sanitize_filename::sanitize_stem("Q3: results (draft).tar.gz", &options);
+/// assert_eq!(name, "Q3_ resul.tar.gz");
+/// ```
+///
+/// Extensions are the trailing ones of ASCII letters and digits (and no longer than
+/// `max_extension_len`) that sanitizing would keep, up to `max_extensions` of them, or two
+/// when that is not set. An extension that does not qualify stops the search and is
+/// sanitized as part of the stem. The stem gets what is left of `max_length` and
+/// `min_length` after the extensions; their case is normalized as `normalize_extension`
+/// says. If nothing is left of the stem, or the result would not be sanitized, the whole
+/// name is sanitized as usual instead.
+pub fn sanitize_stem<S: AsRef<str>>(name: S, options: &Options) -> String {
+    let name = name.as_ref();
+    let (stem, extensions) = name.split_at(extensions_start(name, options));
+    if extensions.is_empty() {
+        return sanitize_with_options(name, options.borrowed());
+    }
+
+    let length = options.length_unit.measure(extensions);
+    let mut stem_options = options.borrowed();
+    stem_options.max_length = options.max_length.saturating_sub(length);
+    stem_options.min_length = options.min_length.map(|min| min.saturating_sub(length));
+    stem_options.allowed_extensions = None;
+    stem_options.normalize_extension = ExtensionNormalization::Keep;
+    stem_options.max_extensions = None;
+    if stem_options.truncation.truncates() {
+        stem_options.truncation = TruncationStrategy::CutEnd;
+    }
+    let stem = sanitize_with_options(stem, stem_options);
+    if stem.is_empty() {
+        return sanitize_with_options(name, options.borrowed());
+    }
+
+    let mut out = stem + extensions;
+    if let Some(normalized) = normalized_extension(&out, options.normalize_extension) {
+        out = normalized;
+    }
+    if !is_sanitized_with_options(&out, options.into()) {
+        return sanitize_with_options(name, options.borrowed());
+    }
+    out
+}
+
+// Where the extensions that `sanitize_stem` keeps start, or the end of `name`.
+fn extensions_start(name: &str, options: &Options) -> usize {
+    let max = options.max_extensions.unwrap_or(2);
+    let lookup = Lookup::from(options);
+    let mut start = name.len();
+    for _ in 0..max {
+        let idx = match name[..start].rfind('.') {
+            Some(idx) if idx > 0 => idx,
+            _ => break,
+        };
+        let extension = &name[idx + 1..start];
+        let qualifies = !extension.is_empty()
+            && extension.bytes().all(|b| b.is_ascii_alphanumeric())
+            && options
+                .max_extension_len
+                .map_or(true, |max| extension.len() <= max)
+            && replace_each_char(extension, options, &lookup, false) == extension;
+        if !qualifies {
+            break;
+        }
+        start = idx;
+    }
+    start
+}
+
+#[cfg(test)]
+mod tests {
+    use super::sanitize_stem;
+
+    #[test]
+    fn it_sanitizes_only_the_stem() {
+        let options = crate::Options::new().with_replacement("_");
+
+        assert_eq!(sanitize_stem("a:b.tar.gz", &options), "a_b.tar.gz");
+        assert_eq!(
+            sanitize_stem("my.notes.v2.txt", &options),
+            "my.notes.v2.txt"
+        );
+        assert_eq!(sanitize_stem("a?b", &options), "a_b");
+        assert_eq!(sanitize_stem("x.t?t", &options), "x.t_t");
+
+        let short = options.clone().with_max_length(8);
+        assert_eq!(sanitize_stem("abcdefgh.tar.gz", &short), "a.tar.gz");
+        assert_eq!(
+            sanitize_stem("abcdefgh.tar.gz", &short.clone().with_max_extensions(1)),
+            "abcde.gz"
+        );
+        // Nothing is left for the stem.
+        assert_eq!(
+            sanitize_stem("abc.tar.gz", &options.clone().with_max_length(7)),
+            "abc.tar"
+        );
+        assert_eq!(sanitize_stem("???.gz", &crate::Options::new()), ".gz");
+
+        let canonical = options
+            .clone()
+            .with_normalize_extension(crate::ExtensionNormalization::Canonical);
+        assert_eq!(sanitize_stem("a|b.JPEG", &canonical), "a_b.jpg");
+    }
+}
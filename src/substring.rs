@@ -0,0 +1,183 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+
+/// Substrings that names must not contain, for
+/// [`Options::with_deny_substrings`](crate::Options::with_deny_substrings), e.g. `~$` or
+/// words that must not appear in shared folders.
+///
+/// The substrings are compiled into a single Aho-Corasick automaton once, and cloning
+/// shares it, so that the options can be reused across millions of names. When
+/// sanitizing, each match is replaced with
+/// [`Options::replacement`](crate::Options::replacement), preferring the longest
+/// substring where several start at the same place; when checking, a name with a match
+/// is rejected. Matching is exact and happens after illegal characters are replaced, so
+/// substrings containing them never match. Built with the `aho-corasick` feature; two
+/// sets are equal when their substrings are.
+#[derive(Clone)]
+pub struct DenySubstrings {
+    #[cfg(feature = "aho-corasick")]
+    matcher: aho_corasick::AhoCorasick,
+    // Matchers can only be built with the `aho-corasick` feature, so without it there are
+    // none.
+    #[cfg(not(feature = "aho-corasick"))]
+    _unbuildable: std::convert::Infallible,
+    substrings: Arc<[String]>,
+}
+
+impl DenySubstrings {
+    /// Builds the matcher of `substrings`, ignoring empty ones.
+    ///
+    /// ```
+    /// use sanitize_filename::{sanitize_with_options, DenySubstrings, Options};
+    ///
+    /// let deny = DenySubstrings::new(["~$", "confidential"]).unwrap();
+    /// let options = Options::new().with_replacement("_").with_deny_substrings(deny);
+    /// assert_eq!(
+    ///     sanitize_with_options("~$confidential plan.docx", options),
+    ///     "__ plan.docx"
+    /// );
+    /// ```
+    #[cfg(feature = "aho-corasick")]
+    pub fn new<I, S>(substrings: I) -> Result<Self, aho_corasick::BuildError>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let substrings: Arc<[String]> = substrings
+            .into_iter()
+            .map(Into::into)
+            .filter(|substring: &String| !substring.is_empty())
+            .collect();
+        let matcher = aho_corasick::AhoCorasick::builder()
+            .match_kind(aho_corasick::MatchKind::LeftmostLongest)
+            .build(substrings.iter())?;
+        Ok(DenySubstrings {
+            matcher,
+            substrings,
+        })
+    }
+
+    /// The substrings, in the order they were given.
+    pub fn substrings(&self) -> &[String] {
+        &self.substrings
+    }
+
+    // The byte ranges of the matches in `name`, from left to right.
+    pub(crate) fn find_iter<'n>(
+        &'n self,
+        name: &'n str,
+    ) -> impl Iterator<Item = std::ops::Range<usize>> + 'n {
+        #[cfg(feature = "aho-corasick")]
+        {
+            self.matcher.find_iter(name).map(|found| found.range())
+        }
+        #[cfg(not(feature = "aho-corasick"))]
+        {
+            let _ = name;
+            std::iter::empty()
+        }
+    }
+
+    pub(crate) fn replace_all<'n>(&self, name: Cow<'n, str>, replacement: &str) -> Cow<'n, str> {
+        let mut out = String::new();
+        let mut last = 0;
+        for range in self.find_iter(&name) {
+            out.push_str(&name[last..range.start]);
+            out.push_str(replacement);
+            last = range.end;
+        }
+        if last == 0 {
+            return name;
+        }
+        out.push_str(&name[last..]);
+        Cow::Owned(out)
+    }
+}
+
+impl fmt::Debug for DenySubstrings {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("DenySubstrings")
+            .field(&self.substrings)
+            .finish()
+    }
+}
+
+impl PartialEq for DenySubstrings {
+    fn eq(&self, other: &DenySubstrings) -> bool {
+        self.substrings == other.substrings
+    }
+}
+
+impl Eq for DenySubstrings {}
+
+impl Hash for DenySubstrings {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.substrings.hash(state);
+    }
+}
+
+// As the list of substrings, which is compiled again when deserializing.
+#[cfg(feature = "serde")]
+impl serde::Serialize for DenySubstrings {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.substrings.serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for DenySubstrings {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let substrings = Vec::<String>::deserialize(deserializer)?;
+        #[cfg(feature = "aho-corasick")]
+        {
+            DenySubstrings::new(substrings).map_err(serde::de::Error::custom)
+        }
+        #[cfg(not(feature = "aho-corasick"))]
+        {
+            let _ = substrings;
+            Err(serde::de::Error::custom(
+                "deny substrings need the `aho-corasick` feature",
+            ))
+        }
+    }
+}
+
+#[cfg(all(test, feature = "aho-corasick"))]
+mod tests {
+    use super::DenySubstrings;
+
+    #[test]
+    fn it_denies_substrings() {
+        let deny = DenySubstrings::new(["..", "~$", "acme", "acme corp", ""]).unwrap();
+        assert_eq!(deny.substrings(), ["..", "~$", "acme", "acme corp"]);
+        let options = crate::Options::new()
+            .with_replacement("_")
+            .with_deny_substrings(deny.clone());
+        let check = crate::OptionsForCheck::from(&options);
+        let sanitize = |name: &str| crate::sanitize_with_options(name, options.clone());
+
+        assert_eq!(sanitize("~$acme corp..docx"), "___docx");
+        assert_eq!(sanitize("acme.txt"), "_.txt");
+        assert_eq!(sanitize("a.c.m.e"), "a.c.m.e");
+        assert!(crate::is_sanitized_with_options("ACME.txt", check.clone()));
+        assert_eq!(
+            crate::violations("x~$y..z", check),
+            [
+                crate::Violation::DeniedSubstring { offset: 1, len: 2 },
+                crate::Violation::DeniedSubstring { offset: 4, len: 2 },
+            ]
+        );
+
+        // Stripping a match must not leave another one behind.
+        let options = crate::Options::new().with_deny_substrings(deny.clone());
+        assert_eq!(crate::sanitize_with_options("a.~$.b", options), "ab");
+
+        assert_eq!(
+            deny,
+            DenySubstrings::new(["..", "~$", "acme", "acme corp"]).unwrap()
+        );
+        assert_ne!(deny, DenySubstrings::new(["acme"]).unwrap());
+    }
+}
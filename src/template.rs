@@ -0,0 +1,202 @@
+use std::borrow::Borrow;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::hash::{BuildHasher, Hash};
+
+use crate::{FileNameBuilder, Options};
+
+/// A source of values for the placeholders of [`format_filename`].
+pub trait TemplateValues {
+    fn get_value(&self, key: &str) -> Option<&str>;
+}
+
+impl<K, V, H> TemplateValues for HashMap<K, V, H>
+where
+    K: Borrow<str> + Hash + Eq,
+    V: AsRef<str>,
+    H: BuildHasher,
+{
+    fn get_value(&self, key: &str) -> Option<&str> {
+        self.get(key).map(AsRef::as_ref)
+    }
+}
+
+impl<K, V> TemplateValues for BTreeMap<K, V>
+where
+    K: Borrow<str> + Ord,
+    V: AsRef<str>,
+{
+    fn get_value(&self, key: &str) -> Option<&str> {
+        self.get(key).map(AsRef::as_ref)
+    }
+}
+
+impl<K, V> TemplateValues for [(K, V)]
+where
+    K: AsRef<str>,
+    V: AsRef<str>,
+{
+    fn get_value(&self, key: &str) -> Option<&str> {
+        self.iter()
+            .find(|(k, _)| k.as_ref() == key)
+            .map(|(_, v)| v.as_ref())
+    }
+}
+
+impl<K, V, const N: usize> TemplateValues for [(K, V); N]
+where
+    K: AsRef<str>,
+    V: AsRef<str>,
+{
+    fn get_value(&self, key: &str) -> Option<&str> {
+        self[..].get_value(key)
+    }
+}
+
+/// An error from [`format_filename`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TemplateError {
+    /// The template refers to a placeholder that has no value.
+    MissingValue(String),
+    /// A `{` without a matching `}`, or a lone `}` (use `{{` and `}}` for literal braces).
+    UnbalancedBrace(usize),
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TemplateError::MissingValue(key) => write!(f, "no value for placeholder `{}`", key),
+            TemplateError::UnbalancedBrace(idx) => write!(f, "unbalanced brace at byte {}", idx),
+        }
+    }
+}
+
+impl std::error::Error for TemplateError {}
+
+/// Formats a file name from a template such as `"{artist} - {title}.{ext}"`.
+///
+/// The characters of each interpolated value are replaced as those of names are; the
+/// literal template text is trusted and kept as written. The whole-name rules (reserved
+/// names, trailing dots and spaces on Windows, the affixes) apply once, to the assembled
+/// name. When the result is too long,
+/// the values are shortened, last placeholder first, and the literal text is kept.
+pub fn format_filename<V: TemplateValues + ?Sized>(
+    template: &str,
+    values: &V,
+    options: &Options,
+) -> Result<String, TemplateError> {
+    let mut builder = FileNameBuilder::with_options(options.clone());
+    let mut literal = String::new();
+    let mut rest = template.char_indices().peekable();
+
+    while let Some((idx, c)) = rest.next() {
+        match c {
+            '{' if rest.peek().map(|&(_, c)| c) == Some('{') => {
+                rest.next();
+                literal.push('{');
+            }
+            '}' if rest.peek().map(|&(_, c)| c) == Some('}') => {
+                rest.next();
+                literal.push('}');
+            }
+            '{' => {
+                let start = idx + 1;
+                let end = loop {
+                    match rest.next() {
+                        Some((end, '}')) => break end,
+                        Some(_) => {}
+                        None => return Err(TemplateError::UnbalancedBrace(idx)),
+                    }
+                };
+                let key = &template[start..end];
+                let value = values
+                    .get_value(key)
+                    .ok_or_else(|| TemplateError::MissingValue(key.to_owned()))?;
+                builder.push_trusted(&literal, u32::MAX);
+                literal.clear();
+                builder.push(value);
+            }
+            '}' => return Err(TemplateError::UnbalancedBrace(idx)),
+            c => literal.push(c),
+        }
+    }
+    builder.push_trusted(&literal, u32::MAX);
+
+    Ok(builder.build())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_filename, TemplateError};
+
+    #[test]
+    fn it_formats_templates() {
+        let options = crate::Options {
+            windows: true,
+            ..Default::default()
+        };
+        let values = [
+            ("artist", "AC/DC"),
+            ("title", "What's Next?"),
+            ("ext", "mp3"),
+        ];
+
+        assert_eq!(
+            format_filename("{artist} - {title}.{ext}", &values, &options),
+            Ok("ACDC - What's Next.mp3".to_owned())
+        );
+        assert_eq!(
+            format_filename("{{{ext}}}", &values, &options),
+            Ok("{mp3}".to_owned())
+        );
+        assert_eq!(
+            format_filename("{album}", &values, &options),
+            Err(TemplateError::MissingValue("album".to_owned()))
+        );
+        assert_eq!(
+            format_filename("{title", &values, &options),
+            Err(TemplateError::UnbalancedBrace(0))
+        );
+
+        let long = [("title", "t".repeat(300))];
+        let name = format_filename("prefix {title}.txt", &long, &options).unwrap();
+        assert_eq!(name.len(), 255);
+        assert!(name.starts_with("prefix t"));
+        assert!(name.ends_with("t.txt"));
+    }
+
+    #[test]
+    fn it_applies_whole_name_rules_once() {
+        let options = crate::Options::new();
+        let values = [("artist", "AC/DC"), ("title", "Live"), ("ext", "mp3")];
+        let format = |template: &str, values: &[(&str, &str)], options: crate::Options| {
+            format_filename(template, values, &options).unwrap()
+        };
+
+        let template = "{artist} - {title}.{ext}";
+        assert_eq!(
+            format(template, &values, options.clone().with_prefix("up_")),
+            "up_ACDC - Live.mp3"
+        );
+        assert_eq!(
+            format(template, &values, options.clone().with_min_length(6)),
+            "ACDC - Live.mp3"
+        );
+        assert_eq!(
+            format(
+                template,
+                &[("artist", "?"), ("title", "Live"), ("ext", "mp3")],
+                options.clone().with_empty_fallback("untitled")
+            ),
+            " - Live.mp3"
+        );
+        assert_eq!(
+            format(
+                "{artist}{title}",
+                &[("artist", "x"), ("title", ".env")],
+                options.with_leading_dot(crate::LeadingDotPolicy::Prefix)
+            ),
+            "x.env"
+        );
+    }
+}
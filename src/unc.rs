@@ -0,0 +1,154 @@
+use std::fmt;
+
+use crate::path::raw_components;
+use crate::{is_control_char, sanitize_path_components, truncate_to, Options, PathError};
+
+// The characters that Windows does not allow in share names.
+const SHARE_ILLEGAL_CHARS: &[char] = &[
+    '"', '/', '\\', '[', ']', ':', '|', '<', '>', '+', '=', ';', ',', '?', '*',
+];
+const SHARE_MAX_LENGTH: usize = 80;
+const LABEL_MAX_LENGTH: usize = 63;
+const HOST_MAX_LENGTH: usize = 253;
+
+/// A sanitized UNC path, `\\server\share\path`, as returned by [`sanitize_unc_path`].
+///
+/// Its `Display` implementation joins the parts back into a path.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct UncPath {
+    pub server: String,
+    pub share: String,
+    /// The components of the path within the share.
+    pub components: Vec<String>,
+}
+
+impl fmt::Display for UncPath {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, r"\\{}\{}", self.server, self.share)?;
+        for component in &self.components {
+            write!(f, r"\{}", component)?;
+        }
+        Ok(())
+    }
+}
+
+// Keeps the letters, digits and hyphens of each dot-separated label, as in host names.
+fn sanitize_server(server: &str) -> String {
+    let mut out = String::with_capacity(server.len());
+    for label in server.split('.') {
+        let label = label
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric() || *c == '-')
+            .collect::<String>();
+        let label = truncate_to(label.trim_matches('-'), LABEL_MAX_LENGTH).trim_end_matches('-');
+        if label.is_empty() {
+            continue;
+        }
+        if !out.is_empty() {
+            out.push('.');
+        }
+        out.push_str(label);
+    }
+    truncate_to(&out, HOST_MAX_LENGTH)
+        .trim_end_matches(['.', '-'])
+        .to_owned()
+}
+
+fn sanitize_share(share: &str) -> String {
+    let share = share
+        .chars()
+        .filter(|&c| !SHARE_ILLEGAL_CHARS.contains(&c) && !is_control_char(c))
+        .collect::<String>();
+    truncate_to(share.trim(), SHARE_MAX_LENGTH)
+        .trim_end()
+        .to_owned()
+}
+
+/// Sanitizes a UNC path such as `\\server\share\dir\file.txt`, keeping its structure.
+///
+/// The server is sanitized like a host name and the share name by the Windows rules for
+/// shares; the rest of the path is sanitized like [`sanitize_path_components`] does,
+/// with the Windows rules on. Either separator is accepted, as is the
+/// `\\?\UNC\server\share` form.
+///
+/// ```
+/// let options = sanitize_filename::Options::new();
+/// let path = sanitize_filename::sanitize_unc_path(r"\\file_server\team[1]\q3:report.doc", &options)
+///     .unwrap();
+/// assert_eq!(path.server, "fileserver");
+/// assert_eq!(path.to_string(), r"\\fileserver\team1\q3report.doc");
+/// ```
+pub fn sanitize_unc_path<S: AsRef<str>>(path: S, options: &Options) -> Result<UncPath, PathError> {
+    let path = path.as_ref();
+    let rest = match path.get(..2) {
+        Some(prefix) if prefix.chars().all(|c| c == '/' || c == '\\') => &path[2..],
+        _ => return Err(PathError::NotUnc),
+    };
+    let rest = match rest.get(..6) {
+        Some(prefix) if prefix.replace('/', "\\").eq_ignore_ascii_case(r"?\UNC\") => &rest[6..],
+        _ => rest,
+    };
+
+    let mut parts = rest.splitn(3, ['/', '\\']);
+    let server = sanitize_server(parts.next().unwrap_or_default());
+    if server.is_empty() {
+        return Err(PathError::InvalidServer);
+    }
+    let share = sanitize_share(parts.next().unwrap_or_default());
+    if share.is_empty() || raw_components(&share).is_empty() {
+        return Err(PathError::InvalidShare);
+    }
+    let components = sanitize_path_components(
+        parts.next().unwrap_or_default(),
+        &options.borrowed().with_windows(true),
+    )?;
+
+    Ok(UncPath {
+        server,
+        share,
+        components,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::sanitize_unc_path;
+    use crate::PathError;
+
+    #[test]
+    fn it_sanitizes_unc_paths() {
+        let options = crate::Options::new().with_replacement("_");
+        let unc = |path: &str| sanitize_unc_path(path, &options).map(|path| path.to_string());
+
+        assert_eq!(unc(r"\\srv\c$\a\con"), Ok(r"\\srv\c$\a\_".to_owned()));
+        assert_eq!(
+            unc("//my server!.corp..example.com/share=1/../a b./c?"),
+            Ok(r"\\myserver.corp.example.com\share1\a b_\c_".to_owned())
+        );
+        assert_eq!(
+            unc(r"\\?\UNC\srv\share\x.txt"),
+            Ok(r"\\srv\share\x.txt".to_owned())
+        );
+        assert_eq!(unc(r"\\srv\share"), Ok(r"\\srv\share".to_owned()));
+        assert_eq!(
+            sanitize_unc_path(r"\\-.-\share", &options),
+            Err(PathError::InvalidServer)
+        );
+        assert_eq!(
+            sanitize_unc_path(r"\\srv\[:]\x", &options),
+            Err(PathError::InvalidShare)
+        );
+        assert_eq!(
+            sanitize_unc_path(r"\\srv\..\x", &options),
+            Err(PathError::InvalidShare)
+        );
+        assert_eq!(
+            sanitize_unc_path(r"C:\dir", &options),
+            Err(PathError::NotUnc)
+        );
+
+        let long = format!(r"\\{}\{}", "a".repeat(70), "b".repeat(100));
+        let path = sanitize_unc_path(&long, &options).unwrap();
+        assert_eq!((path.server.len(), path.share.len()), (63, 80));
+    }
+}
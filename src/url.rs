@@ -0,0 +1,94 @@
+use crate::os_str::decode_utf8;
+use crate::{sanitize_with_options, Options};
+
+/// Names a download after its URL: takes the last segment of the path, without the query
+/// and the fragment, percent-decodes it and sanitizes the result.
+///
+/// ```
+/// let options = sanitize_filename::Options::new().with_replacement("_");
+/// let name = sanitize_filename::filename_from_url(
+///     "https://example.com/files/Q3%20report%3A%20final.pdf?download=1#page=2",
+///     &options,
+/// );
+/// assert_eq!(name, "Q3 report_ final.pdf");
+/// ```
+///
+/// A trailing `/` is skipped, so `https://example.com/docs/` gives `docs`; a URL without a
+/// path, such as `https://example.com`, gives what sanitizing an empty name does, i.e.
+/// the [`Options::empty_fallback`] if there is one. Escapes that do not spell out UTF-8
+/// get the replacement, and an encoded `/` (`%2F`) is sanitized like any other.
+pub fn filename_from_url<S: AsRef<str>>(url: S, options: &Options) -> String {
+    let url = url.as_ref();
+    let url = url.split('#').next().unwrap_or_default();
+    let url = url.split('?').next().unwrap_or_default();
+    // Skip the scheme and the host, which are not part of the path
+    let path = match url.find("://") {
+        Some(idx) => {
+            let rest = &url[idx + 3..];
+            rest.find('/').map_or("", |slash| &rest[slash..])
+        }
+        None => url,
+    };
+    let segment = path
+        .rsplit('/')
+        .find(|segment| !segment.is_empty())
+        .unwrap_or_default();
+    let segment = decode_utf8(&percent_decode(segment), &options.replacement);
+    sanitize_with_options(segment, options.borrowed())
+}
+
+// Decodes the `%XX` escapes of `segment`, keeping malformed ones as they are.
+pub(crate) fn percent_decode(segment: &str) -> Vec<u8> {
+    let bytes = segment.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut idx = 0;
+    while idx < bytes.len() {
+        let byte = match bytes[idx] {
+            b'%' => segment
+                .get(idx + 1..idx + 3)
+                .filter(|hex| hex.bytes().all(|b| b.is_ascii_hexdigit()))
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok()),
+            _ => None,
+        };
+        match byte {
+            Some(byte) => {
+                decoded.push(byte);
+                idx += 3;
+            }
+            None => {
+                decoded.push(bytes[idx]);
+                idx += 1;
+            }
+        }
+    }
+    decoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::filename_from_url;
+
+    #[test]
+    fn it_names_downloads() {
+        let options = crate::Options::new().with_replacement("_");
+        let name = |url: &str| filename_from_url(url, &options);
+
+        assert_eq!(name("https://example.com/a/b.txt"), "b.txt");
+        assert_eq!(name("https://example.com/docs/"), "docs");
+        assert_eq!(name("https://example.com"), "");
+        assert_eq!(name("https://example.com?q=a/b"), "");
+        assert_eq!(name("http://h/caf%C3%A9.txt#x/y"), "café.txt");
+        assert_eq!(name("http://h/%2E%2E%2Fetc%2Fpasswd"), ".._etc_passwd");
+        assert_eq!(name("http://h/%2e%2e"), "_");
+        assert_eq!(name("http://h/100%.txt"), "100%.txt");
+        assert_eq!(name("http://h/a%FFb"), "a_b");
+        assert_eq!(name("relative/path/file.zip?x=1"), "file.zip");
+        assert_eq!(
+            filename_from_url(
+                "https://example.com/",
+                &options.clone().with_empty_fallback("download")
+            ),
+            "download"
+        );
+    }
+}
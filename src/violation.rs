@@ -0,0 +1,657 @@
+use std::fmt;
+use std::ops::{BitAnd, BitOr, BitOrAssign};
+
+use crate::risk::{has_double_extension, is_bidi_control, is_confusable};
+use crate::security::{is_expansion_char, is_shell_metachar};
+use crate::{
+    extension_count, has_allowed_extension, has_portable_edges, has_windows_trailing,
+    is_control_char, is_illegal_char, is_listed_reserved, is_portable_char, is_reserved,
+    is_sync_junk, is_whitespace_only, is_windows_reserved, normalized_extension, Charset,
+    LeadingDotPolicy, LengthUnit, OptionsForCheck, RuleSet,
+};
+
+/// A reason why a name is not sanitized, as reported by [`violations`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[non_exhaustive]
+pub enum Violation {
+    /// One of [`ILLEGAL_CHARS`](crate::ILLEGAL_CHARS) or of
+    /// [`OptionsForCheck::extra_illegal`], at the given byte offset.
+    IllegalChar { offset: usize, c: char },
+    /// One of [`CONTROL_CHARS`](crate::CONTROL_CHARS), at the given byte offset.
+    ControlChar { offset: usize, c: char },
+    /// A character outside of [`Charset::Portable`], at the given byte offset.
+    NonPortableChar { offset: usize, c: char },
+    /// A leading `.` or `-`, or a trailing `.`, under [`Charset::Portable`].
+    NonPortableEdge,
+    /// A character outside of [`OptionsForCheck::allowlist`], at the given byte offset.
+    NotAllowlisted { offset: usize, c: char },
+    /// A character that is not ASCII, under [`OptionsForCheck::ascii_only`], at the given
+    /// byte offset.
+    NonAscii { offset: usize, c: char },
+    /// A bidirectional control character, at the given byte offset.
+    BidiControl { offset: usize, c: char },
+    /// One of [`SHELL_METACHARS`](crate::SHELL_METACHARS), at the given byte offset.
+    ShellMetachar { offset: usize, c: char },
+    /// One of [`EXPANSION_CHARS`](crate::EXPANSION_CHARS), at the given byte offset.
+    ExpansionChar { offset: usize, c: char },
+    /// A character that looks like an ASCII one, at the given byte offset.
+    Confusable { offset: usize, c: char },
+    /// A leading dot, which hides the file on Unix-like systems.
+    HiddenPrefix,
+    /// A leading hyphen, which commands read as an option.
+    LeadingHyphen,
+    /// An executable extension behind a harmless-looking one (`invoice.pdf.exe`).
+    DoubleExtension,
+    /// `.`, `..` or another name made only of dots.
+    DotsOnly,
+    /// A name made only of spaces, NBSPs or other whitespace, which is invisible.
+    WhitespaceOnly,
+    /// Longer than the maximum length (255 bytes by default), as measured in
+    /// [`OptionsForCheck::length_unit`].
+    TooLong { len: usize, unit: LengthUnit },
+    /// Shorter than the [`OptionsForCheck::min_length`], as measured in
+    /// [`OptionsForCheck::length_unit`].
+    TooShort { len: usize, unit: LengthUnit },
+    /// A Windows device name, such as `con` or `LPT1.txt`, or one of
+    /// [`OptionsForCheck::reserved_names`].
+    WindowsReserved,
+    /// A trailing dot or space, which Windows strips.
+    WindowsTrailing,
+    /// An extension that is not one of the [`OptionsForCheck::allowed_extensions`].
+    DisallowedExtension,
+    /// An extension that is not spelled as [`OptionsForCheck::normalize_extension`] says.
+    UnnormalizedExtension,
+    /// More extensions than [`OptionsForCheck::max_extensions`].
+    TooManyExtensions,
+    /// A match of the [`OptionsForCheck::deny_patterns`] at the given index.
+    DeniedPattern { index: usize },
+    /// One of the [`OptionsForCheck::deny_substrings`], at the given byte offset.
+    DeniedSubstring { offset: usize, len: usize },
+    /// A name that sync clients refuse or treat specially; see
+    /// [`SYNC_JUNK_NAMES`](crate::SYNC_JUNK_NAMES).
+    SyncJunk,
+}
+
+impl Violation {
+    /// A stable identifier, e.g. for logs and metrics.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Violation::IllegalChar { .. } => "illegal_char",
+            Violation::ControlChar { .. } => "control_char",
+            Violation::NonPortableChar { .. } => "non_portable_char",
+            Violation::NonPortableEdge => "non_portable_edge",
+            Violation::NotAllowlisted { .. } => "not_allowlisted",
+            Violation::NonAscii { .. } => "non_ascii",
+            Violation::BidiControl { .. } => "bidi_control",
+            Violation::ShellMetachar { .. } => "shell_metachar",
+            Violation::ExpansionChar { .. } => "expansion_char",
+            Violation::Confusable { .. } => "confusable",
+            Violation::HiddenPrefix => "hidden_prefix",
+            Violation::LeadingHyphen => "leading_hyphen",
+            Violation::DoubleExtension => "double_extension",
+            Violation::DotsOnly => "dots_only",
+            Violation::WhitespaceOnly => "whitespace_only",
+            Violation::TooLong { .. } => "too_long",
+            Violation::TooShort { .. } => "too_short",
+            Violation::WindowsReserved => "windows_reserved",
+            Violation::WindowsTrailing => "windows_trailing",
+            Violation::SyncJunk => "sync_junk",
+            Violation::DisallowedExtension => "disallowed_extension",
+            Violation::UnnormalizedExtension => "unnormalized_extension",
+            Violation::TooManyExtensions => "too_many_extensions",
+            Violation::DeniedPattern { .. } => "denied_pattern",
+            Violation::DeniedSubstring { .. } => "denied_substring",
+        }
+    }
+
+    /// The flag of this kind of violation in a [`ViolationFlags`].
+    pub fn flag(&self) -> ViolationFlags {
+        match self {
+            Violation::IllegalChar { .. } => ViolationFlags::ILLEGAL_CHAR,
+            Violation::ControlChar { .. } => ViolationFlags::CONTROL_CHAR,
+            Violation::NonPortableChar { .. } => ViolationFlags::NON_PORTABLE_CHAR,
+            Violation::NonPortableEdge => ViolationFlags::NON_PORTABLE_EDGE,
+            Violation::NotAllowlisted { .. } => ViolationFlags::NOT_ALLOWLISTED,
+            Violation::NonAscii { .. } => ViolationFlags::NON_ASCII,
+            Violation::BidiControl { .. } => ViolationFlags::BIDI_CONTROL,
+            Violation::ShellMetachar { .. } => ViolationFlags::SHELL_METACHAR,
+            Violation::ExpansionChar { .. } => ViolationFlags::EXPANSION_CHAR,
+            Violation::Confusable { .. } => ViolationFlags::CONFUSABLE,
+            Violation::HiddenPrefix => ViolationFlags::HIDDEN_PREFIX,
+            Violation::LeadingHyphen => ViolationFlags::LEADING_HYPHEN,
+            Violation::DoubleExtension => ViolationFlags::DOUBLE_EXTENSION,
+            Violation::DotsOnly => ViolationFlags::DOTS_ONLY,
+            Violation::WhitespaceOnly => ViolationFlags::WHITESPACE_ONLY,
+            Violation::TooLong { .. } => ViolationFlags::TOO_LONG,
+            Violation::TooShort { .. } => ViolationFlags::TOO_SHORT,
+            Violation::WindowsReserved => ViolationFlags::WINDOWS_RESERVED,
+            Violation::WindowsTrailing => ViolationFlags::WINDOWS_TRAILING,
+            Violation::SyncJunk => ViolationFlags::SYNC_JUNK,
+            Violation::DisallowedExtension => ViolationFlags::DISALLOWED_EXTENSION,
+            Violation::UnnormalizedExtension => ViolationFlags::UNNORMALIZED_EXTENSION,
+            Violation::TooManyExtensions => ViolationFlags::TOO_MANY_EXTENSIONS,
+            Violation::DeniedPattern { .. } => ViolationFlags::DENIED_PATTERN,
+            Violation::DeniedSubstring { .. } => ViolationFlags::DENIED_SUBSTRING,
+        }
+    }
+}
+
+/// Describes the violation, e.g. `illegal character ':' at byte 4`.
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Violation::IllegalChar { offset, c } => {
+                write!(f, "illegal character {:?} at byte {}", c, offset)
+            }
+            Violation::ControlChar { offset, c } => {
+                write!(f, "control character {:?} at byte {}", c, offset)
+            }
+            Violation::NonPortableChar { offset, c } => {
+                write!(f, "non-portable character {:?} at byte {}", c, offset)
+            }
+            Violation::NonPortableEdge => write!(f, "non-portable first or last character"),
+            Violation::NotAllowlisted { offset, c } => {
+                write!(f, "character {:?} at byte {} is not allowed", c, offset)
+            }
+            Violation::NonAscii { offset, c } => {
+                write!(f, "non-ASCII character {:?} at byte {}", c, offset)
+            }
+            Violation::BidiControl { offset, c } => {
+                write!(f, "bidirectional control {:?} at byte {}", c, offset)
+            }
+            Violation::ShellMetachar { offset, c } => {
+                write!(f, "shell metacharacter {:?} at byte {}", c, offset)
+            }
+            Violation::ExpansionChar { offset, c } => {
+                write!(f, "expansion character {:?} at byte {}", c, offset)
+            }
+            Violation::Confusable { offset, c } => {
+                write!(f, "confusable character {:?} at byte {}", c, offset)
+            }
+            Violation::HiddenPrefix => write!(f, "leading dot"),
+            Violation::LeadingHyphen => write!(f, "leading hyphen"),
+            Violation::DoubleExtension => write!(f, "double extension"),
+            Violation::DotsOnly => write!(f, "name of only dots"),
+            Violation::WhitespaceOnly => write!(f, "name of only whitespace"),
+            Violation::TooLong { len, unit } => write!(f, "too long ({} {})", len, unit.name()),
+            Violation::TooShort { len, unit } => {
+                write!(f, "too short ({} {})", len, unit.name())
+            }
+            Violation::WindowsReserved => write!(f, "reserved device name"),
+            Violation::WindowsTrailing => write!(f, "trailing dot or space"),
+            Violation::DisallowedExtension => write!(f, "extension not allowed"),
+            Violation::UnnormalizedExtension => write!(f, "extension not normalized"),
+            Violation::TooManyExtensions => write!(f, "too many extensions"),
+            Violation::DeniedPattern { index } => write!(f, "matches deny pattern {}", index),
+            Violation::DeniedSubstring { offset, len } => {
+                write!(f, "denied substring of {} bytes at byte {}", len, offset)
+            }
+            Violation::SyncJunk => write!(f, "name refused by sync clients"),
+        }
+    }
+}
+
+/// The error returned by [`try_sanitize_with_options`](crate::try_sanitize_with_options):
+/// the first rule that the name breaks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SanitizeError(pub Violation);
+
+impl fmt::Display for SanitizeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "not a sanitized file name: {}", self.0)
+    }
+}
+
+impl std::error::Error for SanitizeError {}
+
+/// The kinds of [`Violation`] a name has, as a set of bit flags, for scanners that only
+/// need to test for some kinds and would rather not allocate a list per name.
+///
+/// ```
+/// use sanitize_filename::{violation_flags, OptionsForCheck, ViolationFlags};
+///
+/// let flags = violation_flags("../etc/passwd", OptionsForCheck::new());
+/// assert!(flags.intersects(ViolationFlags::SECURITY));
+/// assert!(violation_flags("report.pdf", OptionsForCheck::new()).is_empty());
+/// ```
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct ViolationFlags(u32);
+
+impl ViolationFlags {
+    pub const ILLEGAL_CHAR: ViolationFlags = ViolationFlags(1 << 0);
+    pub const CONTROL_CHAR: ViolationFlags = ViolationFlags(1 << 1);
+    pub const NON_PORTABLE_CHAR: ViolationFlags = ViolationFlags(1 << 2);
+    pub const NON_PORTABLE_EDGE: ViolationFlags = ViolationFlags(1 << 3);
+    pub const BIDI_CONTROL: ViolationFlags = ViolationFlags(1 << 4);
+    pub const SHELL_METACHAR: ViolationFlags = ViolationFlags(1 << 5);
+    pub const CONFUSABLE: ViolationFlags = ViolationFlags(1 << 6);
+    pub const HIDDEN_PREFIX: ViolationFlags = ViolationFlags(1 << 7);
+    pub const LEADING_HYPHEN: ViolationFlags = ViolationFlags(1 << 8);
+    pub const DOUBLE_EXTENSION: ViolationFlags = ViolationFlags(1 << 9);
+    pub const DOTS_ONLY: ViolationFlags = ViolationFlags(1 << 10);
+    pub const TOO_LONG: ViolationFlags = ViolationFlags(1 << 11);
+    pub const WINDOWS_RESERVED: ViolationFlags = ViolationFlags(1 << 12);
+    pub const WINDOWS_TRAILING: ViolationFlags = ViolationFlags(1 << 13);
+    pub const SYNC_JUNK: ViolationFlags = ViolationFlags(1 << 14);
+    pub const WHITESPACE_ONLY: ViolationFlags = ViolationFlags(1 << 15);
+    pub const NOT_ALLOWLISTED: ViolationFlags = ViolationFlags(1 << 16);
+    pub const DENIED_PATTERN: ViolationFlags = ViolationFlags(1 << 17);
+    pub const DENIED_SUBSTRING: ViolationFlags = ViolationFlags(1 << 18);
+    pub const EXPANSION_CHAR: ViolationFlags = ViolationFlags(1 << 19);
+    pub const NON_ASCII: ViolationFlags = ViolationFlags(1 << 20);
+    pub const DISALLOWED_EXTENSION: ViolationFlags = ViolationFlags(1 << 21);
+    pub const TOO_SHORT: ViolationFlags = ViolationFlags(1 << 22);
+    pub const UNNORMALIZED_EXTENSION: ViolationFlags = ViolationFlags(1 << 23);
+    pub const TOO_MANY_EXTENSIONS: ViolationFlags = ViolationFlags(1 << 24);
+
+    /// The violations that can be used in an attack rather than just failing: path
+    /// separators and `..` (traversal), control characters (terminal and log injection),
+    /// disguised names and extensions, names that commands read as options, and Windows
+    /// device names and trailing characters (which Windows silently changes).
+    pub const SECURITY: ViolationFlags = ViolationFlags(
+        Self::ILLEGAL_CHAR.0
+            | Self::CONTROL_CHAR.0
+            | Self::BIDI_CONTROL.0
+            | Self::SHELL_METACHAR.0
+            | Self::EXPANSION_CHAR.0
+            | Self::CONFUSABLE.0
+            | Self::HIDDEN_PREFIX.0
+            | Self::LEADING_HYPHEN.0
+            | Self::DOUBLE_EXTENSION.0
+            | Self::DOTS_ONLY.0
+            | Self::WINDOWS_RESERVED.0
+            | Self::WINDOWS_TRAILING.0,
+    );
+
+    pub(crate) const NAMES: [(ViolationFlags, &'static str); 25] = [
+        (Self::ILLEGAL_CHAR, "ILLEGAL_CHAR"),
+        (Self::CONTROL_CHAR, "CONTROL_CHAR"),
+        (Self::NON_PORTABLE_CHAR, "NON_PORTABLE_CHAR"),
+        (Self::NON_PORTABLE_EDGE, "NON_PORTABLE_EDGE"),
+        (Self::BIDI_CONTROL, "BIDI_CONTROL"),
+        (Self::SHELL_METACHAR, "SHELL_METACHAR"),
+        (Self::CONFUSABLE, "CONFUSABLE"),
+        (Self::HIDDEN_PREFIX, "HIDDEN_PREFIX"),
+        (Self::LEADING_HYPHEN, "LEADING_HYPHEN"),
+        (Self::DOUBLE_EXTENSION, "DOUBLE_EXTENSION"),
+        (Self::DOTS_ONLY, "DOTS_ONLY"),
+        (Self::TOO_LONG, "TOO_LONG"),
+        (Self::WINDOWS_RESERVED, "WINDOWS_RESERVED"),
+        (Self::WINDOWS_TRAILING, "WINDOWS_TRAILING"),
+        (Self::SYNC_JUNK, "SYNC_JUNK"),
+        (Self::WHITESPACE_ONLY, "WHITESPACE_ONLY"),
+        (Self::NOT_ALLOWLISTED, "NOT_ALLOWLISTED"),
+        (Self::DENIED_PATTERN, "DENIED_PATTERN"),
+        (Self::DENIED_SUBSTRING, "DENIED_SUBSTRING"),
+        (Self::EXPANSION_CHAR, "EXPANSION_CHAR"),
+        (Self::NON_ASCII, "NON_ASCII"),
+        (Self::DISALLOWED_EXTENSION, "DISALLOWED_EXTENSION"),
+        (Self::TOO_SHORT, "TOO_SHORT"),
+        (Self::UNNORMALIZED_EXTENSION, "UNNORMALIZED_EXTENSION"),
+        (Self::TOO_MANY_EXTENSIONS, "TOO_MANY_EXTENSIONS"),
+    ];
+
+    pub const fn empty() -> Self {
+        ViolationFlags(0)
+    }
+
+    pub const fn bits(&self) -> u32 {
+        self.0
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Whether all the flags in `other` are set.
+    pub const fn contains(&self, other: ViolationFlags) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Whether any of the flags in `other` is set.
+    pub const fn intersects(&self, other: ViolationFlags) -> bool {
+        self.0 & other.0 != 0
+    }
+}
+
+impl BitOr for ViolationFlags {
+    type Output = ViolationFlags;
+
+    fn bitor(self, other: ViolationFlags) -> ViolationFlags {
+        ViolationFlags(self.0 | other.0)
+    }
+}
+
+impl BitOrAssign for ViolationFlags {
+    fn bitor_assign(&mut self, other: ViolationFlags) {
+        self.0 |= other.0;
+    }
+}
+
+impl BitAnd for ViolationFlags {
+    type Output = ViolationFlags;
+
+    fn bitand(self, other: ViolationFlags) -> ViolationFlags {
+        ViolationFlags(self.0 & other.0)
+    }
+}
+
+impl fmt::Debug for ViolationFlags {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ViolationFlags(")?;
+        let mut first = true;
+        for (flag, name) in Self::NAMES {
+            if self.contains(flag) {
+                write!(f, "{}{}", if first { "" } else { " | " }, name)?;
+                first = false;
+            }
+        }
+        write!(f, ")")
+    }
+}
+
+/// Lists every rule that `name` breaks under `options`, so callers can decide per kind
+/// whether to rename or reject; the list is empty exactly when
+/// [`is_sanitized_with_options`](crate::is_sanitized_with_options) returns true.
+pub fn violations<S: AsRef<str>>(name: S, options: OptionsForCheck) -> Vec<Violation> {
+    let mut found = Vec::new();
+    visit(name.as_ref(), &options, |violation| found.push(violation));
+    found
+}
+
+/// The kinds of violation that [`violations`] would list, without allocating.
+pub fn violation_flags<S: AsRef<str>>(name: S, options: OptionsForCheck) -> ViolationFlags {
+    let mut flags = ViolationFlags::empty();
+    visit(name.as_ref(), &options, |violation| {
+        flags |= violation.flag()
+    });
+    flags
+}
+
+/// The first rule that `name` breaks under `options`, or `None` if it is sanitized, as a
+/// companion to [`is_sanitized_with_options`](crate::is_sanitized_with_options) for error
+/// messages:
+///
+/// ```
+/// use sanitize_filename::{why_not_sanitized, OptionsForCheck};
+///
+/// let options = OptionsForCheck::new().with_windows(true);
+/// let why = why_not_sanitized("LPT9", options.clone()).unwrap();
+/// assert_eq!(why.to_string(), "reserved device name");
+/// let why = why_not_sanitized("q3: results", options.clone()).unwrap();
+/// assert_eq!(why.to_string(), "illegal character ':' at byte 2");
+/// assert_eq!(why_not_sanitized("results", options), None);
+/// ```
+pub fn why_not_sanitized<S: AsRef<str>>(name: S, options: OptionsForCheck) -> Option<Violation> {
+    first_violation(name.as_ref(), &options)
+}
+
+// The first violation that `violations` would list.
+pub(crate) fn first_violation(name: &str, options: &OptionsForCheck) -> Option<Violation> {
+    let mut first = None;
+    visit(name, options, |violation| {
+        first.get_or_insert(violation);
+    });
+    first
+}
+
+fn visit<F: FnMut(Violation)>(name: &str, options: &OptionsForCheck, mut found: F) {
+    let portable = options.charset == Charset::Portable;
+    for (offset, c) in name.char_indices() {
+        if is_illegal_char(c) || options.extra_illegal.contains(&c) {
+            found(Violation::IllegalChar { offset, c });
+        } else if is_control_char(c) {
+            found(Violation::ControlChar { offset, c });
+        } else if options.allow.contains(&c) {
+            // Exempt from the optional rules below
+        } else if options.strip_bidi && is_bidi_control(c) {
+            found(Violation::BidiControl { offset, c });
+        } else if options.strip_shell_metachars && is_shell_metachar(c) {
+            found(Violation::ShellMetachar { offset, c });
+        } else if options.strip_expansion_chars && is_expansion_char(c) {
+            found(Violation::ExpansionChar { offset, c });
+        } else if options.fold_confusables && is_confusable(c) {
+            found(Violation::Confusable { offset, c });
+        } else if portable && !is_portable_char(c) {
+            found(Violation::NonPortableChar { offset, c });
+        } else if (options.allowlist.as_ref()).is_some_and(|allowlist| !allowlist.contains(&c)) {
+            found(Violation::NotAllowlisted { offset, c });
+        } else if options.ascii_only && !c.is_ascii() {
+            found(Violation::NonAscii { offset, c });
+        }
+    }
+    let hides = options.leading_dot != LeadingDotPolicy::Allow;
+    if hides && name.starts_with('.') && !is_reserved(name) {
+        found(Violation::HiddenPrefix);
+    }
+    if options.strip_shell_metachars && name.starts_with('-') {
+        found(Violation::LeadingHyphen);
+    }
+    if options.defuse_double_extensions && has_double_extension(name) {
+        found(Violation::DoubleExtension);
+    }
+    if portable && !has_portable_edges(name) {
+        found(Violation::NonPortableEdge);
+    }
+    if is_reserved(name) {
+        found(Violation::DotsOnly);
+    }
+    if options.rule_set == RuleSet::Latest && is_whitespace_only(name) {
+        found(Violation::WhitespaceOnly);
+    }
+    let unit = options.length_unit;
+    let len = unit.measure(name);
+    if options.truncation.truncates() && len > options.max_length {
+        found(Violation::TooLong { len, unit });
+    }
+    if (options.min_length).is_some_and(|min| len < min) {
+        found(Violation::TooShort { len, unit });
+    }
+    if (options.allowed_extensions.as_ref())
+        .is_some_and(|extensions| !has_allowed_extension(name, extensions))
+    {
+        found(Violation::DisallowedExtension);
+    }
+    if normalized_extension(name, options.normalize_extension).is_some() {
+        found(Violation::UnnormalizedExtension);
+    }
+    if (options.max_extensions).is_some_and(|max| extension_count(name) > max) {
+        found(Violation::TooManyExtensions);
+    }
+    for (index, pattern) in options.deny_patterns.iter().enumerate() {
+        if pattern.is_match(name) {
+            found(Violation::DeniedPattern { index });
+        }
+    }
+    if let Some(deny_substrings) = &options.deny_substrings {
+        for range in deny_substrings.find_iter(name) {
+            found(Violation::DeniedSubstring {
+                offset: range.start,
+                len: range.len(),
+            });
+        }
+    }
+    if options.windows_reserved_names.unwrap_or(options.windows)
+        && (is_windows_reserved(name, options.rule_set)
+            || is_listed_reserved(name, &options.reserved_names))
+    {
+        found(Violation::WindowsReserved);
+    }
+    if options.windows_trailing.unwrap_or(options.windows) && has_windows_trailing(name) {
+        found(Violation::WindowsTrailing);
+    }
+    if options.block_sync_junk && is_sync_junk(name) {
+        found(Violation::SyncJunk);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{violation_flags, violations, why_not_sanitized, Violation, ViolationFlags};
+
+    #[test]
+    fn it_lists_violations() {
+        let options = crate::OptionsForCheck::new()
+            .with_windows(true)
+            .with_block_sync_junk(true);
+
+        assert_eq!(
+            violations("Icon\r", options.clone()),
+            vec![
+                Violation::ControlChar { offset: 4, c: '\r' },
+                Violation::SyncJunk
+            ]
+        );
+        assert_eq!(
+            violations("a/b.", options.clone()),
+            vec![
+                Violation::IllegalChar { offset: 1, c: '/' },
+                Violation::WindowsTrailing
+            ]
+        );
+        assert_eq!(
+            violations("Thumbs.db", options.clone()),
+            vec![Violation::SyncJunk]
+        );
+        assert!(violations("Thumbs.db", crate::OptionsForCheck::new()).is_empty());
+
+        let archival = crate::OptionsForCheck::from(&crate::Options::archival());
+        assert_eq!(
+            violations(".a b/", archival.clone()),
+            vec![
+                Violation::NonPortableChar { offset: 2, c: ' ' },
+                Violation::IllegalChar { offset: 4, c: '/' },
+                Violation::NonPortableEdge
+            ]
+        );
+
+        let paranoid = crate::OptionsForCheck::new().with_security(crate::Security::Paranoid);
+        assert_eq!(
+            violations("-a$.pdf.exe", paranoid.clone()),
+            vec![
+                Violation::ShellMetachar { offset: 2, c: '$' },
+                Violation::LeadingHyphen,
+                Violation::DoubleExtension
+            ]
+        );
+
+        for name in [
+            ".x",
+            "",
+            "..",
+            "con.txt",
+            "desktop.ini",
+            "a\u{85}",
+            "ok.txt",
+            "-x",
+            "é",
+            " \u{a0}",
+        ] {
+            for options in [options.clone(), archival.clone(), paranoid.clone()] {
+                let flags = violations(name, options.clone())
+                    .iter()
+                    .fold(ViolationFlags::empty(), |flags, v| flags | v.flag());
+                assert_eq!(violation_flags(name, options.clone()), flags);
+                assert_eq!(
+                    flags.is_empty(),
+                    crate::is_sanitized_with_options(name, options)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn it_explains_the_first_violation() {
+        let options = crate::OptionsForCheck::new().with_windows(true);
+
+        for name in ["a:b.", "con", "ok", "..", "x\u{1}"] {
+            assert_eq!(
+                why_not_sanitized(name, options.clone()),
+                violations(name, options.clone()).first().copied()
+            );
+        }
+        assert_eq!(
+            why_not_sanitized("a. ", options),
+            Some(Violation::WindowsTrailing)
+        );
+
+        let chars = crate::OptionsForCheck::new()
+            .with_length_unit(crate::LengthUnit::Chars)
+            .with_max_length(4);
+        let why = why_not_sanitized("日本語.txt", chars.clone()).unwrap();
+        assert_eq!(
+            why,
+            Violation::TooLong {
+                len: 7,
+                unit: crate::LengthUnit::Chars
+            }
+        );
+        assert_eq!(why.to_string(), "too long (7 characters)");
+        let why = why_not_sanitized("日本", chars.with_min_length(3)).unwrap();
+        assert_eq!(why.to_string(), "too short (2 characters)");
+    }
+
+    #[test]
+    fn it_blocks_sync_junk() {
+        let options = crate::Options::new()
+            .with_block_sync_junk(true)
+            .with_replacement("_");
+
+        assert_eq!(
+            crate::sanitize_with_options(".DS_Store", options.clone()),
+            "_"
+        );
+        assert_eq!(crate::sanitize_with_options("Icon\r", options.clone()), "_");
+        assert_eq!(
+            crate::sanitize_with_options("Icon", options.clone()),
+            "Icon"
+        );
+        assert_eq!(
+            crate::sanitize_with_options(".DS_Store", crate::Options::new()),
+            ".DS_Store"
+        );
+    }
+
+    #[test]
+    fn it_combines_violation_flags() {
+        let flags = violation_flags("a/b\u{202e}", crate::OptionsForCheck::new())
+            | ViolationFlags::TOO_LONG;
+
+        assert!(flags.contains(ViolationFlags::ILLEGAL_CHAR | ViolationFlags::TOO_LONG));
+        assert!(!flags.contains(ViolationFlags::BIDI_CONTROL));
+        assert!(flags.intersects(ViolationFlags::SECURITY));
+        assert!(!ViolationFlags::TOO_LONG.intersects(ViolationFlags::SECURITY));
+        assert_eq!(
+            format!("{:?}", flags & ViolationFlags::SECURITY),
+            "ViolationFlags(ILLEGAL_CHAR)"
+        );
+        assert_eq!(format!("{:?}", ViolationFlags::empty()), "ViolationFlags()");
+    }
+
+    #[test]
+    fn it_replaces_whitespace_only_names() {
+        let options = crate::Options::new().with_replacement("_");
+
+        assert_eq!(
+            crate::sanitize_with_options(" \u{a0} ", options.clone()),
+            "_"
+        );
+        assert_eq!(crate::sanitize_with_options(" a ", options.clone()), " a ");
+        assert_eq!(
+            crate::sanitize_with_options(
+                "  ",
+                options.clone().with_fallback(crate::Fallback::Base32)
+            ),
+            "eaqa"
+        );
+        assert_eq!(
+            crate::sanitize_with_options("  ", options.with_rule_set(crate::RuleSet::V1)),
+            "  "
+        );
+        assert_eq!(
+            violations("\u{3000}", crate::OptionsForCheck::new()),
+            vec![Violation::WhitespaceOnly]
+        );
+    }
+}
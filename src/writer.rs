@@ -0,0 +1,141 @@
+use std::{io, mem, str};
+
+use crate::{Options, SanitizedChars};
+
+/// Sanitizes filename data on its way into `inner`, as it arrives in chunks, e.g. from a
+/// network protocol, before the name has been assembled.
+///
+/// The data is decoded as UTF-8, keeping a code point that is split between two writes
+/// until the rest of it arrives; invalid sequences become U+FFFD, as in
+/// [`String::from_utf8_lossy`]. The characters then go through the same rules as
+/// [`SanitizedChars`], without those that need the whole name, such as reserved names and
+/// truncation; sanitize or check the assembled name when they matter.
+///
+/// Call [`finish`](SanitizingWriter::finish) once the name is complete, to write out a
+/// sequence cut short at the end and get `inner` back.
+///
+/// ```
+/// use std::io::Write;
+///
+/// let options = sanitize_filename::Options::new().with_replacement("_");
+/// let mut writer = sanitize_filename::SanitizingWriter::new(Vec::new(), &options);
+/// writer.write_all(b"caf\xc3").unwrap();
+/// writer.write_all(b"\xa9/menu?.txt").unwrap();
+/// assert_eq!(writer.finish().unwrap(), "café_menu_.txt".as_bytes());
+/// ```
+#[derive(Debug)]
+pub struct SanitizingWriter<'a, W: io::Write> {
+    inner: W,
+    options: &'a Options<'a>,
+    // The start of a code point that the last write cut short.
+    partial: Vec<u8>,
+    sanitized: String,
+}
+
+impl<'a, W: io::Write> SanitizingWriter<'a, W> {
+    pub fn new(inner: W, options: &'a Options<'a>) -> Self {
+        SanitizingWriter {
+            inner,
+            options,
+            partial: Vec::new(),
+            sanitized: String::new(),
+        }
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Writes out what is left of a code point cut short at the end, as U+FFFD, flushes
+    /// and returns the underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        if !mem::take(&mut self.partial).is_empty() {
+            self.write_sanitized("\u{FFFD}")?;
+        }
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+
+    fn write_sanitized(&mut self, chunk: &str) -> io::Result<()> {
+        self.sanitized.clear();
+        self.sanitized
+            .extend(SanitizedChars::new(chunk.chars(), self.options));
+        self.inner.write_all(self.sanitized.as_bytes())
+    }
+
+    fn write_decoded(&mut self, mut bytes: &[u8]) -> io::Result<()> {
+        loop {
+            match str::from_utf8(bytes) {
+                Ok(chunk) => return self.write_sanitized(chunk),
+                Err(err) => {
+                    let (valid, rest) = bytes.split_at(err.valid_up_to());
+                    self.write_sanitized(str::from_utf8(valid).unwrap_or_default())?;
+                    match err.error_len() {
+                        Some(len) => {
+                            self.write_sanitized("\u{FFFD}")?;
+                            bytes = &rest[len..];
+                        }
+                        None => {
+                            self.partial = rest.to_vec();
+                            return Ok(());
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<'a, W: io::Write> io::Write for SanitizingWriter<'a, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.partial.is_empty() {
+            self.write_decoded(buf)?;
+        } else {
+            let mut bytes = mem::take(&mut self.partial);
+            bytes.extend_from_slice(buf);
+            self.write_decoded(&bytes)?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::SanitizingWriter;
+
+    #[test]
+    fn it_sanitizes_chunks() {
+        let options = crate::Options::new().with_replacement("_");
+        let mut writer = SanitizingWriter::new(Vec::new(), &options);
+        for chunk in [
+            &b"a/"[..],
+            b"\xc3",
+            b"\xa9:b",
+            b"\xff",
+            b"\xf0\x9f",
+            b"\x98",
+            b"\x80",
+        ] {
+            writer.write_all(chunk).unwrap();
+        }
+        assert_eq!(writer.get_ref().as_slice(), "a_é_b\u{FFFD}😀".as_bytes());
+        writer.write_all(b"\xe2\x82").unwrap();
+        assert_eq!(
+            String::from_utf8(writer.finish().unwrap()).unwrap(),
+            "a_é_b\u{FFFD}😀\u{FFFD}"
+        );
+
+        let mut writer = SanitizingWriter::new(Vec::new(), &options);
+        writer.write_all("x\u{1}y".as_bytes()).unwrap();
+        assert_eq!(
+            writer.finish().unwrap(),
+            crate::sanitize_with_options("x\u{1}y", options.clone()).as_bytes()
+        );
+    }
+}